@@ -0,0 +1,75 @@
+use sea_orm::*;
+
+use crate::common::db as common_db;
+use crate::common::errors::FieldError;
+use crate::common::models::ContentWithMetasUsersFields;
+use crate::common::utils::get_now;
+use crate::entity::{content, content::Entity as Content};
+use crate::AppState;
+
+pub async fn get_published_posts_created(state: &AppState) -> Result<Vec<u32>, FieldError> {
+    Content::find()
+        .select_only()
+        .column(content::Column::Created)
+        .filter(content::Column::Type.eq("post"))
+        .filter(content::Column::Status.eq("publish"))
+        .filter(content::Column::Created.lte(get_now()))
+        .into_tuple()
+        .all(&state.conn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("fetch contents failed".to_string()))
+}
+
+fn get_posts_by_created_range_stmt(private: bool, start: u32, end: u32) -> Select<Content> {
+    let stmt = Content::find()
+        .filter(content::Column::Type.eq("post"))
+        .filter(content::Column::Created.gte(start))
+        .filter(content::Column::Created.lt(end));
+    if private {
+        stmt.filter(content::Column::Status.ne("trash"))
+    } else {
+        stmt.filter(content::Column::Status.eq("publish"))
+            .filter(content::Column::Created.lte(get_now()))
+    }
+}
+
+pub async fn get_posts_count_by_created_range_with_private(
+    state: &AppState,
+    private: bool,
+    start: u32,
+    end: u32,
+) -> u64 {
+    get_posts_by_created_range_stmt(private, start, end)
+        .count(&state.conn)
+        .await
+        .unwrap_or(0)
+}
+
+pub async fn get_contents_with_metas_user_and_fields_by_created_range_and_list_query(
+    state: &AppState,
+    private: bool,
+    (start, end): (u32, u32),
+    page_size: u64,
+    page: u64,
+    order_by: &str,
+) -> Result<Vec<ContentWithMetasUsersFields>, FieldError> {
+    let stmt = get_posts_by_created_range_stmt(private, start, end);
+    let stmt = match order_by {
+        "-cid" => stmt.order_by_desc(content::Column::Cid),
+        "cid" => stmt.order_by_asc(content::Column::Cid),
+        "-slug" => stmt.order_by_desc(content::Column::Slug),
+        "slug" => stmt.order_by_asc(content::Column::Slug),
+        "-created" => stmt.order_by_desc(content::Column::Created),
+        "created" => stmt.order_by_asc(content::Column::Created),
+        _ => stmt.order_by_desc(content::Column::Created),
+    };
+
+    let paginator = stmt.paginate(&state.conn, page_size);
+
+    let contents = paginator
+        .fetch_page(page - 1)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("fetch contents failed".to_string()))?;
+
+    common_db::get_contents_with_metas_users_fields(state, contents).await
+}
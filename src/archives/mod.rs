@@ -0,0 +1,6 @@
+mod db;
+mod urls;
+mod utils;
+mod views;
+
+pub use urls::archives_routers;
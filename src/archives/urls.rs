@@ -0,0 +1,19 @@
+use axum::{routing::get, Router};
+use std::sync::Arc;
+
+use super::views;
+use crate::AppState;
+
+pub fn archives_routers(_ro: bool) -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/api/archives/", get(views::list_archives))
+        .route("/api/archives/:year/", get(views::list_year_archive_posts))
+        .route(
+            "/api/archives/:year/:month/",
+            get(views::list_month_archive_posts),
+        )
+        .route(
+            "/api/archives/:year/:month/:day/",
+            get(views::list_day_archive_posts),
+        )
+}
@@ -0,0 +1,52 @@
+use chrono::{DateTime, Datelike, FixedOffset, Months, NaiveDate, TimeZone};
+
+use crate::common::errors::FieldError;
+
+pub fn get_year_and_month(timestamp: u32, timezone: &FixedOffset) -> (i32, u32) {
+    let date = DateTime::from_timestamp(timestamp as i64, 0)
+        .unwrap_or_default()
+        .with_timezone(timezone);
+    (date.year(), date.month())
+}
+
+/// Returns the `[start, end)` range of unix timestamps covered by the archive.
+pub fn get_archive_range(
+    timezone: &FixedOffset,
+    year: i32,
+    month: Option<u32>,
+    day: Option<u32>,
+) -> Result<(u32, u32), FieldError> {
+    let start = NaiveDate::from_ymd_opt(year, month.unwrap_or(1), day.unwrap_or(1))
+        .ok_or(FieldError::InvalidParams("date".to_string()))?;
+    let end = match (month, day) {
+        (_, Some(_)) => start.succ_opt(),
+        (Some(_), None) => start.checked_add_months(Months::new(1)),
+        (None, None) => start.checked_add_months(Months::new(12)),
+    }
+    .ok_or(FieldError::InvalidParams("date".to_string()))?;
+
+    let to_timestamp = |date: NaiveDate| {
+        timezone
+            .from_local_datetime(&date.and_hms_opt(0, 0, 0).unwrap())
+            .single()
+            .map(|d| d.timestamp().clamp(0, u32::MAX as i64) as u32)
+            .ok_or(FieldError::InvalidParams("date".to_string()))
+    };
+    Ok((to_timestamp(start)?, to_timestamp(end)?))
+}
+
+#[test]
+fn test_get_archive_range() {
+    let timezone = FixedOffset::east_opt(28800).unwrap();
+    assert_eq!(
+        (1704038400, 1706716800),
+        get_archive_range(&timezone, 2024, Some(1), None).unwrap()
+    );
+    assert_eq!(
+        (1709136000, 1709222400),
+        get_archive_range(&timezone, 2024, Some(2), Some(29)).unwrap()
+    );
+    assert!(get_archive_range(&timezone, 2023, Some(2), Some(29)).is_err());
+    assert_eq!((2024, 1), get_year_and_month(1704038400, &timezone));
+    assert_eq!((2023, 12), get_year_and_month(1704038399, &timezone));
+}
@@ -0,0 +1,110 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use axum::extract::{Path, State};
+use axum::response::Json;
+use serde_json::{json, Value};
+
+use super::db;
+use super::utils::{get_archive_range, get_year_and_month};
+use crate::common::db as common_db;
+use crate::common::errors::FieldError;
+use crate::common::extractors::{PMVisitor, ValidatedQuery};
+use crate::common::markdown::is_html_format;
+use crate::common::utils::get_timezone;
+use crate::entity::user;
+use crate::posts::forms::PostsQuery;
+use crate::posts::utils::{get_posts_results, is_summary_fields};
+use crate::AppState;
+
+pub async fn list_archives(State(state): State<Arc<AppState>>) -> Result<Json<Value>, FieldError> {
+    let options = common_db::get_site_options(&state).await?;
+    let timezone = get_timezone(&options);
+
+    let mut archives = BTreeMap::new();
+    for created in db::get_published_posts_created(&state).await? {
+        *archives
+            .entry(get_year_and_month(created, &timezone))
+            .or_insert(0u64) += 1;
+    }
+
+    let results: Vec<Value> = archives
+        .into_iter()
+        .rev()
+        .map(|((year, month), count)| json!({"year": year, "month": month, "count": count}))
+        .collect();
+    Ok(Json(json!({
+        "count": results.len(),
+        "results": results
+    })))
+}
+
+async fn list_archive_posts(
+    state: &AppState,
+    user: &user::Model,
+    q: PostsQuery,
+    year: i32,
+    month: Option<u32>,
+    day: Option<u32>,
+) -> Result<Json<Value>, FieldError> {
+    let private =
+        q.private.unwrap_or(false) && (user.group == "editor" || user.group == "administrator");
+    let html = is_html_format(q.format.as_deref())?;
+    let summary = is_summary_fields(q.fields.as_deref())?;
+
+    let options = common_db::get_site_options(state).await?;
+    let timezone = get_timezone(&options);
+    let (start, end) = get_archive_range(&timezone, year, month, day)?;
+
+    let all_count =
+        db::get_posts_count_by_created_range_with_private(state, private, start, end).await;
+
+    let page = q.page.unwrap_or(1);
+    let page_size = q.page_size.unwrap_or(10);
+    let order_by = q.order_by.unwrap_or("-created".to_string());
+
+    let posts = db::get_contents_with_metas_user_and_fields_by_created_range_and_list_query(
+        state,
+        private,
+        (start, end),
+        page_size,
+        page,
+        &order_by,
+    )
+    .await?;
+    let posts = get_posts_results(posts, html, summary, state.excerpt_length);
+    Ok(Json(json!({
+        "page": page,
+        "page_size": page_size,
+        "all_count": all_count,
+        "count": posts.len(),
+        "results": posts
+    })))
+}
+
+pub async fn list_year_archive_posts(
+    State(state): State<Arc<AppState>>,
+    PMVisitor(user): PMVisitor,
+    Path(year): Path<i32>,
+    ValidatedQuery(q): ValidatedQuery<PostsQuery>,
+) -> Result<Json<Value>, FieldError> {
+    list_archive_posts(&state, &user, q, year, None, None).await
+}
+
+pub async fn list_month_archive_posts(
+    State(state): State<Arc<AppState>>,
+    PMVisitor(user): PMVisitor,
+    Path((year, month)): Path<(i32, u32)>,
+    ValidatedQuery(q): ValidatedQuery<PostsQuery>,
+) -> Result<Json<Value>, FieldError> {
+    list_archive_posts(&state, &user, q, year, Some(month), None).await
+}
+
+pub async fn list_day_archive_posts(
+    State(state): State<Arc<AppState>>,
+    PMVisitor(user): PMVisitor,
+    Path((year, month, day)): Path<(i32, u32, u32)>,
+    ValidatedQuery(q): ValidatedQuery<PostsQuery>,
+) -> Result<Json<Value>, FieldError> {
+    list_archive_posts(&state, &user, q, year, Some(month), Some(day)).await
+}
@@ -61,7 +61,7 @@ pub async fn get_attachments_by_list_query(
 ) -> Result<Vec<content::Model>, FieldError> {
     let stmt = Content::find().filter(content::Column::Type.eq("attachment"));
     let stmt = if private {
-        stmt
+        stmt.filter(content::Column::Status.ne("trash"))
     } else {
         stmt.filter(content::Column::Status.eq("publish"))
     };
@@ -5,7 +5,7 @@ mod forms;
 pub mod models;
 mod ser;
 mod urls;
-mod utils;
+pub mod utils;
 mod views;
 
 pub use urls::attachments_routers;
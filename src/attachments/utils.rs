@@ -1,5 +1,7 @@
 use axum::{body::Bytes, BoxError};
+use chrono::prelude::*;
 use futures::{Stream, TryStreamExt};
+use rand::Rng;
 use std::{io, path::PathBuf};
 use tokio::{
     fs::{create_dir_all, remove_file, File},
@@ -7,7 +9,12 @@ use tokio::{
 };
 use tokio_util::io::StreamReader;
 
+use super::db;
+use super::models::{AttachmentInfo, AttachmentText};
+use super::ser::to_string;
 use crate::common::errors::FieldError;
+use crate::entity::content;
+use crate::AppState;
 
 pub fn filename_is_valid(filename: &str) -> bool {
     let path = std::path::Path::new(filename);
@@ -54,6 +61,52 @@ where
     .map_err(|_| FieldError::InvalidParams("files".to_string()))
 }
 
+/// Writes the uploaded file under `usr/uploads/{year}/{month}` and creates its attachment.
+pub async fn save_attachment<S, E>(
+    state: &AppState,
+    uid: u32,
+    file_name: String,
+    mime: String,
+    stream: S,
+) -> Result<(u32, AttachmentText), FieldError>
+where
+    S: Stream<Item = Result<Bytes, E>>,
+    E: Into<BoxError>,
+{
+    let now = Local::now();
+    let dot_pos = match file_name.find(".") {
+        Some(f) => f,
+        None => return Err(FieldError::InvalidParams("file".to_string())),
+    };
+    let ext = file_name[dot_pos + 1..].to_string();
+
+    let rand_name: u64 = rand::thread_rng().gen_range(1_000_000_000..9_999_999_999);
+    let name = format!("{rand_name}.{ext}");
+
+    let filedir = format!("usr/uploads/{}/{}", now.year(), now.month());
+    let base_dir = std::path::Path::new(&state.upload_root).join(&filedir);
+    let size = stream_to_file(base_dir, &name, stream).await?;
+
+    let path = format!("/{filedir}/{name}");
+    let text = AttachmentText {
+        name: file_name,
+        path,
+        size,
+        r#type: ext,
+        mime,
+    };
+    let attachment_text = match to_string(&text) {
+        Ok(t) => t,
+        Err(_) => return Err(FieldError::InvalidParams("file".to_string())),
+    };
+    let now_timestamp = now.timestamp() as u32;
+
+    let attachment =
+        db::create_attachment_with_params(state, &text.name, now_timestamp, &attachment_text, uid)
+            .await?;
+    Ok((attachment.cid.unwrap(), text))
+}
+
 pub async fn delete_file(base_dir: PathBuf, filepath: &str) -> bool {
     let filepath = filepath.strip_prefix("/");
     if filepath.is_none() {
@@ -64,3 +117,10 @@ pub async fn delete_file(base_dir: PathBuf, filepath: &str) -> bool {
     let _ = remove_file(path).await;
     true
 }
+
+pub async fn delete_attachment_files(base_dir: PathBuf, attachments: Vec<content::Model>) {
+    for at in attachments {
+        let info = AttachmentInfo::from(at);
+        let _ = delete_file(base_dir.clone(), &info.path).await;
+    }
+}
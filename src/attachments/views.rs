@@ -12,10 +12,11 @@ use super::de::from_str;
 use super::forms::{AttachmentCreate, AttachmentsQuery};
 use super::models::{AttachmentInfo, AttachmentText};
 use super::ser::to_string;
-use super::utils::{delete_file, stream_to_file};
+use super::utils::{delete_file, save_attachment, stream_to_file};
 use crate::common::db as common_db;
 use crate::common::errors::FieldError;
 use crate::common::extractors::{PMContributor, ValidatedJson, ValidatedQuery};
+use crate::trash::db as trash_db;
 use crate::AppState;
 
 pub async fn list_attachments(
@@ -57,7 +58,6 @@ pub async fn create_attachment(
     PMContributor(user): PMContributor,
     mut multipart: Multipart,
 ) -> Result<(StatusCode, Json<Value>), FieldError> {
-    let now = Local::now();
     let field = match multipart.next_field().await {
         Ok(Some(f)) => f,
         _ => return Err(FieldError::InvalidParams("file".to_string())),
@@ -70,41 +70,8 @@ pub async fn create_attachment(
         Some(f) => f.to_string(),
         None => return Err(FieldError::InvalidParams("file".to_string())),
     };
-    let dot_pos = match file_name.find(".") {
-        Some(f) => f,
-        None => return Err(FieldError::InvalidParams("file".to_string())),
-    };
-    let ext = (&file_name[dot_pos + 1..]).to_string();
-
-    let rand_name: u64 = rand::thread_rng().gen_range(1_000_000_000..9_999_999_999);
-    let name = format!("{rand_name}.{ext}");
-
-    let filedir = format!("usr/uploads/{}/{}", now.year(), now.month());
-    let base_dir = std::path::Path::new(&state.upload_root).join(&filedir);
-    let size = stream_to_file(base_dir, &name, field).await?;
 
-    let path = format!("/{filedir}/{name}");
-    let text = AttachmentText {
-        name: file_name,
-        path,
-        size,
-        r#type: ext,
-        mime: content_type,
-    };
-    let attachment_text = match to_string(&text) {
-        Ok(t) => t,
-        Err(_) => return Err(FieldError::InvalidParams("file".to_string())),
-    };
-    let now_timestamp = now.timestamp() as u32;
-
-    let _ = db::create_attachment_with_params(
-        &state,
-        &text.name,
-        now_timestamp,
-        &attachment_text,
-        user.uid,
-    )
-    .await?;
+    let _ = save_attachment(&state, user.uid, file_name, content_type, field).await?;
     Ok((StatusCode::CREATED, Json(json!({"msg":"ok"}))))
 }
 
@@ -114,7 +81,7 @@ pub async fn get_attachment_by_cid(
     Path(cid): Path<u32>,
 ) -> Result<Json<Value>, FieldError> {
     let attachment = match common_db::get_content_by_cid(&state, cid).await {
-        Ok(Some(a)) => a,
+        Ok(Some(a)) if a.status != "trash" => a,
         _ => return Err(FieldError::NotFound("cid".to_string())),
     };
 
@@ -206,7 +173,7 @@ pub async fn delete_attachment_by_cid(
     Path(cid): Path<u32>,
 ) -> Result<Json<Value>, FieldError> {
     let attachment = match common_db::get_content_by_cid(&state, cid).await {
-        Ok(Some(a)) => a,
+        Ok(Some(a)) if a.status != "trash" => a,
         _ => return Err(FieldError::NotFound("cid".to_string())),
     };
 
@@ -215,15 +182,7 @@ pub async fn delete_attachment_by_cid(
         return Err(FieldError::PermissionDeny);
     }
 
-    let text = attachment.text.unwrap_or("".to_string());
-    let text = from_str::<AttachmentText>(&text)
-        .map_err(|_| FieldError::DatabaseFailed("attachment decode error".to_string()))?;
-
-    let base_dir = std::path::Path::new(&state.upload_root);
-    let filepath = text.path;
-    let _ = delete_file(base_dir.to_path_buf(), &filepath).await;
-
-    let _ = common_db::delete_content_by_cid(&state, cid).await?;
+    let _ = trash_db::move_content_to_trash_with_uid(&state, &attachment, user.uid, false).await?;
     Ok(Json(json!({ "msg": "ok" })))
 }
 
@@ -1,9 +1,14 @@
+use sea_orm::sea_query::Expr;
 use sea_orm::*;
 
 use super::forms::CategoryCreate;
+use super::utils::get_descendant_mids;
 use crate::common::db as common_db;
 use crate::common::errors::FieldError;
-use crate::entity::meta;
+use crate::common::utils::get_now;
+use crate::entity::{
+    content, meta, meta::Entity as Meta, relationship, relationship::Entity as Relationship,
+};
 use crate::AppState;
 
 pub async fn create_category_by_category_create(
@@ -11,8 +16,8 @@ pub async fn create_category_by_category_create(
     category_create: &CategoryCreate,
 ) -> Result<meta::ActiveModel, FieldError> {
     let category_parent = match category_create.parent {
-        Some(mid) => match common_db::get_meta_by_mid(&state, mid).await {
-            Ok(Some(_)) => mid,
+        Some(mid) if mid > 0 => match common_db::get_meta_by_mid(state, mid).await {
+            Ok(Some(m)) if m.r#type == "category" => mid,
             _ => return Err(FieldError::InvalidParams("parent".to_string())),
         },
         _ => 0,
@@ -37,10 +42,17 @@ pub async fn modify_category_by_mid_and_category_modify(
     category_modify: &CategoryCreate,
 ) -> Result<meta::Model, FieldError> {
     let category_parent = match category_modify.parent {
-        Some(mid) => match common_db::get_meta_by_mid(&state, mid).await {
-            Ok(Some(_)) => mid,
-            _ => return Err(FieldError::InvalidParams("parent".to_string())),
-        },
+        Some(parent) if parent > 0 => {
+            // a category can not be moved below itself or one of its descendants
+            let categories = get_categories(state).await?;
+            if parent == mid
+                || !categories.iter().any(|c| c.mid == parent)
+                || get_descendant_mids(&categories, mid).contains(&parent)
+            {
+                return Err(FieldError::InvalidParams("parent".to_string()));
+            }
+            parent
+        }
         _ => 0,
     };
 
@@ -58,3 +70,63 @@ pub async fn modify_category_by_mid_and_category_modify(
         .await
         .map_err(|_| FieldError::DatabaseFailed("modify category failed".to_string()))
 }
+
+pub async fn get_categories(state: &AppState) -> Result<Vec<meta::Model>, FieldError> {
+    Meta::find()
+        .filter(meta::Column::Type.eq("category"))
+        .order_by_asc(meta::Column::Order)
+        .order_by_asc(meta::Column::Mid)
+        .all(&state.conn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("fetch categories failed".to_string()))
+}
+
+pub async fn get_published_posts_relationships_by_mids(
+    state: &AppState,
+    mids: Vec<u32>,
+) -> Result<Vec<(u32, u32)>, FieldError> {
+    let relationships = Relationship::find()
+        .join(JoinType::InnerJoin, relationship::Relation::Content.def())
+        .filter(relationship::Column::Mid.is_in(mids))
+        .filter(content::Column::Type.eq("post"))
+        .filter(content::Column::Status.eq("publish"))
+        .filter(content::Column::Created.lte(get_now()))
+        .all(&state.conn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("fetch relationships failed".to_string()))?;
+    Ok(relationships.into_iter().map(|r| (r.mid, r.cid)).collect())
+}
+
+/// Same as typecho, children of a deleted category are moved to its parent.
+pub async fn delete_category_with_relationships_by_exist_category(
+    state: &AppState,
+    exist_category: &meta::Model,
+) -> Result<(), FieldError> {
+    let txn = state
+        .conn
+        .begin()
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("begin transaction failed".to_string()))?;
+
+    Meta::update_many()
+        .col_expr(meta::Column::Parent, Expr::value(exist_category.parent))
+        .filter(meta::Column::Type.eq("category"))
+        .filter(meta::Column::Parent.eq(exist_category.mid))
+        .exec(&txn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("update categories failed".to_string()))?;
+    Relationship::delete_many()
+        .filter(relationship::Column::Mid.eq(exist_category.mid))
+        .exec(&txn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("delete relationships failed".to_string()))?;
+    Meta::delete_many()
+        .filter(meta::Column::Mid.eq(exist_category.mid))
+        .exec(&txn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("delete category failed".to_string()))?;
+
+    txn.commit()
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("commit transaction failed".to_string()))
+}
@@ -19,3 +19,19 @@ pub struct CategoryPostAdd {
     #[validate(length(min = 1, max = 150, message = "slug can not be longer than 150"))]
     pub slug: String,
 }
+
+#[derive(Serialize, Deserialize, Validate)]
+pub struct CategoryPostsQuery {
+    #[validate(range(min = 1, message = "page must greater than 1"))]
+    pub page: Option<u64>,
+    #[validate(range(min = 1, message = "page_size must greater than 1"))]
+    pub page_size: Option<u64>,
+    #[validate(length(min = 1, max = 13, message = "order_by length must greater than 1"))]
+    pub order_by: Option<String>,
+    pub private: Option<bool>,
+    pub descendants: Option<bool>,
+    #[validate(length(min = 1, max = 8, message = "format length must greater than 1"))]
+    pub format: Option<String>,
+    #[validate(length(min = 1, max = 8, message = "fields length must greater than 1"))]
+    pub fields: Option<String>,
+}
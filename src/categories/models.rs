@@ -0,0 +1,11 @@
+use serde::Serialize;
+
+use crate::entity::meta;
+
+#[derive(Serialize)]
+pub struct CategoryTree {
+    #[serde(flatten)]
+    pub category: meta::Model,
+    pub posts_count: usize,
+    pub children: Vec<CategoryTree>,
+}
@@ -10,6 +10,7 @@ use crate::AppState;
 pub fn categories_routers(ro: bool) -> Router<Arc<AppState>> {
     let categories_route = Router::new()
         .route("/api/categories/", get(views::list_categories))
+        .route("/api/categories/tree", get(views::list_categories_tree))
         .route("/api/categories/:slug", get(views::get_category_by_slug))
         .route(
             "/api/categories/:slug/posts/",
@@ -0,0 +1,100 @@
+use std::collections::{HashMap, HashSet};
+
+use super::models::CategoryTree;
+use crate::entity::meta;
+
+/// Collects every category below `mid`, cycles left by older versions are tolerated.
+pub fn get_descendant_mids(categories: &[meta::Model], mid: u32) -> Vec<u32> {
+    let mut res = vec![];
+    let mut visited = HashSet::from([mid]);
+    let mut queue = vec![mid];
+    while let Some(parent) = queue.pop() {
+        for c in categories.iter().filter(|c| c.parent == parent) {
+            if visited.insert(c.mid) {
+                res.push(c.mid);
+                queue.push(c.mid);
+            }
+        }
+    }
+    res
+}
+
+fn build_category_node(
+    category: meta::Model,
+    children: &mut HashMap<u32, Vec<meta::Model>>,
+    posts: &HashMap<u32, HashSet<u32>>,
+) -> (CategoryTree, HashSet<u32>) {
+    let mut cids = posts.get(&category.mid).cloned().unwrap_or_default();
+    let mut nodes = vec![];
+    for child in children.remove(&category.mid).unwrap_or_default() {
+        let (node, child_cids) = build_category_node(child, children, posts);
+        cids.extend(child_cids);
+        nodes.push(node);
+    }
+
+    let node = CategoryTree {
+        category,
+        posts_count: cids.len(),
+        children: nodes,
+    };
+    (node, cids)
+}
+
+/// Nests categories under their parents, `posts_count` counts each post once per subtree.
+pub fn build_category_tree(
+    categories: Vec<meta::Model>,
+    relationships: Vec<(u32, u32)>,
+) -> Vec<CategoryTree> {
+    let mut posts: HashMap<u32, HashSet<u32>> = HashMap::new();
+    for (mid, cid) in relationships {
+        posts.entry(mid).or_default().insert(cid);
+    }
+
+    let mids: HashSet<u32> = categories.iter().map(|c| c.mid).collect();
+    let mut roots = vec![];
+    let mut children: HashMap<u32, Vec<meta::Model>> = HashMap::new();
+    for c in categories {
+        if c.parent == 0 || !mids.contains(&c.parent) {
+            roots.push(c);
+        } else {
+            children.entry(c.parent).or_default().push(c);
+        }
+    }
+
+    roots
+        .into_iter()
+        .map(|c| build_category_node(c, &mut children, &posts).0)
+        .collect()
+}
+
+#[test]
+fn test_build_category_tree() {
+    let category = |mid: u32, parent: u32| meta::Model {
+        mid,
+        name: Some(format!("category{}", mid)),
+        slug: Some(format!("category-{}", mid)),
+        r#type: "category".to_string(),
+        description: None,
+        count: 0,
+        order: 0,
+        parent,
+    };
+    let categories = vec![
+        category(1, 0),
+        category(2, 1),
+        category(3, 2),
+        category(4, 0),
+    ];
+
+    let mut descendants = get_descendant_mids(&categories, 1);
+    descendants.sort();
+    assert_eq!(vec![2, 3], descendants);
+    assert!(get_descendant_mids(&categories, 3).is_empty());
+
+    let tree = build_category_tree(categories, vec![(1, 10), (2, 10), (3, 11), (4, 12)]);
+    assert_eq!(2, tree.len());
+    assert_eq!(2, tree[0].posts_count);
+    assert_eq!(2, tree[0].children[0].posts_count);
+    assert_eq!(3, tree[0].children[0].children[0].category.mid);
+    assert_eq!(1, tree[1].posts_count);
+}
@@ -6,12 +6,14 @@ use axum::response::Json;
 use serde_json::{json, Value};
 
 use super::db;
-use super::forms::{CategoryCreate, CategoryPostAdd};
-use crate::common::db as common_db;
+use super::forms::{CategoryCreate, CategoryPostAdd, CategoryPostsQuery};
+use super::utils::{build_category_tree, get_descendant_mids};
+use crate::common::db::{self as common_db, ContentsPage};
 use crate::common::errors::FieldError;
 use crate::common::extractors::{PMEditor, PMVisitor, ValidatedJson, ValidatedQuery};
 use crate::common::forms::ListQuery;
-use crate::posts::forms::PostsQuery;
+use crate::common::markdown::is_html_format;
+use crate::posts::utils::{get_posts_results, is_summary_fields};
 use crate::AppState;
 
 pub async fn create_category(
@@ -49,6 +51,20 @@ pub async fn list_categories(
     })))
 }
 
+pub async fn list_categories_tree(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<Value>, FieldError> {
+    let categories = db::get_categories(&state).await?;
+    let mids = categories.iter().map(|c| c.mid).collect();
+    let relationships = db::get_published_posts_relationships_by_mids(&state, mids).await?;
+
+    let tree = build_category_tree(categories, relationships);
+    Ok(Json(json!({
+        "count": tree.len(),
+        "results": tree
+    })))
+}
+
 pub async fn get_category_by_slug(
     State(state): State<Arc<AppState>>,
     Path(slug): Path<String>,
@@ -93,8 +109,7 @@ pub async fn delete_category_by_slug(
         _ => return Err(FieldError::InvalidParams("slug".to_owned())),
     };
 
-    let _ = common_db::delete_relationships_by_mid(&state, exist_cate.mid).await?;
-    let _ = common_db::delete_meta_by_mid(&state, exist_cate.mid).await?;
+    db::delete_category_with_relationships_by_exist_category(&state, &exist_cate).await?;
     Ok(Json(json!({ "msg": "ok" })))
 }
 
@@ -118,7 +133,7 @@ pub async fn add_post_to_category(
 
     if !exist {
         let _ = common_db::create_relationship_by_cid_and_mid(&state, cid, mid).await?;
-        let _ = common_db::update_meta_by_mid_for_increase_count(&state, mid).await?;
+        common_db::update_metas_count_by_mids(&state.conn, &[mid]).await?;
         Ok((StatusCode::CREATED, Json(json!({"msg": "ok"}))))
     } else {
         Err(FieldError::AlreadyExist("slug".to_string()))
@@ -144,7 +159,7 @@ pub async fn delete_post_from_category(
 
     if exist {
         let _ = common_db::delete_relationship_by_cid_and_mid(&state, cid, mid).await?;
-        let _ = common_db::update_meta_by_mid_for_decrease_count(&state, mid).await?;
+        common_db::update_metas_count_by_mids(&state.conn, &[mid]).await?;
         Ok(Json(json!({"msg": "ok"})))
     } else {
         Err(FieldError::AlreadyExist("slug".to_string()))
@@ -155,34 +170,45 @@ pub async fn list_category_posts_by_slug(
     State(state): State<Arc<AppState>>,
     PMVisitor(user): PMVisitor,
     Path(slug): Path<String>,
-    ValidatedQuery(q): ValidatedQuery<PostsQuery>,
+    ValidatedQuery(q): ValidatedQuery<CategoryPostsQuery>,
 ) -> Result<Json<Value>, FieldError> {
     let mid = match common_db::get_meta_by_slug(&state, &slug, false).await {
         Ok(Some(category)) => category.mid,
         _ => return Err(FieldError::InvalidParams("slug".to_string())),
     };
+    let mut mids = vec![mid];
+    if q.descendants.unwrap_or(false) {
+        let categories = db::get_categories(&state).await?;
+        mids.extend(get_descendant_mids(&categories, mid));
+    }
 
     let private =
         q.private.unwrap_or(false) && (user.group == "editor" || user.group == "administrator");
+    let html = is_html_format(q.format.as_deref())?;
+    let summary = is_summary_fields(q.fields.as_deref())?;
 
     let all_count =
-        common_db::get_meta_posts_count_by_mid_with_private(&state, mid, private).await;
+        common_db::get_meta_posts_count_by_mids_with_private(&state, &mids, private).await;
 
     let page = q.page.unwrap_or(1);
     let page_size = q.page_size.unwrap_or(10);
     let order_by = q.order_by.unwrap_or("-cid".to_string());
 
-    let posts = common_db::get_contents_with_metas_user_and_fields_by_mid_list_query_and_private(
-        &state,
-        mid,
-        private,
-        &user,
+    let contents_page = ContentsPage {
         page_size,
         page,
-        &order_by,
+        order_by: &order_by,
+    };
+    let posts = common_db::get_contents_with_metas_user_and_fields_by_mids_list_query_and_private(
+        &state,
+        &mids,
+        private,
+        &contents_page,
         true,
+        false,
     )
     .await?;
+    let posts = get_posts_results(posts, html, summary, state.excerpt_length);
     Ok(Json(json!({
         "page": page,
         "page_size": page_size,
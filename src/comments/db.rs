@@ -2,6 +2,7 @@ use std::time::SystemTime;
 
 use sea_orm::*;
 
+use super::utils::get_comment_retry_after;
 use crate::common::db as common_db;
 use crate::common::errors::FieldError;
 use crate::entity::{comment, comment::Entity as Comment, content};
@@ -31,13 +32,19 @@ pub async fn create_comment_with_params(
     text: &str,
     status: &str,
     parent: u32,
-) -> Result<comment::ActiveModel, FieldError> {
+    interval: Option<u64>,
+) -> Result<comment::Model, FieldError> {
     let now = SystemTime::now()
         .duration_since(SystemTime::UNIX_EPOCH)
         .unwrap()
         .as_secs();
 
-    comment::ActiveModel {
+    let txn = state
+        .conn
+        .begin()
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("begin transaction failed".to_string()))?;
+    let comment = comment::ActiveModel {
         cid: Set(cid),
         created: Set(now as u32),
         author: Set(Some(author.to_owned())),
@@ -52,11 +59,77 @@ pub async fn create_comment_with_params(
         parent: Set(parent),
         ..Default::default()
     }
-    .save(&state.conn)
+    .insert(&txn)
+    .await
+    .map_err(|_| FieldError::DatabaseFailed("insert comment failed".to_string()))?;
+
+    // the flood limit is checked after the insert, the write serializes parallel requests on
+    // SQLite and the locking read waits for the other uncommitted insert elsewhere
+    if let Some(interval) = interval {
+        let last = get_latest_comment_created_by_ip_or_mail(&txn, ip, mail, comment.coid).await?;
+        if let Some(retry_after) = get_comment_retry_after(last, now, interval) {
+            return Err(FieldError::TooManyRequests(retry_after));
+        }
+    }
+
+    txn.commit()
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("commit transaction failed".to_string()))?;
+    Ok(comment)
+}
+
+pub async fn create_ping_with_params(
+    state: &AppState,
+    cid: u32,
+    owner_id: u32,
+    ping_type: &str,
+    author: &str,
+    url: &str,
+    ip: &str,
+    ua: &str,
+    text: &str,
+    status: &str,
+) -> Result<comment::Model, FieldError> {
+    let now = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+
+    comment::ActiveModel {
+        cid: Set(cid),
+        created: Set(now as u32),
+        author: Set(Some(author.to_owned())),
+        author_id: Set(0),
+        owner_id: Set(owner_id),
+        url: Set(Some(url.to_owned())),
+        ip: Set(Some(ip.to_owned())),
+        agent: Set(Some(ua.to_owned())),
+        text: Set(Some(text.to_owned())),
+        r#type: Set(ping_type.to_owned()),
+        status: Set(status.to_owned()),
+        parent: Set(0),
+        ..Default::default()
+    }
+    .insert(&state.conn)
     .await
     .map_err(|_| FieldError::DatabaseFailed("insert comment failed".to_string()))
 }
 
+pub async fn check_ping_by_cid_and_url(
+    state: &AppState,
+    cid: u32,
+    url: &str,
+) -> Result<bool, FieldError> {
+    let count = Comment::find()
+        .filter(comment::Column::Cid.eq(cid))
+        .filter(comment::Column::Url.eq(url))
+        .filter(comment::Column::Type.is_in(["pingback", "trackback", "webmention"]))
+        .count(&state.conn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("fetch comments failed".to_string()))?;
+    Ok(count > 0)
+}
+
 pub async fn update_content_count_increase_by_cid(
     state: &AppState,
     cid: u32,
@@ -85,12 +158,48 @@ pub async fn update_content_count_decrease_by_cid(
 
     let count = exist_content.comments_num;
     let mut c = content::ActiveModel::from(exist_content);
-    c.comments_num = Set(count - 1);
+    c.comments_num = Set(count.saturating_sub(1));
     c.update(&state.conn)
         .await
         .map_err(|_| FieldError::DatabaseFailed("update content failed".to_string()))
 }
 
+pub async fn check_approved_comment_by_author_and_mail(
+    state: &AppState,
+    author: &str,
+    mail: &str,
+) -> Result<bool, FieldError> {
+    let count = Comment::find()
+        .filter(comment::Column::Author.eq(author))
+        .filter(comment::Column::Mail.eq(mail))
+        .filter(comment::Column::Status.eq("approved"))
+        .count(&state.conn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("fetch comments failed".to_string()))?;
+    Ok(count > 0)
+}
+
+async fn get_latest_comment_created_by_ip_or_mail<C: ConnectionTrait>(
+    conn: &C,
+    ip: &str,
+    mail: &str,
+    exclude_coid: u32,
+) -> Result<Option<u32>, FieldError> {
+    let mut cond = Condition::any().add(comment::Column::Ip.eq(ip));
+    if !mail.is_empty() {
+        cond = cond.add(comment::Column::Mail.eq(mail));
+    }
+    let comment = Comment::find()
+        .filter(cond)
+        .filter(comment::Column::Coid.ne(exclude_coid))
+        .order_by_desc(comment::Column::Created)
+        .lock_exclusive()
+        .one(conn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("fetch comments failed".to_string()))?;
+    Ok(comment.map(|c| c.created))
+}
+
 pub async fn modify_comment_with_params(
     state: &AppState,
     coid: u32,
@@ -110,32 +219,25 @@ pub async fn modify_comment_with_params(
         .map_err(|_| FieldError::DatabaseFailed("update comment failed".to_string()))
 }
 
-pub async fn delete_comment_by_coid(
-    state: &AppState,
-    coid: u32,
-) -> Result<DeleteResult, FieldError> {
-    let exist_comment = match get_comment_by_coid(state, coid).await? {
-        Some(c) => c,
-        None => return Err(FieldError::InvalidParams("coid".to_string())),
+pub async fn get_comments_count_by_status(state: &AppState, status: Option<&str>) -> u64 {
+    let stmt = match status {
+        Some(s) => Comment::find().filter(comment::Column::Status.eq(s)),
+        None => Comment::find().filter(comment::Column::Status.ne("trash")),
     };
-
-    exist_comment
-        .delete(&state.conn)
-        .await
-        .map_err(|_| FieldError::DatabaseFailed("delete comment failed".to_string()))
-}
-
-pub async fn get_comments_count(state: &AppState) -> u64 {
-    Comment::find().count(&state.conn).await.unwrap_or(0)
+    stmt.count(&state.conn).await.unwrap_or(0)
 }
 
-pub async fn get_comments_by_list_query(
+pub async fn get_comments_by_list_query_and_status(
     state: &AppState,
     page_size: u64,
     page: u64,
     order_by: &str,
+    status: Option<&str>,
 ) -> Result<Vec<comment::Model>, FieldError> {
-    let stmt = Comment::find();
+    let stmt = match status {
+        Some(s) => Comment::find().filter(comment::Column::Status.eq(s)),
+        None => Comment::find().filter(comment::Column::Status.ne("trash")),
+    };
 
     let stmt = match order_by {
         "-coid" => stmt.order_by_desc(comment::Column::Coid),
@@ -146,7 +248,7 @@ pub async fn get_comments_by_list_query(
     };
     let paginator = stmt.paginate(&state.conn, page_size);
     paginator
-        .fetch_page(page)
+        .fetch_page(page - 1)
         .await
         .map_err(|_| FieldError::DatabaseFailed("get comments by list query failed".to_string()))
 }
@@ -159,7 +261,7 @@ pub async fn get_content_comments_count_by_cid_with_private(
     let stmt = Comment::find().filter(comment::Column::Cid.eq(cid));
 
     let stmt = if private {
-        stmt
+        stmt.filter(comment::Column::Status.ne("trash"))
     } else {
         stmt.filter(comment::Column::Status.eq("approved"))
     };
@@ -178,7 +280,7 @@ pub async fn get_comments_by_cid_and_list_query_with_private(
     let stmt = Comment::find().filter(comment::Column::Cid.eq(cid));
 
     let stmt = if private {
-        stmt
+        stmt.filter(comment::Column::Status.ne("trash"))
     } else {
         stmt.filter(comment::Column::Status.eq("approved"))
     };
@@ -196,3 +298,104 @@ pub async fn get_comments_by_cid_and_list_query_with_private(
         .await
         .map_err(|_| FieldError::DatabaseFailed("get comments by list query failed".to_string()))
 }
+
+pub async fn get_content_top_comments_count_by_cid_with_private(
+    state: &AppState,
+    cid: u32,
+    private: bool,
+) -> u64 {
+    let stmt = Comment::find()
+        .filter(comment::Column::Cid.eq(cid))
+        .filter(comment::Column::Parent.eq(0));
+
+    let stmt = if private {
+        stmt.filter(comment::Column::Status.ne("trash"))
+    } else {
+        stmt.filter(comment::Column::Status.eq("approved"))
+    };
+
+    stmt.count(&state.conn).await.unwrap_or(0)
+}
+
+pub async fn get_top_comments_by_cid_and_list_query_with_private(
+    state: &AppState,
+    cid: u32,
+    private: bool,
+    page_size: u64,
+    page: u64,
+    order_by: &str,
+) -> Result<Vec<comment::Model>, FieldError> {
+    let stmt = Comment::find()
+        .filter(comment::Column::Cid.eq(cid))
+        .filter(comment::Column::Parent.eq(0));
+
+    let stmt = if private {
+        stmt.filter(comment::Column::Status.ne("trash"))
+    } else {
+        stmt.filter(comment::Column::Status.eq("approved"))
+    };
+
+    let stmt = match order_by {
+        "-created" => stmt
+            .order_by_desc(comment::Column::Created)
+            .order_by_desc(comment::Column::Coid),
+        _ => stmt
+            .order_by_asc(comment::Column::Created)
+            .order_by_asc(comment::Column::Coid),
+    };
+    let paginator = stmt.paginate(&state.conn, page_size);
+    paginator
+        .fetch_page(page - 1)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("get comments by list query failed".to_string()))
+}
+
+pub async fn get_replies_by_cid_with_private(
+    state: &AppState,
+    cid: u32,
+    private: bool,
+    order_by: &str,
+) -> Result<Vec<comment::Model>, FieldError> {
+    let stmt = Comment::find()
+        .filter(comment::Column::Cid.eq(cid))
+        .filter(comment::Column::Parent.ne(0));
+
+    let stmt = if private {
+        stmt.filter(comment::Column::Status.ne("trash"))
+    } else {
+        stmt.filter(comment::Column::Status.eq("approved"))
+    };
+
+    let stmt = match order_by {
+        "-created" => stmt
+            .order_by_desc(comment::Column::Created)
+            .order_by_desc(comment::Column::Coid),
+        _ => stmt
+            .order_by_asc(comment::Column::Created)
+            .order_by_asc(comment::Column::Coid),
+    };
+    stmt.all(&state.conn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("get replies failed".to_string()))
+}
+
+/// Level of `coid` in its thread, top-level comments are level 1, stops counting at `max_levels`.
+pub async fn get_comment_level_by_coid(
+    state: &AppState,
+    coid: u32,
+    max_levels: u32,
+) -> Result<u32, FieldError> {
+    let mut level = 1;
+    let mut parent = match get_comment_by_coid(state, coid).await? {
+        Some(c) => c.parent,
+        None => return Err(FieldError::InvalidParams("parent".to_string())),
+    };
+    while parent != 0 && level < max_levels {
+        level += 1;
+        parent = match get_comment_by_coid(state, parent).await? {
+            Some(c) => c.parent,
+            None => break,
+        };
+    }
+    Ok(level)
+}
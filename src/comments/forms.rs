@@ -12,6 +12,8 @@ pub struct CommentCreate {
     pub text: String,
     #[validate(range(min = 0, message = "parent must greater than 0"))]
     pub parent: Option<u32>,
+    pub honeypot: Option<String>,
+    pub rendered_at: Option<u64>,
 }
 
 #[derive(Serialize, Deserialize, Validate)]
@@ -30,4 +32,18 @@ pub struct CommentsQuery {
     #[validate(length(min = 1, max = 13, message = "order_by length must greater than 1"))]
     pub order_by: Option<String>,
     pub private: Option<bool>,
+    pub tree: Option<bool>,
+    #[validate(length(min = 1, max = 16, message = "status can not be longer than 16"))]
+    pub status: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Validate)]
+pub struct TrackbackCreate {
+    #[validate(url)]
+    pub url: String,
+    #[validate(length(max = 150, message = "title can not be longer than 150"))]
+    pub title: Option<String>,
+    pub excerpt: Option<String>,
+    #[validate(length(max = 150, message = "blog_name can not be longer than 150"))]
+    pub blog_name: Option<String>,
 }
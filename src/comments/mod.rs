@@ -1,6 +1,10 @@
 mod forms;
+mod models;
+pub mod pings;
+mod spam;
 mod urls;
+mod utils;
 mod views;
-mod db;
+pub mod db;
 
 pub use urls::comments_routers;
\ No newline at end of file
@@ -0,0 +1,10 @@
+use serde::Serialize;
+
+use crate::entity::comment;
+
+#[derive(Serialize)]
+pub struct CommentTree {
+    #[serde(flatten)]
+    pub comment: comment::Model,
+    pub children: Vec<CommentTree>,
+}
@@ -0,0 +1,240 @@
+use regex::Regex;
+use std::collections::HashMap;
+use url::Url;
+
+use super::db;
+use super::utils::get_ping_status;
+use crate::common::db as common_db;
+use crate::common::errors::FieldError;
+use crate::common::utils::Permalinks;
+use crate::entity::content;
+use crate::AppState;
+
+const MAX_AUTHOR_LENGTH: usize = 150;
+const MAX_EXCERPT_LENGTH: usize = 255;
+
+lazy_static! {
+    static ref LINK_RE: Regex =
+        Regex::new(r#"(?is)<a\s[^>]*?href\s*=\s*["']([^"']+)["'][^>]*>(.*?)</a>"#).unwrap();
+    static ref TITLE_RE: Regex = Regex::new(r"(?is)<title[^>]*>(.*?)</title>").unwrap();
+    static ref TAG_RE: Regex = Regex::new(r"(?s)<[^>]*>").unwrap();
+}
+
+/// The failures of the pingback spec, trackback only reports the message.
+#[derive(Debug)]
+pub enum PingError {
+    SourceNotFound,
+    SourceNoLink,
+    TargetNotFound,
+    TargetNotAllowed,
+    AlreadyRegistered,
+    Failed,
+}
+
+impl PingError {
+    pub fn code(&self) -> i32 {
+        match self {
+            PingError::SourceNotFound => 16,
+            PingError::SourceNoLink => 17,
+            PingError::TargetNotFound => 32,
+            PingError::TargetNotAllowed => 33,
+            PingError::AlreadyRegistered => 48,
+            PingError::Failed => 0,
+        }
+    }
+
+    pub fn message(&self) -> &'static str {
+        match self {
+            PingError::SourceNotFound => "The source URL does not exist",
+            PingError::SourceNoLink => "The source URL does not contain a link to the target URL",
+            PingError::TargetNotFound => "The specified target URL does not exist",
+            PingError::TargetNotAllowed => "The specified target URL cannot be used as a target",
+            PingError::AlreadyRegistered => "The pingback has already been registered",
+            PingError::Failed => "Ping failed",
+        }
+    }
+}
+
+impl From<FieldError> for PingError {
+    fn from(_: FieldError) -> Self {
+        PingError::Failed
+    }
+}
+
+pub struct Ping<'a> {
+    pub ping_type: &'a str,
+    pub source: &'a str,
+    pub title: Option<&'a str>,
+    pub excerpt: Option<&'a str>,
+    pub blog_name: Option<&'a str>,
+    pub ip: &'a str,
+    pub ua: &'a str,
+}
+
+fn is_link_to(permalinks: &Permalinks, url: &str, r#type: &str, cid: u32, slug: &str) -> bool {
+    match permalinks.parse_content(url) {
+        Some((name, params)) => {
+            name == r#type
+                && (params.get("cid") == Some(&cid.to_string())
+                    || params.get("slug").map(|s| s.as_str()) == Some(slug))
+        }
+        None => false,
+    }
+}
+
+fn truncate(s: &str, max: usize) -> String {
+    s.chars().take(max).collect()
+}
+
+fn strip_tags(html: &str) -> String {
+    TAG_RE
+        .replace_all(html, "")
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Finds the anchor text of the first link in the source page pointing at the content.
+pub fn find_link_to(
+    permalinks: &Permalinks,
+    source: &str,
+    html: &str,
+    r#type: &str,
+    cid: u32,
+    slug: &str,
+) -> Option<String> {
+    let base = Url::parse(source).ok()?;
+    LINK_RE.captures_iter(html).find_map(|caps| {
+        let href = caps[1].replace("&amp;", "&");
+        let url = base.join(&href).ok()?;
+        if is_link_to(permalinks, url.as_str(), r#type, cid, slug) {
+            Some(strip_tags(&caps[2]))
+        } else {
+            None
+        }
+    })
+}
+
+pub async fn get_content_by_target_url(
+    state: &AppState,
+    options: &HashMap<String, String>,
+    target: &str,
+) -> Result<content::Model, PingError> {
+    let permalinks = Permalinks::from_options(options);
+    let (name, params) = permalinks
+        .parse_content(target)
+        .ok_or(PingError::TargetNotFound)?;
+    let content = if let Some(cid) = params.get("cid").and_then(|c| c.parse::<u32>().ok()) {
+        common_db::get_content_by_cid(state, cid).await?
+    } else if let Some(slug) = params.get("slug") {
+        common_db::get_content_by_slug(state, slug).await?
+    } else {
+        None
+    };
+    content
+        .filter(|c| c.r#type == name && c.status != "trash")
+        .ok_or(PingError::TargetNotFound)
+}
+
+/// Verifies the source page links to the content, then stores the ping as a comment.
+pub async fn create_ping(
+    state: &AppState,
+    options: &HashMap<String, String>,
+    content: &content::Model,
+    ping: Ping<'_>,
+) -> Result<&'static str, PingError> {
+    if content.status != "publish" || (content.r#type != "post" && content.r#type != "page") {
+        return Err(PingError::TargetNotFound);
+    }
+    if content.allow_ping != "1" {
+        return Err(PingError::TargetNotAllowed);
+    }
+    if db::check_ping_by_cid_and_url(state, content.cid, ping.source).await? {
+        return Err(PingError::AlreadyRegistered);
+    }
+
+    let source = Url::parse(ping.source).map_err(|_| PingError::SourceNotFound)?;
+    if source.scheme() != "http" && source.scheme() != "https" {
+        return Err(PingError::SourceNotFound);
+    }
+    let response = state
+        .http_client
+        .get(ping.source)
+        .await
+        .map_err(|_| PingError::SourceNotFound)?;
+    let html = response.body;
+    let permalinks = Permalinks::from_options(options);
+    let anchor = find_link_to(
+        &permalinks,
+        &response.url,
+        &html,
+        &content.r#type,
+        content.cid,
+        content.slug.as_deref().unwrap_or_default(),
+    )
+    .ok_or(PingError::SourceNoLink)?;
+
+    let title = TITLE_RE
+        .captures(&html)
+        .map(|caps| strip_tags(&caps[1]))
+        .filter(|t| !t.is_empty());
+    let author = ping
+        .blog_name
+        .or(ping.title)
+        .map(|s| s.to_string())
+        .or(title)
+        .unwrap_or(source.host_str().unwrap_or_default().to_string());
+    let text = match ping.excerpt {
+        Some(e) => truncate(e, MAX_EXCERPT_LENGTH),
+        None => format!("[...] {} [...]", anchor),
+    };
+
+    let status = get_ping_status(options);
+    db::create_ping_with_params(
+        state,
+        content.cid,
+        content.author_id,
+        ping.ping_type,
+        &truncate(&author, MAX_AUTHOR_LENGTH),
+        ping.source,
+        ping.ip,
+        ping.ua,
+        &text,
+        status,
+    )
+    .await?;
+    if status == "approved" {
+        db::update_content_count_increase_by_cid(state, content.cid).await?;
+    }
+    Ok(status)
+}
+
+#[test]
+fn test_find_link_to() {
+    let options = HashMap::from([("siteUrl".to_string(), "https://rumo.cf".to_string())]);
+    let permalinks = Permalinks::from_options(&options);
+    let html = r#"<p><a href="/archives/8/">other</a> and <a class="x" href="https://rumo.cf/archives/7/?a=1&amp;b=2"><b>this</b> post</a></p>"#;
+    assert_eq!(
+        Some("this post".to_string()),
+        find_link_to(
+            &permalinks,
+            "https://blog.local.host/a",
+            html,
+            "post",
+            7,
+            "hello"
+        )
+    );
+    let html = r#"<a href="/archives/7/">relative</a>"#;
+    assert_eq!(
+        None,
+        find_link_to(
+            &permalinks,
+            "https://blog.local.host/a",
+            html,
+            "post",
+            7,
+            "hello"
+        )
+    );
+}
@@ -0,0 +1,298 @@
+use regex::{Regex, RegexBuilder};
+use std::collections::HashMap;
+use std::net::IpAddr;
+
+/// Score of a check that is sure the comment is spam.
+pub const SPAM_SCORE_BLOCKED: u32 = 10;
+/// Score of a check that only finds the comment suspicious.
+pub const SPAM_SCORE_SUSPICIOUS: u32 = 5;
+pub const DEFAULT_SPAM_THRESHOLD: u32 = 5;
+
+pub struct SpamContext<'a> {
+    pub author: &'a str,
+    pub mail: &'a str,
+    pub url: Option<&'a str>,
+    pub text: &'a str,
+    pub ip: IpAddr,
+    pub honeypot: Option<&'a str>,
+    pub rendered_at: Option<u64>,
+    pub now: u64,
+}
+
+pub trait SpamCheck: Send + Sync {
+    fn score(&self, ctx: &SpamContext) -> u32;
+}
+
+/// Scores every pattern found in the author, mail, url or text.
+pub struct KeywordCheck {
+    patterns: Vec<Regex>,
+}
+
+impl KeywordCheck {
+    /// Lines that are not valid regex are matched literally.
+    pub fn new(lines: &str) -> Self {
+        let patterns = split_option_list(lines, &['\n'])
+            .filter_map(|p| {
+                RegexBuilder::new(p)
+                    .case_insensitive(true)
+                    .build()
+                    .or_else(|_| {
+                        RegexBuilder::new(&regex::escape(p))
+                            .case_insensitive(true)
+                            .build()
+                    })
+                    .ok()
+            })
+            .collect();
+        KeywordCheck { patterns }
+    }
+}
+
+impl SpamCheck for KeywordCheck {
+    fn score(&self, ctx: &SpamContext) -> u32 {
+        let fields = [ctx.author, ctx.mail, ctx.url.unwrap_or_default(), ctx.text];
+        self.patterns
+            .iter()
+            .filter(|p| fields.iter().any(|f| p.is_match(f)))
+            .count() as u32
+            * SPAM_SCORE_SUSPICIOUS
+    }
+}
+
+pub struct LinkCountCheck {
+    max_links: usize,
+}
+
+lazy_static! {
+    static ref LINK_RE: Regex = Regex::new(r"(?i)https?://").unwrap();
+}
+
+impl LinkCountCheck {
+    pub fn new(max_links: usize) -> Self {
+        LinkCountCheck { max_links }
+    }
+}
+
+impl SpamCheck for LinkCountCheck {
+    fn score(&self, ctx: &SpamContext) -> u32 {
+        if LINK_RE.find_iter(ctx.text).count() > self.max_links {
+            SPAM_SCORE_SUSPICIOUS
+        } else {
+            0
+        }
+    }
+}
+
+/// Blocks single addresses and CIDR ranges, both IPv4 and IPv6.
+pub struct IpBlockCheck {
+    ranges: Vec<(IpAddr, u8)>,
+}
+
+impl IpBlockCheck {
+    pub fn new(lines: &str) -> Self {
+        let ranges = split_option_list(lines, &['\n', ','])
+            .filter_map(parse_cidr)
+            .collect();
+        IpBlockCheck { ranges }
+    }
+}
+
+impl SpamCheck for IpBlockCheck {
+    fn score(&self, ctx: &SpamContext) -> u32 {
+        if self
+            .ranges
+            .iter()
+            .any(|(net, prefix)| is_ip_in_range(ctx.ip, *net, *prefix))
+        {
+            SPAM_SCORE_BLOCKED
+        } else {
+            0
+        }
+    }
+}
+
+/// The honeypot field is hidden from people, only bots fill it in.
+pub struct HoneypotCheck;
+
+impl SpamCheck for HoneypotCheck {
+    fn score(&self, ctx: &SpamContext) -> u32 {
+        match ctx.honeypot {
+            Some(h) if !h.is_empty() => SPAM_SCORE_BLOCKED,
+            _ => 0,
+        }
+    }
+}
+
+/// Comments submitted less than `min_seconds` after the form was rendered, or without the render time.
+pub struct SubmitTimeCheck {
+    min_seconds: u64,
+}
+
+impl SubmitTimeCheck {
+    pub fn new(min_seconds: u64) -> Self {
+        SubmitTimeCheck { min_seconds }
+    }
+}
+
+impl SpamCheck for SubmitTimeCheck {
+    fn score(&self, ctx: &SpamContext) -> u32 {
+        match ctx.rendered_at {
+            Some(t) if ctx.now.saturating_sub(t) >= self.min_seconds => 0,
+            _ => SPAM_SCORE_SUSPICIOUS,
+        }
+    }
+}
+
+pub struct SpamFilter {
+    checks: Vec<Box<dyn SpamCheck>>,
+    threshold: u32,
+}
+
+impl SpamFilter {
+    pub fn new(threshold: u32) -> Self {
+        SpamFilter {
+            checks: vec![],
+            threshold,
+        }
+    }
+
+    pub fn with_check(mut self, check: Box<dyn SpamCheck>) -> Self {
+        self.checks.push(check);
+        self
+    }
+
+    /// Builds the built-in checks configured by the `commentsSpam*` site options.
+    pub fn from_options(options: &HashMap<String, String>) -> Self {
+        let threshold = options
+            .get("commentsSpamThreshold")
+            .and_then(|s| s.parse::<u32>().ok())
+            .filter(|t| *t > 0)
+            .unwrap_or(DEFAULT_SPAM_THRESHOLD);
+        let mut filter = SpamFilter::new(threshold).with_check(Box::new(HoneypotCheck));
+
+        if let Some(keywords) = options.get("commentsSpamKeywords") {
+            filter = filter.with_check(Box::new(KeywordCheck::new(keywords)));
+        }
+        if let Some(max_links) = options
+            .get("commentsSpamMaxLinks")
+            .and_then(|s| s.parse::<usize>().ok())
+        {
+            filter = filter.with_check(Box::new(LinkCountCheck::new(max_links)));
+        }
+        if let Some(ips) = options.get("commentsSpamIpBlocklist") {
+            filter = filter.with_check(Box::new(IpBlockCheck::new(ips)));
+        }
+        if let Some(min_seconds) = options
+            .get("commentsSpamMinSubmitSeconds")
+            .and_then(|s| s.parse::<u64>().ok())
+            .filter(|s| *s > 0)
+        {
+            filter = filter.with_check(Box::new(SubmitTimeCheck::new(min_seconds)));
+        }
+        filter
+    }
+
+    pub fn score(&self, ctx: &SpamContext) -> u32 {
+        self.checks.iter().map(|c| c.score(ctx)).sum()
+    }
+
+    pub fn is_spam(&self, ctx: &SpamContext) -> bool {
+        self.score(ctx) >= self.threshold
+    }
+}
+
+fn split_option_list<'a>(value: &'a str, separators: &'a [char]) -> impl Iterator<Item = &'a str> {
+    value
+        .split(separators)
+        .map(|s| s.trim())
+        .filter(|s| !s.is_empty())
+}
+
+fn parse_cidr(value: &str) -> Option<(IpAddr, u8)> {
+    let (ip, prefix) = match value.split_once('/') {
+        Some((ip, prefix)) => (ip.parse::<IpAddr>().ok()?, Some(prefix.parse::<u8>().ok()?)),
+        None => (value.parse::<IpAddr>().ok()?, None),
+    };
+    let max = if ip.is_ipv4() { 32 } else { 128 };
+    match prefix {
+        Some(p) if p > max => None,
+        Some(p) => Some((ip, p)),
+        None => Some((ip, max)),
+    }
+}
+
+fn is_ip_in_range(ip: IpAddr, net: IpAddr, prefix: u8) -> bool {
+    match (ip, net) {
+        (IpAddr::V4(ip), IpAddr::V4(net)) => {
+            let mask = u32::MAX.checked_shl(32 - prefix as u32).unwrap_or(0);
+            u32::from(ip) & mask == u32::from(net) & mask
+        }
+        (IpAddr::V6(ip), IpAddr::V6(net)) => {
+            let mask = u128::MAX.checked_shl(128 - prefix as u32).unwrap_or(0);
+            u128::from(ip) & mask == u128::from(net) & mask
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+fn get_test_context<'a>(text: &'a str, ip: &str) -> SpamContext<'a> {
+    SpamContext {
+        author: "author",
+        mail: "author@local.host",
+        url: None,
+        text,
+        ip: ip.parse().unwrap(),
+        honeypot: None,
+        rendered_at: None,
+        now: 1000,
+    }
+}
+
+#[test]
+fn test_spam_filter() {
+    let mut options = HashMap::new();
+    let keywords = "casino\nche[a@]p pills";
+    options.insert("commentsSpamKeywords".to_string(), keywords.to_string());
+    options.insert("commentsSpamMaxLinks".to_string(), "1".to_string());
+    let ips = "10.1.0.0/16, 2001:db8::/32";
+    options.insert("commentsSpamIpBlocklist".to_string(), ips.to_string());
+    let filter = SpamFilter::from_options(&options);
+
+    assert!(!filter.is_spam(&get_test_context("see https://127.0.0.1", "10.2.0.1")));
+    assert!(filter.is_spam(&get_test_context("Ch3ap pills CASINO", "10.2.0.1")));
+    assert_eq!(
+        0,
+        filter.score(&get_test_context("Ch3ap pills", "10.2.0.1"))
+    );
+    assert!(filter.is_spam(&get_test_context("http://a https://b", "10.2.0.1")));
+    assert!(filter.is_spam(&get_test_context("text", "10.1.200.3")));
+    assert!(filter.is_spam(&get_test_context("text", "2001:db8::1")));
+
+    let ctx = get_test_context("text", "10.2.0.1");
+    assert!(filter.is_spam(&SpamContext {
+        honeypot: Some("x"),
+        ..ctx
+    }));
+}
+
+#[test]
+fn test_submit_time_check() {
+    let check = SubmitTimeCheck::new(5);
+    let ctx = get_test_context("text", "127.0.0.1");
+    assert_eq!(SPAM_SCORE_SUSPICIOUS, check.score(&ctx));
+    assert_eq!(
+        0,
+        check.score(&SpamContext {
+            rendered_at: Some(990),
+            ..ctx
+        })
+    );
+    assert_eq!(
+        SPAM_SCORE_SUSPICIOUS,
+        check.score(&SpamContext {
+            rendered_at: Some(998),
+            ..ctx
+        })
+    );
+}
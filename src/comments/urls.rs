@@ -24,6 +24,10 @@ pub fn comments_routers(ro: bool) -> Router<Arc<AppState>> {
         comments_route
             .route("/api/comments/:coid", patch(views::modify_comment_by_coid))
             .route("/api/comments/:coid", delete(views::delete_comment_by_coid))
+            .route(
+                "/api/comments/:coid/ham",
+                post(views::mark_comment_as_ham_by_coid),
+            )
             .route(
                 "/api/pages/:slug/comments/",
                 post(views::create_page_comment),
@@ -32,6 +36,8 @@ pub fn comments_routers(ro: bool) -> Router<Arc<AppState>> {
                 "/api/posts/:slug/comments/",
                 post(views::create_post_comment),
             )
+            .route("/api/pages/:slug/trackback", post(views::create_trackback))
+            .route("/api/posts/:slug/trackback", post(views::create_trackback))
     } else {
         comments_route
     }
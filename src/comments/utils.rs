@@ -0,0 +1,265 @@
+use md5::{Digest, Md5};
+use std::collections::HashMap;
+
+use super::models::CommentTree;
+use crate::common::errors::FieldError;
+use crate::entity::comment;
+
+fn is_option_enabled(options: &HashMap<String, String>, name: &str) -> bool {
+    options.get(name).map(|v| v == "1").unwrap_or(false)
+}
+
+/// With `commentsAutoClose` on, contents older than `commentsPostTimeout` seconds take no new comments.
+pub fn is_comment_closed(options: &HashMap<String, String>, created: u32, now: u64) -> bool {
+    if !is_option_enabled(options, "commentsAutoClose") {
+        return false;
+    }
+    let timeout = options
+        .get("commentsPostTimeout")
+        .and_then(|s| s.parse::<u64>().ok())
+        .unwrap_or(0);
+    timeout > 0 && now.saturating_sub(created as u64) > timeout
+}
+
+pub fn check_comment_required_fields(
+    options: &HashMap<String, String>,
+    mail: &str,
+    url: Option<&str>,
+) -> Result<(), FieldError> {
+    if is_option_enabled(options, "commentsRequireMail") && mail.is_empty() {
+        return Err(FieldError::InvalidParams("mail".to_string()));
+    }
+    if is_option_enabled(options, "commentsRequireURL") && url.unwrap_or_default().is_empty() {
+        return Err(FieldError::InvalidParams("url".to_string()));
+    }
+    Ok(())
+}
+
+pub fn is_whitelist_enabled(options: &HashMap<String, String>) -> bool {
+    is_option_enabled(options, "commentsWhitelist")
+}
+
+/// `trusted` authors are always approved. Same as typecho, with `commentsWhitelist` on only
+/// `whitelisted` authors, the ones with an approved comment, are approved, otherwise
+/// `commentsRequireModeration` holds every comment for review.
+pub fn get_comment_status(
+    options: &HashMap<String, String>,
+    trusted: bool,
+    whitelisted: bool,
+) -> &'static str {
+    let approved = if is_whitelist_enabled(options) {
+        whitelisted
+    } else {
+        !is_option_enabled(options, "commentsRequireModeration")
+    };
+    if trusted || approved {
+        "approved"
+    } else {
+        "waiting"
+    }
+}
+
+/// Pings are not written by an author, so only `commentsRequireModeration` applies.
+pub fn get_ping_status(options: &HashMap<String, String>) -> &'static str {
+    if is_option_enabled(options, "commentsRequireModeration") {
+        "waiting"
+    } else {
+        "approved"
+    }
+}
+
+/// `commentsPostInterval` in seconds, `None` when `commentsPostIntervalEnable` is off.
+pub fn get_comment_post_interval(options: &HashMap<String, String>) -> Option<u64> {
+    if !is_option_enabled(options, "commentsPostIntervalEnable") {
+        return None;
+    }
+    options
+        .get("commentsPostInterval")
+        .and_then(|s| s.parse::<u64>().ok())
+        .filter(|i| *i > 0)
+}
+
+/// Seconds left before another comment is allowed, `None` when it is allowed now.
+pub fn get_comment_retry_after(last: Option<u32>, now: u64, interval: u64) -> Option<u64> {
+    let elapsed = now.saturating_sub(last? as u64);
+    if elapsed < interval {
+        Some(interval - elapsed)
+    } else {
+        None
+    }
+}
+
+pub fn is_comments_threaded(options: &HashMap<String, String>) -> bool {
+    is_option_enabled(options, "commentsThreaded")
+}
+
+/// Top-level comments are level 1, `commentsMaxNestingLevels` is the deepest level a reply can reach.
+pub fn get_comments_max_nesting_levels(options: &HashMap<String, String>) -> u32 {
+    options
+        .get("commentsMaxNestingLevels")
+        .and_then(|s| s.parse::<u32>().ok())
+        .unwrap_or(5)
+        .max(1)
+}
+
+/// Maps `commentsOrder` to an `order_by` of the comments list query.
+pub fn get_comments_order_by(options: &HashMap<String, String>) -> &'static str {
+    match options.get("commentsOrder").map(|s| s.as_str()) {
+        Some("DESC") => "-created",
+        _ => "created",
+    }
+}
+
+pub fn hash_comments_mail(comments: Vec<comment::Model>) -> Vec<comment::Model> {
+    let mut hasher = Md5::new();
+    comments
+        .into_iter()
+        .map(|cm| {
+            let mail = cm.mail.map(|m| {
+                hasher.update(m.as_bytes());
+                format!("{:x}", hasher.finalize_reset())
+            });
+            comment::Model { mail, ..cm }
+        })
+        .collect()
+}
+
+fn collect_comment_descendants(
+    coid: u32,
+    replies: &mut HashMap<u32, Vec<comment::Model>>,
+    descendants: &mut Vec<comment::Model>,
+) {
+    for reply in replies.remove(&coid).unwrap_or_default() {
+        let coid = reply.coid;
+        descendants.push(reply);
+        collect_comment_descendants(coid, replies, descendants);
+    }
+}
+
+fn build_comment_node(
+    comment: comment::Model,
+    level: u32,
+    max_levels: u32,
+    desc: bool,
+    replies: &mut HashMap<u32, Vec<comment::Model>>,
+) -> CommentTree {
+    let children = if level >= max_levels {
+        vec![]
+    } else if level + 1 < max_levels {
+        replies
+            .remove(&comment.coid)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|reply| build_comment_node(reply, level + 1, max_levels, desc, replies))
+            .collect()
+    } else {
+        // replies nested deeper than the cap are flattened into the last level
+        let mut descendants = vec![];
+        collect_comment_descendants(comment.coid, replies, &mut descendants);
+        descendants.sort_by_key(|c| (c.created, c.coid));
+        if desc {
+            descendants.reverse();
+        }
+        descendants
+            .into_iter()
+            .map(|reply| CommentTree {
+                comment: reply,
+                children: vec![],
+            })
+            .collect()
+    };
+    CommentTree { comment, children }
+}
+
+/// Nests `replies`, already in display order, under the top-level `comments`.
+pub fn build_comment_tree(
+    comments: Vec<comment::Model>,
+    replies: Vec<comment::Model>,
+    max_levels: u32,
+    desc: bool,
+) -> Vec<CommentTree> {
+    let mut children: HashMap<u32, Vec<comment::Model>> = HashMap::new();
+    for reply in replies {
+        children.entry(reply.parent).or_default().push(reply);
+    }
+
+    comments
+        .into_iter()
+        .map(|c| build_comment_node(c, 1, max_levels, desc, &mut children))
+        .collect()
+}
+
+#[test]
+fn test_is_comment_closed() {
+    let mut options = HashMap::new();
+    options.insert("commentsPostTimeout".to_string(), "100".to_string());
+    assert!(!is_comment_closed(&options, 0, 1000));
+
+    options.insert("commentsAutoClose".to_string(), "1".to_string());
+    assert!(is_comment_closed(&options, 0, 1000));
+    assert!(!is_comment_closed(&options, 950, 1000));
+}
+
+#[test]
+fn test_get_comment_status() {
+    let mut options = HashMap::new();
+    assert_eq!("approved", get_comment_status(&options, false, false));
+
+    options.insert("commentsRequireModeration".to_string(), "1".to_string());
+    assert_eq!("waiting", get_comment_status(&options, false, false));
+    assert_eq!("approved", get_comment_status(&options, true, false));
+    assert!(!is_whitelist_enabled(&options));
+
+    options.insert("commentsRequireModeration".to_string(), "0".to_string());
+    options.insert("commentsWhitelist".to_string(), "1".to_string());
+    assert!(is_whitelist_enabled(&options));
+    assert_eq!("waiting", get_comment_status(&options, false, false));
+    assert_eq!("approved", get_comment_status(&options, false, true));
+    assert_eq!("approved", get_comment_status(&options, true, false));
+}
+
+#[test]
+fn test_get_comment_retry_after() {
+    let mut options = HashMap::new();
+    options.insert("commentsPostInterval".to_string(), "60".to_string());
+    assert_eq!(None, get_comment_post_interval(&options));
+
+    options.insert("commentsPostIntervalEnable".to_string(), "1".to_string());
+    assert_eq!(Some(60), get_comment_post_interval(&options));
+
+    assert_eq!(None, get_comment_retry_after(None, 1000, 60));
+    assert_eq!(Some(50), get_comment_retry_after(Some(990), 1000, 60));
+    assert_eq!(None, get_comment_retry_after(Some(940), 1000, 60));
+}
+
+#[test]
+fn test_build_comment_tree() {
+    let comment = |coid: u32, parent: u32| comment::Model {
+        coid,
+        cid: 1,
+        created: coid,
+        author: None,
+        author_id: 0,
+        owner_id: 1,
+        mail: None,
+        url: None,
+        ip: None,
+        agent: None,
+        text: None,
+        r#type: "comment".to_string(),
+        status: "approved".to_string(),
+        parent,
+    };
+    let replies = vec![comment(2, 1), comment(3, 2), comment(4, 3), comment(5, 1)];
+
+    let tree = build_comment_tree(vec![comment(1, 0)], replies.clone(), 5, false);
+    assert_eq!(2, tree[0].children.len());
+    assert_eq!(4, tree[0].children[0].children[0].children[0].comment.coid);
+
+    let tree = build_comment_tree(vec![comment(1, 0)], replies.clone(), 2, false);
+    let coids: Vec<u32> = tree[0].children.iter().map(|c| c.comment.coid).collect();
+    assert_eq!(vec![2, 3, 4, 5], coids);
+
+    let tree = build_comment_tree(vec![comment(1, 0)], replies, 1, false);
+    assert!(tree[0].children.is_empty());
+}
@@ -1,37 +1,50 @@
-use axum::extract::{Path, State};
-use axum::http::StatusCode;
-use axum::response::Json;
-use axum_client_ip::InsecureClientIp;
+use axum::extract::{rejection::FormRejection, Path, State};
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Json, Response};
+use axum::Form;
+use axum_client_ip::SecureClientIp;
 use axum_extra::{headers::UserAgent, TypedHeader};
-use md5::{Digest, Md5};
 use serde_json::{json, Value};
+use std::net::IpAddr;
 use std::sync::Arc;
+use std::time::SystemTime;
+use validator::Validate;
 
 use super::db;
-use super::forms::{CommentCreate, CommentModify, CommentsQuery};
+use super::forms::{CommentCreate, CommentModify, CommentsQuery, TrackbackCreate};
+use super::pings::{create_ping, Ping, PingError};
+use super::spam::{SpamContext, SpamFilter};
+use super::utils::{
+    build_comment_tree, check_comment_required_fields, get_comment_post_interval,
+    get_comment_status, get_comments_max_nesting_levels, get_comments_order_by, hash_comments_mail,
+    is_comment_closed, is_comments_threaded, is_whitelist_enabled,
+};
 use crate::common::db as common_db;
 use crate::common::errors::FieldError;
 use crate::common::extractors::{PMEditor, PMVisitor, ValidatedJson, ValidatedQuery};
-use crate::entity::comment;
+use crate::common::utils::escape_xml;
+use crate::entity::user::Model as User;
+use crate::mails::notify::notify_new_comment;
+use crate::trash::db as trash_db;
 use crate::AppState;
 
-pub async fn create_page_comment(
-    State(state): State<Arc<AppState>>,
-    PMVisitor(user): PMVisitor,
-    TypedHeader(user_agent): TypedHeader<UserAgent>,
-    InsecureClientIp(ip): InsecureClientIp,
-    Path(slug): Path<String>,
-    ValidatedJson(comment_create): ValidatedJson<CommentCreate>,
+/// Creates a comment of a post or page, the pipeline is the same for both: the content
+/// must be open, then the flood limit, the spam filter and the moderation options decide.
+async fn create_content_comment(
+    state: Arc<AppState>,
+    user: User,
+    user_agent: UserAgent,
+    client_ip: IpAddr,
+    slug: &str,
+    comment_create: CommentCreate,
 ) -> Result<(StatusCode, Json<Value>), FieldError> {
-    let page = match common_db::get_content_by_slug(&state, &slug).await {
-        Ok(Some(p)) => {
-            if p.allow_comment == "0" {
-                return Err(FieldError::InvalidParams("slug".to_string()));
-            }
-            p
-        }
+    let content = match common_db::get_content_by_slug(&state, slug).await {
+        Ok(Some(c)) => c,
         _ => return Err(FieldError::InvalidParams("slug".to_string())),
     };
+    if content.allow_comment == "0" {
+        return Err(FieldError::CommentsClosed);
+    }
 
     let mut parent = 0;
     if let Some(coid) = comment_create.parent {
@@ -44,9 +57,9 @@ pub async fn create_page_comment(
         }
     }
 
-    if user.group == "visitor" && (comment_create.author.is_none() || comment_create.mail.is_none())
-    {
-        return Err(FieldError::InvalidParams("author or mail".to_string()));
+    // mail and url of visitors are checked by the options below
+    if user.group == "visitor" && comment_create.author.is_none() {
+        return Err(FieldError::InvalidParams("author".to_string()));
     }
 
     let author;
@@ -57,7 +70,7 @@ pub async fn create_page_comment(
         "visitor" => {
             author = comment_create.author.unwrap();
             author_id = 0;
-            mail = comment_create.mail.unwrap();
+            mail = comment_create.mail.unwrap_or_default();
             url = comment_create.url;
         }
         _ => {
@@ -67,16 +80,63 @@ pub async fn create_page_comment(
             url = user.url;
         }
     };
-    let ip = ip.to_string();
+    let options = common_db::get_site_options(&state).await?;
+    let now = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    if is_comment_closed(&options, content.created, now) {
+        return Err(FieldError::CommentsClosed);
+    }
+    if parent != 0 {
+        let max_levels = get_comments_max_nesting_levels(&options);
+        if db::get_comment_level_by_coid(&state, parent, max_levels).await? >= max_levels {
+            return Err(FieldError::InvalidParams("parent".to_string()));
+        }
+    }
+    if author_id == 0 {
+        check_comment_required_fields(&options, &mail, url.as_deref())?;
+    }
+
+    let ip = client_ip.to_string();
+    let trusted =
+        author_id == content.author_id || user.group == "editor" || user.group == "administrator";
+    let interval = if trusted {
+        None
+    } else {
+        get_comment_post_interval(&options)
+    };
+    let spam = !trusted && {
+        let ctx = SpamContext {
+            author: &author,
+            mail: &mail,
+            url: url.as_deref(),
+            text: &comment_create.text,
+            ip: client_ip,
+            honeypot: comment_create.honeypot.as_deref(),
+            rendered_at: comment_create.rendered_at,
+            now,
+        };
+        SpamFilter::from_options(&options).is_spam(&ctx)
+    };
+    let whitelisted = !trusted
+        && !spam
+        && is_whitelist_enabled(&options)
+        && db::check_approved_comment_by_author_and_mail(&state, &author, &mail).await?;
+
     let ua = user_agent.to_string();
-    let status = "approved";
+    let status = if spam {
+        "spam"
+    } else {
+        get_comment_status(&options, trusted, whitelisted)
+    };
 
-    let _ = db::create_comment_with_params(
+    let comment = db::create_comment_with_params(
         &state,
-        page.cid,
+        content.cid,
         &author,
         author_id,
-        page.author_id,
+        content.author_id,
         &mail,
         url,
         &ip,
@@ -84,85 +144,96 @@ pub async fn create_page_comment(
         &comment_create.text,
         status,
         parent,
+        interval,
     )
     .await?;
-    let _ = db::update_content_count_increase_by_cid(&state, page.cid).await?;
-    Ok((StatusCode::CREATED, Json(json!({ "msg": "ok" }))))
+    if status == "approved" {
+        let _ = db::update_content_count_increase_by_cid(&state, content.cid).await?;
+    }
+    if state.smtp.is_some() {
+        tokio::spawn(notify_new_comment(state.clone(), content, comment));
+    }
+    Ok((
+        StatusCode::CREATED,
+        Json(json!({ "msg": "ok", "status": status })),
+    ))
 }
 
-pub async fn create_post_comment(
+pub async fn create_page_comment(
     State(state): State<Arc<AppState>>,
     PMVisitor(user): PMVisitor,
     TypedHeader(user_agent): TypedHeader<UserAgent>,
-    InsecureClientIp(ip): InsecureClientIp,
+    SecureClientIp(client_ip): SecureClientIp,
     Path(slug): Path<String>,
     ValidatedJson(comment_create): ValidatedJson<CommentCreate>,
 ) -> Result<(StatusCode, Json<Value>), FieldError> {
-    let post = match common_db::get_content_by_slug(&state, &slug).await {
-        Ok(Some(p)) => {
-            if p.allow_comment == "0" {
-                return Err(FieldError::InvalidParams("slug".to_string()));
-            }
-            p
-        }
-        _ => return Err(FieldError::InvalidParams("slug".to_string())),
-    };
+    create_content_comment(state, user, user_agent, client_ip, &slug, comment_create).await
+}
 
-    let mut parent = 0;
-    if let Some(coid) = comment_create.parent {
-        if let Ok(Some(comment)) = db::get_comment_by_coid(&state, coid).await {
-            if comment.status == "approved" {
-                parent = coid;
-            }
-        } else {
-            return Err(FieldError::InvalidParams("parent".to_string()));
-        }
-    }
+pub async fn create_post_comment(
+    State(state): State<Arc<AppState>>,
+    PMVisitor(user): PMVisitor,
+    TypedHeader(user_agent): TypedHeader<UserAgent>,
+    SecureClientIp(client_ip): SecureClientIp,
+    Path(slug): Path<String>,
+    ValidatedJson(comment_create): ValidatedJson<CommentCreate>,
+) -> Result<(StatusCode, Json<Value>), FieldError> {
+    create_content_comment(state, user, user_agent, client_ip, &slug, comment_create).await
+}
 
-    if user.group == "visitor" && (comment_create.author.is_none() || comment_create.mail.is_none())
-    {
-        return Err(FieldError::InvalidParams("author or mail".to_string()));
-    }
+fn trackback_response(error: Option<&str>) -> Response {
+    let body = match error {
+        Some(message) => format!(
+            "<error>1</error>\n<message>{}</message>",
+            escape_xml(message)
+        ),
+        None => "<error>0</error>".to_string(),
+    };
+    (
+        [(header::CONTENT_TYPE, "text/xml; charset=utf-8")],
+        format!(
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<response>\n{}\n</response>",
+            body
+        ),
+    )
+        .into_response()
+}
 
-    let author;
-    let author_id;
-    let mail;
-    let url;
-    match user.group.as_str() {
-        "visitor" => {
-            author = comment_create.author.unwrap();
-            author_id = 0;
-            mail = comment_create.mail.unwrap();
-            url = comment_create.url;
-        }
-        _ => {
-            author = user.screen_name.unwrap_or("".to_string());
-            author_id = user.uid;
-            mail = user.mail.unwrap_or("".to_string());
-            url = user.url;
-        }
+pub async fn create_trackback(
+    State(state): State<Arc<AppState>>,
+    user_agent: Option<TypedHeader<UserAgent>>,
+    SecureClientIp(client_ip): SecureClientIp,
+    Path(slug): Path<String>,
+    form: Result<Form<TrackbackCreate>, FormRejection>,
+) -> Response {
+    let trackback = match form {
+        Ok(Form(t)) if t.validate().is_ok() => t,
+        _ => return trackback_response(Some("Invalid trackback")),
+    };
+    let content = match common_db::get_content_by_slug(&state, &slug).await {
+        Ok(Some(c)) => c,
+        _ => return trackback_response(Some(PingError::TargetNotFound.message())),
+    };
+    let options = match common_db::get_site_options(&state).await {
+        Ok(o) => o,
+        Err(_) => return trackback_response(Some(PingError::Failed.message())),
     };
-    let ip = ip.to_string();
-    let ua = user_agent.to_string();
-    let status = "approved";
 
-    let _ = db::create_comment_with_params(
-        &state,
-        post.cid,
-        &author,
-        author_id,
-        post.author_id,
-        &mail,
-        url,
-        &ip,
-        &ua,
-        &comment_create.text,
-        status,
-        parent,
-    )
-    .await?;
-    let _ = db::update_content_count_increase_by_cid(&state, post.cid).await?;
-    Ok((StatusCode::CREATED, Json(json!({ "msg": "ok" }))))
+    let ip = client_ip.to_string();
+    let ua = user_agent.map(|u| u.to_string()).unwrap_or_default();
+    let ping = Ping {
+        ping_type: "trackback",
+        source: &trackback.url,
+        title: trackback.title.as_deref(),
+        excerpt: trackback.excerpt.as_deref(),
+        blog_name: trackback.blog_name.as_deref(),
+        ip: &ip,
+        ua: &ua,
+    };
+    match create_ping(&state, &options, &content, ping).await {
+        Ok(_) => trackback_response(None),
+        Err(e) => trackback_response(Some(e.message())),
+    }
 }
 
 pub async fn list_comments(
@@ -170,13 +241,16 @@ pub async fn list_comments(
     PMEditor(_): PMEditor,
     ValidatedQuery(q): ValidatedQuery<CommentsQuery>,
 ) -> Result<Json<Value>, FieldError> {
-    let all_count = db::get_comments_count(&state).await;
+    let status = q.status.as_deref();
+    let all_count = db::get_comments_count_by_status(&state, status).await;
 
     let page = q.page.unwrap_or(1);
     let page_size = q.page_size.unwrap_or(10);
     let order_by = q.order_by.unwrap_or("-coid".to_string());
 
-    let comments = db::get_comments_by_list_query(&state, page_size, page, &order_by).await?;
+    let comments =
+        db::get_comments_by_list_query_and_status(&state, page_size, page, &order_by, status)
+            .await?;
     Ok(Json(json!({
         "page": page,
         "page_size": page_size,
@@ -200,11 +274,15 @@ pub async fn list_page_comments_by_slug(
         _ => return Err(FieldError::InvalidParams("slug".to_string())),
     };
 
+    let page = q.page.unwrap_or(1);
+    let page_size = q.page_size.unwrap_or(10);
+    if q.tree.unwrap_or(false) {
+        return list_comments_tree(&state, target_page.cid, private, page, page_size).await;
+    }
+
     let all_count =
         db::get_content_comments_count_by_cid_with_private(&state, target_page.cid, private).await;
 
-    let page = q.page.unwrap_or(1);
-    let page_size = q.page_size.unwrap_or(10);
     let order_by = q.order_by.unwrap_or("-coid".to_string());
 
     let comments = db::get_comments_by_cid_and_list_query_with_private(
@@ -216,26 +294,14 @@ pub async fn list_page_comments_by_slug(
         &order_by,
     )
     .await?;
-
-    let mut hasher = Md5::new();
-    let mut hashed_comments = vec![];
-    for cm in comments {
-        let mail = match cm.mail {
-            Some(m) => {
-                hasher.update(m.as_bytes());
-                Some(format!("{:x}", hasher.finalize_reset()))
-            }
-            None => None,
-        };
-        hashed_comments.push(comment::Model { mail: mail, ..cm })
-    }
-    return Ok(Json(json!({
+    let comments = hash_comments_mail(comments);
+    Ok(Json(json!({
         "page": page,
         "page_size": page_size,
         "all_count": all_count,
-        "count": hashed_comments.len(),
-        "results": hashed_comments
-    })));
+        "count": comments.len(),
+        "results": comments
+    })))
 }
 
 pub async fn list_post_comments_by_slug(
@@ -252,11 +318,15 @@ pub async fn list_post_comments_by_slug(
         _ => return Err(FieldError::InvalidParams("slug".to_string())),
     };
 
+    let page = q.page.unwrap_or(1);
+    let page_size = q.page_size.unwrap_or(10);
+    if q.tree.unwrap_or(false) {
+        return list_comments_tree(&state, target_post.cid, private, page, page_size).await;
+    }
+
     let all_count =
         db::get_content_comments_count_by_cid_with_private(&state, target_post.cid, private).await;
 
-    let page = q.page.unwrap_or(1);
-    let page_size = q.page_size.unwrap_or(10);
     let order_by = q.order_by.unwrap_or("-coid".to_string());
 
     let comments = db::get_comments_by_cid_and_list_query_with_private(
@@ -268,29 +338,14 @@ pub async fn list_post_comments_by_slug(
         &order_by,
     )
     .await?;
-
-    let mut hasher = Md5::new();
-    let mut hashed_comments = vec![];
-    for comment in comments {
-        let mail = match comment.mail {
-            Some(m) => {
-                hasher.update(m.as_bytes());
-                Some(format!("{:x}", hasher.finalize_reset()))
-            }
-            None => None,
-        };
-        hashed_comments.push(comment::Model {
-            mail: mail,
-            ..comment
-        })
-    }
-    return Ok(Json(json!({
+    let comments = hash_comments_mail(comments);
+    Ok(Json(json!({
         "page": page,
         "page_size": page_size,
         "all_count": all_count,
-        "count": hashed_comments.len(),
-        "results": hashed_comments
-    })));
+        "count": comments.len(),
+        "results": comments
+    })))
 }
 
 pub async fn get_comment_by_coid(
@@ -299,7 +354,7 @@ pub async fn get_comment_by_coid(
     Path(coid): Path<u32>,
 ) -> Result<Json<Value>, FieldError> {
     match db::get_comment_by_coid(&state, coid).await {
-        Ok(Some(comment)) => Ok(Json(json!(comment))),
+        Ok(Some(comment)) if comment.status != "trash" => Ok(Json(json!(comment))),
         _ => Err(FieldError::NotFound("coid".to_string())),
     }
 }
@@ -310,8 +365,9 @@ pub async fn modify_comment_by_coid(
     Path(coid): Path<u32>,
     ValidatedJson(comment_modify): ValidatedJson<CommentModify>,
 ) -> Result<Json<Value>, FieldError> {
-    match db::get_comment_by_coid(&state, coid).await {
-        Ok(Some(comment)) => Some(comment),
+    // trashed comments are only restored through the trash
+    let exist_comment = match db::get_comment_by_coid(&state, coid).await {
+        Ok(Some(comment)) if comment.status != "trash" => comment,
         _ => return Err(FieldError::NotFound("coid".to_string())),
     };
 
@@ -323,21 +379,78 @@ pub async fn modify_comment_by_coid(
     };
 
     let _ = db::modify_comment_with_params(&state, coid, &comment_modify.text, &status).await?;
+    if exist_comment.status != "approved" && status == "approved" {
+        let _ = db::update_content_count_increase_by_cid(&state, exist_comment.cid).await?;
+    } else if exist_comment.status == "approved" && status != "approved" {
+        let _ = db::update_content_count_decrease_by_cid(&state, exist_comment.cid).await?;
+    }
     Ok(Json(json!({ "msg": "ok" })))
 }
 
-pub async fn delete_comment_by_coid(
+pub async fn mark_comment_as_ham_by_coid(
     State(state): State<Arc<AppState>>,
     PMEditor(_): PMEditor,
     Path(coid): Path<u32>,
 ) -> Result<Json<Value>, FieldError> {
     let comment = match db::get_comment_by_coid(&state, coid).await {
-        Ok(Some(comment)) => comment,
+        Ok(Some(comment)) if comment.status == "spam" => comment,
+        _ => return Err(FieldError::NotFound("coid".to_string())),
+    };
+
+    let text = comment.text.unwrap_or_default();
+    let _ = db::modify_comment_with_params(&state, coid, &text, "approved").await?;
+    let _ = db::update_content_count_increase_by_cid(&state, comment.cid).await?;
+    Ok(Json(json!({ "msg": "ok" })))
+}
+
+pub async fn delete_comment_by_coid(
+    State(state): State<Arc<AppState>>,
+    PMEditor(user): PMEditor,
+    Path(coid): Path<u32>,
+) -> Result<Json<Value>, FieldError> {
+    let comment = match db::get_comment_by_coid(&state, coid).await {
+        Ok(Some(comment)) if comment.status != "trash" => comment,
         _ => return Err(FieldError::NotFound("coid".to_string())),
     };
-    let cid = comment.cid;
-    let _ = db::update_content_count_decrease_by_cid(&state, cid).await?;
 
-    let _ = db::delete_comment_by_coid(&state, coid).await?;
+    let _ = trash_db::move_comment_to_trash_with_uid(&state, &comment, user.uid).await?;
     Ok(Json(json!({ "msg": "ok" })))
 }
+
+async fn list_comments_tree(
+    state: &AppState,
+    cid: u32,
+    private: bool,
+    page: u64,
+    page_size: u64,
+) -> Result<Json<Value>, FieldError> {
+    let options = common_db::get_site_options(state).await?;
+    let max_levels = if is_comments_threaded(&options) {
+        get_comments_max_nesting_levels(&options)
+    } else {
+        1
+    };
+    let order_by = get_comments_order_by(&options);
+
+    let all_count =
+        db::get_content_top_comments_count_by_cid_with_private(state, cid, private).await;
+
+    let comments = db::get_top_comments_by_cid_and_list_query_with_private(
+        state, cid, private, page_size, page, order_by,
+    )
+    .await?;
+    let replies = db::get_replies_by_cid_with_private(state, cid, private, order_by).await?;
+    let comments = build_comment_tree(
+        hash_comments_mail(comments),
+        hash_comments_mail(replies),
+        max_levels,
+        order_by.starts_with('-'),
+    );
+    Ok(Json(json!({
+        "page": page,
+        "page_size": page_size,
+        "all_count": all_count,
+        "count": comments.len(),
+        "results": comments
+    })))
+}
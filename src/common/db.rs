@@ -1,15 +1,38 @@
+use std::collections::HashMap;
+
+use sea_orm::sea_query::{Expr, Query};
 use sea_orm::*;
 
 use super::forms::FieldCreate;
-use super::utils::get_field_params;
+use super::utils::{get_field_params, get_now};
 use crate::common::errors::FieldError;
+use crate::common::markdown::remove_html_cache;
 use crate::common::models::ContentWithMetasUsersFields;
 use crate::entity::{
-    content, content::Entity as Content, field, field::Entity as ContentField, meta,
-    meta::Entity as Meta, relationship, relationship::Entity as Relationship, user,
+    comment, comment::Entity as Comment, content, content::Entity as Content, field,
+    field::Entity as ContentField, meta, meta::Entity as Meta, option,
+    option::Entity as SiteOption, relationship, relationship::Entity as Relationship, trash,
+    trash::Entity as Trash, user,
 };
 use crate::AppState;
 
+/// Site options are seeded under the first administrator (uid 1) by `init_options`,
+/// Typecho keeps them under uid 0, so values stored for uid 0 take precedence.
+pub async fn get_site_options(state: &AppState) -> Result<HashMap<String, String>, FieldError> {
+    let options = SiteOption::find()
+        .filter(option::Column::User.is_in([0, 1]))
+        .order_by_desc(option::Column::User)
+        .all(&state.conn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("fetch options failed".to_string()))?;
+
+    let mut res = HashMap::new();
+    for opt in options {
+        res.insert(opt.name, opt.value.unwrap_or_default());
+    }
+    Ok(res)
+}
+
 pub async fn get_content_by_cid(
     state: &AppState,
     cid: u32,
@@ -21,9 +44,23 @@ pub async fn get_content_by_cid(
         .map_err(|_| FieldError::InvalidParams("cid".to_string()))
 }
 
+/// Trashed contents can only be reached through the trash.
 pub async fn get_content_by_slug(
     state: &AppState,
     slug: &str,
+) -> Result<Option<content::Model>, FieldError> {
+    Content::find()
+        .filter(content::Column::Slug.eq(slug))
+        .filter(content::Column::Status.ne("trash"))
+        .one(&state.conn)
+        .await
+        .map_err(|_| FieldError::InvalidParams("slug".to_string()))
+}
+
+/// Slugs of trashed contents are still taken, used to check the uniqueness.
+pub async fn get_content_by_slug_with_trash(
+    state: &AppState,
+    slug: &str,
 ) -> Result<Option<content::Model>, FieldError> {
     Content::find()
         .filter(content::Column::Slug.eq(slug))
@@ -46,38 +83,48 @@ pub async fn get_contents_count_with_private(
         stmt
     };
     let stmt = if private {
-        stmt
+        stmt.filter(content::Column::Status.ne("trash"))
     } else {
         stmt.filter(content::Column::Status.eq("publish"))
+            .filter(content::Column::Created.lte(get_now()))
     };
     stmt.count(&state.conn).await.unwrap_or(0)
 }
 
-pub async fn get_contents_with_metas_user_and_fields_by_mid_list_query_and_private(
+/// The page of a content list, with the defaults already applied.
+pub struct ContentsPage<'a> {
+    pub page_size: u64,
+    pub page: u64,
+    pub order_by: &'a str,
+}
+
+pub async fn get_contents_with_metas_user_and_fields_by_mids_list_query_and_private(
     state: &AppState,
-    mid: u32,
+    mids: &[u32],
     private: bool,
-    author: &user::Model,
-    page_size: u64,
-    page: u64,
-    order_by: &str,
+    contents_page: &ContentsPage<'_>,
     post: bool,
+    feed: bool,
 ) -> Result<Vec<ContentWithMetasUsersFields>, FieldError> {
     let content_type = if post { "post" } else { "page" };
 
-    let stmt = Content::find().left_join(Meta).filter(
-        meta::Column::Mid
-            .eq(mid)
-            .and(content::Column::Type.eq(content_type)),
-    );
+    let stmt = Content::find()
+        .filter(content::Column::Cid.in_subquery(get_cids_by_mids_subquery(mids)))
+        .filter(content::Column::Type.eq(content_type));
 
     let stmt = if private {
-        stmt
+        stmt.filter(content::Column::Status.ne("trash"))
     } else {
         stmt.filter(content::Column::Status.eq("publish"))
+            .filter(content::Column::Created.lte(get_now()))
+    };
+    let stmt = if feed {
+        stmt.filter(content::Column::AllowFeed.eq("1"))
+    } else {
+        stmt
     };
 
-    let stmt = match order_by {
+    let stmt = match contents_page.order_by {
         "-cid" => stmt.order_by_desc(content::Column::Cid),
         "cid" => stmt.order_by_asc(content::Column::Cid),
         "-slug" => stmt.order_by_desc(content::Column::Slug),
@@ -85,13 +132,20 @@ pub async fn get_contents_with_metas_user_and_fields_by_mid_list_query_and_priva
         _ => stmt.order_by_desc(content::Column::Cid),
     };
 
-    let paginator = stmt.paginate(&state.conn, page_size);
+    let paginator = stmt.paginate(&state.conn, contents_page.page_size);
 
     let contents = paginator
-        .fetch_page(page - 1)
+        .fetch_page(contents_page.page - 1)
         .await
         .map_err(|_| FieldError::DatabaseFailed("fetch contents failed".to_string()))?;
+    get_contents_with_metas_users_fields(state, contents).await
+}
 
+/// Loads metas, fields and the author of each content, shared by the content lists.
+pub async fn get_contents_with_metas_users_fields(
+    state: &AppState,
+    contents: Vec<content::Model>,
+) -> Result<Vec<ContentWithMetasUsersFields>, FieldError> {
     let metas = contents
         .load_many_to_many(meta::Entity, relationship::Entity, &state.conn)
         .await
@@ -102,15 +156,23 @@ pub async fn get_contents_with_metas_user_and_fields_by_mid_list_query_and_priva
         .await
         .map_err(|_| FieldError::DatabaseFailed("fetch fields failed".to_string()))?;
 
+    let authors = contents
+        .load_one(user::Entity, &state.conn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("fetch authors failed".to_string()))?;
+
     let mut res = vec![];
-    for ((content, meta_list), field_list) in contents
+    for (((content, meta_list), field_list), author) in contents
         .into_iter()
         .zip(metas.into_iter())
         .zip(fields.into_iter())
+        .zip(authors.into_iter())
     {
         let mut c = ContentWithMetasUsersFields::from(content);
-        c.screen_name = author.screen_name.clone();
-        c.group = author.group.clone();
+        if let Some(author) = author {
+            c.screen_name = author.screen_name;
+            c.group = author.group;
+        }
 
         let mut tags = vec![];
         let mut categories = vec![];
@@ -129,15 +191,12 @@ pub async fn get_contents_with_metas_user_and_fields_by_mid_list_query_and_priva
     Ok(res)
 }
 
-pub async fn delete_content_by_cid(state: &AppState, cid: u32) -> Result<DeleteResult, FieldError> {
-    let c: content::ActiveModel = get_content_by_cid(state, cid)
-        .await?
-        .ok_or(FieldError::InvalidParams("cid".to_string()))
-        .map(Into::into)?;
-
-    c.delete(&state.conn)
-        .await
-        .map_err(|_| FieldError::DatabaseFailed("delete content failed".to_string()))
+fn get_cids_by_mids_subquery(mids: &[u32]) -> sea_orm::sea_query::SelectStatement {
+    Query::select()
+        .column(relationship::Column::Cid)
+        .from(Relationship)
+        .and_where(relationship::Column::Mid.is_in(mids.to_vec()))
+        .to_owned()
 }
 
 pub async fn check_relationship_by_cid_and_mid(
@@ -281,14 +340,6 @@ pub async fn delete_field_by_cid_and_name(
         .map_err(|_| FieldError::DatabaseFailed(format!("delete field {} failed", name)))
 }
 
-pub async fn delete_fields_by_cid(state: &AppState, cid: u32) -> Result<DeleteResult, FieldError> {
-    ContentField::delete_many()
-        .filter(field::Column::Cid.eq(cid))
-        .exec(&state.conn)
-        .await
-        .map_err(|_| FieldError::DatabaseFailed("delete fields failed".to_string()))
-}
-
 pub async fn get_meta_by_mid(
     state: &AppState,
     mid: u32,
@@ -354,67 +405,176 @@ pub async fn get_metas_count(state: &AppState, tag: bool) -> u64 {
         .unwrap_or(0)
 }
 
-pub async fn get_meta_posts_count_by_mid_with_private(
+pub async fn get_meta_posts_count_by_mids_with_private(
     state: &AppState,
-    mid: u32,
+    mids: &[u32],
     private: bool,
 ) -> u64 {
-    let stmt = Content::find()
-        .left_join(Meta)
-        .filter(meta::Column::Mid.eq(mid));
+    let stmt =
+        Content::find().filter(content::Column::Cid.in_subquery(get_cids_by_mids_subquery(mids)));
 
     if private {
-        stmt.count(&state.conn).await.unwrap_or(0)
+        stmt.filter(content::Column::Status.ne("trash"))
+            .count(&state.conn)
+            .await
+            .unwrap_or(0)
     } else {
         stmt.filter(content::Column::Status.eq("publish"))
+            .filter(content::Column::Created.lte(get_now()))
             .count(&state.conn)
             .await
             .unwrap_or(0)
     }
 }
 
-pub async fn update_meta_by_mid_for_increase_count(
+/// Removes a content with everything pointing at it in one transaction, trash included,
+/// returns the attachments deleted along with it so their files can be removed.
+pub async fn delete_content_with_relations_by_cid(
     state: &AppState,
-    mid: u32,
-) -> Result<meta::Model, FieldError> {
-    let exist_meta = Meta::find()
-        .filter(meta::Column::Mid.eq(mid))
-        .one(&state.conn)
+    cid: u32,
+    delete_attachments: bool,
+) -> Result<Vec<content::Model>, FieldError> {
+    let txn = state
+        .conn
+        .begin()
         .await
-        .map_err(|_| FieldError::InvalidParams("mid".to_owned()))?;
-    if exist_meta.is_none() {
-        return Err(FieldError::InvalidParams("mid".to_owned()));
+        .map_err(|_| FieldError::DatabaseFailed("begin transaction failed".to_string()))?;
+
+    let coids: Vec<u32> = Comment::find()
+        .filter(comment::Column::Cid.eq(cid))
+        .all(&txn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("fetch comments failed".to_string()))?
+        .into_iter()
+        .map(|c| c.coid)
+        .collect();
+    Comment::delete_many()
+        .filter(comment::Column::Cid.eq(cid))
+        .exec(&txn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("delete comments failed".to_string()))?;
+
+    let mids: Vec<u32> = Relationship::find()
+        .filter(relationship::Column::Cid.eq(cid))
+        .all(&txn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("fetch relationships failed".to_string()))?
+        .into_iter()
+        .map(|r| r.mid)
+        .collect();
+    Relationship::delete_many()
+        .filter(relationship::Column::Cid.eq(cid))
+        .exec(&txn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("delete relationships failed".to_string()))?;
+    update_metas_count_by_mids(&txn, &mids).await?;
+
+    let drafts = Content::find()
+        .filter(content::Column::Type.eq("post_draft"))
+        .filter(content::Column::Parent.eq(cid))
+        .all(&txn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("fetch post drafts failed".to_string()))?;
+    let mut cids: Vec<u32> = drafts.iter().map(|d| d.cid).collect();
+    cids.push(cid);
+    ContentField::delete_many()
+        .filter(field::Column::Cid.is_in(cids.clone()))
+        .exec(&txn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("delete fields failed".to_string()))?;
+
+    let attachments = Content::find()
+        .filter(content::Column::Type.eq("attachment"))
+        .filter(content::Column::Parent.eq(cid))
+        .all(&txn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("fetch attachments failed".to_string()))?;
+    if delete_attachments {
+        cids.extend(attachments.iter().map(|a| a.cid));
+    } else {
+        Content::update_many()
+            .col_expr(content::Column::Parent, Expr::value(0))
+            .filter(content::Column::Type.eq("attachment"))
+            .filter(content::Column::Parent.eq(cid))
+            .exec(&txn)
+            .await
+            .map_err(|_| FieldError::DatabaseFailed("detach attachments failed".to_string()))?;
     }
-    let exist_meta = exist_meta.unwrap();
-    let count = exist_meta.count;
 
-    let mut m = meta::ActiveModel::from(exist_meta);
-    m.count = Set(count + 1);
-    m.update(&state.conn)
+    Trash::delete_many()
+        .filter(
+            Condition::any()
+                .add(
+                    Condition::all()
+                        .add(trash::Column::Type.eq("comment"))
+                        .add(trash::Column::ItemId.is_in(coids)),
+                )
+                .add(
+                    Condition::all()
+                        .add(trash::Column::Type.ne("comment"))
+                        .add(trash::Column::ItemId.is_in(cids.clone())),
+                ),
+        )
+        .exec(&txn)
         .await
-        .map_err(|_| FieldError::DatabaseFailed("update meta count failed".to_string()))
-}
+        .map_err(|_| FieldError::DatabaseFailed("delete trash failed".to_string()))?;
+    Content::delete_many()
+        .filter(content::Column::Cid.is_in(cids.clone()))
+        .exec(&txn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("delete content failed".to_string()))?;
 
-pub async fn update_meta_by_mid_for_decrease_count(
-    state: &AppState,
-    mid: u32,
-) -> Result<meta::Model, FieldError> {
-    let exist_meta = Meta::find()
-        .filter(meta::Column::Mid.eq(mid))
-        .one(&state.conn)
+    txn.commit()
         .await
-        .map_err(|_| FieldError::InvalidParams("mid".to_owned()))?;
-    if exist_meta.is_none() {
-        return Err(FieldError::InvalidParams("mid".to_owned()));
+        .map_err(|_| FieldError::DatabaseFailed("commit transaction failed".to_string()))?;
+    remove_html_cache(&cids);
+
+    if delete_attachments {
+        Ok(attachments)
+    } else {
+        Ok(vec![])
     }
-    let exist_meta = exist_meta.unwrap();
-    let count = exist_meta.count;
+}
 
-    let mut m = meta::ActiveModel::from(exist_meta);
-    m.count = Set(count - 1);
-    m.update(&state.conn)
+/// Same as typecho, a meta only counts its published posts, so counts are recomputed
+/// whenever relationships or post statuses change.
+pub async fn update_metas_count_by_mids<C: ConnectionTrait>(
+    conn: &C,
+    mids: &[u32],
+) -> Result<(), FieldError> {
+    for mid in mids {
+        let count = Relationship::find()
+            .join(JoinType::InnerJoin, relationship::Relation::Content.def())
+            .filter(relationship::Column::Mid.eq(*mid))
+            .filter(content::Column::Type.eq("post"))
+            .filter(content::Column::Status.eq("publish"))
+            .count(conn)
+            .await
+            .map_err(|_| FieldError::DatabaseFailed("count relationships failed".to_string()))?;
+        Meta::update_many()
+            .col_expr(meta::Column::Count, Expr::value(count as u32))
+            .filter(meta::Column::Mid.eq(*mid))
+            .exec(conn)
+            .await
+            .map_err(|_| FieldError::DatabaseFailed("update meta count failed".to_string()))?;
+    }
+    Ok(())
+}
+
+/// Recomputes the counts of all metas of a content, used after its status changed.
+pub async fn update_metas_count_by_cid<C: ConnectionTrait>(
+    conn: &C,
+    cid: u32,
+) -> Result<(), FieldError> {
+    let mids: Vec<u32> = Relationship::find()
+        .filter(relationship::Column::Cid.eq(cid))
+        .all(conn)
         .await
-        .map_err(|_| FieldError::DatabaseFailed("update meta count failed".to_string()))
+        .map_err(|_| FieldError::DatabaseFailed("fetch relationships failed".to_string()))?
+        .into_iter()
+        .map(|r| r.mid)
+        .collect();
+    update_metas_count_by_mids(conn, &mids).await
 }
 
 pub async fn delete_meta_by_mid(state: &AppState, mid: u32) -> Result<DeleteResult, FieldError> {
@@ -1,10 +1,10 @@
 use axum::{
-    extract::rejection::{JsonRejection, QueryRejection},
-    http::StatusCode,
+    extract::rejection::{FormRejection, JsonRejection, QueryRejection},
+    http::{header, StatusCode},
     response::{IntoResponse, Response},
     Json,
 };
-use serde_json::json;
+use serde_json::{json, Value};
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -15,6 +15,8 @@ pub enum ValidateRequestError {
     AxumFormRejection(#[from] JsonRejection),
     #[error(transparent)]
     AxumQueryRejection(#[from] QueryRejection),
+    #[error(transparent)]
+    AxumUrlencodedRejection(#[from] FormRejection),
 }
 
 impl IntoResponse for ValidateRequestError {
@@ -33,6 +35,10 @@ impl IntoResponse for ValidateRequestError {
                 StatusCode::BAD_REQUEST,
                 Json(json!({"msg": "Invalid query params"})),
             ),
+            ValidateRequestError::AxumUrlencodedRejection(_) => (
+                StatusCode::BAD_REQUEST,
+                Json(json!({"msg": "Invalid form"})),
+            ),
         }
         .into_response()
     }
@@ -73,6 +79,10 @@ pub enum FieldError {
     PasswordRequired,
     InvalidParams(String),
     DatabaseFailed(String),
+    CommentsClosed,
+    PreconditionRequired,
+    PreconditionFailed(String, Value),
+    TooManyRequests(u64),
 }
 
 impl IntoResponse for FieldError {
@@ -102,6 +112,30 @@ impl IntoResponse for FieldError {
                 StatusCode::INTERNAL_SERVER_ERROR,
                 Json(json!({ "msg": format!("{}", s) })),
             ),
+            FieldError::CommentsClosed => (
+                StatusCode::FORBIDDEN,
+                Json(json!({"msg": "Comments closed"})),
+            ),
+            FieldError::PreconditionRequired => (
+                StatusCode::PRECONDITION_REQUIRED,
+                Json(json!({"msg": "If-Match required"})),
+            ),
+            FieldError::PreconditionFailed(etag, current) => {
+                return (
+                    StatusCode::PRECONDITION_FAILED,
+                    [(header::ETAG, etag)],
+                    Json(json!({"msg": "Precondition failed", "current": current})),
+                )
+                    .into_response()
+            }
+            FieldError::TooManyRequests(retry_after) => {
+                return (
+                    StatusCode::TOO_MANY_REQUESTS,
+                    [(header::RETRY_AFTER, retry_after.to_string())],
+                    Json(json!({"msg": "Too many requests"})),
+                )
+                    .into_response()
+            }
         }
         .into_response()
     }
@@ -1,9 +1,13 @@
 use axum::{
     async_trait,
-    body::Body,
-    extract::{rejection::JsonRejection, FromRef, FromRequest, FromRequestParts, Query},
-    http::{request::Parts, Request},
-    Json,
+    body::{Body, Bytes},
+    extract::{
+        rejection::{FormRejection, JsonRejection},
+        FromRef, FromRequest, FromRequestParts, Query,
+    },
+    http::{header, request::Parts, Request},
+    response::{IntoResponse, Response},
+    Form, Json,
 };
 use serde::de::DeserializeOwned;
 use std::sync::Arc;
@@ -11,7 +15,7 @@ use validator::Validate;
 
 use super::errors::{AuthError, ValidateRequestError};
 use crate::entity::user::Model as User;
-use crate::users::utils::get_user;
+use crate::users::utils::{get_user, get_user_with_scope, get_user_with_scope_by_token};
 use crate::AppState;
 
 #[derive(Debug, Clone, Copy, Default)]
@@ -33,6 +37,24 @@ where
     }
 }
 
+pub struct ValidatedForm<T>(pub T);
+
+#[async_trait]
+impl<S, T> FromRequest<S> for ValidatedForm<T>
+where
+    T: DeserializeOwned + Validate,
+    S: Send + Sync,
+    Form<T>: FromRequest<S, Rejection = FormRejection>,
+{
+    type Rejection = ValidateRequestError;
+
+    async fn from_request(req: Request<Body>, state: &S) -> Result<Self, Self::Rejection> {
+        let Form(value) = Form::<T>::from_request(req, state).await?;
+        value.validate()?;
+        Ok(ValidatedForm(value))
+    }
+}
+
 pub struct ValidatedQuery<T>(pub T);
 
 #[async_trait]
@@ -140,6 +162,85 @@ where
     }
 }
 
+/// Same as `PMContributor`, but also accepts tokens limited to a scope.
+pub struct PMScopedContributor(pub User, pub Option<String>);
+
+#[async_trait]
+impl<S> FromRequestParts<Arc<S>> for PMScopedContributor
+where
+    AppState: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = AuthError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &Arc<S>,
+    ) -> Result<Self, Self::Rejection> {
+        let state = AppState::from_ref(state);
+        let (user, scope) = get_user_with_scope(parts, state).await?;
+        match user.group.as_str() {
+            "contributor" | "editor" | "administrator" => Ok(PMScopedContributor(user, scope)),
+            _ => Err(AuthError::PermissionDeny),
+        }
+    }
+}
+
+/// Same as `PMScopedContributor`, a form-encoded body may carry the token as `access_token`
+/// instead, the body is returned as well.
+pub struct PMScopedContributorWithBody(pub User, pub Option<String>, pub Bytes);
+
+#[async_trait]
+impl<S> FromRequest<Arc<S>> for PMScopedContributorWithBody
+where
+    AppState: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = Response;
+
+    async fn from_request(req: Request<Body>, state: &Arc<S>) -> Result<Self, Self::Rejection> {
+        let app_state = AppState::from_ref(state);
+        let (mut parts, body) = req.into_parts();
+        let header_user = match parts.headers.contains_key(header::AUTHORIZATION) {
+            true => Some(
+                get_user_with_scope(&mut parts, app_state.clone())
+                    .await
+                    .map_err(|e| e.into_response())?,
+            ),
+            false => None,
+        };
+        let is_form = parts
+            .headers
+            .get(header::CONTENT_TYPE)
+            .and_then(|c| c.to_str().ok())
+            .is_some_and(|c| c.starts_with("application/x-www-form-urlencoded"));
+        let body = Bytes::from_request(Request::from_parts(parts, body), state)
+            .await
+            .map_err(|e| e.into_response())?;
+
+        let body_token = match is_form {
+            true => url::form_urlencoded::parse(&body)
+                .find(|(k, _)| k == "access_token")
+                .map(|(_, v)| v.into_owned()),
+            false => None,
+        };
+        let (user, scope) = match (header_user, body_token) {
+            (Some(user), None) => user,
+            (None, Some(token)) => get_user_with_scope_by_token(&app_state, &token)
+                .await
+                .map_err(|e| e.into_response())?,
+            // a token in both places is an invalid request
+            _ => return Err(AuthError::InvalidToken.into_response()),
+        };
+        match user.group.as_str() {
+            "contributor" | "editor" | "administrator" => {
+                Ok(PMScopedContributorWithBody(user, scope, body))
+            }
+            _ => Err(AuthError::PermissionDeny.into_response()),
+        }
+    }
+}
+
 pub struct PMEditor(pub User);
 
 #[async_trait]
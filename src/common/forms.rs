@@ -21,6 +21,8 @@ pub struct ListQueryWithPrivate {
     #[validate(length(min = 1, max = 13, message = "order_by length must greater than 1"))]
     pub order_by: Option<String>,
     pub private: Option<bool>,
+    #[validate(length(min = 1, max = 8, message = "format length must greater than 1"))]
+    pub format: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Validate)]
@@ -34,3 +36,8 @@ pub struct FieldCreate {
     pub int_value: Option<i32>,
     pub float_value: Option<f32>,
 }
+
+#[derive(Serialize, Deserialize, Validate)]
+pub struct DeleteQuery {
+    pub delete_attachments: Option<bool>,
+}
@@ -0,0 +1,414 @@
+use futures::future::BoxFuture;
+use rustls::pki_types::ServerName;
+use std::io::{Read, Write};
+use std::net::{IpAddr, SocketAddr, TcpStream, ToSocketAddrs};
+use std::sync::Arc;
+use std::time::Duration;
+use url::{Host, Url};
+
+const HTTP_TIMEOUT: u64 = 10;
+const MAX_REDIRECTS: usize = 3;
+const MAX_BODY_SIZE: u64 = 1024 * 1024;
+
+pub struct HttpResponse {
+    /// The url after redirects, relative links in the page resolve against it.
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl HttpResponse {
+    pub fn header_values<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a str> {
+        self.headers
+            .iter()
+            .filter(move |(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+/// Fetches remote pages, tests replace it with a local stub.
+pub trait HttpClient: Send + Sync {
+    /// Returns the page after following redirects, statuses other than 2xx are errors.
+    fn get(&self, url: &str) -> BoxFuture<'static, Result<HttpResponse, String>>;
+
+    /// Posts an urlencoded form and returns the response status.
+    fn post_form(
+        &self,
+        url: &str,
+        form: Vec<(String, String)>,
+    ) -> BoxFuture<'static, Result<u16, String>>;
+}
+
+/// Plain HTTP/1.0 client over http and https, private addresses are refused.
+pub struct DefaultHttpClient;
+
+impl HttpClient for DefaultHttpClient {
+    fn get(&self, url: &str) -> BoxFuture<'static, Result<HttpResponse, String>> {
+        let url = url.to_string();
+        Box::pin(async move {
+            tokio::task::spawn_blocking(move || fetch(&url))
+                .await
+                .map_err(|e| e.to_string())?
+        })
+    }
+
+    fn post_form(
+        &self,
+        url: &str,
+        form: Vec<(String, String)>,
+    ) -> BoxFuture<'static, Result<u16, String>> {
+        let url = url.to_string();
+        Box::pin(async move {
+            tokio::task::spawn_blocking(move || {
+                let url = Url::parse(&url).map_err(|e| e.to_string())?;
+                let body = url::form_urlencoded::Serializer::new(String::new())
+                    .extend_pairs(form)
+                    .finish();
+                request(&url, Some(&body)).map(|(status, _, _)| status)
+            })
+            .await
+            .map_err(|e| e.to_string())?
+        })
+    }
+}
+
+fn fetch(url: &str) -> Result<HttpResponse, String> {
+    let mut url = Url::parse(url).map_err(|e| e.to_string())?;
+    for _ in 0..=MAX_REDIRECTS {
+        let (status, headers, body) = request(&url, None)?;
+        let location = headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case("location"))
+            .map(|(_, v)| v.clone());
+        match (status, location) {
+            (200..=299, _) => {
+                return Ok(HttpResponse {
+                    url: url.to_string(),
+                    headers,
+                    body: String::from_utf8_lossy(&body).into_owned(),
+                })
+            }
+            (300..=399, Some(location)) => {
+                url = url.join(&location).map_err(|e| e.to_string())?;
+            }
+            _ => return Err(format!("http status {}", status)),
+        }
+    }
+    Err("too many redirects".to_string())
+}
+
+fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            !(ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_multicast()
+                || ip.is_documentation()
+                // 0.0.0.0/8, 100.64.0.0/10 shared, 198.18.0.0/15 benchmarking, 240.0.0.0/4 reserved
+                || a == 0
+                || (a == 100 && (b & 0xc0) == 64)
+                || (a == 198 && (b & 0xfe) == 18)
+                || a >= 240)
+        }
+        IpAddr::V6(ip) => {
+            // ::ffff:a.b.c.d reaches the IPv4 address
+            if let Some(ip) = ip.to_ipv4_mapped() {
+                return is_public_ip(IpAddr::V4(ip));
+            }
+            let segments = ip.segments();
+            !(ip.is_loopback()
+                || ip.is_unspecified()
+                || ip.is_multicast()
+                || (segments[0] & 0xfe00) == 0xfc00
+                || (segments[0] & 0xffc0) == 0xfe80
+                || (segments[0] == 0x2001 && segments[1] == 0x0db8))
+        }
+    }
+}
+
+/// Resolves the host once and keeps only public addresses, the connection goes to one of them
+/// so a second lookup can't point it somewhere else.
+fn connect_public(host: &str, port: u16) -> Result<TcpStream, String> {
+    let addrs: Vec<SocketAddr> = (host, port)
+        .to_socket_addrs()
+        .map_err(|e| e.to_string())?
+        .filter(|a| is_public_ip(a.ip()))
+        .collect();
+    if addrs.is_empty() {
+        return Err("host is not public".to_string());
+    }
+    let mut error = String::new();
+    for addr in addrs {
+        match TcpStream::connect_timeout(&addr, Duration::from_secs(HTTP_TIMEOUT)) {
+            Ok(stream) => return Ok(stream),
+            Err(e) => error = e.to_string(),
+        }
+    }
+    Err(error)
+}
+
+type RawResponse = (u16, Vec<(String, String)>, Vec<u8>);
+
+/// Sends a GET, or a POST of the urlencoded `form`.
+fn request(url: &Url, form: Option<&str>) -> Result<RawResponse, String> {
+    let (host, host_header) = get_host(url)?;
+    let port = url.port_or_known_default().ok_or("url without port")?;
+    let stream = connect_public(&host, port)?;
+    stream
+        .set_read_timeout(Some(Duration::from_secs(HTTP_TIMEOUT)))
+        .map_err(|e| e.to_string())?;
+    stream
+        .set_write_timeout(Some(Duration::from_secs(HTTP_TIMEOUT)))
+        .map_err(|e| e.to_string())?;
+
+    let path = match url.query() {
+        Some(q) => format!("{}?{}", url.path(), q),
+        None => url.path().to_string(),
+    };
+    let head = match form {
+        Some(body) => format!(
+            "POST {} HTTP/1.0\r\nHost: {}\r\nUser-Agent: rumo\r\nContent-Type: application/x-www-form-urlencoded\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            path,
+            host_header,
+            body.len(),
+            body
+        ),
+        None => format!(
+            "GET {} HTTP/1.0\r\nHost: {}\r\nUser-Agent: rumo\r\nAccept: text/html\r\nConnection: close\r\n\r\n",
+            path, host_header
+        ),
+    };
+    match url.scheme() {
+        "http" => send(stream, &head),
+        "https" => send(
+            rustls::StreamOwned::new(get_tls_connection(&host)?, stream),
+            &head,
+        ),
+        _ => Err("unsupported scheme".to_string()),
+    }
+}
+
+/// Returns the host to connect to, without the brackets of an IPv6 literal, and the Host header.
+fn get_host(url: &Url) -> Result<(String, String), String> {
+    let host = match url.host().ok_or("url without host")? {
+        Host::Domain(domain) => domain.to_string(),
+        Host::Ipv4(ip) => ip.to_string(),
+        Host::Ipv6(ip) => ip.to_string(),
+    };
+    let host_header = match url.port() {
+        Some(port) => format!("{}:{}", url.host_str().unwrap_or_default(), port),
+        None => url.host_str().unwrap_or_default().to_string(),
+    };
+    Ok((host, host_header))
+}
+
+/// A TLS client verified against the bundled web PKI roots, also used by the SMTP client.
+pub fn get_tls_connection(host: &str) -> Result<rustls::ClientConnection, String> {
+    let roots = rustls::RootCertStore {
+        roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+    };
+    let config = rustls::ClientConfig::builder_with_provider(Arc::new(
+        rustls::crypto::ring::default_provider(),
+    ))
+    .with_safe_default_protocol_versions()
+    .map_err(|e| e.to_string())?
+    .with_root_certificates(roots)
+    .with_no_client_auth();
+    let server_name = ServerName::try_from(host.to_string()).map_err(|e| e.to_string())?;
+    rustls::ClientConnection::new(Arc::new(config), server_name).map_err(|e| e.to_string())
+}
+
+fn send<S: Read + Write>(mut stream: S, head: &str) -> Result<RawResponse, String> {
+    stream
+        .write_all(head.as_bytes())
+        .map_err(|e| e.to_string())?;
+    let mut response = vec![];
+    // many servers close https connections without close_notify, the body is only complete
+    // when it has the announced Content-Length
+    let closed = match stream.take(MAX_BODY_SIZE + 1).read_to_end(&mut response) {
+        Ok(_) => true,
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => false,
+        Err(e) => return Err(e.to_string()),
+    };
+    if response.len() as u64 > MAX_BODY_SIZE {
+        return Err("http response too large".to_string());
+    }
+
+    let (status, headers, mut body) = parse_response(&response)?;
+    let content_length = headers
+        .iter()
+        .find(|(n, _)| n.eq_ignore_ascii_case("content-length"))
+        .and_then(|(_, v)| v.parse::<usize>().ok());
+    match content_length {
+        Some(length) if body.len() < length => return Err("truncated http response".to_string()),
+        Some(length) => body.truncate(length),
+        None if !closed => return Err("truncated http response".to_string()),
+        None => (),
+    }
+    Ok((status, headers, body))
+}
+
+fn parse_response(response: &[u8]) -> Result<RawResponse, String> {
+    let end = response
+        .windows(4)
+        .position(|w| w == b"\r\n\r\n")
+        .ok_or("invalid http response")?;
+    let head = String::from_utf8_lossy(&response[..end]);
+    let mut lines = head.lines();
+    let status = lines
+        .next()
+        .and_then(|l| l.split_whitespace().nth(1))
+        .and_then(|s| s.parse::<u16>().ok())
+        .ok_or("invalid http status")?;
+    let headers = lines
+        .filter_map(|l| {
+            let (name, value) = l.split_once(':')?;
+            Some((name.trim().to_string(), value.trim().to_string()))
+        })
+        .collect();
+    Ok((status, headers, response[end + 4..].to_vec()))
+}
+
+#[test]
+fn test_parse_response() {
+    let response = b"HTTP/1.1 301 Moved\r\nLocation: /new\r\n\r\n";
+    assert_eq!(
+        Ok((
+            301,
+            vec![("Location".to_string(), "/new".to_string())],
+            vec![]
+        )),
+        parse_response(response)
+    );
+    let response = b"HTTP/1.0 200 OK\r\nContent-Type: text/html\r\n\r\n<html>";
+    assert_eq!(
+        Ok((
+            200,
+            vec![("Content-Type".to_string(), "text/html".to_string())],
+            b"<html>".to_vec()
+        )),
+        parse_response(response)
+    );
+}
+
+#[test]
+fn test_is_public_ip() {
+    for ip in [
+        "127.0.0.1",
+        "192.168.1.1",
+        "0.1.2.3",
+        "100.64.0.1",
+        "100.127.255.255",
+        "169.254.169.254",
+        "224.0.0.1",
+        "198.18.0.1",
+        "240.0.0.1",
+        "fd00::1",
+        "fe80::1",
+        "ff02::1",
+        "2001:db8::1",
+        "::ffff:127.0.0.1",
+        "::ffff:10.0.0.1",
+    ] {
+        assert!(!is_public_ip(ip.parse().unwrap()), "{}", ip);
+    }
+    for ip in [
+        "1.1.1.1",
+        "100.128.0.1",
+        "2606:4700::1111",
+        "::ffff:1.1.1.1",
+    ] {
+        assert!(is_public_ip(ip.parse().unwrap()), "{}", ip);
+    }
+}
+
+#[cfg(test)]
+struct MockStream {
+    response: std::io::Cursor<Vec<u8>>,
+    close_notify: bool,
+}
+
+#[cfg(test)]
+impl Read for MockStream {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.response.read(buf)?;
+        if n == 0 && !buf.is_empty() && !self.close_notify {
+            return Err(std::io::ErrorKind::UnexpectedEof.into());
+        }
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+impl Write for MockStream {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn test_send_truncated_response() {
+    let mock = |response: &[u8], close_notify| MockStream {
+        response: std::io::Cursor::new(response.to_vec()),
+        close_notify,
+    };
+    let complete = b"HTTP/1.0 200 OK\r\nContent-Length: 6\r\n\r\n<html>";
+    assert_eq!(
+        Ok(b"<html>".to_vec()),
+        send(mock(complete, false), "").map(|(_, _, body)| body)
+    );
+
+    let truncated = b"HTTP/1.0 200 OK\r\nContent-Length: 12\r\n\r\n<html>";
+    assert!(send(mock(truncated, false), "").is_err());
+    assert!(send(mock(truncated, true), "").is_err());
+
+    let unknown_length = b"HTTP/1.0 200 OK\r\n\r\n<html>";
+    assert!(send(mock(unknown_length, false), "").is_err());
+    assert!(send(mock(unknown_length, true), "").is_ok());
+}
+
+#[test]
+fn test_send_too_large_response() {
+    let mut response = b"HTTP/1.0 200 OK\r\n\r\n".to_vec();
+    response.resize(MAX_BODY_SIZE as usize + 1, b'a');
+    let stream = MockStream {
+        response: std::io::Cursor::new(response),
+        close_notify: true,
+    };
+    assert_eq!(
+        Err("http response too large".to_string()),
+        send(stream, "").map(|_| ())
+    );
+}
+
+#[test]
+fn test_get_host() {
+    for (url, host, host_header) in [
+        ("http://rumo.cf/post", "rumo.cf", "rumo.cf"),
+        ("https://1.1.1.1:8443/", "1.1.1.1", "1.1.1.1:8443"),
+        (
+            "http://[2606:4700::1111]/",
+            "2606:4700::1111",
+            "[2606:4700::1111]",
+        ),
+        (
+            "http://[2606:4700::1111]:8080/",
+            "2606:4700::1111",
+            "[2606:4700::1111]:8080",
+        ),
+    ] {
+        let url = Url::parse(url).unwrap();
+        assert_eq!(
+            Ok((host.to_string(), host_header.to_string())),
+            get_host(&url)
+        );
+    }
+}
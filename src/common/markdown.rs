@@ -0,0 +1,197 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+
+use pulldown_cmark::{html, CowStr, Event, Options, Parser, Tag, TagEnd};
+
+use super::errors::FieldError;
+use super::utils::escape_xml;
+
+lazy_static! {
+    static ref HTML_CACHE: Mutex<HashMap<u32, (u32, String)>> = Mutex::new(HashMap::new());
+}
+
+pub fn is_html_format(format: Option<&str>) -> Result<bool, FieldError> {
+    match format {
+        None | Some("raw") => Ok(false),
+        Some("html") => Ok(true),
+        _ => Err(FieldError::InvalidParams("format".to_string())),
+    }
+}
+
+fn get_heading_anchor(text: &str, exist: &mut HashSet<String>) -> String {
+    let mut anchor = String::new();
+    for c in text.trim().chars() {
+        if c.is_alphanumeric() || c == '_' {
+            anchor.extend(c.to_lowercase());
+        } else if (c.is_whitespace() || c == '-') && !anchor.ends_with('-') {
+            anchor.push('-');
+        }
+    }
+    let anchor = anchor.trim_matches('-');
+    let anchor = if anchor.is_empty() { "section" } else { anchor };
+
+    let mut res = anchor.to_string();
+    let mut index = 1;
+    while exist.contains(&res) {
+        res = format!("{}-{}", anchor, index);
+        index += 1;
+    }
+    exist.insert(res.clone());
+    res
+}
+
+fn render_markdown(text: &str) -> String {
+    let mut options = Options::empty();
+    options.insert(Options::ENABLE_TABLES);
+    options.insert(Options::ENABLE_STRIKETHROUGH);
+    options.insert(Options::ENABLE_FOOTNOTES);
+
+    let mut events: Vec<Event> = Parser::new_ext(text, options).collect();
+    let mut anchors = HashSet::new();
+    for i in 0..events.len() {
+        if let Event::Start(Tag::Heading { id: None, .. }) = &events[i] {
+            let mut heading = String::new();
+            for event in events[i + 1..].iter() {
+                match event {
+                    Event::End(TagEnd::Heading(_)) => break,
+                    Event::Text(s) | Event::Code(s) => heading.push_str(s),
+                    _ => {}
+                }
+            }
+            let anchor = get_heading_anchor(&heading, &mut anchors);
+            if let Event::Start(Tag::Heading { id, .. }) = &mut events[i] {
+                *id = Some(CowStr::from(anchor));
+            }
+        }
+    }
+
+    let mut output = String::with_capacity(text.len() * 3 / 2);
+    html::push_html(&mut output, events.into_iter());
+    output
+}
+
+fn sanitize_html(text: &str) -> String {
+    ammonia::Builder::default()
+        .add_generic_attributes(["id"])
+        .add_tag_attributes("code", ["class"])
+        .add_tag_attributes("div", ["class"])
+        .add_tag_attributes("sup", ["class"])
+        .clean(text)
+        .to_string()
+}
+
+fn render(text: &str) -> String {
+    match text.strip_prefix("<!--markdown-->") {
+        Some(markdown) => sanitize_html(&render_markdown(markdown)),
+        None => sanitize_html(text),
+    }
+}
+
+pub fn strip_tags(html: &str) -> String {
+    let mut res = String::with_capacity(html.len());
+    let mut in_tag = false;
+    for c in html.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => {
+                in_tag = false;
+                res.push(' ');
+            }
+            _ if !in_tag => res.push(c),
+            _ => {}
+        }
+    }
+    let res = res
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&");
+    res.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Contents starting with `<!--markdown-->` are Markdown, others are stored as HTML.
+pub fn render_text_to_html(cid: u32, modified: u32, text: &str) -> String {
+    if let Some((m, html)) = HTML_CACHE.lock().unwrap().get(&cid) {
+        if *m == modified {
+            return html.clone();
+        }
+    }
+
+    let html = render(text);
+    HTML_CACHE
+        .lock()
+        .unwrap()
+        .insert(cid, (modified, html.clone()));
+    html
+}
+
+/// Drops the rendered html of deleted contents, cids are not reused but the cache would
+/// keep them forever.
+pub fn remove_html_cache(cids: &[u32]) {
+    let mut cache = HTML_CACHE.lock().unwrap();
+    for cid in cids {
+        cache.remove(cid);
+    }
+}
+
+/// Both the `<!--more-->` part and the automatic excerpt are plain text, or html when
+/// `html` is set.
+pub fn get_excerpt(cid: u32, modified: u32, text: &str, length: usize, html: bool) -> String {
+    if let Some((excerpt, _)) = text.split_once("<!--more-->") {
+        let excerpt = render(excerpt);
+        return if html { excerpt } else { strip_tags(&excerpt) };
+    }
+
+    let plain = strip_tags(&render_text_to_html(cid, modified, text));
+    let excerpt = if plain.chars().count() > length {
+        let mut excerpt: String = plain.chars().take(length).collect();
+        excerpt.push_str("...");
+        excerpt
+    } else {
+        plain
+    };
+    if html {
+        format!("<p>{}</p>\n", escape_xml(&excerpt))
+    } else {
+        excerpt
+    }
+}
+
+#[test]
+fn test_render_text_to_html() {
+    let text =
+        "<!--markdown-->## Hello World\n\n## Hello World\n\n**bold**<script>alert(1)</script>";
+    let expected = "<h2 id=\"hello-world\">Hello World</h2>\n<h2 id=\"hello-world-1\">Hello World</h2>\n<p><strong>bold</strong></p>\n";
+    assert_eq!(expected, render_text_to_html(0, 1, text));
+    assert_eq!(expected, render_text_to_html(0, 1, "changed"));
+    assert_eq!(
+        "<p>changed</p>",
+        render_text_to_html(0, 2, "<p>changed</p>")
+    );
+
+    remove_html_cache(&[0]);
+    assert!(!HTML_CACHE.lock().unwrap().contains_key(&0));
+}
+
+#[test]
+fn test_get_excerpt() {
+    let text = "<!--markdown-->first **part**\n\n<!--more-->\n\nsecond part";
+    assert_eq!("first part", get_excerpt(1, 1, text, 5, false));
+    assert_eq!(
+        "<p>first <strong>part</strong></p>\n",
+        get_excerpt(1, 1, text, 5, true)
+    );
+
+    let text = "<!--markdown-->## Title\n\nsome &amp; text";
+    assert_eq!("Title some & text", get_excerpt(2, 1, text, 100, false));
+    assert_eq!("<p>Title...</p>\n", get_excerpt(2, 1, text, 5, true));
+    assert_eq!(
+        "<p>Title some &amp; text</p>\n",
+        get_excerpt(2, 1, text, 100, true)
+    );
+
+    let text = "<p>first &amp; <b>html</b></p><!--more--><p>second</p>";
+    assert_eq!("first & html", get_excerpt(3, 1, text, 5, false));
+}
@@ -3,4 +3,6 @@ pub mod errors;
 pub mod extractors;
 pub mod models;
 pub mod forms;
+pub mod http;
+pub mod markdown;
 pub mod utils;
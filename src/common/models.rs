@@ -1,5 +1,6 @@
 use serde::{Deserialize, Serialize};
 
+use crate::common::utils::get_now;
 use crate::entity::{content, field::Model as Field, meta::Model as Meta};
 
 #[derive(Serialize, Deserialize)]
@@ -68,6 +69,9 @@ pub struct ContentWithMetasUsersFields {
     pub allow_ping: String,
     pub allow_feed: String,
     pub parent: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub excerpt: Option<String>,
+    pub scheduled: bool,
 
     pub screen_name: Option<String>,
     pub group: String,
@@ -78,6 +82,7 @@ pub struct ContentWithMetasUsersFields {
 
 impl From<content::Model> for ContentWithMetasUsersFields {
     fn from(content: content::Model) -> Self {
+        let scheduled = content.status == "publish" && content.created > get_now();
         Self {
             cid: content.cid,
             title: content.title,
@@ -96,6 +101,8 @@ impl From<content::Model> for ContentWithMetasUsersFields {
             allow_ping: content.allow_ping,
             allow_feed: content.allow_feed,
             parent: content.parent,
+            excerpt: None,
+            scheduled,
             screen_name: None,
             group: "visitor".to_string(),
             categories: vec![],
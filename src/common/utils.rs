@@ -1,6 +1,69 @@
+use std::collections::HashMap;
+use std::time::SystemTime;
+
+use axum::http::{header, HeaderMap};
+use chrono::{DateTime, FixedOffset};
+use md5::{Digest, Md5};
+use regex::Regex;
+
 use super::forms::FieldCreate;
 use crate::common::errors::FieldError;
 
+lazy_static! {
+    static ref SLUG_WORD_RE: Regex = Regex::new(r"[\w-]+").unwrap();
+}
+
+/// Same as typecho's `slugName`, words are joined by `-`, `None` when nothing is left.
+pub fn get_slug_name(name: &str, max_length: usize) -> Option<String> {
+    let words: Vec<&str> = SLUG_WORD_RE.find_iter(name).map(|m| m.as_str()).collect();
+    let slug: String = words
+        .join("-")
+        .trim_matches(|c| c == '-' || c == '_')
+        .chars()
+        .take(max_length)
+        .collect();
+    if slug.is_empty() {
+        None
+    } else {
+        Some(slug)
+    }
+}
+
+pub fn get_now() -> u32 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as u32
+}
+
+/// Every modify moves `modified` forward, the hash also covers changes made by others,
+/// like a scheduled post being published.
+pub fn get_etag(cid: u32, modified: u32, status: &str, text: Option<&str>) -> String {
+    let mut hasher = Md5::new();
+    hasher.update(status.as_bytes());
+    hasher.update(text.unwrap_or_default().as_bytes());
+    format!("\"{}-{}-{:x}\"", cid, modified, hasher.finalize())
+}
+
+/// `modified` of a modify, always later than the last one so it works as a revision counter.
+pub fn get_next_modified(now: u32, exist_modified: u32) -> u32 {
+    now.max(exist_modified.saturating_add(1))
+}
+
+pub fn etag_matches(if_match: &str, etag: &str) -> bool {
+    if_match
+        .split(',')
+        .map(|t| t.trim())
+        .any(|t| t == "*" || t.trim_start_matches("W/") == etag)
+}
+
+pub fn get_if_match(headers: &HeaderMap) -> Result<&str, FieldError> {
+    headers
+        .get(header::IF_MATCH)
+        .and_then(|h| h.to_str().ok())
+        .ok_or(FieldError::PreconditionRequired)
+}
+
 pub fn get_field_params(
     field_create: &FieldCreate,
 ) -> Result<(String, Option<String>, i32, f32), FieldError> {
@@ -43,4 +106,273 @@ pub fn get_field_params(
         _ => return Err(FieldError::InvalidParams("type".to_string())),
     };
     Ok((field_type.to_string(), str_value, int_value, float_value))
-}
\ No newline at end of file
+}
+
+pub fn escape_xml(s: &str) -> String {
+    let mut res = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => res.push_str("&amp;"),
+            '<' => res.push_str("&lt;"),
+            '>' => res.push_str("&gt;"),
+            '"' => res.push_str("&quot;"),
+            '\'' => res.push_str("&apos;"),
+            _ => res.push(c),
+        }
+    }
+    res
+}
+
+pub fn get_route_url(routing_table: &str, name: &str) -> Option<String> {
+    // the table seeded by `init_options` keeps the quotes doubled
+    let table = routing_table.replace("\"\"", "\"");
+    let key = format!("s:{}:\"{}\";a:", name.len(), name);
+    let start = table.find(&key)? + key.len();
+    let rest = &table[start..];
+
+    let key = "s:3:\"url\";s:";
+    let start = rest.find(key)? + key.len();
+    let (len, rest) = rest[start..].split_once(':')?;
+    let len = len.parse::<usize>().ok()?;
+    rest.get(1..len + 1).map(|s| s.to_string())
+}
+
+pub fn get_default_route_url(name: &str) -> String {
+    match name {
+        "post" => "/archives/[cid:digital]/",
+        "page" => "/[slug].html",
+        "category" => "/category/[slug]/",
+        "tag" => "/tag/[slug]/",
+        _ => "/",
+    }
+    .to_string()
+}
+
+pub fn build_permalink(url: &str, params: &HashMap<&str, String>) -> String {
+    let mut res = String::with_capacity(url.len());
+    let mut rest = url;
+    while let Some(left) = rest.find('[') {
+        res.push_str(&rest[..left]);
+        let Some(right) = rest[left..].find(']') else {
+            rest = &rest[left..];
+            break;
+        };
+        let name = rest[left + 1..left + right].split(':').next().unwrap_or("");
+        if let Some(value) = params.get(name) {
+            res.push_str(value);
+        }
+        rest = &rest[left + right + 1..];
+    }
+    res.push_str(rest);
+    res
+}
+
+/// Turns a route url like `/archives/[cid:digital]/` into a regex capturing each param by name.
+pub fn get_route_regex(url: &str) -> Option<Regex> {
+    let mut res = String::from("^");
+    let mut rest = url.trim_end_matches('/');
+    while let Some(left) = rest.find('[') {
+        res.push_str(&regex::escape(&rest[..left]));
+        let right = rest[left..].find(']')?;
+        let mut parts = rest[left + 1..left + right].split(':');
+        let name = parts.next().unwrap_or("");
+        let pattern = match (parts.next(), parts.next()) {
+            (Some("digital"), Some(len)) => format!("[0-9]{{{}}}", len),
+            (Some("digital"), None) => "[0-9]+".to_string(),
+            (Some("alpha"), _) => "[_0-9a-zA-Z-]+".to_string(),
+            _ => "[^/]+".to_string(),
+        };
+        res.push_str(&format!("(?P<{}>{})", name, pattern));
+        rest = &rest[left + right + 1..];
+    }
+    res.push_str(&regex::escape(rest));
+    res.push_str("/?$");
+    Regex::new(&res).ok()
+}
+
+pub fn get_timezone(options: &HashMap<String, String>) -> FixedOffset {
+    options
+        .get("timezone")
+        .and_then(|s| s.parse::<i32>().ok())
+        .and_then(FixedOffset::east_opt)
+        .unwrap_or(FixedOffset::east_opt(0).unwrap())
+}
+
+pub struct Permalinks {
+    pub site_url: String,
+    index_url: String,
+    timezone: FixedOffset,
+    routes: HashMap<String, String>,
+}
+
+impl Permalinks {
+    pub fn from_options(options: &HashMap<String, String>) -> Self {
+        let site_url = options
+            .get("siteUrl")
+            .map(|s| s.trim_end_matches('/').to_string())
+            .unwrap_or_default();
+        let index_url = match options.get("rewrite").map(|s| s.as_str()) {
+            Some("0") => format!("{}/index.php", site_url),
+            _ => site_url.clone(),
+        };
+        let timezone = get_timezone(options);
+
+        let routing_table = options.get("routingTable").cloned().unwrap_or_default();
+        let mut routes = HashMap::new();
+        for name in ["post", "page", "category", "tag"] {
+            let url = get_route_url(&routing_table, name)
+                .unwrap_or_else(|| get_default_route_url(name));
+            routes.insert(name.to_string(), url);
+        }
+
+        Self {
+            site_url,
+            index_url,
+            timezone,
+            routes,
+        }
+    }
+
+    fn build(&self, name: &str, params: &HashMap<&str, String>) -> String {
+        let url = self.routes.get(name).cloned().unwrap_or_default();
+        format!("{}{}", self.index_url, build_permalink(&url, params))
+    }
+
+    pub fn content(
+        &self,
+        r#type: &str,
+        cid: u32,
+        slug: &str,
+        created: u32,
+        category: Option<&str>,
+    ) -> String {
+        let created = DateTime::from_timestamp(created as i64, 0)
+            .unwrap_or_default()
+            .with_timezone(&self.timezone);
+        let category = category.unwrap_or("default").to_string();
+        let params = HashMap::from([
+            ("cid", cid.to_string()),
+            ("slug", slug.to_string()),
+            ("category", category.clone()),
+            ("directory", category),
+            ("year", created.format("%Y").to_string()),
+            ("month", created.format("%m").to_string()),
+            ("day", created.format("%d").to_string()),
+        ]);
+        let name = if r#type == "page" { "page" } else { "post" };
+        self.build(name, &params)
+    }
+
+    /// Finds the content type and the route params of a url on this site, the scheme is ignored.
+    pub fn parse_content(&self, url: &str) -> Option<(&'static str, HashMap<String, String>)> {
+        let strip_scheme = |s: &str| {
+            s.trim_start_matches("https://")
+                .trim_start_matches("http://")
+                .to_string()
+        };
+        let url = strip_scheme(url);
+        let path = url.strip_prefix(&strip_scheme(&self.index_url))?;
+        let path = path.split(['?', '#']).next().unwrap_or_default();
+        if !path.starts_with('/') {
+            return None;
+        }
+
+        for name in ["post", "page"] {
+            let Some(re) = self.routes.get(name).and_then(|u| get_route_regex(u)) else {
+                continue;
+            };
+            if let Some(caps) = re.captures(path) {
+                let params = re
+                    .capture_names()
+                    .flatten()
+                    .filter_map(|n| Some((n.to_string(), caps.name(n)?.as_str().to_string())))
+                    .collect();
+                return Some((name, params));
+            }
+        }
+        None
+    }
+
+    pub fn meta(&self, r#type: &str, mid: u32, slug: &str) -> String {
+        let params = HashMap::from([
+            ("mid", mid.to_string()),
+            ("slug", slug.to_string()),
+            ("directory", slug.to_string()),
+        ]);
+        let name = if r#type == "tag" { "tag" } else { "category" };
+        self.build(name, &params)
+    }
+}
+
+#[test]
+fn test_etag_matches() {
+    let etag = get_etag(1, 1666666666, "publish", Some("text"));
+    assert!(etag_matches(&etag, &etag));
+    assert!(etag_matches("*", &etag));
+    assert!(etag_matches(&format!(r#""2-1", W/{}"#, etag), &etag));
+    assert!(!etag_matches(r#""1-1666666666""#, &etag));
+    assert_ne!(etag, get_etag(1, 1666666666, "publish", Some("new")));
+    assert_ne!(etag, get_etag(1, 1666666666, "draft", Some("text")));
+
+    assert_eq!(1666666667, get_next_modified(1666666666, 1666666666));
+    assert_eq!(1666666667, get_next_modified(1666666667, 1666666600));
+    assert_eq!(1999999999, get_next_modified(1666666666, 1999999998));
+}
+
+#[test]
+fn test_get_slug_name() {
+    assert_eq!(Some("testTag".to_string()), get_slug_name("testTag", 150));
+    assert_eq!(
+        Some("hello-world".to_string()),
+        get_slug_name(" hello world! ", 150)
+    );
+    assert_eq!(Some("标签-a_b".to_string()), get_slug_name("标签 a_b", 150));
+    assert_eq!(Some("ab".to_string()), get_slug_name("-abc-", 2));
+    assert_eq!(None, get_slug_name("!?", 150));
+}
+
+#[test]
+fn test_get_route_url() {
+    let table = r#"a:2:{s:4:""post"";a:3:{s:3:""url"";s:24:""/archives/[cid:digital]/"";s:6:""widget"";s:15:""\Widget\Archive"";s:6:""action"";s:6:""render"";}s:4:""page"";a:3:{s:3:""url"";s:12:""/[slug].html"";s:6:""widget"";s:15:""\Widget\Archive"";s:6:""action"";s:6:""render"";}}"#;
+    assert_eq!(
+        Some("/archives/[cid:digital]/".to_string()),
+        get_route_url(table, "post")
+    );
+    assert_eq!(Some("/[slug].html".to_string()), get_route_url(table, "page"));
+    assert_eq!(None, get_route_url(table, "tag"));
+
+    let table = r#"a:1:{s:4:"post";a:1:{s:3:"url";s:29:"/[category]/[slug]-[cid].html";}}"#;
+    let params = HashMap::from([
+        ("cid", "1".to_string()),
+        ("slug", "hello".to_string()),
+        ("category", "default".to_string()),
+    ]);
+    assert_eq!(
+        "/default/hello-1.html",
+        build_permalink(&get_route_url(table, "post").unwrap(), &params)
+    );
+}
+
+#[test]
+fn test_parse_content() {
+    let options = HashMap::from([("siteUrl".to_string(), "https://rumo.cf/".to_string())]);
+    let permalinks = Permalinks::from_options(&options);
+    let (name, params) = permalinks
+        .parse_content("http://rumo.cf/archives/12/?replytocom=1")
+        .unwrap();
+    assert_eq!("post", name);
+    assert_eq!(Some(&"12".to_string()), params.get("cid"));
+    let (name, params) = permalinks
+        .parse_content("https://rumo.cf/about.html")
+        .unwrap();
+    assert_eq!("page", name);
+    assert_eq!(Some(&"about".to_string()), params.get("slug"));
+    assert_eq!(
+        None,
+        permalinks.parse_content("https://other.cf/archives/12/")
+    );
+    assert_eq!(
+        None,
+        permalinks.parse_content("https://rumo.cf/archives/abc/")
+    );
+}
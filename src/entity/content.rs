@@ -1,8 +1,20 @@
 use sea_orm::entity::prelude::*;
 use serde::{Deserialize, Serialize};
 
+lazy_static! {
+    static ref TABLE_NAME: String = format!("{}contents", *crate::TABLE_PREFIX);
+}
+
+#[derive(Copy, Clone, Default, Debug, DeriveEntity)]
+pub struct Entity;
+
+impl EntityName for Entity {
+    fn table_name(&self) -> &str {
+        &TABLE_NAME
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
-#[sea_orm(table_name = "typecho_contents")]
 pub struct Model {
     #[sea_orm(primary_key)]
     #[serde(skip_deserializing)]
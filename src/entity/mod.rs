@@ -1,7 +1,12 @@
 pub mod comment;
 pub mod content;
 pub mod field;
+pub mod mail;
 pub mod meta;
 pub mod option;
+pub mod redirect;
 pub mod relationship;
+pub mod trash;
+pub mod unsubscribe;
 pub mod user;
+pub mod webmention;
@@ -0,0 +1,36 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+lazy_static! {
+    static ref TABLE_NAME: String = format!("{}trash", *crate::TABLE_PREFIX);
+}
+
+#[derive(Copy, Clone, Default, Debug, DeriveEntity)]
+pub struct Entity;
+
+impl EntityName for Entity {
+    fn table_name(&self) -> &str {
+        &TABLE_NAME
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    #[serde(skip_deserializing)]
+    pub tid: u32,
+    pub r#type: String,
+    #[sea_orm(column_name = "itemId")]
+    pub item_id: u32,
+    pub status: String,
+    pub created: u32,
+    #[sea_orm(column_name = "authorId")]
+    pub author_id: u32,
+    #[sea_orm(column_name = "deleteAttachments")]
+    pub delete_attachments: bool,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,39 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+lazy_static! {
+    static ref TABLE_NAME: String = format!("{}webmentions", *crate::TABLE_PREFIX);
+}
+
+#[derive(Copy, Clone, Default, Debug, DeriveEntity)]
+pub struct Entity;
+
+impl EntityName for Entity {
+    fn table_name(&self) -> &str {
+        &TABLE_NAME
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    #[serde(skip_deserializing)]
+    pub wid: u32,
+    pub cid: u32,
+    pub source: String,
+    pub target: String,
+    pub direction: String,
+    pub ip: Option<String>,
+    pub status: String,
+    pub attempts: u32,
+    #[sea_orm(column_name = "nextAttempt")]
+    pub next_attempt: u32,
+    #[sea_orm(column_name = "lastError")]
+    pub last_error: Option<String>,
+    pub created: u32,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
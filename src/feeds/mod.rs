@@ -0,0 +1,6 @@
+mod models;
+mod urls;
+mod utils;
+mod views;
+
+pub use urls::feeds_routers;
@@ -0,0 +1,17 @@
+pub struct FeedInfo {
+    pub title: String,
+    pub description: String,
+    pub link: String,
+    pub feed_url: String,
+    pub updated: u32,
+}
+
+pub struct FeedItem {
+    pub title: String,
+    pub link: String,
+    pub content: String,
+    pub author: String,
+    pub created: u32,
+    pub modified: u32,
+    pub categories: Vec<String>,
+}
@@ -0,0 +1,21 @@
+use axum::{routing::get, Router};
+use std::sync::Arc;
+
+use super::views;
+use crate::AppState;
+
+pub fn feeds_routers(_ro: bool) -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/feed", get(views::get_feed))
+        .route("/feed/:format", get(views::get_feed_by_format))
+        .route("/api/categories/:slug/feed", get(views::get_category_feed))
+        .route(
+            "/api/categories/:slug/feed/:format",
+            get(views::get_category_feed_by_format),
+        )
+        .route("/api/tags/:slug/feed", get(views::get_tag_feed))
+        .route(
+            "/api/tags/:slug/feed/:format",
+            get(views::get_tag_feed_by_format),
+        )
+}
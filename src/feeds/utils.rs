@@ -0,0 +1,185 @@
+use chrono::DateTime;
+use serde_json::{json, Value};
+
+use super::models::{FeedInfo, FeedItem};
+use crate::common::markdown::{get_excerpt, render_text_to_html};
+use crate::common::utils::escape_xml;
+
+fn to_rfc2822(timestamp: u32) -> String {
+    DateTime::from_timestamp(timestamp as i64, 0)
+        .unwrap_or_default()
+        .to_rfc2822()
+}
+
+fn to_rfc3339(timestamp: u32) -> String {
+    DateTime::from_timestamp(timestamp as i64, 0)
+        .unwrap_or_default()
+        .to_rfc3339()
+}
+
+/// Renders the text the same way as `format=html`, only the part before `<!--more-->`
+/// unless `full_text` is set.
+pub fn get_feed_content(
+    cid: u32,
+    modified: u32,
+    text: &str,
+    link: &str,
+    title: &str,
+    full_text: bool,
+) -> String {
+    if full_text || !text.contains("<!--more-->") {
+        return render_text_to_html(cid, modified, text);
+    }
+
+    format!(
+        "{}<p class=\"more\"><a href=\"{}\" title=\"{}\">[...]</a></p>",
+        get_excerpt(cid, modified, text, 0, true),
+        escape_xml(link),
+        escape_xml(title)
+    )
+}
+
+pub fn render_rss(info: &FeedInfo, items: &[FeedItem]) -> String {
+    let mut output = String::from(r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+    output.push_str(
+        r#"<rss version="2.0" xmlns:content="http://purl.org/rss/1.0/modules/content/" xmlns:dc="http://purl.org/dc/elements/1.1/" xmlns:atom="http://www.w3.org/2005/Atom">"#,
+    );
+    output.push_str("<channel>");
+    output.push_str(&format!("<title>{}</title>", escape_xml(&info.title)));
+    output.push_str(&format!("<link>{}</link>", escape_xml(&info.link)));
+    output.push_str(&format!(
+        r#"<atom:link href="{}" rel="self" type="application/rss+xml" />"#,
+        escape_xml(&info.feed_url)
+    ));
+    output.push_str(&format!(
+        "<description>{}</description>",
+        escape_xml(&info.description)
+    ));
+    output.push_str(&format!(
+        "<lastBuildDate>{}</lastBuildDate>",
+        to_rfc2822(info.updated)
+    ));
+
+    for item in items {
+        output.push_str("<item>");
+        output.push_str(&format!("<title>{}</title>", escape_xml(&item.title)));
+        output.push_str(&format!("<link>{}</link>", escape_xml(&item.link)));
+        output.push_str(&format!("<guid>{}</guid>", escape_xml(&item.link)));
+        output.push_str(&format!("<pubDate>{}</pubDate>", to_rfc2822(item.created)));
+        output.push_str(&format!(
+            "<dc:creator>{}</dc:creator>",
+            escape_xml(&item.author)
+        ));
+        for category in &item.categories {
+            output.push_str(&format!("<category>{}</category>", escape_xml(category)));
+        }
+        output.push_str(&format!(
+            "<description>{}</description>",
+            escape_xml(&item.content)
+        ));
+        output.push_str(&format!(
+            "<content:encoded>{}</content:encoded>",
+            escape_xml(&item.content)
+        ));
+        output.push_str("</item>");
+    }
+
+    output.push_str("</channel></rss>");
+    output
+}
+
+pub fn render_atom(info: &FeedInfo, items: &[FeedItem]) -> String {
+    let mut output = String::from(r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+    output.push_str(r#"<feed xmlns="http://www.w3.org/2005/Atom">"#);
+    output.push_str(&format!("<title>{}</title>", escape_xml(&info.title)));
+    output.push_str(&format!(
+        "<subtitle>{}</subtitle>",
+        escape_xml(&info.description)
+    ));
+    output.push_str(&format!("<id>{}</id>", escape_xml(&info.feed_url)));
+    output.push_str(&format!(
+        r#"<link href="{}" rel="alternate" type="text/html" />"#,
+        escape_xml(&info.link)
+    ));
+    output.push_str(&format!(
+        r#"<link href="{}" rel="self" type="application/atom+xml" />"#,
+        escape_xml(&info.feed_url)
+    ));
+    output.push_str(&format!("<updated>{}</updated>", to_rfc3339(info.updated)));
+    output.push_str(&format!(
+        "<author><name>{}</name></author>",
+        escape_xml(&info.title)
+    ));
+
+    for item in items {
+        output.push_str("<entry>");
+        output.push_str(&format!("<title>{}</title>", escape_xml(&item.title)));
+        output.push_str(&format!(
+            r#"<link href="{}" rel="alternate" type="text/html" />"#,
+            escape_xml(&item.link)
+        ));
+        output.push_str(&format!("<id>{}</id>", escape_xml(&item.link)));
+        output.push_str(&format!(
+            "<published>{}</published>",
+            to_rfc3339(item.created)
+        ));
+        output.push_str(&format!("<updated>{}</updated>", to_rfc3339(item.modified)));
+        output.push_str(&format!(
+            "<author><name>{}</name></author>",
+            escape_xml(&item.author)
+        ));
+        for category in &item.categories {
+            output.push_str(&format!(r#"<category term="{}" />"#, escape_xml(category)));
+        }
+        output.push_str(&format!(
+            r#"<content type="html">{}</content>"#,
+            escape_xml(&item.content)
+        ));
+        output.push_str("</entry>");
+    }
+
+    output.push_str("</feed>");
+    output
+}
+
+pub fn render_json(info: &FeedInfo, items: &[FeedItem]) -> Value {
+    let items: Vec<Value> = items
+        .iter()
+        .map(|item| {
+            json!({
+                "id": item.link,
+                "url": item.link,
+                "title": item.title,
+                "content_html": item.content,
+                "date_published": to_rfc3339(item.created),
+                "date_modified": to_rfc3339(item.modified),
+                "authors": [{ "name": item.author }],
+                "tags": item.categories,
+            })
+        })
+        .collect();
+
+    json!({
+        "version": "https://jsonfeed.org/version/1.1",
+        "title": info.title,
+        "description": info.description,
+        "home_page_url": info.link,
+        "feed_url": info.feed_url,
+        "items": items,
+    })
+}
+
+#[test]
+fn test_get_feed_content() {
+    let text = "<!--markdown-->## Title\n\n[link](https://rumo.cf/)\n\n<!--more-->\n\n**more**";
+    let excerpt = get_feed_content(100, 1, text, "https://rumo.cf/1", "a&b", false);
+    assert!(excerpt.starts_with("<h2 id=\"title\">Title</h2>\n<p><a href=\"https://rumo.cf/\""));
+    assert!(!excerpt.contains("more</strong>"));
+    assert!(excerpt.ends_with(
+        "<p class=\"more\"><a href=\"https://rumo.cf/1\" title=\"a&amp;b\">[...]</a></p>"
+    ));
+
+    let full = get_feed_content(100, 1, text, "https://rumo.cf/1", "a&b", true);
+    assert!(full.contains("<h2 id=\"title\">Title</h2>"));
+    assert!(full.contains("<strong>more</strong>"));
+}
@@ -0,0 +1,219 @@
+use std::collections::{hash_map::Entry, HashMap};
+use std::sync::Arc;
+
+use axum::extract::{Path, State};
+use axum::http::header;
+use axum::response::{IntoResponse, Json, Response};
+
+use super::models::{FeedInfo, FeedItem};
+use super::utils::{get_feed_content, render_atom, render_json, render_rss};
+use crate::common::db::{self as common_db, ContentsPage};
+use crate::common::errors::FieldError;
+use crate::common::extractors::PMVisitor;
+use crate::common::models::ContentWithMetasUsersFields;
+use crate::common::utils::Permalinks;
+use crate::entity::{meta, user};
+use crate::posts::db as posts_db;
+use crate::tags::db as tags_db;
+use crate::users::db as users_db;
+use crate::AppState;
+
+const FEED_SIZE: u64 = 10;
+
+async fn get_feed_items(
+    state: &AppState,
+    permalinks: &Permalinks,
+    full_text: bool,
+    posts: Vec<ContentWithMetasUsersFields>,
+) -> Vec<FeedItem> {
+    let mut authors: HashMap<u32, String> = HashMap::new();
+    let mut items = vec![];
+    for post in posts {
+        if let Entry::Vacant(e) = authors.entry(post.author_id) {
+            let name = match users_db::get_user_by_uid(state, post.author_id).await {
+                Ok(Some(u)) => u.screen_name.or(u.name).unwrap_or_default(),
+                _ => String::new(),
+            };
+            e.insert(name);
+        }
+
+        let title = post.title.unwrap_or_default();
+        let link = permalinks.content(
+            &post.r#type,
+            post.cid,
+            post.slug.as_deref().unwrap_or_default(),
+            post.created,
+            post.categories.first().and_then(|m| m.slug.as_deref()),
+        );
+        let content = if post.password.as_deref().is_some_and(|p| !p.is_empty()) {
+            String::new()
+        } else {
+            get_feed_content(
+                post.cid,
+                post.modified,
+                post.text.as_deref().unwrap_or_default(),
+                &link,
+                &title,
+                full_text,
+            )
+        };
+        let categories = post
+            .categories
+            .into_iter()
+            .chain(post.tags)
+            .filter_map(|m| m.name)
+            .collect();
+
+        items.push(FeedItem {
+            title,
+            link,
+            content,
+            author: authors.get(&post.author_id).cloned().unwrap_or_default(),
+            created: post.created,
+            modified: post.modified,
+            categories,
+        });
+    }
+    items
+}
+
+async fn render_feed(
+    state: &AppState,
+    user: &user::Model,
+    meta: Option<meta::Model>,
+    path: &str,
+    format: &str,
+) -> Result<Response, FieldError> {
+    let options = common_db::get_site_options(state).await?;
+    let site_title = options.get("title").cloned().unwrap_or_default();
+    let description = options.get("description").cloned().unwrap_or_default();
+    let permalinks = Permalinks::from_options(&options);
+    let full_text = options.get("feedFullText").map(|s| s.as_str()) != Some("0");
+
+    let (title, posts) = match meta {
+        Some(m) => {
+            let mids = [m.mid];
+            let contents_page = ContentsPage {
+                page_size: FEED_SIZE,
+                page: 1,
+                order_by: "-cid",
+            };
+            let posts =
+                common_db::get_contents_with_metas_user_and_fields_by_mids_list_query_and_private(
+                    state,
+                    &mids,
+                    false,
+                    &contents_page,
+                    true,
+                    true,
+                )
+                .await?;
+            let title = format!("{} - {}", site_title, m.name.unwrap_or_default());
+            (title, posts)
+        }
+        None => {
+            let posts = posts_db::get_contents_with_metas_user_and_fields_by_filter_and_list_query(
+                state, false, false, user, FEED_SIZE, 1, "-cid", true, true,
+            )
+            .await?;
+            (site_title, posts)
+        }
+    };
+
+    let updated = posts.iter().map(|p| p.modified).max().unwrap_or(0);
+    let items = get_feed_items(state, &permalinks, full_text, posts).await;
+    let info = FeedInfo {
+        title,
+        description,
+        link: format!("{}/", permalinks.site_url),
+        feed_url: format!("{}{}", permalinks.site_url, path),
+        updated,
+    };
+
+    match format {
+        "rss" => Ok((
+            [(header::CONTENT_TYPE, "application/rss+xml; charset=UTF-8")],
+            render_rss(&info, &items),
+        )
+            .into_response()),
+        "atom" => Ok((
+            [(header::CONTENT_TYPE, "application/atom+xml; charset=UTF-8")],
+            render_atom(&info, &items),
+        )
+            .into_response()),
+        "json" => Ok((
+            [(header::CONTENT_TYPE, "application/feed+json; charset=UTF-8")],
+            Json(render_json(&info, &items)),
+        )
+            .into_response()),
+        _ => Err(FieldError::NotFound("format".to_string())),
+    }
+}
+
+pub async fn get_feed(
+    State(state): State<Arc<AppState>>,
+    PMVisitor(user): PMVisitor,
+) -> Result<Response, FieldError> {
+    render_feed(&state, &user, None, "/feed", "rss").await
+}
+
+pub async fn get_feed_by_format(
+    State(state): State<Arc<AppState>>,
+    PMVisitor(user): PMVisitor,
+    Path(format): Path<String>,
+) -> Result<Response, FieldError> {
+    let path = format!("/feed/{}", format);
+    render_feed(&state, &user, None, &path, &format).await
+}
+
+pub async fn get_category_feed(
+    State(state): State<Arc<AppState>>,
+    PMVisitor(user): PMVisitor,
+    Path(slug): Path<String>,
+) -> Result<Response, FieldError> {
+    let category = match common_db::get_meta_by_slug(&state, &slug, false).await {
+        Ok(Some(c)) => c,
+        _ => return Err(FieldError::NotFound("slug".to_string())),
+    };
+    let path = format!("/api/categories/{}/feed", slug);
+    render_feed(&state, &user, Some(category), &path, "rss").await
+}
+
+pub async fn get_category_feed_by_format(
+    State(state): State<Arc<AppState>>,
+    PMVisitor(user): PMVisitor,
+    Path((slug, format)): Path<(String, String)>,
+) -> Result<Response, FieldError> {
+    let category = match common_db::get_meta_by_slug(&state, &slug, false).await {
+        Ok(Some(c)) => c,
+        _ => return Err(FieldError::NotFound("slug".to_string())),
+    };
+    let path = format!("/api/categories/{}/feed/{}", slug, format);
+    render_feed(&state, &user, Some(category), &path, &format).await
+}
+
+pub async fn get_tag_feed(
+    State(state): State<Arc<AppState>>,
+    PMVisitor(user): PMVisitor,
+    Path(slug): Path<String>,
+) -> Result<Response, FieldError> {
+    let tag = match tags_db::get_tag_by_slug_with_redirect(&state, &slug).await {
+        Ok(Some(t)) => t,
+        _ => return Err(FieldError::NotFound("slug".to_string())),
+    };
+    let path = format!("/api/tags/{}/feed", slug);
+    render_feed(&state, &user, Some(tag), &path, "rss").await
+}
+
+pub async fn get_tag_feed_by_format(
+    State(state): State<Arc<AppState>>,
+    PMVisitor(user): PMVisitor,
+    Path((slug, format)): Path<(String, String)>,
+) -> Result<Response, FieldError> {
+    let tag = match tags_db::get_tag_by_slug_with_redirect(&state, &slug).await {
+        Ok(Some(t)) => t,
+        _ => return Err(FieldError::NotFound("slug".to_string())),
+    };
+    let path = format!("/api/tags/{}/feed/{}", slug, format);
+    render_feed(&state, &user, Some(tag), &path, &format).await
+}
@@ -1,10 +1,11 @@
 use std::time::SystemTime;
 
 use sea_orm::*;
+use tracing::warn;
 
-use super::entity::{option, option::Entity as Option, user};
+use super::entity::{meta, option, option::Entity as Option, user};
 use super::users::{forms::UserRegister, utils::hash};
-use super::AppState;
+use super::{AppState, TABLE_PREFIX};
 
 pub async fn init_table(state: &AppState) {
     let db_backend = state.conn.get_database_backend();
@@ -313,6 +314,7 @@ pub async fn init_table(state: &AppState) {
             "#
         }
     };
+    let sql = sql.replace("typecho_", &TABLE_PREFIX);
     let stmts = sql.split(";");
     for stmt in stmts {
         let stmt = stmt.trim();
@@ -327,6 +329,274 @@ pub async fn init_table(state: &AppState) {
     }
 }
 
+pub async fn init_search(state: &AppState) {
+    let db_backend = state.conn.get_database_backend();
+    let stmts = match db_backend {
+        DatabaseBackend::Postgres => vec![
+            r#"CREATE INDEX "typecho_contents_search" ON "typecho_contents" USING GIN (to_tsvector('simple', coalesce("title", '') || ' ' || coalesce("text", '')))"#,
+        ],
+        DatabaseBackend::MySql => {
+            vec!["ALTER TABLE `typecho_contents` ADD FULLTEXT INDEX `search` (`title`, `text`)"]
+        }
+        DatabaseBackend::Sqlite => vec![
+            r#"CREATE VIRTUAL TABLE typecho_contents_fts USING fts5(title, text, content='typecho_contents', content_rowid='cid', tokenize='trigram')"#,
+            r#"CREATE TRIGGER typecho_contents_fts_insert AFTER INSERT ON typecho_contents BEGIN
+                INSERT INTO typecho_contents_fts(rowid, title, text) VALUES (new.cid, new.title, new.text);
+            END"#,
+            r#"CREATE TRIGGER typecho_contents_fts_delete AFTER DELETE ON typecho_contents BEGIN
+                INSERT INTO typecho_contents_fts(typecho_contents_fts, rowid, title, text) VALUES ('delete', old.cid, old.title, old.text);
+            END"#,
+            r#"CREATE TRIGGER typecho_contents_fts_update AFTER UPDATE ON typecho_contents BEGIN
+                INSERT INTO typecho_contents_fts(typecho_contents_fts, rowid, title, text) VALUES ('delete', old.cid, old.title, old.text);
+                INSERT INTO typecho_contents_fts(rowid, title, text) VALUES (new.cid, new.title, new.text);
+            END"#,
+            "INSERT INTO typecho_contents_fts(typecho_contents_fts) VALUES ('rebuild')",
+        ],
+    };
+    for stmt in stmts {
+        let stmt = stmt.replace("typecho_", &TABLE_PREFIX);
+        if let Err(e) = state
+            .conn
+            .execute(Statement::from_string(db_backend, stmt))
+            .await
+        {
+            warn!("create search index failed, fallback to LIKE: {}", e);
+            return;
+        }
+    }
+}
+
+pub async fn init_trash(state: &AppState) {
+    let db_backend = state.conn.get_database_backend();
+    let stmts = match db_backend {
+        DatabaseBackend::Postgres => vec![
+            r#"CREATE SEQUENCE IF NOT EXISTS "typecho_trash_seq""#,
+            r#"CREATE TABLE IF NOT EXISTS "typecho_trash" (
+                "tid" INT NOT NULL DEFAULT nextval('typecho_trash_seq'),
+                "type" VARCHAR(16) NULL DEFAULT 'post',
+                "itemId" INT NULL DEFAULT '0',
+                "status" VARCHAR(16) NULL DEFAULT 'publish',
+                "created" INT NULL DEFAULT '0',
+                "authorId" INT NULL DEFAULT '0',
+                "deleteAttachments" BOOLEAN NOT NULL DEFAULT FALSE,
+                PRIMARY KEY ("tid")
+            )"#,
+        ],
+        DatabaseBackend::MySql => vec![
+            r#"CREATE TABLE IF NOT EXISTS `typecho_trash` (
+                `tid` int(10) unsigned NOT NULL auto_increment,
+                `type` varchar(16) default 'post',
+                `itemId` int(10) unsigned default '0',
+                `status` varchar(16) default 'publish',
+                `created` int(10) unsigned default '0',
+                `authorId` int(10) unsigned default '0',
+                `deleteAttachments` tinyint(1) NOT NULL default '0',
+                PRIMARY KEY  (`tid`),
+                KEY `created` (`created`)
+            ) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4"#,
+        ],
+        DatabaseBackend::Sqlite => vec![
+            r#"CREATE TABLE IF NOT EXISTS typecho_trash (
+                "tid" INTEGER NOT NULL PRIMARY KEY,
+                "type" varchar(16) default 'post' ,
+                "itemId" int(10) default '0' ,
+                "status" varchar(16) default 'publish' ,
+                "created" int(10) default '0' ,
+                "authorId" int(10) default '0' ,
+                "deleteAttachments" int(1) NOT NULL default '0'
+            )"#,
+            r#"CREATE INDEX IF NOT EXISTS typecho_trash_created ON typecho_trash ("created")"#,
+        ],
+    };
+    for stmt in stmts {
+        let stmt = stmt.replace("typecho_", &TABLE_PREFIX);
+        state
+            .conn
+            .execute(Statement::from_string(db_backend, stmt))
+            .await
+            .expect("create trash table failed");
+    }
+}
+
+pub async fn init_redirect(state: &AppState) {
+    let db_backend = state.conn.get_database_backend();
+    let stmts = match db_backend {
+        DatabaseBackend::Postgres => vec![
+            r#"CREATE SEQUENCE IF NOT EXISTS "typecho_redirects_seq""#,
+            r#"CREATE TABLE IF NOT EXISTS "typecho_redirects" (
+                "rid" INT NOT NULL DEFAULT nextval('typecho_redirects_seq'),
+                "type" VARCHAR(16) NOT NULL DEFAULT 'tag',
+                "slug" VARCHAR(150) NOT NULL,
+                "mid" INT NOT NULL DEFAULT '0',
+                PRIMARY KEY ("rid")
+            )"#,
+        ],
+        DatabaseBackend::MySql => vec![
+            r#"CREATE TABLE IF NOT EXISTS `typecho_redirects` (
+                `rid` int(10) unsigned NOT NULL auto_increment,
+                `type` varchar(16) NOT NULL default 'tag',
+                `slug` varchar(150) NOT NULL,
+                `mid` int(10) unsigned NOT NULL default '0',
+                PRIMARY KEY  (`rid`),
+                KEY `slug` (`slug`)
+            ) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4"#,
+        ],
+        DatabaseBackend::Sqlite => vec![
+            r#"CREATE TABLE IF NOT EXISTS typecho_redirects (
+                "rid" INTEGER NOT NULL PRIMARY KEY,
+                "type" varchar(16) NOT NULL default 'tag' ,
+                "slug" varchar(150) NOT NULL ,
+                "mid" int(10) NOT NULL default '0'
+            )"#,
+            r#"CREATE INDEX IF NOT EXISTS typecho_redirects_slug ON typecho_redirects ("slug")"#,
+        ],
+    };
+    for stmt in stmts {
+        let stmt = stmt.replace("typecho_", &TABLE_PREFIX);
+        state
+            .conn
+            .execute(Statement::from_string(db_backend, stmt))
+            .await
+            .expect("create redirects table failed");
+    }
+}
+
+pub async fn init_mail(state: &AppState) {
+    let db_backend = state.conn.get_database_backend();
+    let stmts = match db_backend {
+        DatabaseBackend::Postgres => vec![
+            r#"CREATE SEQUENCE IF NOT EXISTS "typecho_mails_seq""#,
+            r#"CREATE TABLE IF NOT EXISTS "typecho_mails" (
+                "qid" INT NOT NULL DEFAULT nextval('typecho_mails_seq'),
+                "recipient" VARCHAR(150) NOT NULL,
+                "subject" VARCHAR(255) NOT NULL,
+                "body" TEXT NOT NULL,
+                "status" VARCHAR(16) NOT NULL DEFAULT 'pending',
+                "attempts" INT NOT NULL DEFAULT '0',
+                "nextAttempt" INT NOT NULL DEFAULT '0',
+                "lastError" VARCHAR(255) NULL DEFAULT NULL,
+                "created" INT NOT NULL DEFAULT '0',
+                PRIMARY KEY ("qid")
+            )"#,
+            r#"CREATE TABLE IF NOT EXISTS "typecho_unsubscribes" (
+                "mail" VARCHAR(150) NOT NULL,
+                "created" INT NOT NULL DEFAULT '0',
+                PRIMARY KEY ("mail")
+            )"#,
+        ],
+        DatabaseBackend::MySql => vec![
+            r#"CREATE TABLE IF NOT EXISTS `typecho_mails` (
+                `qid` int(10) unsigned NOT NULL auto_increment,
+                `recipient` varchar(150) NOT NULL,
+                `subject` varchar(255) NOT NULL,
+                `body` text NOT NULL,
+                `status` varchar(16) NOT NULL default 'pending',
+                `attempts` int(10) unsigned NOT NULL default '0',
+                `nextAttempt` int(10) unsigned NOT NULL default '0',
+                `lastError` varchar(255) default NULL,
+                `created` int(10) unsigned NOT NULL default '0',
+                PRIMARY KEY  (`qid`),
+                KEY `nextAttempt` (`nextAttempt`)
+            ) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4"#,
+            r#"CREATE TABLE IF NOT EXISTS `typecho_unsubscribes` (
+                `mail` varchar(150) NOT NULL,
+                `created` int(10) unsigned NOT NULL default '0',
+                PRIMARY KEY  (`mail`)
+            ) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4"#,
+        ],
+        DatabaseBackend::Sqlite => vec![
+            r#"CREATE TABLE IF NOT EXISTS typecho_mails (
+                "qid" INTEGER NOT NULL PRIMARY KEY,
+                "recipient" varchar(150) NOT NULL ,
+                "subject" varchar(255) NOT NULL ,
+                "body" text NOT NULL ,
+                "status" varchar(16) NOT NULL default 'pending' ,
+                "attempts" int(10) NOT NULL default '0' ,
+                "nextAttempt" int(10) NOT NULL default '0' ,
+                "lastError" varchar(255) default NULL ,
+                "created" int(10) NOT NULL default '0'
+            )"#,
+            r#"CREATE INDEX IF NOT EXISTS typecho_mails_nextAttempt ON typecho_mails ("nextAttempt")"#,
+            r#"CREATE TABLE IF NOT EXISTS typecho_unsubscribes (
+                "mail" varchar(150) NOT NULL PRIMARY KEY,
+                "created" int(10) NOT NULL default '0'
+            )"#,
+        ],
+    };
+    for stmt in stmts {
+        let stmt = stmt.replace("typecho_", &TABLE_PREFIX);
+        state
+            .conn
+            .execute(Statement::from_string(db_backend, stmt))
+            .await
+            .expect("create mails table failed");
+    }
+}
+
+pub async fn init_webmention(state: &AppState) {
+    let db_backend = state.conn.get_database_backend();
+    let stmts = match db_backend {
+        DatabaseBackend::Postgres => vec![
+            r#"CREATE SEQUENCE IF NOT EXISTS "typecho_webmentions_seq""#,
+            r#"CREATE TABLE IF NOT EXISTS "typecho_webmentions" (
+                "wid" INT NOT NULL DEFAULT nextval('typecho_webmentions_seq'),
+                "cid" INT NOT NULL DEFAULT '0',
+                "source" VARCHAR(255) NOT NULL,
+                "target" VARCHAR(255) NOT NULL,
+                "direction" VARCHAR(16) NOT NULL DEFAULT 'in',
+                "ip" VARCHAR(64) NULL DEFAULT NULL,
+                "status" VARCHAR(16) NOT NULL DEFAULT 'pending',
+                "attempts" INT NOT NULL DEFAULT '0',
+                "nextAttempt" INT NOT NULL DEFAULT '0',
+                "lastError" VARCHAR(255) NULL DEFAULT NULL,
+                "created" INT NOT NULL DEFAULT '0',
+                PRIMARY KEY ("wid")
+            )"#,
+        ],
+        DatabaseBackend::MySql => vec![
+            r#"CREATE TABLE IF NOT EXISTS `typecho_webmentions` (
+                `wid` int(10) unsigned NOT NULL auto_increment,
+                `cid` int(10) unsigned NOT NULL default '0',
+                `source` varchar(255) NOT NULL,
+                `target` varchar(255) NOT NULL,
+                `direction` varchar(16) NOT NULL default 'in',
+                `ip` varchar(64) default NULL,
+                `status` varchar(16) NOT NULL default 'pending',
+                `attempts` int(10) unsigned NOT NULL default '0',
+                `nextAttempt` int(10) unsigned NOT NULL default '0',
+                `lastError` varchar(255) default NULL,
+                `created` int(10) unsigned NOT NULL default '0',
+                PRIMARY KEY  (`wid`),
+                KEY `nextAttempt` (`nextAttempt`)
+            ) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4"#,
+        ],
+        DatabaseBackend::Sqlite => vec![
+            r#"CREATE TABLE IF NOT EXISTS typecho_webmentions (
+                "wid" INTEGER NOT NULL PRIMARY KEY,
+                "cid" int(10) NOT NULL default '0' ,
+                "source" varchar(255) NOT NULL ,
+                "target" varchar(255) NOT NULL ,
+                "direction" varchar(16) NOT NULL default 'in' ,
+                "ip" varchar(64) default NULL ,
+                "status" varchar(16) NOT NULL default 'pending' ,
+                "attempts" int(10) NOT NULL default '0' ,
+                "nextAttempt" int(10) NOT NULL default '0' ,
+                "lastError" varchar(255) default NULL ,
+                "created" int(10) NOT NULL default '0'
+            )"#,
+            r#"CREATE INDEX IF NOT EXISTS typecho_webmentions_nextAttempt ON typecho_webmentions ("nextAttempt")"#,
+        ],
+    };
+    for stmt in stmts {
+        let stmt = stmt.replace("typecho_", &TABLE_PREFIX);
+        state
+            .conn
+            .execute(Statement::from_string(db_backend, stmt))
+            .await
+            .expect("create webmentions table failed");
+    }
+}
+
 pub async fn init_admin(state: &AppState, user_register: UserRegister) {
     let now = SystemTime::now()
         .duration_since(SystemTime::UNIX_EPOCH)
@@ -349,6 +619,20 @@ pub async fn init_admin(state: &AppState, user_register: UserRegister) {
     .expect("user already exists");
 }
 
+pub async fn init_category(state: &AppState) {
+    // same as typecho, the first category is the `defaultCategory`
+    meta::ActiveModel {
+        name: Set(Some("默认分类".to_string())),
+        slug: Set(Some("default".to_string())),
+        r#type: Set("category".to_string()),
+        description: Set(Some("只是一个默认分类".to_string())),
+        ..Default::default()
+    }
+    .insert(&state.conn)
+    .await
+    .expect("insert category failed");
+}
+
 pub async fn init_options(state: &AppState) {
     let secret = format!("{}", &state.secret_key);
     let options = [
@@ -3,6 +3,7 @@ use std::fs;
 use std::sync::Arc;
 
 use axum::Router;
+pub use axum_client_ip::SecureClientIpSource;
 use minijinja::Environment;
 use sea_orm::{Database, DatabaseConnection};
 use tower_http::trace::TraceLayer;
@@ -11,24 +12,48 @@ use tracing::info;
 #[macro_use]
 extern crate lazy_static;
 
+mod archives;
 mod attachments;
 mod categories;
 mod comments;
 mod common;
 mod entity;
+mod feeds;
 mod init;
+mod mails;
+mod micropub;
 mod pages;
 mod posts;
 mod preload;
+mod repair;
+mod search;
+mod sitemaps;
 mod tags;
+mod trash;
 mod users;
+mod webmentions;
+mod xmlrpc;
+use archives::archives_routers;
 use attachments::attachments_routers;
 use categories::categories_routers;
 use comments::comments_routers;
+pub use common::http::{DefaultHttpClient, HttpClient, HttpResponse};
+use feeds::feeds_routers;
+use mails::mails_routers;
+pub use mails::scheduler::send_pending_mails;
+pub use mails::{SmtpConfig, SmtpSecurity, COMMENT_MAIL_TPL, REPLY_MAIL_TPL};
+use micropub::micropub_routers;
 use pages::pages_routers;
 use posts::posts_routers;
+use repair::repair_routers;
+use search::search_routers;
+use sitemaps::sitemaps_routers;
 use tags::tags_routers;
+pub use trash::scheduler::purge_trash_created_before;
+use trash::trash_routers;
 use users::{forms::UserRegister, users_routers};
+use webmentions::webmentions_routers;
+use xmlrpc::xmlrpc_routers;
 
 lazy_static! {
     pub static ref INDEX_TPL: String = {
@@ -53,6 +78,16 @@ lazy_static! {
         }
         index_page
     };
+    pub static ref TABLE_PREFIX: String = {
+        let prefix = env::var("TABLE_PREFIX").unwrap_or(String::from("typecho_"));
+        if !prefix
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_')
+        {
+            panic!("TABLE_PREFIX is invalid")
+        }
+        prefix
+    };
 }
 
 #[derive(Clone)]
@@ -60,10 +95,15 @@ pub struct AppState {
     pub conn: DatabaseConnection,
     pub secret_key: String,
     pub access_token_expire_secondes: u64,
+    pub excerpt_length: usize,
+    pub trash_retention_days: u32,
     pub upload_root: String,
     pub read_only: bool,
     pub preload_index: bool,
     pub jinja_env: Environment<'static>,
+    pub smtp: Option<SmtpConfig>,
+    pub http_client: Arc<dyn HttpClient>,
+    pub client_ip_source: SecureClientIpSource,
 }
 
 async fn get_state(app_state: Option<AppState>) -> AppState {
@@ -81,6 +121,16 @@ async fn get_state(app_state: Option<AppState>) -> AppState {
                 .parse::<u64>()
                 .expect("TOKEN_EXPIRE is invalid");
 
+            let excerpt_length = env::var("EXCERPT_LENGTH")
+                .unwrap_or("200".to_string())
+                .parse::<usize>()
+                .expect("EXCERPT_LENGTH is invalid");
+
+            let trash_retention_days = env::var("TRASH_RETENTION_DAYS")
+                .unwrap_or("30".to_string())
+                .parse::<u32>()
+                .expect("TRASH_RETENTION_DAYS is invalid");
+
             let preload_index = match env::var("PRELOAD_INDEX") {
                 Ok(s) => {
                     if s == "true" {
@@ -94,6 +144,12 @@ async fn get_state(app_state: Option<AppState>) -> AppState {
 
             let mut jinja_env = Environment::new();
             jinja_env.add_template("index.html", &INDEX_TPL).unwrap();
+            jinja_env
+                .add_template("mail_comment.txt", COMMENT_MAIL_TPL)
+                .unwrap();
+            jinja_env
+                .add_template("mail_reply.txt", REPLY_MAIL_TPL)
+                .unwrap();
 
             let upload_root = env::var("UPLOAD_ROOT").unwrap_or(String::from("."));
             let read_only = match env::var("READ_ONLY") {
@@ -107,14 +163,43 @@ async fn get_state(app_state: Option<AppState>) -> AppState {
                 _ => false,
             };
 
+            let smtp = match env::var("SMTP_HOST") {
+                Ok(host) => Some(SmtpConfig {
+                    host,
+                    port: env::var("SMTP_PORT")
+                        .unwrap_or("25".to_string())
+                        .parse::<u16>()
+                        .expect("SMTP_PORT is invalid"),
+                    security: env::var("SMTP_SECURITY")
+                        .unwrap_or("none".to_string())
+                        .parse::<SmtpSecurity>()
+                        .expect("SMTP_SECURITY is invalid"),
+                    user: env::var("SMTP_USER").ok(),
+                    password: env::var("SMTP_PASSWORD").ok(),
+                    from: env::var("SMTP_FROM").expect("SMTP_FROM is required"),
+                }),
+                _ => None,
+            };
+
+            // behind a proxy the client ip comes from the header the proxy sets
+            let client_ip_source = env::var("CLIENT_IP_SOURCE")
+                .unwrap_or("ConnectInfo".to_string())
+                .parse::<SecureClientIpSource>()
+                .expect("CLIENT_IP_SOURCE is invalid");
+
             let s = AppState {
                 conn,
                 secret_key,
                 access_token_expire_secondes,
+                excerpt_length,
+                trash_retention_days,
                 upload_root,
                 read_only,
                 preload_index,
                 jinja_env,
+                smtp,
+                http_client: Arc::new(DefaultHttpClient),
+                client_ip_source,
             };
             s
         }
@@ -123,7 +208,20 @@ async fn get_state(app_state: Option<AppState>) -> AppState {
 }
 
 pub async fn app(app_state: Option<AppState>) -> Router {
+    let spawn_scheduler = app_state.is_none();
     let state = Arc::new(get_state(app_state).await);
+    if spawn_scheduler {
+        init::init_trash(&state).await;
+        init::init_redirect(&state).await;
+        init::init_mail(&state).await;
+        init::init_webmention(&state).await;
+        tokio::spawn(posts::scheduler::publish_scheduled_posts(state.clone()));
+        tokio::spawn(trash::scheduler::purge_expired_trash(state.clone()));
+        tokio::spawn(mails::scheduler::send_queued_mails(state.clone()));
+        tokio::spawn(webmentions::scheduler::process_queued_webmentions(
+            state.clone(),
+        ));
+    }
     let ro = state.read_only;
     let mut router = Router::new()
         .merge(users_routers(ro))
@@ -132,12 +230,25 @@ pub async fn app(app_state: Option<AppState>) -> Router {
         .merge(posts_routers(ro))
         .merge(pages_routers(ro))
         .merge(comments_routers(ro))
-        .merge(attachments_routers(ro));
+        .merge(attachments_routers(ro))
+        .merge(feeds_routers(ro))
+        .merge(sitemaps_routers(ro))
+        .merge(search_routers(ro))
+        .merge(archives_routers(ro))
+        .merge(trash_routers(ro))
+        .merge(repair_routers(ro))
+        .merge(mails_routers(ro))
+        .merge(webmentions_routers(ro))
+        .merge(xmlrpc_routers(ro))
+        .merge(micropub_routers(ro));
 
     if state.preload_index {
         router = router.fallback(preload::index);
     }
-    let app = router.layer(TraceLayer::new_for_http()).with_state(state);
+    let app = router
+        .layer(state.client_ip_source.clone().into_extension())
+        .layer(TraceLayer::new_for_http())
+        .with_state(state);
     app
 }
 
@@ -152,8 +263,36 @@ pub async fn init(name: String, mail: String, password: String) {
 
     init::init_table(&state).await;
     info!("schema created");
+    init::init_search(&state).await;
+    info!("search index created");
+    init::init_trash(&state).await;
+    info!("trash created");
+    init::init_redirect(&state).await;
+    info!("redirects created");
+    init::init_mail(&state).await;
+    info!("mails created");
+    init::init_webmention(&state).await;
+    info!("webmentions created");
+    init::init_category(&state).await;
+    info!("default category created");
     init::init_options(&state).await;
     info!("options created");
     init::init_admin(&state, user_register).await;
     info!("admin user created");
 }
+
+pub async fn repair(dry_run: bool) {
+    let state = get_state(None).await;
+
+    let changes = repair::db::repair_counts(&state, dry_run)
+        .await
+        .expect("repair counts failed");
+    for change in changes.iter() {
+        println!("{}", change);
+    }
+    if dry_run {
+        println!("{} counts to repair", changes.len());
+    } else {
+        println!("{} counts repaired", changes.len());
+    }
+}
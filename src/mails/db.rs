@@ -0,0 +1,113 @@
+use sea_orm::*;
+
+use crate::common::errors::FieldError;
+use crate::common::utils::get_now;
+use crate::entity::{mail, mail::Entity as Mail, unsubscribe, unsubscribe::Entity as Unsubscribe};
+use crate::AppState;
+
+pub async fn create_mail_with_params(
+    state: &AppState,
+    recipient: &str,
+    subject: &str,
+    body: &str,
+) -> Result<mail::Model, FieldError> {
+    let now = get_now();
+    mail::ActiveModel {
+        recipient: Set(recipient.to_string()),
+        subject: Set(subject.to_string()),
+        body: Set(body.to_string()),
+        status: Set("pending".to_string()),
+        attempts: Set(0),
+        next_attempt: Set(now),
+        created: Set(now),
+        ..Default::default()
+    }
+    .insert(&state.conn)
+    .await
+    .map_err(|_| FieldError::DatabaseFailed("create mail failed".to_string()))
+}
+
+pub async fn get_pending_mails_before(
+    state: &AppState,
+    now: u32,
+    limit: u64,
+) -> Result<Vec<mail::Model>, FieldError> {
+    Mail::find()
+        .filter(mail::Column::Status.eq("pending"))
+        .filter(mail::Column::NextAttempt.lte(now))
+        .order_by_asc(mail::Column::Qid)
+        .limit(limit)
+        .all(&state.conn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("fetch mails failed".to_string()))
+}
+
+/// Moves `next_attempt` to `lease_until` only if no other sender did it first.
+pub async fn claim_mail_until(
+    state: &AppState,
+    exist_mail: &mail::Model,
+    lease_until: u32,
+) -> Result<bool, FieldError> {
+    let res = Mail::update_many()
+        .col_expr(
+            mail::Column::NextAttempt,
+            sea_query::Expr::value(lease_until),
+        )
+        .filter(mail::Column::Qid.eq(exist_mail.qid))
+        .filter(mail::Column::Status.eq("pending"))
+        .filter(mail::Column::NextAttempt.eq(exist_mail.next_attempt))
+        .exec(&state.conn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("claim mail failed".to_string()))?;
+    Ok(res.rows_affected == 1)
+}
+
+pub async fn delete_mail_by_qid(state: &AppState, qid: u32) -> Result<DeleteResult, FieldError> {
+    Mail::delete_many()
+        .filter(mail::Column::Qid.eq(qid))
+        .exec(&state.conn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("delete mail failed".to_string()))
+}
+
+pub async fn modify_mail_for_retry(
+    state: &AppState,
+    exist_mail: mail::Model,
+    next_attempt: Option<u32>,
+    error: &str,
+) -> Result<mail::Model, FieldError> {
+    let attempts = exist_mail.attempts + 1;
+    let mut m = mail::ActiveModel::from(exist_mail);
+    m.attempts = Set(attempts);
+    m.last_error = Set(Some(error.chars().take(255).collect()));
+    match next_attempt {
+        Some(t) => m.next_attempt = Set(t),
+        None => m.status = Set("failed".to_string()),
+    }
+    m.update(&state.conn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("update mail failed".to_string()))
+}
+
+pub async fn check_unsubscribe_by_mail(state: &AppState, mail: &str) -> Result<bool, FieldError> {
+    let count = Unsubscribe::find()
+        .filter(unsubscribe::Column::Mail.eq(mail))
+        .count(&state.conn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("fetch unsubscribes failed".to_string()))?;
+    Ok(count > 0)
+}
+
+pub async fn create_unsubscribe_by_mail(state: &AppState, mail: &str) -> Result<(), FieldError> {
+    if check_unsubscribe_by_mail(state, mail).await? {
+        return Ok(());
+    }
+    unsubscribe::ActiveModel {
+        mail: Set(mail.to_string()),
+        created: Set(get_now()),
+    }
+    .insert(&state.conn)
+    .await
+    .map_err(|_| FieldError::DatabaseFailed("create unsubscribe failed".to_string()))?;
+    Ok(())
+}
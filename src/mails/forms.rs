@@ -0,0 +1,8 @@
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+#[derive(Serialize, Deserialize, Validate)]
+pub struct UnsubscribeQuery {
+    #[validate(length(min = 1, max = 512, message = "token can not be longer than 512"))]
+    pub token: String,
+}
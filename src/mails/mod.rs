@@ -0,0 +1,44 @@
+pub mod db;
+mod forms;
+pub mod notify;
+pub mod scheduler;
+mod smtp;
+mod urls;
+mod utils;
+mod views;
+
+pub use urls::mails_routers;
+
+pub const COMMENT_MAIL_TPL: &str = include_str!("templates/comment.txt");
+pub const REPLY_MAIL_TPL: &str = include_str!("templates/reply.txt");
+
+/// How the SMTP connection is encrypted, `Tls` is implicit TLS as on port 465.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SmtpSecurity {
+    None,
+    StartTls,
+    Tls,
+}
+
+impl std::str::FromStr for SmtpSecurity {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(SmtpSecurity::None),
+            "starttls" => Ok(SmtpSecurity::StartTls),
+            "tls" => Ok(SmtpSecurity::Tls),
+            _ => Err(format!("invalid smtp security {}", s)),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct SmtpConfig {
+    pub host: String,
+    pub port: u16,
+    pub security: SmtpSecurity,
+    pub user: Option<String>,
+    pub password: Option<String>,
+    pub from: String,
+}
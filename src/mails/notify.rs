@@ -0,0 +1,133 @@
+use minijinja::context;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tracing::warn;
+
+use super::db;
+use super::scheduler::send_pending_mails;
+use super::utils::{build_message, get_unsubscribe_token};
+use crate::comments::db as comments_db;
+use crate::common::db as common_db;
+use crate::common::errors::FieldError;
+use crate::common::utils::get_now;
+use crate::entity::{comment, content};
+use crate::users::db as users_db;
+use crate::AppState;
+
+/// Tells the content owner about a new comment and the parent author about a reply, then sends the queue.
+pub async fn notify_new_comment(
+    state: Arc<AppState>,
+    content: content::Model,
+    comment: comment::Model,
+) {
+    if let Err(e) = queue_comment_mails(&state, &content, &comment).await {
+        warn!("queue mails of comment {} failed: {:?}", comment.coid, e);
+    }
+    send_pending_mails(&state, get_now()).await;
+}
+
+async fn queue_comment_mails(
+    state: &AppState,
+    content: &content::Model,
+    comment: &comment::Model,
+) -> Result<(), FieldError> {
+    if state.smtp.is_none() || comment.status == "spam" {
+        return Ok(());
+    }
+    let options = common_db::get_site_options(state).await?;
+
+    let mut notified = vec![comment.mail.clone().unwrap_or_default()];
+    if comment.author_id != comment.owner_id {
+        if let Some(mail) = users_db::get_user_by_uid(state, comment.owner_id)
+            .await?
+            .and_then(|u| u.mail)
+        {
+            let subject = format!(
+                "《{}》有新评论",
+                content.title.as_deref().unwrap_or_default()
+            );
+            queue_mail(
+                state,
+                &options,
+                &mail,
+                &subject,
+                "mail_comment.txt",
+                context! {
+                    content => content,
+                    comment => comment,
+                },
+            )
+            .await?;
+            notified.push(mail);
+        }
+    }
+
+    // replies waiting for review are not shown, so their parent author hears nothing yet
+    if comment.parent == 0 || comment.status != "approved" {
+        return Ok(());
+    }
+    let parent = match comments_db::get_comment_by_coid(state, comment.parent).await? {
+        Some(p) => p,
+        None => return Ok(()),
+    };
+    if let Some(mail) = parent.mail.clone().filter(|m| !notified.contains(m)) {
+        let subject = format!(
+            "您在《{}》中的评论有了新回复",
+            content.title.as_deref().unwrap_or_default()
+        );
+        queue_mail(
+            state,
+            &options,
+            &mail,
+            &subject,
+            "mail_reply.txt",
+            context! {
+                content => content,
+                comment => comment,
+                parent => parent,
+            },
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+async fn queue_mail(
+    state: &AppState,
+    options: &HashMap<String, String>,
+    to: &str,
+    subject: &str,
+    template: &str,
+    ctx: minijinja::Value,
+) -> Result<(), FieldError> {
+    let config = match &state.smtp {
+        Some(c) => c,
+        None => return Ok(()),
+    };
+    if to.is_empty() || db::check_unsubscribe_by_mail(state, to).await? {
+        return Ok(());
+    }
+
+    let site_url = options.get("siteUrl").cloned().unwrap_or_default();
+    let unsubscribe_url = format!(
+        "{}/api/mails/unsubscribe?token={}",
+        site_url.trim_end_matches('/'),
+        get_unsubscribe_token(&state.secret_key, to)
+    );
+    let body = state
+        .jinja_env
+        .get_template(template)
+        .and_then(|tpl| {
+            tpl.render(context! {
+                site_url => site_url,
+                site_title => options.get("title"),
+                unsubscribe_url => unsubscribe_url,
+                ..ctx
+            })
+        })
+        .map_err(|_| FieldError::InvalidParams("template".to_string()))?;
+
+    let message = build_message(&config.from, to, subject, &body, Some(&unsubscribe_url));
+    db::create_mail_with_params(state, to, subject, &message).await?;
+    Ok(())
+}
@@ -0,0 +1,73 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use tracing::{info, warn};
+
+use super::db;
+use super::smtp::send_message;
+use crate::common::utils::get_now;
+use crate::AppState;
+
+const SEND_INTERVAL: u64 = 60;
+const SEND_BATCH: u64 = 50;
+const SEND_LEASE: u32 = 300;
+const MAX_ATTEMPTS: u32 = 5;
+
+pub async fn send_queued_mails(state: Arc<AppState>) {
+    if state.smtp.is_none() {
+        return;
+    }
+
+    let mut interval = tokio::time::interval(Duration::from_secs(SEND_INTERVAL));
+    loop {
+        interval.tick().await;
+        send_pending_mails(&state, get_now()).await;
+    }
+}
+
+/// Sends the mails due at `now` once, failures are retried with exponential backoff until `MAX_ATTEMPTS`.
+pub async fn send_pending_mails(state: &AppState, now: u32) {
+    let config = match &state.smtp {
+        Some(c) => c,
+        None => return,
+    };
+
+    let mails = match db::get_pending_mails_before(state, now, SEND_BATCH).await {
+        Ok(mails) => mails,
+        Err(e) => {
+            warn!("check pending mails failed: {:?}", e);
+            return;
+        }
+    };
+    for mail in mails {
+        match db::claim_mail_until(state, &mail, now + SEND_LEASE).await {
+            Ok(true) => (),
+            Ok(false) => continue,
+            Err(e) => {
+                warn!("claim mail {} failed: {:?}", mail.qid, e);
+                continue;
+            }
+        }
+
+        let qid = mail.qid;
+        match send_message(config, &mail.recipient, &mail.body).await {
+            Ok(_) => {
+                info!("mail sent: qid={} to={}", qid, mail.recipient);
+                if let Err(e) = db::delete_mail_by_qid(state, qid).await {
+                    warn!("delete mail {} failed: {:?}", qid, e);
+                }
+            }
+            Err(error) => {
+                warn!("send mail {} failed: {}", qid, error);
+                let next_attempt = if mail.attempts + 1 < MAX_ATTEMPTS {
+                    Some(now + SEND_INTERVAL as u32 * 2u32.pow(mail.attempts))
+                } else {
+                    None
+                };
+                if let Err(e) = db::modify_mail_for_retry(state, mail, next_attempt, &error).await {
+                    warn!("update mail {} failed: {:?}", qid, e);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,136 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+use super::{SmtpConfig, SmtpSecurity};
+use crate::common::http::get_tls_connection;
+
+const SMTP_TIMEOUT: u64 = 30;
+
+struct SmtpConnection<S: Read + Write> {
+    stream: BufReader<S>,
+}
+
+impl<S: Read + Write> SmtpConnection<S> {
+    fn new(stream: S) -> Self {
+        SmtpConnection {
+            stream: BufReader::new(stream),
+        }
+    }
+
+    /// Returns the lines of the reply, an unexpected code is an error.
+    fn read_reply(&mut self, expected: &str) -> Result<Vec<String>, String> {
+        let mut lines = vec![];
+        loop {
+            let mut line = String::new();
+            let n = self
+                .stream
+                .read_line(&mut line)
+                .map_err(|e| e.to_string())?;
+            if n == 0 {
+                return Err("smtp connection closed".to_string());
+            }
+            if !line.starts_with(expected) {
+                return Err(format!("smtp unexpected reply: {}", line.trim_end()));
+            }
+            // "250-" continues a multiline reply, "250 " ends it
+            let last = line.as_bytes().get(3) != Some(&b'-');
+            lines.push(line.trim_end().to_string());
+            if last {
+                return Ok(lines);
+            }
+        }
+    }
+
+    fn command(&mut self, command: &str, expected: &str) -> Result<Vec<String>, String> {
+        let stream = self.stream.get_mut();
+        stream
+            .write_all(format!("{}\r\n", command).as_bytes())
+            .and_then(|_| stream.flush())
+            .map_err(|e| e.to_string())?;
+        self.read_reply(expected)
+    }
+
+    fn send_mail(&mut self, config: &SmtpConfig, to: &str, message: &str) -> Result<(), String> {
+        if let (Some(user), Some(password)) = (&config.user, &config.password) {
+            let credentials = STANDARD.encode(format!("\0{}\0{}", user, password));
+            self.command(&format!("AUTH PLAIN {}", credentials), "235")?;
+        }
+        self.command(&format!("MAIL FROM:<{}>", config.from), "250")?;
+        self.command(&format!("RCPT TO:<{}>", to), "250")?;
+        self.command("DATA", "354")?;
+        self.command(&format!("{}\r\n.", message), "250")?;
+        self.command("QUIT", "221")?;
+        Ok(())
+    }
+}
+
+fn connect(config: &SmtpConfig) -> Result<TcpStream, String> {
+    let addr = (config.host.as_str(), config.port)
+        .to_socket_addrs()
+        .map_err(|e| e.to_string())?
+        .next()
+        .ok_or("smtp host not found")?;
+    let stream = TcpStream::connect_timeout(&addr, Duration::from_secs(SMTP_TIMEOUT))
+        .map_err(|e| e.to_string())?;
+    stream
+        .set_read_timeout(Some(Duration::from_secs(SMTP_TIMEOUT)))
+        .map_err(|e| e.to_string())?;
+    stream
+        .set_write_timeout(Some(Duration::from_secs(SMTP_TIMEOUT)))
+        .map_err(|e| e.to_string())?;
+    Ok(stream)
+}
+
+fn send_message_blocking(config: &SmtpConfig, to: &str, message: &str) -> Result<(), String> {
+    // credentials never go over a plain connection
+    if config.security == SmtpSecurity::None && (config.user.is_some() || config.password.is_some())
+    {
+        return Err("smtp auth requires tls".to_string());
+    }
+
+    let stream = connect(config)?;
+    match config.security {
+        SmtpSecurity::None => {
+            let mut conn = SmtpConnection::new(stream);
+            conn.read_reply("220")?;
+            conn.command("EHLO rumo", "250")?;
+            conn.send_mail(config, to, message)
+        }
+        SmtpSecurity::StartTls => {
+            let mut conn = SmtpConnection::new(stream);
+            conn.read_reply("220")?;
+            let extensions = conn.command("EHLO rumo", "250")?;
+            if !extensions.iter().any(|l| {
+                l.get(4..)
+                    .is_some_and(|e| e.eq_ignore_ascii_case("STARTTLS"))
+            }) {
+                return Err("smtp server does not support STARTTLS".to_string());
+            }
+            conn.command("STARTTLS", "220")?;
+            let tls = get_tls_connection(&config.host)?;
+            let stream = conn.stream.into_inner();
+            let mut conn = SmtpConnection::new(rustls::StreamOwned::new(tls, stream));
+            conn.command("EHLO rumo", "250")?;
+            conn.send_mail(config, to, message)
+        }
+        SmtpSecurity::Tls => {
+            let tls = get_tls_connection(&config.host)?;
+            let mut conn = SmtpConnection::new(rustls::StreamOwned::new(tls, stream));
+            conn.read_reply("220")?;
+            conn.command("EHLO rumo", "250")?;
+            conn.send_mail(config, to, message)
+        }
+    }
+}
+
+/// Sends a message that already carries its headers, AUTH PLAIN is only used over TLS.
+pub async fn send_message(config: &SmtpConfig, to: &str, message: &str) -> Result<(), String> {
+    let config = config.clone();
+    let to = to.to_string();
+    let message = message.to_string();
+    tokio::task::spawn_blocking(move || send_message_blocking(&config, &to, &message))
+        .await
+        .map_err(|e| e.to_string())?
+}
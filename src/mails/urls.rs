@@ -0,0 +1,17 @@
+use axum::{routing::get, Router};
+use std::sync::Arc;
+
+use super::views;
+use crate::AppState;
+
+pub fn mails_routers(ro: bool) -> Router<Arc<AppState>> {
+    let mails_route = Router::new();
+    if !ro {
+        mails_route.route(
+            "/api/mails/unsubscribe",
+            get(views::get_unsubscribe_by_token).post(views::unsubscribe_by_token),
+        )
+    } else {
+        mails_route
+    }
+}
@@ -0,0 +1,75 @@
+use base64::{
+    engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD},
+    Engine,
+};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+fn get_unsubscribe_signature(secret_key: &str, mail: &str) -> String {
+    let mut mac: Hmac<Sha256> = Hmac::new_from_slice(secret_key.as_bytes()).unwrap();
+    mac.update(format!("unsubscribe:{}", mail).as_bytes());
+    URL_SAFE_NO_PAD.encode(mac.finalize().into_bytes())
+}
+
+/// The token carries the mail itself, so no token has to be stored.
+pub fn get_unsubscribe_token(secret_key: &str, mail: &str) -> String {
+    format!(
+        "{}.{}",
+        URL_SAFE_NO_PAD.encode(mail),
+        get_unsubscribe_signature(secret_key, mail)
+    )
+}
+
+pub fn get_mail_by_unsubscribe_token(secret_key: &str, token: &str) -> Option<String> {
+    let (mail, signature) = token.split_once('.')?;
+    let mail = String::from_utf8(URL_SAFE_NO_PAD.decode(mail).ok()?).ok()?;
+    let mut mac: Hmac<Sha256> = Hmac::new_from_slice(secret_key.as_bytes()).ok()?;
+    mac.update(format!("unsubscribe:{}", mail).as_bytes());
+    mac.verify_slice(&URL_SAFE_NO_PAD.decode(signature).ok()?)
+        .ok()?;
+    Some(mail)
+}
+
+/// Builds a UTF-8 plain text message, the body is base64 encoded so no line needs dot-stuffing.
+pub fn build_message(
+    from: &str,
+    to: &str,
+    subject: &str,
+    body: &str,
+    unsubscribe_url: Option<&str>,
+) -> String {
+    let mut message = format!(
+        "From: {}\r\nTo: {}\r\nSubject: =?UTF-8?B?{}?=\r\nDate: {}\r\nMIME-Version: 1.0\r\n",
+        from,
+        to,
+        STANDARD.encode(subject),
+        chrono::Utc::now().to_rfc2822()
+    );
+    if let Some(url) = unsubscribe_url {
+        message.push_str(&format!("List-Unsubscribe: <{}>\r\n", url));
+        // RFC 8058, mail clients unsubscribe with a POST to the link
+        message.push_str("List-Unsubscribe-Post: List-Unsubscribe=One-Click\r\n");
+    }
+    message.push_str("Content-Type: text/plain; charset=UTF-8\r\n");
+    message.push_str("Content-Transfer-Encoding: base64\r\n\r\n");
+
+    let encoded = STANDARD.encode(body);
+    for line in encoded.as_bytes().chunks(76) {
+        message.push_str(std::str::from_utf8(line).unwrap());
+        message.push_str("\r\n");
+    }
+    message.trim_end().to_string()
+}
+
+#[test]
+fn test_unsubscribe_token() {
+    let token = get_unsubscribe_token("secret", "test@local.host");
+    assert_eq!(
+        Some("test@local.host".to_string()),
+        get_mail_by_unsubscribe_token("secret", &token)
+    );
+    assert_eq!(None, get_mail_by_unsubscribe_token("other", &token));
+
+    let forged = format!("{}.{}", URL_SAFE_NO_PAD.encode("other@local.host"), "abc");
+    assert_eq!(None, get_mail_by_unsubscribe_token("secret", &forged));
+}
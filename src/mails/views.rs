@@ -0,0 +1,38 @@
+use axum::extract::State;
+use axum::response::Json;
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+use super::db;
+use super::forms::UnsubscribeQuery;
+use super::utils::get_mail_by_unsubscribe_token;
+use crate::common::errors::FieldError;
+use crate::common::extractors::ValidatedQuery;
+use crate::AppState;
+
+/// Only checks the token, link scanners open the link without the user meaning to unsubscribe.
+pub async fn get_unsubscribe_by_token(
+    State(state): State<Arc<AppState>>,
+    ValidatedQuery(q): ValidatedQuery<UnsubscribeQuery>,
+) -> Result<Json<Value>, FieldError> {
+    let mail = match get_mail_by_unsubscribe_token(&state.secret_key, &q.token) {
+        Some(m) => m,
+        None => return Err(FieldError::InvalidParams("token".to_string())),
+    };
+
+    let unsubscribed = db::check_unsubscribe_by_mail(&state, &mail).await?;
+    Ok(Json(json!({ "mail": mail, "unsubscribed": unsubscribed })))
+}
+
+pub async fn unsubscribe_by_token(
+    State(state): State<Arc<AppState>>,
+    ValidatedQuery(q): ValidatedQuery<UnsubscribeQuery>,
+) -> Result<Json<Value>, FieldError> {
+    let mail = match get_mail_by_unsubscribe_token(&state.secret_key, &q.token) {
+        Some(m) => m,
+        None => return Err(FieldError::InvalidParams("token".to_string())),
+    };
+
+    db::create_unsubscribe_by_mail(&state, &mail).await?;
+    Ok(Json(json!({ "msg": "ok" })))
+}
@@ -1,4 +1,5 @@
 use std::env;
+use std::net::SocketAddr;
 
 use getopts::Options;
 use tracing::{info, Level};
@@ -6,10 +7,10 @@ use tracing_subscriber::FmtSubscriber;
 use tokio::signal;
 use tokio::net::TcpListener;
 
-use rumo::{app, init};
+use rumo::{app, init, repair};
 
 fn print_usage(program: &str, opts: Options) {
-    let brief = format!("Usage: {} run|init [options]", program);
+    let brief = format!("Usage: {} run|init|repair [options]", program);
     print!("{}", opts.usage(&brief));
 }
 
@@ -48,6 +49,7 @@ async fn main() {
     opts.optopt("n", "name", "set admin name", "NAME");
     opts.optopt("m", "mail", "set admin mail", "MAIL");
     opts.optopt("p", "password", "set admin password", "PASSWORD");
+    opts.optflag("d", "dry-run", "print counts to repair without saving");
     opts.optflag("h", "help", "print this help menu");
     let matches = match opts.parse(&args[1..]) {
         Ok(m) => m,
@@ -82,6 +84,7 @@ async fn main() {
             };
 
             let app = app(None).await;
+            let app = app.into_make_service_with_connect_info::<SocketAddr>();
             let _ = axum::serve(listener, app)
                 .with_graceful_shutdown(shutdown_signal())
                 .await;
@@ -100,6 +103,9 @@ async fn main() {
             init(name, mail, password).await;
             info!("database created")
         }
+        "repair" => {
+            repair(matches.opt_present("d")).await;
+        }
         _ => {
             print_usage(&program, opts);
             return;
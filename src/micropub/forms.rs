@@ -0,0 +1,10 @@
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+#[derive(Serialize, Deserialize, Validate)]
+pub struct MicropubQuery {
+    #[validate(length(min = 1, max = 16, message = "q length must greater than 1"))]
+    pub q: String,
+    #[validate(url, length(max = 255, message = "url can not be longer than 255"))]
+    pub url: Option<String>,
+}
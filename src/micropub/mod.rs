@@ -0,0 +1,7 @@
+mod forms;
+mod models;
+mod urls;
+mod utils;
+mod views;
+
+pub use urls::micropub_routers;
@@ -0,0 +1,19 @@
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Micropub properties, every property is a list of values.
+pub type Properties = HashMap<String, Vec<Value>>;
+
+/// Form-encoded and JSON requests are both read into an action.
+#[derive(Debug, PartialEq)]
+pub enum MicropubAction {
+    Create(Properties),
+    /// A property in `delete` without values is removed entirely.
+    Update {
+        url: String,
+        replace: Properties,
+        add: Properties,
+        delete: Properties,
+    },
+    Delete(String),
+}
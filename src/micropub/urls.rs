@@ -0,0 +1,19 @@
+use axum::{
+    routing::{get, post},
+    Router,
+};
+use std::sync::Arc;
+
+use super::views;
+use crate::AppState;
+
+pub fn micropub_routers(ro: bool) -> Router<Arc<AppState>> {
+    let micropub_route = Router::new().route("/api/micropub", get(views::query_micropub));
+    if !ro {
+        micropub_route
+            .route("/api/micropub", post(views::post_micropub))
+            .route("/api/micropub/media", post(views::create_media))
+    } else {
+        micropub_route
+    }
+}
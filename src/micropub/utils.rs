@@ -0,0 +1,343 @@
+use chrono::DateTime;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+use super::models::{MicropubAction, Properties};
+use crate::common::errors::FieldError;
+use crate::common::markdown::strip_tags;
+use crate::common::models::ContentWithMetasUsersFields;
+use crate::common::utils::escape_xml;
+use crate::posts::forms::PostCreate;
+
+const TITLE_LENGTH: usize = 30;
+
+/// A token without scope is a login token, it can do everything.
+pub fn check_scope(scope: &Option<String>, name: &str) -> Result<(), FieldError> {
+    match scope {
+        Some(s) if !s.split_whitespace().any(|w| w == name) => Err(FieldError::PermissionDeny),
+        _ => Ok(()),
+    }
+}
+
+/// Reads a form-encoded request, `category[]` and `category` are the same property.
+pub fn parse_form(body: &[u8]) -> Result<MicropubAction, FieldError> {
+    let mut properties = Properties::new();
+    let mut h = None;
+    let mut action = None;
+    let mut url = None;
+    for (key, value) in url::form_urlencoded::parse(body) {
+        let value = value.into_owned();
+        match key.trim_end_matches("[]") {
+            "h" => h = Some(value),
+            "action" => action = Some(value),
+            "url" => url = Some(value),
+            // already checked by the extractor
+            "access_token" => (),
+            key => properties
+                .entry(key.to_string())
+                .or_default()
+                .push(Value::String(value)),
+        }
+    }
+
+    match action.as_deref() {
+        None if h.as_deref() == Some("entry") => Ok(MicropubAction::Create(properties)),
+        None => Err(FieldError::InvalidParams("h".to_string())),
+        // updates are only sent as json
+        Some("delete") => url
+            .map(MicropubAction::Delete)
+            .ok_or(FieldError::InvalidParams("url".to_string())),
+        _ => Err(FieldError::InvalidParams("action".to_string())),
+    }
+}
+
+fn get_properties(value: &Value, field: &str) -> Result<Properties, FieldError> {
+    match value {
+        Value::Null => Ok(Properties::new()),
+        Value::Object(map) => map
+            .iter()
+            .map(|(k, v)| match v {
+                Value::Array(values) => Ok((k.clone(), values.clone())),
+                _ => Err(FieldError::InvalidParams(k.clone())),
+            })
+            .collect(),
+        _ => Err(FieldError::InvalidParams(field.to_string())),
+    }
+}
+
+/// Reads a JSON request, the properties of an `h-entry` or the changes of an update.
+pub fn parse_json(value: &Value) -> Result<MicropubAction, FieldError> {
+    let url = || {
+        value["url"]
+            .as_str()
+            .map(|u| u.to_string())
+            .ok_or(FieldError::InvalidParams("url".to_string()))
+    };
+    match value["action"].as_str() {
+        None => {
+            let entry = value["type"]
+                .as_array()
+                .is_some_and(|t| t.iter().any(|t| t == "h-entry"));
+            if !entry {
+                return Err(FieldError::InvalidParams("type".to_string()));
+            }
+            Ok(MicropubAction::Create(get_properties(
+                &value["properties"],
+                "properties",
+            )?))
+        }
+        Some("update") => {
+            let delete = match &value["delete"] {
+                Value::Array(names) => names
+                    .iter()
+                    .filter_map(|n| n.as_str())
+                    .map(|n| (n.to_string(), vec![]))
+                    .collect(),
+                v => get_properties(v, "delete")?,
+            };
+            Ok(MicropubAction::Update {
+                url: url()?,
+                replace: get_properties(&value["replace"], "replace")?,
+                add: get_properties(&value["add"], "add")?,
+                delete,
+            })
+        }
+        Some("delete") => Ok(MicropubAction::Delete(url()?)),
+        _ => Err(FieldError::InvalidParams("action".to_string())),
+    }
+}
+
+fn get_strings(values: &[Value]) -> Vec<String> {
+    values
+        .iter()
+        .filter_map(|v| v.as_str())
+        .map(|s| s.to_string())
+        .collect()
+}
+
+fn first_str<'a>(properties: &'a Properties, name: &str) -> Option<&'a str> {
+    properties.get(name)?.first()?.as_str()
+}
+
+/// Plain text is stored as Markdown, `{"html": ..}` as HTML.
+fn get_text(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(format!("<!--markdown-->{}", s)),
+        Value::Object(o) => match (o.get("html"), o.get("value")) {
+            (Some(Value::String(html)), _) => Some(html.clone()),
+            (_, Some(Value::String(s))) => Some(format!("<!--markdown-->{}", s)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn append_photo(text: &mut String, url: &str, alt: &str) {
+    if text.is_empty() {
+        text.push_str("<!--markdown-->");
+    }
+    if text.starts_with("<!--markdown-->") {
+        text.push_str(&format!("\n\n![{}]({})", alt, url));
+    } else {
+        text.push_str(&format!(
+            "\n<img src=\"{}\" alt=\"{}\">",
+            escape_xml(url),
+            escape_xml(alt)
+        ));
+    }
+}
+
+/// Fills the post with micropub properties, categories become tags.
+pub fn apply_properties(post: &mut PostCreate, properties: &Properties) -> Result<(), FieldError> {
+    if let Some(name) = first_str(properties, "name") {
+        post.title = name.to_string();
+    }
+    if let Some(content) = properties.get("content").and_then(|c| c.first()) {
+        post.text = get_text(content).ok_or(FieldError::InvalidParams("content".to_string()))?;
+    }
+    for photo in properties
+        .get("photo")
+        .map(|p| p.as_slice())
+        .unwrap_or_default()
+    {
+        match photo {
+            Value::String(url) => append_photo(&mut post.text, url, ""),
+            Value::Object(o) => {
+                let url = o.get("value").and_then(|v| v.as_str());
+                let url = url.ok_or(FieldError::InvalidParams("photo".to_string()))?;
+                let alt = o.get("alt").and_then(|v| v.as_str()).unwrap_or_default();
+                append_photo(&mut post.text, url, alt);
+            }
+            _ => return Err(FieldError::InvalidParams("photo".to_string())),
+        }
+    }
+    if let Some(categories) = properties.get("category") {
+        post.tags = Some(get_strings(categories));
+    }
+    if let Some(slug) = first_str(properties, "mp-slug") {
+        post.slug = slug.to_string();
+    }
+    if let Some(status) = first_str(properties, "post-status") {
+        post.status = match status {
+            "published" => "publish".to_string(),
+            "draft" => "draft".to_string(),
+            _ => return Err(FieldError::InvalidParams("post-status".to_string())),
+        };
+    }
+    if let Some(published) = first_str(properties, "published") {
+        post.created = DateTime::parse_from_rfc3339(published)
+            .ok()
+            .and_then(|d| u32::try_from(d.timestamp()).ok())
+            .ok_or(FieldError::InvalidParams("published".to_string()))?;
+    }
+    Ok(())
+}
+
+/// Applies an update, only categories can have single values added or removed.
+pub fn apply_update(
+    post: &mut PostCreate,
+    replace: &Properties,
+    add: &Properties,
+    delete: &Properties,
+) -> Result<(), FieldError> {
+    apply_properties(post, replace)?;
+    for (name, values) in add {
+        if name == "category" {
+            post.tags
+                .get_or_insert_with(Vec::new)
+                .extend(get_strings(values));
+        } else {
+            apply_properties(post, &HashMap::from([(name.clone(), values.clone())]))?;
+        }
+    }
+    for (name, values) in delete {
+        if name != "category" {
+            return Err(FieldError::InvalidParams(name.clone()));
+        }
+        let removed = get_strings(values);
+        let tags = post.tags.get_or_insert_with(Vec::new);
+        if removed.is_empty() {
+            tags.clear();
+        } else {
+            tags.retain(|t| !removed.contains(t));
+        }
+    }
+    Ok(())
+}
+
+/// Notes usually have no name, so the title comes from the start of the text.
+pub fn get_default_title(text: &str) -> String {
+    let text = text.strip_prefix("<!--markdown-->").unwrap_or(text);
+    let title: String = strip_tags(text).chars().take(TITLE_LENGTH).collect();
+    if title.is_empty() {
+        "未命名文档".to_string()
+    } else {
+        title
+    }
+}
+
+/// Returns the post as an `h-entry` for `q=source`.
+pub fn get_source_properties(post: &ContentWithMetasUsersFields) -> Value {
+    let text = post.text.as_deref().unwrap_or_default();
+    let content = match text.strip_prefix("<!--markdown-->") {
+        Some(markdown) => json!(markdown),
+        None => json!({ "html": text }),
+    };
+    let published = DateTime::from_timestamp(post.created as i64, 0)
+        .unwrap_or_default()
+        .to_rfc3339();
+    let status = if post.status == "publish" {
+        "published"
+    } else {
+        "draft"
+    };
+    let categories: Vec<&str> = post.tags.iter().filter_map(|m| m.name.as_deref()).collect();
+    json!({
+        "type": ["h-entry"],
+        "properties": {
+            "name": [post.title.as_deref().unwrap_or_default()],
+            "content": [content],
+            "category": categories,
+            "published": [published],
+            "post-status": [status],
+            "mp-slug": [post.slug.as_deref().unwrap_or_default()],
+        }
+    })
+}
+
+#[test]
+fn test_parse_micropub_request() {
+    let body = b"h=entry&content=hello+world&category[]=a&category[]=b&mp-slug=hello";
+    let action = parse_form(body).unwrap();
+    let MicropubAction::Create(properties) = action else {
+        panic!("create expected");
+    };
+    assert_eq!(vec![json!("a"), json!("b")], properties["category"]);
+    assert_eq!(Some("hello world"), first_str(&properties, "content"));
+
+    assert_eq!(
+        MicropubAction::Delete("https://rumo.cf/archives/1/".to_string()),
+        parse_form(b"action=delete&url=https://rumo.cf/archives/1/").unwrap()
+    );
+    assert!(parse_form(b"h=card&name=a").is_err());
+
+    let value = json!({
+        "action": "update",
+        "url": "https://rumo.cf/archives/1/",
+        "replace": {"content": ["new"]},
+        "delete": ["category"],
+    });
+    let MicropubAction::Update {
+        replace, delete, ..
+    } = parse_json(&value).unwrap()
+    else {
+        panic!("update expected");
+    };
+    assert_eq!(vec![json!("new")], replace["content"]);
+    assert_eq!(Vec::<Value>::new(), delete["category"]);
+    assert!(parse_json(&json!({"type": ["h-card"]})).is_err());
+
+    assert!(check_scope(&None, "delete").is_ok());
+    assert!(check_scope(&Some("create media".to_string()), "media").is_ok());
+    assert!(check_scope(&Some("create".to_string()), "delete").is_err());
+}
+
+#[test]
+fn test_apply_properties() {
+    let mut post = PostCreate {
+        title: String::new(),
+        slug: String::new(),
+        created: 0,
+        text: String::new(),
+        status: "publish".to_string(),
+        password: None,
+        allowComment: None,
+        allowPing: None,
+        allowFeed: None,
+        categories: None,
+        tags: None,
+    };
+    let properties = Properties::from([
+        ("content".to_string(), vec![json!({"html": "<p>hi</p>"})]),
+        (
+            "photo".to_string(),
+            vec![json!({"value": "https://a/b.jpg", "alt": "b"})],
+        ),
+        ("category".to_string(), vec![json!("a"), json!("b")]),
+        ("published".to_string(), vec![json!("2022-10-25T02:57:46Z")]),
+        ("post-status".to_string(), vec![json!("draft")]),
+    ]);
+    apply_properties(&mut post, &properties).unwrap();
+    assert_eq!(
+        "<p>hi</p>\n<img src=\"https://a/b.jpg\" alt=\"b\">",
+        post.text
+    );
+    assert_eq!(1666666666, post.created);
+    assert_eq!("draft", post.status);
+    assert_eq!("hi", get_default_title(&post.text));
+
+    let add = Properties::from([("category".to_string(), vec![json!("c")])]);
+    let delete = Properties::from([("category".to_string(), vec![json!("a")])]);
+    apply_update(&mut post, &Properties::new(), &add, &delete).unwrap();
+    assert_eq!(Some(vec!["b".to_string(), "c".to_string()]), post.tags);
+}
@@ -0,0 +1,237 @@
+use axum::extract::{Multipart, State};
+use axum::http::{header, HeaderMap, HeaderValue, StatusCode};
+use axum::response::Json;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::Arc;
+use validator::Validate;
+
+use super::forms::MicropubQuery;
+use super::models::MicropubAction;
+use super::utils::{
+    apply_properties, apply_update, check_scope, get_default_title, get_source_properties,
+    parse_form, parse_json,
+};
+use crate::attachments::utils::save_attachment;
+use crate::comments::pings::get_content_by_target_url;
+use crate::common::db as common_db;
+use crate::common::errors::FieldError;
+use crate::common::extractors::{PMScopedContributor, PMScopedContributorWithBody, ValidatedQuery};
+use crate::common::utils::{get_now, Permalinks};
+use crate::entity::{content, user::Model as User};
+use crate::posts::db as posts_db;
+use crate::posts::forms::PostCreate;
+use crate::posts::utils::{
+    create_post_with_default_slug, get_default_category, get_post_create_by_content, is_admin,
+    modify_post_with_exist_post, set_contributor_status,
+};
+use crate::trash::db as trash_db;
+use crate::webmentions::sender::send_post_webmentions;
+use crate::AppState;
+
+type MicropubResponse = (StatusCode, HeaderMap, Json<Value>);
+
+fn location(url: &str) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    if let Ok(value) = HeaderValue::from_str(url) {
+        headers.insert(header::LOCATION, value);
+    }
+    headers
+}
+
+async fn get_exist_post_by_url(
+    state: &AppState,
+    options: &HashMap<String, String>,
+    user: &User,
+    url: &str,
+) -> Result<content::Model, FieldError> {
+    let exist_post = match get_content_by_target_url(state, options, url).await {
+        Ok(p) if p.r#type == "post" && p.status != "trash" => p,
+        _ => return Err(FieldError::NotFound("url".to_string())),
+    };
+    if exist_post.author_id != user.uid && !is_admin(user) {
+        return Err(FieldError::PermissionDeny);
+    }
+    Ok(exist_post)
+}
+
+pub async fn query_micropub(
+    State(state): State<Arc<AppState>>,
+    PMScopedContributor(user, _): PMScopedContributor,
+    ValidatedQuery(q): ValidatedQuery<MicropubQuery>,
+) -> Result<Json<Value>, FieldError> {
+    let options = common_db::get_site_options(&state).await?;
+    match q.q.as_str() {
+        "config" => {
+            let permalinks = Permalinks::from_options(&options);
+            Ok(Json(json!({
+                "media-endpoint": format!("{}/api/micropub/media", permalinks.site_url),
+                "syndicate-to": [],
+            })))
+        }
+        "syndicate-to" => Ok(Json(json!({"syndicate-to": []}))),
+        "source" => {
+            let url = q.url.ok_or(FieldError::InvalidParams("url".to_string()))?;
+            let exist_post = get_exist_post_by_url(&state, &options, &user, &url).await?;
+            let slug = exist_post.slug.as_deref().unwrap_or_default();
+            let post = posts_db::get_content_with_metas_user_fields_by_slug_and_private(
+                &state, slug, true,
+            )
+            .await?;
+            Ok(Json(get_source_properties(&post)))
+        }
+        _ => Err(FieldError::InvalidParams("q".to_string())),
+    }
+}
+
+pub async fn post_micropub(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    PMScopedContributorWithBody(user, scope, body): PMScopedContributorWithBody,
+) -> Result<MicropubResponse, FieldError> {
+    let is_json = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|c| c.to_str().ok())
+        .is_some_and(|c| c.starts_with("application/json"));
+    let action = if is_json {
+        let value: Value = serde_json::from_slice(&body)
+            .map_err(|_| FieldError::InvalidParams("body".to_string()))?;
+        parse_json(&value)?
+    } else {
+        parse_form(&body)?
+    };
+
+    let options = common_db::get_site_options(&state).await?;
+    let default_category = get_default_category(&options);
+    match action {
+        MicropubAction::Create(properties) => {
+            check_scope(&scope, "create")?;
+            let mut post_create = PostCreate {
+                title: String::new(),
+                slug: String::new(),
+                created: get_now(),
+                text: String::new(),
+                status: "publish".to_string(),
+                password: None,
+                allowComment: None,
+                allowPing: None,
+                allowFeed: None,
+                categories: None,
+                tags: None,
+            };
+            apply_properties(&mut post_create, &properties)?;
+            if post_create.title.is_empty() {
+                post_create.title = get_default_title(&post_create.text);
+            }
+            set_contributor_status(&user, &mut post_create);
+
+            let post =
+                create_post_with_default_slug(&state, post_create, user.uid, default_category)
+                    .await?;
+            let slug = post.slug.clone().unwrap_or_default();
+            tokio::spawn(send_post_webmentions(state.clone(), post));
+
+            let post = posts_db::get_content_with_metas_user_fields_by_slug_and_private(
+                &state, &slug, true,
+            )
+            .await?;
+            let url = Permalinks::from_options(&options).content(
+                &post.r#type,
+                post.cid,
+                &slug,
+                post.created,
+                post.categories.first().and_then(|m| m.slug.as_deref()),
+            );
+            Ok((
+                StatusCode::CREATED,
+                location(&url),
+                Json(json!({"msg": "ok", "url": url})),
+            ))
+        }
+        MicropubAction::Update {
+            url,
+            replace,
+            add,
+            delete,
+        } => {
+            check_scope(&scope, "update")?;
+            let exist_post = get_exist_post_by_url(&state, &options, &user, &url).await?;
+            let mut post_modify = get_post_create_by_content(&exist_post);
+            // tags are only written back when the update touches categories
+            let touch_tags = [&replace, &add, &delete]
+                .iter()
+                .any(|p| p.contains_key("category"));
+            if touch_tags && !replace.contains_key("category") {
+                let slug = exist_post.slug.as_deref().unwrap_or_default();
+                let post = posts_db::get_content_with_metas_user_fields_by_slug_and_private(
+                    &state, slug, true,
+                )
+                .await?;
+                post_modify.tags = Some(post.tags.into_iter().filter_map(|m| m.name).collect());
+            }
+            apply_update(&mut post_modify, &replace, &add, &delete)?;
+            set_contributor_status(&user, &mut post_modify);
+            if let Err(e) = post_modify.validate() {
+                let fields: Vec<String> = e.field_errors().keys().map(|k| k.to_string()).collect();
+                return Err(FieldError::InvalidParams(fields.join(", ")));
+            }
+
+            let post = modify_post_with_exist_post(
+                &state,
+                &post_modify,
+                &exist_post,
+                "*",
+                default_category,
+            )
+            .await?;
+            tokio::spawn(send_post_webmentions(state.clone(), post));
+            Ok((StatusCode::OK, HeaderMap::new(), Json(json!({"msg": "ok"}))))
+        }
+        MicropubAction::Delete(url) => {
+            check_scope(&scope, "delete")?;
+            let exist_post = get_exist_post_by_url(&state, &options, &user, &url).await?;
+            let _ = trash_db::move_content_to_trash_with_uid(&state, &exist_post, user.uid, false)
+                .await?;
+            Ok((StatusCode::OK, HeaderMap::new(), Json(json!({"msg": "ok"}))))
+        }
+    }
+}
+
+/// The media endpoint, the uploaded `file` is saved as an attachment.
+pub async fn create_media(
+    State(state): State<Arc<AppState>>,
+    PMScopedContributor(user, scope): PMScopedContributor,
+    mut multipart: Multipart,
+) -> Result<MicropubResponse, FieldError> {
+    if check_scope(&scope, "media").is_err() {
+        check_scope(&scope, "create")?;
+    }
+    let field = loop {
+        match multipart.next_field().await {
+            Ok(Some(f)) if f.name() == Some("file") => break f,
+            Ok(Some(_)) => continue,
+            _ => return Err(FieldError::InvalidParams("file".to_string())),
+        }
+    };
+    let file_name = match field.file_name() {
+        Some(f) => f.to_string(),
+        None => return Err(FieldError::InvalidParams("file".to_string())),
+    };
+    let content_type = match field.content_type() {
+        Some(f) => f.to_string(),
+        None => return Err(FieldError::InvalidParams("file".to_string())),
+    };
+
+    let (_, text) = save_attachment(&state, user.uid, file_name, content_type, field).await?;
+    let options = common_db::get_site_options(&state).await?;
+    let url = format!(
+        "{}{}",
+        Permalinks::from_options(&options).site_url,
+        text.path
+    );
+    Ok((
+        StatusCode::CREATED,
+        location(&url),
+        Json(json!({"msg": "ok", "url": url})),
+    ))
+}
@@ -1,10 +1,12 @@
 use std::time::SystemTime;
 
 use sea_orm::*;
+use serde_json::json;
 
 use super::forms::PageCreate;
 use crate::common::errors::FieldError;
 use crate::common::models::ContentWithFields;
+use crate::common::utils::{get_etag, get_next_modified};
 use crate::entity::{content, content::Entity as Content, field::Entity as ContentField};
 use crate::AppState;
 
@@ -101,15 +103,39 @@ pub async fn modify_page_by_page_modify_with_exist_page(
     c.title = Set(Some(page_modify.title.to_owned()));
     c.slug = Set(Some(page_modify.slug.to_owned()));
     c.created = Set(now);
-    c.modified = Set(now);
+    c.modified = Set(get_next_modified(now, exist_page.modified));
     c.text = Set(Some(page_modify.text.to_owned()));
     c.status = Set(status.to_owned());
     c.allow_comment = Set(allow_comment.to_string());
     c.allow_ping = Set(allow_ping.to_string());
     c.allow_feed = Set(allow_feed.to_string());
-    c.update(&state.conn)
+
+    // the page may be modified by others since it was read
+    let result = Content::update_many()
+        .set(c)
+        .filter(content::Column::Cid.eq(exist_page.cid))
+        .filter(content::Column::Modified.eq(exist_page.modified))
+        .exec(&state.conn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("update post failed".to_string()))?;
+    let page = Content::find_by_id(exist_page.cid)
+        .one(&state.conn)
         .await
-        .map_err(|_| FieldError::DatabaseFailed("update post failed".to_string()))
+        .map_err(|_| FieldError::DatabaseFailed("fetch page failed".to_string()))?
+        .ok_or(FieldError::NotFound("slug".to_string()))?;
+    if result.rows_affected == 0 {
+        // a 412 with the current page, so the client can merge its changes
+        let slug = page.slug.unwrap_or_default();
+        let current = get_content_with_fields_by_slug(state, &slug).await?;
+        let etag = get_etag(
+            current.cid,
+            current.modified,
+            &current.status,
+            current.text.as_deref(),
+        );
+        return Err(FieldError::PreconditionFailed(etag, json!(current)));
+    }
+    Ok(page)
 }
 
 pub async fn get_content_with_fields_by_slug(
@@ -118,6 +144,7 @@ pub async fn get_content_with_fields_by_slug(
 ) -> Result<ContentWithFields, FieldError> {
     let c = Content::find()
         .filter(content::Column::Slug.eq(slug))
+        .filter(content::Column::Status.ne("trash"))
         .one(&state.conn)
         .await
         .map_err(|_| FieldError::DatabaseFailed("fetch content failed".to_string()))?;
@@ -147,7 +174,7 @@ pub async fn get_contents_with_fields_by_list_query_with_private(
 
     let stmt = Content::find().filter(content::Column::Type.eq(content_type));
     let stmt = if private {
-        stmt
+        stmt.filter(content::Column::Status.ne("trash"))
     } else {
         stmt.filter(content::Column::Status.eq("publish"))
     };
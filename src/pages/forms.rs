@@ -17,3 +17,9 @@ pub struct PageCreate {
     pub allowPing: Option<bool>,
     pub allowFeed: Option<bool>,
 }
+
+#[derive(Serialize, Deserialize, Validate)]
+pub struct PageQuery {
+    #[validate(length(min = 1, max = 8, message = "format length must greater than 1"))]
+    pub format: Option<String>,
+}
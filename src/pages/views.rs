@@ -1,16 +1,18 @@
 use axum::extract::{Path, State};
-use axum::http::StatusCode;
+use axum::http::{header, HeaderMap, HeaderName, StatusCode};
 use axum::response::Json;
 use serde_json::{json, Value};
 use std::sync::Arc;
 
 use super::db;
-use super::forms::PageCreate;
+use super::forms::{PageCreate, PageQuery};
 use crate::common::db as common_db;
 use crate::common::errors::FieldError;
 use crate::common::extractors::{PMEditor, PMVisitor, ValidatedJson, ValidatedQuery};
-use crate::common::forms::FieldCreate;
-use crate::common::forms::ListQueryWithPrivate;
+use crate::common::forms::{DeleteQuery, FieldCreate, ListQueryWithPrivate};
+use crate::common::markdown::{is_html_format, render_text_to_html};
+use crate::common::utils::{etag_matches, get_etag, get_if_match};
+use crate::trash::db as trash_db;
 use crate::AppState;
 
 pub async fn create_page(
@@ -18,7 +20,7 @@ pub async fn create_page(
     PMEditor(user): PMEditor,
     ValidatedJson(page_create): ValidatedJson<PageCreate>,
 ) -> Result<(StatusCode, Json<Value>), FieldError> {
-    match common_db::get_content_by_slug(&state, &page_create.slug).await {
+    match common_db::get_content_by_slug_with_trash(&state, &page_create.slug).await {
         Ok(Some(_)) => return Err(FieldError::AlreadyExist("page".to_owned())),
         _ => (),
     };
@@ -31,23 +33,37 @@ pub async fn modify_page_by_slug(
     State(state): State<Arc<AppState>>,
     PMEditor(_): PMEditor,
     Path(slug): Path<String>,
+    headers: HeaderMap,
     ValidatedJson(page_modify): ValidatedJson<PageCreate>,
-) -> Result<Json<Value>, FieldError> {
+) -> Result<([(HeaderName, String); 1], Json<Value>), FieldError> {
     let exist_page = match common_db::get_content_by_slug(&state, &slug).await {
         Ok(Some(p)) => p,
         _ => return Err(FieldError::NotFound("page".to_owned())),
     };
 
+    let if_match = get_if_match(&headers)?;
+    let etag = get_etag(
+        exist_page.cid,
+        exist_page.modified,
+        &exist_page.status,
+        exist_page.text.as_deref(),
+    );
+    if !etag_matches(if_match, &etag) {
+        let current = db::get_content_with_fields_by_slug(&state, &slug).await?;
+        return Err(FieldError::PreconditionFailed(etag, json!(current)));
+    }
+
     if slug != page_modify.slug {
-        match common_db::get_content_by_slug(&state, &page_modify.slug).await {
+        match common_db::get_content_by_slug_with_trash(&state, &page_modify.slug).await {
             Ok(Some(_)) => return Err(FieldError::AlreadyExist("page slug".to_owned())),
             _ => (),
         };
     }
 
-    let _ =
+    let page =
         db::modify_page_by_page_modify_with_exist_page(&state, &page_modify, &exist_page).await?;
-    Ok(Json(json!({ "msg": "ok" })))
+    let etag = get_etag(page.cid, page.modified, &page.status, page.text.as_deref());
+    Ok(([(header::ETAG, etag)], Json(json!({ "msg": "ok" }))))
 }
 
 pub async fn list_pages(
@@ -60,6 +76,7 @@ pub async fn list_pages(
     if private && !admin {
         return Err(FieldError::PermissionDeny);
     }
+    let html = is_html_format(q.format.as_deref())?;
 
     let all_count =
         common_db::get_contents_count_with_private(&state, private, false, &user, "page").await;
@@ -68,10 +85,16 @@ pub async fn list_pages(
     let page_size = q.page_size.unwrap_or(10);
     let order_by = q.order_by.unwrap_or("-cid".to_string());
 
-    let pages = db::get_contents_with_fields_by_list_query_with_private(
+    let mut pages = db::get_contents_with_fields_by_list_query_with_private(
         &state, private, page_size, page, &order_by, false,
     )
     .await?;
+    if html {
+        for page in pages.iter_mut() {
+            let text = page.text.as_deref().unwrap_or_default();
+            page.text = Some(render_text_to_html(page.cid, page.modified, text));
+        }
+    }
     Ok(Json(json!({
         "page": page,
         "page_size": page_size,
@@ -85,30 +108,38 @@ pub async fn get_page_by_slug(
     State(state): State<Arc<AppState>>,
     PMVisitor(user): PMVisitor,
     Path(slug): Path<String>,
-) -> Result<Json<Value>, FieldError> {
-    let page = db::get_content_with_fields_by_slug(&state, &slug).await?;
+    ValidatedQuery(q): ValidatedQuery<PageQuery>,
+) -> Result<([(HeaderName, String); 1], Json<Value>), FieldError> {
+    let html = is_html_format(q.format.as_deref())?;
+    let mut page = db::get_content_with_fields_by_slug(&state, &slug).await?;
     let admin = user.group == "editor" || user.group == "administrator";
 
     if page.status == "hidden" && !admin {
         Err(FieldError::PermissionDeny)
     } else {
-        Ok(Json(json!(page)))
+        let etag = get_etag(page.cid, page.modified, &page.status, page.text.as_deref());
+        if html {
+            let text = page.text.as_deref().unwrap_or_default();
+            page.text = Some(render_text_to_html(page.cid, page.modified, text));
+        }
+        Ok(([(header::ETAG, etag)], Json(json!(page))))
     }
 }
 
 pub async fn delete_page_by_slug(
     State(state): State<Arc<AppState>>,
-    PMEditor(_): PMEditor,
+    PMEditor(user): PMEditor,
     Path(slug): Path<String>,
+    ValidatedQuery(q): ValidatedQuery<DeleteQuery>,
 ) -> Result<Json<Value>, FieldError> {
     let page = match common_db::get_content_by_slug(&state, &slug).await {
-        Ok(Some(p)) => p,
+        Ok(Some(p)) if p.status != "trash" => p,
         _ => return Err(FieldError::NotFound("slug".to_owned())),
     };
 
-    let _ = common_db::delete_fields_by_cid(&state, page.cid).await?;
-
-    let _ = common_db::delete_content_by_cid(&state, page.cid).await?;
+    let delete_attachments = q.delete_attachments.unwrap_or(false);
+    let _ = trash_db::move_content_to_trash_with_uid(&state, &page, user.uid, delete_attachments)
+        .await?;
     Ok(Json(json!({ "msg": "ok" })))
 }
 
@@ -133,7 +164,7 @@ pub async fn get_page_field_by_slug_and_name(
     Path((slug, name)): Path<(String, String)>,
 ) -> Result<Json<Value>, FieldError> {
     let exist_page = match common_db::get_content_by_slug(&state, &slug).await {
-        Ok(Some(p)) => p,
+        Ok(Some(p)) if p.status != "trash" => p,
         _ => return Err(FieldError::NotFound("slug".to_owned())),
     };
 
@@ -1,22 +1,59 @@
 use std::time::SystemTime;
 
+use sea_orm::sea_query::OnConflict;
 use sea_orm::*;
+use serde_json::json;
 
 use super::forms::PostCreate;
+use crate::common::db as common_db;
 use crate::common::errors::FieldError;
 use crate::common::models::ContentWithMetasUsersFields;
-use crate::entity::{content, content::Entity as Content, field, meta, relationship, user};
+use crate::common::utils::{get_etag, get_next_modified, get_now, get_slug_name};
+use crate::entity::{
+    content, content::Entity as Content, field, meta, meta::Entity as Meta, option,
+    option::Entity as SiteOption, relationship, relationship::Entity as Relationship, user,
+};
 use crate::AppState;
 
+/// Same as typecho, a post without slug uses its cid, or the cid with a `-n` suffix when taken.
+async fn get_free_slug_by_cid<C: ConnectionTrait>(
+    conn: &C,
+    cid: u32,
+) -> Result<String, FieldError> {
+    let mut slug = cid.to_string();
+    let mut suffix = 0;
+    loop {
+        let count = Content::find()
+            .filter(content::Column::Slug.eq(&slug))
+            .count(conn)
+            .await
+            .map_err(|_| FieldError::DatabaseFailed("fetch content failed".to_string()))?;
+        if count == 0 {
+            return Ok(slug);
+        }
+        suffix += 1;
+        slug = format!("{}-{}", cid, suffix);
+    }
+}
+
+/// With `slug_by_cid` the slug of `post_create` is ignored, the post gets its cid in the same
+/// transaction.
 pub async fn create_post_by_post_create_with_uid(
     state: &AppState,
     post_create: &PostCreate,
     uid: u32,
-) -> Result<content::ActiveModel, FieldError> {
+    default_category: u32,
+    slug_by_cid: bool,
+) -> Result<content::Model, FieldError> {
     let now = SystemTime::now()
         .duration_since(SystemTime::UNIX_EPOCH)
         .unwrap()
         .as_secs() as u32;
+    let created = if now > post_create.created {
+        now
+    } else {
+        post_create.created
+    };
     let allow_comment = match post_create.allowComment.unwrap_or(true) {
         true => "1",
         false => "0",
@@ -30,11 +67,21 @@ pub async fn create_post_by_post_create_with_uid(
         false => "0",
     };
 
-    content::ActiveModel {
+    let txn = state
+        .conn
+        .begin()
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("begin transaction failed".to_string()))?;
+
+    let slug = match slug_by_cid {
+        true => None,
+        false => Some(post_create.slug.to_owned()),
+    };
+    let post = content::ActiveModel {
         r#type: Set("post".to_string()),
         title: Set(Some(post_create.title.to_owned())),
-        slug: Set(Some(post_create.slug.to_owned())),
-        created: Set(now),
+        slug: Set(slug),
+        created: Set(created),
         modified: Set(now),
         text: Set(Some(post_create.text.to_owned())),
         author_id: Set(uid),
@@ -45,15 +92,61 @@ pub async fn create_post_by_post_create_with_uid(
         allow_feed: Set(allow_feed.to_string()),
         ..Default::default()
     }
-    .save(&state.conn)
+    .insert(&txn)
     .await
-    .map_err(|_| FieldError::DatabaseFailed("create post failed".to_string()))
+    .map_err(|_| FieldError::DatabaseFailed("create post failed".to_string()))?;
+    let post = if slug_by_cid {
+        let slug = get_free_slug_by_cid(&txn, post.cid).await?;
+        let mut p = content::ActiveModel::from(post);
+        p.slug = Set(Some(slug));
+        p.update(&txn)
+            .await
+            .map_err(|_| FieldError::DatabaseFailed("update post failed".to_string()))?
+    } else {
+        post
+    };
+
+    let categories = post_create.categories.clone().unwrap_or_default();
+    set_post_metas_by_cid(
+        &txn,
+        post.cid,
+        Some(&categories),
+        post_create.tags.as_deref(),
+        default_category,
+    )
+    .await?;
+
+    txn.commit()
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("commit transaction failed".to_string()))?;
+    Ok(post)
+}
+
+/// A 412 with the current post, so the client can merge its changes.
+async fn get_modify_conflict(state: &AppState, cid: u32) -> FieldError {
+    let slug = match common_db::get_content_by_cid(state, cid).await {
+        Ok(Some(c)) => c.slug.unwrap_or_default(),
+        _ => return FieldError::NotFound("slug".to_string()),
+    };
+    match get_content_with_metas_user_fields_by_slug_and_private(state, &slug, true).await {
+        Ok(current) => FieldError::PreconditionFailed(
+            get_etag(
+                current.cid,
+                current.modified,
+                &current.status,
+                current.text.as_deref(),
+            ),
+            json!(current),
+        ),
+        Err(e) => e,
+    }
 }
 
 pub async fn modify_post_by_post_create_with_exist_post(
     state: &AppState,
     post_modify: &PostCreate,
     exist_post: &content::Model,
+    default_category: u32,
 ) -> Result<content::Model, FieldError> {
     let now = SystemTime::now()
         .duration_since(SystemTime::UNIX_EPOCH)
@@ -91,16 +184,51 @@ pub async fn modify_post_by_post_create_with_exist_post(
     c.title = Set(Some(post_modify.title.to_owned()));
     c.slug = Set(Some(post_modify.slug.to_owned()));
     c.created = Set(now);
-    c.modified = Set(now);
+    c.modified = Set(get_next_modified(now, exist_post.modified));
     c.text = Set(Some(post_modify.text.to_owned()));
     c.status = Set(post_modify.status.to_owned());
     c.password = Set(post_modify.password.to_owned());
     c.allow_comment = Set(allow_comment.to_string());
     c.allow_ping = Set(allow_ping.to_string());
     c.allow_feed = Set(allow_feed.to_string());
-    c.update(&state.conn)
+
+    let txn = state
+        .conn
+        .begin()
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("begin transaction failed".to_string()))?;
+
+    // the post may be modified by others since it was read
+    let result = Content::update_many()
+        .set(c)
+        .filter(content::Column::Cid.eq(exist_post.cid))
+        .filter(content::Column::Modified.eq(exist_post.modified))
+        .exec(&txn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("update post failed".to_string()))?;
+    if result.rows_affected == 0 {
+        drop(txn);
+        return Err(get_modify_conflict(state, exist_post.cid).await);
+    }
+    let post = Content::find_by_id(exist_post.cid)
+        .one(&txn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("fetch post failed".to_string()))?
+        .ok_or(FieldError::NotFound("slug".to_string()))?;
+
+    set_post_metas_by_cid(
+        &txn,
+        post.cid,
+        post_modify.categories.as_deref(),
+        post_modify.tags.as_deref(),
+        default_category,
+    )
+    .await?;
+
+    txn.commit()
         .await
-        .map_err(|_| FieldError::DatabaseFailed("update post failed".to_string()))
+        .map_err(|_| FieldError::DatabaseFailed("commit transaction failed".to_string()))?;
+    Ok(post)
 }
 
 pub async fn get_contents_with_metas_user_and_fields_by_filter_and_list_query(
@@ -112,6 +240,7 @@ pub async fn get_contents_with_metas_user_and_fields_by_filter_and_list_query(
     page: u64,
     order_by: &str,
     post: bool,
+    feed: bool,
 ) -> Result<Vec<ContentWithMetasUsersFields>, FieldError> {
     let content_type = if post { "post" } else { "page" };
 
@@ -123,6 +252,12 @@ pub async fn get_contents_with_metas_user_and_fields_by_filter_and_list_query(
     };
     let stmt = if !private {
         stmt.filter(content::Column::Status.eq("publish"))
+            .filter(content::Column::Created.lte(get_now()))
+    } else {
+        stmt.filter(content::Column::Status.ne("trash"))
+    };
+    let stmt = if feed {
+        stmt.filter(content::Column::AllowFeed.eq("1"))
     } else {
         stmt
     };
@@ -142,41 +277,7 @@ pub async fn get_contents_with_metas_user_and_fields_by_filter_and_list_query(
         .await
         .map_err(|_| FieldError::DatabaseFailed("fetch contents failed".to_string()))?;
 
-    let metas = contents
-        .load_many_to_many(meta::Entity, relationship::Entity, &state.conn)
-        .await
-        .map_err(|_| FieldError::DatabaseFailed("fetch metas failed".to_string()))?;
-
-    let fields = contents
-        .load_many(field::Entity, &state.conn)
-        .await
-        .map_err(|_| FieldError::DatabaseFailed("fetch fields failed".to_string()))?;
-
-    let mut res = vec![];
-    for ((content, meta_list), field_list) in contents
-        .into_iter()
-        .zip(metas.into_iter())
-        .zip(fields.into_iter())
-    {
-        let mut ct = ContentWithMetasUsersFields::from(content);
-        ct.screen_name = author.screen_name.clone();
-        ct.group = author.group.clone();
-
-        let mut tags = vec![];
-        let mut categories = vec![];
-        for m in meta_list {
-            if m.r#type == "tag" {
-                tags.push(m);
-            } else {
-                categories.push(m);
-            }
-        }
-        ct.tags = tags;
-        ct.categories = categories;
-        ct.fields = field_list;
-        res.push(ct);
-    }
-    Ok(res)
+    common_db::get_contents_with_metas_users_fields(state, contents).await
 }
 
 pub async fn get_content_with_metas_user_fields_by_slug_and_private(
@@ -184,11 +285,14 @@ pub async fn get_content_with_metas_user_fields_by_slug_and_private(
     slug: &str,
     private: bool,
 ) -> Result<ContentWithMetasUsersFields, FieldError> {
-    let stmt = Content::find().filter(content::Column::Slug.eq(slug));
+    let stmt = Content::find()
+        .filter(content::Column::Slug.eq(slug))
+        .filter(content::Column::Type.ne("post_draft"));
     let stmt = if private {
-        stmt
+        stmt.filter(content::Column::Status.ne("trash"))
     } else {
         stmt.filter(content::Column::Status.eq("publish"))
+            .filter(content::Column::Created.lte(get_now()))
     };
 
     let content = stmt
@@ -226,3 +330,332 @@ pub async fn get_content_with_metas_user_fields_by_slug_and_private(
     res.fields = fields;
     Ok(res)
 }
+
+pub async fn get_published_posts_by_created_range(
+    state: &AppState,
+    start: u32,
+    end: u32,
+) -> Result<Vec<content::Model>, FieldError> {
+    Content::find()
+        .filter(content::Column::Type.eq("post"))
+        .filter(content::Column::Status.eq("publish"))
+        .filter(content::Column::Created.gt(start))
+        .filter(content::Column::Created.lte(end))
+        .order_by_asc(content::Column::Created)
+        .all(&state.conn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("fetch contents failed".to_string()))
+}
+
+const SCHEDULE_CHECK_OPTION: &str = "scheduleCheckTime";
+
+/// The time scheduled posts were last checked, kept so a restart picks up posts that
+/// became due while the server was down.
+pub async fn get_schedule_check_time(state: &AppState) -> Result<Option<u32>, FieldError> {
+    let opt = SiteOption::find()
+        .filter(option::Column::Name.eq(SCHEDULE_CHECK_OPTION))
+        .filter(option::Column::User.eq(0))
+        .one(&state.conn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("fetch option failed".to_string()))?;
+    Ok(opt.and_then(|o| o.value).and_then(|v| v.parse().ok()))
+}
+
+pub async fn set_schedule_check_time(state: &AppState, time: u32) -> Result<(), FieldError> {
+    let opt = option::ActiveModel {
+        name: Set(SCHEDULE_CHECK_OPTION.to_string()),
+        user: Set(0),
+        value: Set(Some(time.to_string())),
+    };
+    SiteOption::insert(opt)
+        .on_conflict(
+            OnConflict::columns([option::Column::Name, option::Column::User])
+                .update_column(option::Column::Value)
+                .to_owned(),
+        )
+        .exec(&state.conn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("update option failed".to_string()))?;
+    Ok(())
+}
+
+pub async fn create_post_draft_by_content_with_parent<C: ConnectionTrait>(
+    conn: &C,
+    content: &content::Model,
+    parent: u32,
+) -> Result<content::Model, FieldError> {
+    let now = get_now();
+    let draft = content::ActiveModel {
+        r#type: Set("post_draft".to_string()),
+        title: Set(content.title.to_owned()),
+        created: Set(now),
+        modified: Set(now),
+        text: Set(content.text.to_owned()),
+        author_id: Set(content.author_id),
+        template: Set(content.template.to_owned()),
+        status: Set(content.status.to_owned()),
+        password: Set(content.password.to_owned()),
+        allow_comment: Set(content.allow_comment.to_owned()),
+        allow_ping: Set(content.allow_ping.to_owned()),
+        allow_feed: Set(content.allow_feed.to_owned()),
+        parent: Set(parent),
+        ..Default::default()
+    }
+    .insert(conn)
+    .await
+    .map_err(|_| FieldError::DatabaseFailed("create post draft failed".to_string()))?;
+
+    // same as typecho, drafts are named after their own cid
+    let slug = format!("@{}", draft.cid);
+    let mut d = content::ActiveModel::from(draft);
+    d.slug = Set(Some(slug));
+    d.update(conn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("update post draft failed".to_string()))
+}
+
+pub async fn get_post_drafts_count_by_parent(state: &AppState, parent: u32) -> u64 {
+    Content::find()
+        .filter(content::Column::Type.eq("post_draft"))
+        .filter(content::Column::Parent.eq(parent))
+        .count(&state.conn)
+        .await
+        .unwrap_or(0)
+}
+
+pub async fn get_post_drafts_by_parent_and_list_query(
+    state: &AppState,
+    parent: u32,
+    page_size: u64,
+    page: u64,
+    order_by: &str,
+) -> Result<Vec<content::Model>, FieldError> {
+    let stmt = Content::find()
+        .filter(content::Column::Type.eq("post_draft"))
+        .filter(content::Column::Parent.eq(parent));
+
+    let stmt = match order_by {
+        "-cid" => stmt.order_by_desc(content::Column::Cid),
+        "cid" => stmt.order_by_asc(content::Column::Cid),
+        _ => stmt.order_by_desc(content::Column::Cid),
+    };
+    let paginator = stmt.paginate(&state.conn, page_size);
+
+    paginator
+        .fetch_page(page - 1)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("fetch post drafts failed".to_string()))
+}
+
+pub async fn get_post_draft_by_parent_and_cid(
+    state: &AppState,
+    parent: u32,
+    cid: u32,
+) -> Result<Option<content::Model>, FieldError> {
+    Content::find()
+        .filter(content::Column::Type.eq("post_draft"))
+        .filter(content::Column::Parent.eq(parent))
+        .filter(content::Column::Cid.eq(cid))
+        .one(&state.conn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("fetch post draft failed".to_string()))
+}
+
+async fn modify_post_by_post_draft_with_exist_post<C: ConnectionTrait>(
+    conn: &C,
+    draft: &content::Model,
+    exist_post: &content::Model,
+) -> Result<content::Model, FieldError> {
+    let mut c = content::ActiveModel::from(exist_post.clone());
+    c.title = Set(draft.title.to_owned());
+    c.modified = Set(get_next_modified(get_now(), exist_post.modified));
+    c.text = Set(draft.text.to_owned());
+    c.template = Set(draft.template.to_owned());
+    c.status = Set(draft.status.to_owned());
+    c.password = Set(draft.password.to_owned());
+    c.allow_comment = Set(draft.allow_comment.to_owned());
+    c.allow_ping = Set(draft.allow_ping.to_owned());
+    c.allow_feed = Set(draft.allow_feed.to_owned());
+    let post = c
+        .update(conn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("update post failed".to_string()))?;
+    common_db::update_metas_count_by_cid(conn, post.cid).await?;
+    Ok(post)
+}
+
+/// Replaces the post with a revision, the current version is kept as a new revision so
+/// the restore can be undone.
+pub async fn restore_post_by_revision_with_exist_post(
+    state: &AppState,
+    revision: &content::Model,
+    exist_post: &content::Model,
+) -> Result<content::Model, FieldError> {
+    let txn = state
+        .conn
+        .begin()
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("begin transaction failed".to_string()))?;
+    let _ = create_post_draft_by_content_with_parent(&txn, exist_post, exist_post.cid).await?;
+    let post = modify_post_by_post_draft_with_exist_post(&txn, revision, exist_post).await?;
+    txn.commit()
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("commit transaction failed".to_string()))?;
+    Ok(post)
+}
+
+/// Replaces the post with a draft and removes the draft.
+pub async fn publish_post_by_draft_with_exist_post(
+    state: &AppState,
+    draft: &content::Model,
+    exist_post: &content::Model,
+) -> Result<content::Model, FieldError> {
+    let txn = state
+        .conn
+        .begin()
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("begin transaction failed".to_string()))?;
+    let post = modify_post_by_post_draft_with_exist_post(&txn, draft, exist_post).await?;
+    Content::delete_by_id(draft.cid)
+        .exec(&txn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("delete post draft failed".to_string()))?;
+    txn.commit()
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("commit transaction failed".to_string()))?;
+    Ok(post)
+}
+
+async fn get_meta_by_slug_or_name<C: ConnectionTrait>(
+    conn: &C,
+    value: &str,
+    meta_type: &str,
+) -> Result<Option<meta::Model>, FieldError> {
+    Meta::find()
+        .filter(meta::Column::Type.eq(meta_type))
+        .filter(
+            meta::Column::Slug
+                .eq(value)
+                .or(meta::Column::Name.eq(value)),
+        )
+        .one(conn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("fetch meta failed".to_string()))
+}
+
+/// Reconciles the categories and tags of a post, `None` keeps the current ones.
+/// Counts of its metas are always recomputed, since the status may have changed.
+async fn set_post_metas_by_cid<C: ConnectionTrait>(
+    txn: &C,
+    cid: u32,
+    categories: Option<&[String]>,
+    tags: Option<&[String]>,
+    default_category: u32,
+) -> Result<(), FieldError> {
+    let mut meta_types = vec![];
+    let mut mids = vec![];
+    if let Some(categories) = categories {
+        meta_types.push("category");
+        for name in categories {
+            match get_meta_by_slug_or_name(txn, name, "category").await? {
+                Some(m) => mids.push(m.mid),
+                None => return Err(FieldError::InvalidParams("categories".to_string())),
+            }
+        }
+        // same as typecho, posts without category fall into the default one
+        if categories.is_empty() {
+            if let Ok(Some(m)) = Meta::find()
+                .filter(meta::Column::Mid.eq(default_category))
+                .filter(meta::Column::Type.eq("category"))
+                .one(txn)
+                .await
+            {
+                mids.push(m.mid);
+            }
+        }
+    }
+    if let Some(tags) = tags {
+        meta_types.push("tag");
+        for name in tags {
+            if name.is_empty() || name.chars().count() > 150 {
+                return Err(FieldError::InvalidParams("tags".to_string()));
+            }
+            let slug =
+                get_slug_name(name, 150).ok_or(FieldError::InvalidParams("tags".to_string()))?;
+            let exist_tag = match get_meta_by_slug_or_name(txn, name, "tag").await? {
+                Some(m) => Some(m),
+                None => get_meta_by_slug_or_name(txn, &slug, "tag").await?,
+            };
+            let mid = match exist_tag {
+                Some(m) => m.mid,
+                None => {
+                    meta::ActiveModel {
+                        r#type: Set("tag".to_string()),
+                        name: Set(Some(name.to_owned())),
+                        slug: Set(Some(slug)),
+                        ..Default::default()
+                    }
+                    .insert(txn)
+                    .await
+                    .map_err(|_| FieldError::DatabaseFailed("create tag failed".to_string()))?
+                    .mid
+                }
+            };
+            mids.push(mid);
+        }
+    }
+    let related_mids: Vec<u32> = Relationship::find()
+        .filter(relationship::Column::Cid.eq(cid))
+        .all(txn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("fetch relationships failed".to_string()))?
+        .into_iter()
+        .map(|r| r.mid)
+        .collect();
+    if meta_types.is_empty() {
+        return common_db::update_metas_count_by_mids(txn, &related_mids).await;
+    }
+    mids.sort_unstable();
+    mids.dedup();
+
+    let exist_mids: Vec<u32> = Meta::find()
+        .filter(meta::Column::Mid.is_in(related_mids.clone()))
+        .filter(meta::Column::Type.is_in(meta_types))
+        .all(txn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("fetch metas failed".to_string()))?
+        .into_iter()
+        .map(|m| m.mid)
+        .collect();
+    let removed: Vec<u32> = exist_mids
+        .iter()
+        .filter(|mid| !mids.contains(mid))
+        .cloned()
+        .collect();
+    let added: Vec<u32> = mids
+        .into_iter()
+        .filter(|mid| !exist_mids.contains(mid))
+        .collect();
+
+    if !removed.is_empty() {
+        Relationship::delete_many()
+            .filter(relationship::Column::Cid.eq(cid))
+            .filter(relationship::Column::Mid.is_in(removed))
+            .exec(txn)
+            .await
+            .map_err(|_| FieldError::DatabaseFailed("delete relationships failed".to_string()))?;
+    }
+    if !added.is_empty() {
+        Relationship::insert_many(added.iter().map(|mid| relationship::ActiveModel {
+            cid: Set(cid),
+            mid: Set(*mid),
+        }))
+        .exec(txn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("create relationships failed".to_string()))?;
+    }
+
+    let mut count_mids = related_mids;
+    count_mids.extend(added);
+    common_db::update_metas_count_by_mids(txn, &count_mids).await
+}
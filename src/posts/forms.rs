@@ -1,6 +1,6 @@
 #![allow(non_snake_case)]
 use serde::{Deserialize, Serialize};
-use validator::Validate;
+use validator::{Validate, ValidationError};
 
 #[derive(Serialize, Deserialize, Validate)]
 pub struct PostsQuery {
@@ -12,6 +12,19 @@ pub struct PostsQuery {
     pub order_by: Option<String>,
     pub private: Option<bool>,
     pub own: Option<bool>,
+    #[validate(length(min = 1, max = 8, message = "format length must greater than 1"))]
+    pub format: Option<String>,
+    #[validate(length(min = 1, max = 8, message = "fields length must greater than 1"))]
+    pub fields: Option<String>,
+}
+
+/// Posts are only moved into the trash by deleting them, and out of it by restoring.
+fn validate_status(status: &str) -> Result<(), ValidationError> {
+    if status == "trash" {
+        Err(ValidationError::new("status"))
+    } else {
+        Ok(())
+    }
 }
 
 #[derive(Serialize, Deserialize, Validate)]
@@ -22,13 +35,20 @@ pub struct PostCreate {
     pub slug: String,
     pub created: u32,
     pub text: String,
-    #[validate(length(min = 1, max = 16, message = "status length must greater than 1"))]
+    #[validate(
+        length(min = 1, max = 16, message = "status length must greater than 1"),
+        custom(function = "validate_status")
+    )]
     pub status: String,
     #[validate(length(min = 1, max = 32, message = "password length must greater than 1"))]
     pub password: Option<String>,
     pub allowComment: Option<bool>,
     pub allowPing: Option<bool>,
     pub allowFeed: Option<bool>,
+    #[validate(length(max = 32, message = "categories length must less than 32"))]
+    pub categories: Option<Vec<String>>,
+    #[validate(length(max = 32, message = "tags length must less than 32"))]
+    pub tags: Option<Vec<String>>,
 }
 
 #[derive(Serialize, Deserialize, Validate)]
@@ -36,4 +56,6 @@ pub struct PostQuery {
     #[validate(length(min = 1, max = 32, message = "password length must greater than 1"))]
     pub password: Option<String>,
     pub private: Option<bool>,
+    #[validate(length(min = 1, max = 8, message = "format length must greater than 1"))]
+    pub format: Option<String>,
 }
@@ -1,6 +1,8 @@
 pub mod db;
 pub mod forms;
+pub mod scheduler;
 mod urls;
+pub mod utils;
 mod views;
 
 pub use urls::posts_routers;
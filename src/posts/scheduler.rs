@@ -0,0 +1,44 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use tracing::{info, warn};
+
+use super::db;
+use crate::common::errors::FieldError;
+use crate::common::utils::get_now;
+use crate::webmentions::sender::send_post_webmentions;
+use crate::AppState;
+
+const SCHEDULE_INTERVAL: u64 = 60;
+
+/// Announces the posts that became due after `last_check`, returns the new check time.
+pub async fn publish_due_posts(state: &Arc<AppState>, last_check: u32) -> Result<u32, FieldError> {
+    let now = get_now();
+    let posts = db::get_published_posts_by_created_range(state, last_check, now).await?;
+    for post in posts {
+        info!(
+            "scheduled post published: cid={} slug={}",
+            post.cid,
+            post.slug.as_deref().unwrap_or_default()
+        );
+        tokio::spawn(send_post_webmentions(state.clone(), post));
+    }
+    db::set_schedule_check_time(state, now).await?;
+    Ok(now)
+}
+
+pub async fn publish_scheduled_posts(state: Arc<AppState>) {
+    let mut interval = tokio::time::interval(Duration::from_secs(SCHEDULE_INTERVAL));
+    // a fresh install has nothing to catch up on
+    let mut last_check = match db::get_schedule_check_time(&state).await {
+        Ok(Some(t)) => t,
+        _ => get_now(),
+    };
+    loop {
+        interval.tick().await;
+        match publish_due_posts(&state, last_check).await {
+            Ok(now) => last_check = now,
+            Err(e) => warn!("check scheduled posts failed: {:?}", e),
+        }
+    }
+}
@@ -10,7 +10,15 @@ use crate::AppState;
 pub fn posts_routers(ro: bool) -> Router<Arc<AppState>> {
     let posts_route = Router::new()
         .route("/api/posts/", get(views::list_posts))
-        .route("/api/posts/:slug", get(views::get_post_by_slug));
+        .route("/api/posts/:slug", get(views::get_post_by_slug))
+        .route(
+            "/api/posts/:slug/revisions/",
+            get(views::list_post_revisions_by_slug),
+        )
+        .route(
+            "/api/posts/:slug/revisions/:cid",
+            get(views::get_post_revision_by_slug_and_cid),
+        );
     if !ro {
         posts_route
             .route("/api/posts/", post(views::create_post))
@@ -32,6 +40,18 @@ pub fn posts_routers(ro: bool) -> Router<Arc<AppState>> {
                 "/api/posts/:slug/fields/:name",
                 delete(views::delete_post_field_by_slug_and_name),
             )
+            .route(
+                "/api/posts/:slug/revisions/",
+                post(views::create_post_revision_by_slug),
+            )
+            .route(
+                "/api/posts/:slug/revisions/:cid/restore",
+                post(views::restore_post_revision_by_slug_and_cid),
+            )
+            .route(
+                "/api/posts/:slug/revisions/:cid/publish",
+                post(views::publish_post_revision_by_slug_and_cid),
+            )
     } else {
         posts_route
     }
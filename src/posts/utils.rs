@@ -0,0 +1,175 @@
+use std::collections::HashMap;
+
+use serde_json::{json, Value};
+use validator::Validate;
+
+use super::db;
+use super::forms::PostCreate;
+use crate::common::db as common_db;
+use crate::common::errors::FieldError;
+use crate::common::markdown::{get_excerpt, render_text_to_html};
+use crate::common::models::ContentWithMetasUsersFields;
+use crate::common::utils::{etag_matches, get_etag};
+use crate::entity::{content, user};
+use crate::AppState;
+
+pub fn is_summary_fields(fields: Option<&str>) -> Result<bool, FieldError> {
+    match fields {
+        None | Some("all") => Ok(false),
+        Some("summary") => Ok(true),
+        _ => Err(FieldError::InvalidParams("fields".to_string())),
+    }
+}
+
+pub fn get_default_category(options: &HashMap<String, String>) -> u32 {
+    options
+        .get("defaultCategory")
+        .and_then(|s| s.parse::<u32>().ok())
+        .unwrap_or(1)
+}
+
+pub fn is_admin(user: &user::Model) -> bool {
+    user.group == "editor" || user.group == "administrator"
+}
+
+/// For clients that save drafts, contributors can keep drafts, everything else needs a review.
+pub fn set_contributor_status(user: &user::Model, post: &mut PostCreate) {
+    if user.group == "contributor" && post.status != "draft" {
+        post.status = "waiting".to_string();
+    }
+}
+
+/// Starts a modify from the current post, so fields the client leaves out are kept.
+pub fn get_post_create_by_content(post: &content::Model) -> PostCreate {
+    PostCreate {
+        title: post.title.clone().unwrap_or_default(),
+        slug: post.slug.clone().unwrap_or_default(),
+        created: post.created,
+        text: post.text.clone().unwrap_or_default(),
+        status: post.status.clone(),
+        password: post.password.clone(),
+        allowComment: Some(post.allow_comment == "1"),
+        allowPing: Some(post.allow_ping == "1"),
+        allowFeed: Some(post.allow_feed == "1"),
+        categories: None,
+        tags: None,
+    }
+}
+
+/// Creates a post for clients that may leave the slug out, same as typecho such posts
+/// use the cid, which is set in the same transaction.
+pub async fn create_post_with_default_slug(
+    state: &AppState,
+    mut post_create: PostCreate,
+    uid: u32,
+    default_category: u32,
+) -> Result<content::Model, FieldError> {
+    let slug_by_cid = post_create.slug.is_empty();
+    if slug_by_cid {
+        // only stands in for the validation
+        post_create.slug = "0".to_string();
+    }
+    if let Err(e) = post_create.validate() {
+        let fields: Vec<String> = e.field_errors().keys().map(|k| k.to_string()).collect();
+        return Err(FieldError::InvalidParams(fields.join(", ")));
+    }
+    if !slug_by_cid {
+        if let Ok(Some(_)) =
+            common_db::get_content_by_slug_with_trash(state, &post_create.slug).await
+        {
+            return Err(FieldError::AlreadyExist("slug".to_string()));
+        }
+    }
+
+    db::create_post_by_post_create_with_uid(state, &post_create, uid, default_category, slug_by_cid)
+        .await
+}
+
+/// Modifies the post in place once `if_match` matches its etag, a changed slug must be free.
+pub async fn modify_post_with_exist_post(
+    state: &AppState,
+    post_modify: &PostCreate,
+    exist_post: &content::Model,
+    if_match: &str,
+    default_category: u32,
+) -> Result<content::Model, FieldError> {
+    let etag = get_etag(
+        exist_post.cid,
+        exist_post.modified,
+        &exist_post.status,
+        exist_post.text.as_deref(),
+    );
+    if !etag_matches(if_match, &etag) {
+        let slug = exist_post.slug.as_deref().unwrap_or_default();
+        let current =
+            db::get_content_with_metas_user_fields_by_slug_and_private(state, slug, true).await?;
+        return Err(FieldError::PreconditionFailed(etag, json!(current)));
+    }
+
+    if exist_post.slug.as_deref() != Some(post_modify.slug.as_str()) {
+        if let Ok(Some(_)) =
+            common_db::get_content_by_slug_with_trash(state, &post_modify.slug).await
+        {
+            return Err(FieldError::AlreadyExist("post slug".to_string()));
+        }
+    }
+
+    db::modify_post_by_post_create_with_exist_post(state, post_modify, exist_post, default_category)
+        .await
+}
+
+/// Saves a revision of the post, the post itself is not touched.
+pub async fn create_post_revision_with_exist_post(
+    state: &AppState,
+    post_draft: PostCreate,
+    exist_post: &content::Model,
+) -> Result<content::Model, FieldError> {
+    let flag = |value: Option<bool>, default: &str| match value {
+        Some(true) => "1".to_string(),
+        Some(false) => "0".to_string(),
+        None => default.to_string(),
+    };
+    let mut draft = exist_post.clone();
+    draft.title = Some(post_draft.title);
+    draft.text = Some(post_draft.text);
+    draft.status = post_draft.status;
+    draft.password = post_draft.password;
+    draft.allow_comment = flag(post_draft.allowComment, &exist_post.allow_comment);
+    draft.allow_ping = flag(post_draft.allowPing, &exist_post.allow_ping);
+    draft.allow_feed = flag(post_draft.allowFeed, &exist_post.allow_feed);
+
+    db::create_post_draft_by_content_with_parent(&state.conn, &draft, exist_post.cid).await
+}
+
+pub fn get_posts_results(
+    posts: Vec<ContentWithMetasUsersFields>,
+    html: bool,
+    summary: bool,
+    excerpt_length: usize,
+) -> Vec<Value> {
+    let mut res = vec![];
+    for mut post in posts {
+        let text = post.text.take().unwrap_or_default();
+        post.excerpt = Some(get_excerpt(
+            post.cid,
+            post.modified,
+            &text,
+            excerpt_length,
+            html,
+        ));
+        if html {
+            post.text = Some(render_text_to_html(post.cid, post.modified, &text));
+        } else {
+            post.text = Some(text);
+        }
+
+        let mut post = json!(post);
+        if summary {
+            if let Some(p) = post.as_object_mut() {
+                p.remove("text");
+            }
+        }
+        res.push(post);
+    }
+    res
+}
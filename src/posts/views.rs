@@ -1,16 +1,25 @@
 use std::sync::Arc;
 
 use axum::extract::{Path, State};
-use axum::http::StatusCode;
+use axum::http::{header, HeaderMap, HeaderName, StatusCode};
 use axum::response::Json;
 use serde_json::{json, Value};
 
 use super::db;
 use super::forms::{PostCreate, PostQuery, PostsQuery};
+use super::utils::{
+    create_post_revision_with_exist_post, get_default_category, get_posts_results,
+    is_summary_fields, modify_post_with_exist_post,
+};
 use crate::common::db as common_db;
 use crate::common::errors::FieldError;
 use crate::common::extractors::{PMContributor, PMVisitor, ValidatedJson, ValidatedQuery};
-use crate::common::forms::FieldCreate;
+use crate::common::forms::{DeleteQuery, FieldCreate, ListQuery};
+use crate::common::markdown::{is_html_format, render_text_to_html};
+use crate::common::utils::{get_etag, get_if_match};
+use crate::entity::{content, user};
+use crate::trash::db as trash_db;
+use crate::webmentions::sender::send_post_webmentions;
 use crate::AppState;
 
 pub async fn create_post(
@@ -18,7 +27,8 @@ pub async fn create_post(
     PMContributor(user): PMContributor,
     ValidatedJson(mut post_create): ValidatedJson<PostCreate>,
 ) -> Result<(StatusCode, Json<Value>), FieldError> {
-    if let Ok(Some(_)) = common_db::get_content_by_slug(&state, &post_create.slug).await {
+    if let Ok(Some(_)) = common_db::get_content_by_slug_with_trash(&state, &post_create.slug).await
+    {
         return Err(FieldError::AlreadyExist("slug".to_owned()));
     }
 
@@ -26,7 +36,17 @@ pub async fn create_post(
         post_create.status = String::from("waiting");
     }
 
-    let _ = db::create_post_by_post_create_with_uid(&state, &post_create, user.uid).await?;
+    let options = common_db::get_site_options(&state).await?;
+    let default_category = get_default_category(&options);
+    let post = db::create_post_by_post_create_with_uid(
+        &state,
+        &post_create,
+        user.uid,
+        default_category,
+        false,
+    )
+    .await?;
+    tokio::spawn(send_post_webmentions(state.clone(), post));
     Ok((StatusCode::CREATED, Json(json!({ "msg": "ok" }))))
 }
 
@@ -34,26 +54,32 @@ pub async fn modify_post_by_slug(
     State(state): State<Arc<AppState>>,
     PMContributor(user): PMContributor,
     Path(slug): Path<String>,
+    headers: HeaderMap,
     ValidatedJson(mut post_modify): ValidatedJson<PostCreate>,
-) -> Result<Json<Value>, FieldError> {
+) -> Result<([(HeaderName, String); 1], Json<Value>), FieldError> {
     let exist_post = match common_db::get_content_by_slug(&state, &slug).await {
         Ok(Some(p)) => p,
         _ => return Err(FieldError::NotFound("slug".to_owned())),
     };
 
-    if slug != post_modify.slug {
-        if let Ok(Some(_)) = common_db::get_content_by_slug(&state, &post_modify.slug).await {
-            return Err(FieldError::AlreadyExist("post slug".to_owned()));
-        }
-    }
-
+    let if_match = get_if_match(&headers)?;
     if user.group == "contributor" {
         post_modify.status = String::from("waiting");
     }
 
-    let _ =
-        db::modify_post_by_post_create_with_exist_post(&state, &post_modify, &exist_post).await?;
-    Ok(Json(json!({ "msg": "ok" })))
+    let options = common_db::get_site_options(&state).await?;
+    let default_category = get_default_category(&options);
+    let post = modify_post_with_exist_post(
+        &state,
+        &post_modify,
+        &exist_post,
+        if_match,
+        default_category,
+    )
+    .await?;
+    let etag = get_etag(post.cid, post.modified, &post.status, post.text.as_deref());
+    tokio::spawn(send_post_webmentions(state.clone(), post));
+    Ok(([(header::ETAG, etag)], Json(json!({ "msg": "ok" }))))
 }
 
 pub async fn list_posts(
@@ -64,6 +90,8 @@ pub async fn list_posts(
     let private =
         q.private.unwrap_or(false) && (user.group == "editor" || user.group == "administrator");
     let own = q.own.unwrap_or(false) && user.group != "visitor";
+    let html = is_html_format(q.format.as_deref())?;
+    let summary = is_summary_fields(q.fields.as_deref())?;
 
     let all_count =
         common_db::get_contents_count_with_private(&state, private, own, &user, "post").await;
@@ -73,9 +101,10 @@ pub async fn list_posts(
     let order_by = q.order_by.unwrap_or("-cid".to_string());
 
     let posts = db::get_contents_with_metas_user_and_fields_by_filter_and_list_query(
-        &state, private, own, &user, page_size, page, &order_by, true,
+        &state, private, own, &user, page_size, page, &order_by, true, false,
     )
     .await?;
+    let posts = get_posts_results(posts, html, summary, state.excerpt_length);
     Ok(Json(json!({
         "page": page,
         "page_size": page_size,
@@ -90,24 +119,31 @@ pub async fn get_post_by_slug(
     PMVisitor(user): PMVisitor,
     Path(slug): Path<String>,
     ValidatedQuery(q): ValidatedQuery<PostQuery>,
-) -> Result<Json<Value>, FieldError> {
+) -> Result<([(HeaderName, String); 1], Json<Value>), FieldError> {
     let admin = user.group == "editor" || user.group == "administrator";
     let private = q.private.unwrap_or(false) && admin;
-
-    let post = db::get_content_with_metas_user_fields_by_slug_and_private(&state, &slug, private)
-        .await
-        .map_err(|_| FieldError::NotFound("slug".to_string()))?;
+    let html = is_html_format(q.format.as_deref())?;
+
+    let mut post =
+        db::get_content_with_metas_user_fields_by_slug_and_private(&state, &slug, private)
+            .await
+            .map_err(|_| FieldError::NotFound("slug".to_string()))?;
+    let etag = get_etag(post.cid, post.modified, &post.status, post.text.as_deref());
+    if html {
+        let text = post.text.as_deref().unwrap_or_default();
+        post.text = Some(render_text_to_html(post.cid, post.modified, text));
+    }
 
     let status = &post.status;
     if admin || status == "publish" || status == "hidden" || status == "password" {
         if post.password.is_none() {
-            return Ok(Json(json!(post)));
+            return Ok(([(header::ETAG, etag)], Json(json!(post))));
         }
 
         let password = post.password.clone().unwrap();
         if let Some(query_password) = q.password {
             if password == query_password {
-                return Ok(Json(json!(post)));
+                return Ok(([(header::ETAG, etag)], Json(json!(post))));
             }
         } else {
             return Err(FieldError::PasswordRequired);
@@ -123,10 +159,11 @@ pub async fn delete_post_by_slug(
     State(state): State<Arc<AppState>>,
     PMContributor(user): PMContributor,
     Path(slug): Path<String>,
+    ValidatedQuery(q): ValidatedQuery<DeleteQuery>,
 ) -> Result<Json<Value>, FieldError> {
     let post = common_db::get_content_by_slug(&state, &slug).await?;
 
-    if post.is_none() {
+    if post.is_none() || post.as_ref().is_some_and(|p| p.status == "trash") {
         return Err(FieldError::InvalidParams("slug".to_string()));
     }
     let post = post.unwrap();
@@ -136,9 +173,9 @@ pub async fn delete_post_by_slug(
         return Err(FieldError::PermissionDeny);
     }
 
-    let _ = common_db::delete_fields_by_cid(&state, post.cid).await?;
-
-    let _ = common_db::delete_content_by_cid(&state, post.cid).await?;
+    let delete_attachments = q.delete_attachments.unwrap_or(false);
+    let _ = trash_db::move_content_to_trash_with_uid(&state, &post, user.uid, delete_attachments)
+        .await?;
     Ok(Json(json!({ "msg": "ok" })))
 }
 
@@ -168,7 +205,7 @@ pub async fn get_post_field_by_slug_and_name(
     Path((slug, name)): Path<(String, String)>,
 ) -> Result<Json<Value>, FieldError> {
     let exist_post = match common_db::get_content_by_slug(&state, &slug).await {
-        Ok(Some(p)) => p,
+        Ok(Some(p)) if p.status != "trash" => p,
         _ => return Err(FieldError::NotFound("slug".to_owned())),
     };
 
@@ -235,3 +272,123 @@ pub async fn delete_post_field_by_slug_and_name(
     let _ = common_db::delete_field_by_cid_and_name(&state, exist_post.cid, &name).await?;
     Ok(Json(json!({ "msg": "ok" })))
 }
+
+async fn get_exist_post_by_slug_with_user(
+    state: &AppState,
+    slug: &str,
+    user: &user::Model,
+) -> Result<content::Model, FieldError> {
+    let exist_post = match common_db::get_content_by_slug(state, slug).await {
+        Ok(Some(p)) if p.r#type == "post" => p,
+        _ => return Err(FieldError::NotFound("slug".to_owned())),
+    };
+
+    let admin = user.group == "editor" || user.group == "administrator";
+    if exist_post.author_id != user.uid && !admin {
+        return Err(FieldError::PermissionDeny);
+    }
+    Ok(exist_post)
+}
+
+pub async fn list_post_revisions_by_slug(
+    State(state): State<Arc<AppState>>,
+    PMContributor(user): PMContributor,
+    Path(slug): Path<String>,
+    ValidatedQuery(q): ValidatedQuery<ListQuery>,
+) -> Result<Json<Value>, FieldError> {
+    let exist_post = get_exist_post_by_slug_with_user(&state, &slug, &user).await?;
+
+    let all_count = db::get_post_drafts_count_by_parent(&state, exist_post.cid).await;
+
+    let page = q.page.unwrap_or(1);
+    let page_size = q.page_size.unwrap_or(10);
+    let order_by = q.order_by.unwrap_or("-cid".to_string());
+
+    let revisions = db::get_post_drafts_by_parent_and_list_query(
+        &state,
+        exist_post.cid,
+        page_size,
+        page,
+        &order_by,
+    )
+    .await?;
+    Ok(Json(json!({
+        "page": page,
+        "page_size": page_size,
+        "all_count": all_count,
+        "count": revisions.len(),
+        "results": revisions
+    })))
+}
+
+pub async fn create_post_revision_by_slug(
+    State(state): State<Arc<AppState>>,
+    PMContributor(user): PMContributor,
+    Path(slug): Path<String>,
+    ValidatedJson(mut post_draft): ValidatedJson<PostCreate>,
+) -> Result<(StatusCode, Json<Value>), FieldError> {
+    let exist_post = get_exist_post_by_slug_with_user(&state, &slug, &user).await?;
+
+    if user.group == "contributor" {
+        post_draft.status = String::from("waiting");
+    }
+
+    let draft = create_post_revision_with_exist_post(&state, post_draft, &exist_post).await?;
+    Ok((
+        StatusCode::CREATED,
+        Json(json!({ "msg": "ok", "cid": draft.cid })),
+    ))
+}
+
+pub async fn get_post_revision_by_slug_and_cid(
+    State(state): State<Arc<AppState>>,
+    PMContributor(user): PMContributor,
+    Path((slug, cid)): Path<(String, u32)>,
+) -> Result<Json<Value>, FieldError> {
+    let exist_post = get_exist_post_by_slug_with_user(&state, &slug, &user).await?;
+
+    match db::get_post_draft_by_parent_and_cid(&state, exist_post.cid, cid).await? {
+        Some(r) => Ok(Json(json!(r))),
+        None => Err(FieldError::NotFound("cid".to_owned())),
+    }
+}
+
+pub async fn restore_post_revision_by_slug_and_cid(
+    State(state): State<Arc<AppState>>,
+    PMContributor(user): PMContributor,
+    Path((slug, cid)): Path<(String, u32)>,
+) -> Result<Json<Value>, FieldError> {
+    let exist_post = get_exist_post_by_slug_with_user(&state, &slug, &user).await?;
+
+    let mut revision =
+        match db::get_post_draft_by_parent_and_cid(&state, exist_post.cid, cid).await? {
+            Some(r) => r,
+            None => return Err(FieldError::NotFound("cid".to_owned())),
+        };
+    if user.group == "contributor" {
+        revision.status = String::from("waiting");
+    }
+
+    let _ = db::restore_post_by_revision_with_exist_post(&state, &revision, &exist_post).await?;
+    Ok(Json(json!({ "msg": "ok" })))
+}
+
+pub async fn publish_post_revision_by_slug_and_cid(
+    State(state): State<Arc<AppState>>,
+    PMContributor(user): PMContributor,
+    Path((slug, cid)): Path<(String, u32)>,
+) -> Result<Json<Value>, FieldError> {
+    let exist_post = get_exist_post_by_slug_with_user(&state, &slug, &user).await?;
+
+    let mut draft = match db::get_post_draft_by_parent_and_cid(&state, exist_post.cid, cid).await? {
+        Some(d) => d,
+        None => return Err(FieldError::NotFound("cid".to_owned())),
+    };
+    if user.group == "contributor" {
+        draft.status = String::from("waiting");
+    }
+
+    let post = db::publish_post_by_draft_with_exist_post(&state, &draft, &exist_post).await?;
+    tokio::spawn(send_post_webmentions(state.clone(), post));
+    Ok(Json(json!({ "msg": "ok" })))
+}
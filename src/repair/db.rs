@@ -0,0 +1,141 @@
+use std::collections::HashMap;
+
+use sea_orm::sea_query::Expr;
+use sea_orm::*;
+
+use super::models::CountChange;
+use crate::common::errors::FieldError;
+use crate::entity::{
+    comment, comment::Entity as Comment, content, content::Entity as Content, meta,
+    meta::Entity as Meta, relationship, relationship::Entity as Relationship,
+};
+use crate::AppState;
+
+/// Same as typecho, a meta only counts its published posts.
+async fn get_meta_count_changes<C: ConnectionTrait>(
+    conn: &C,
+) -> Result<Vec<CountChange>, FieldError> {
+    let counts: HashMap<u32, i64> = Relationship::find()
+        .select_only()
+        .column(relationship::Column::Mid)
+        .column_as(relationship::Column::Cid.count(), "count")
+        .join(JoinType::InnerJoin, relationship::Relation::Content.def())
+        .filter(content::Column::Type.eq("post"))
+        .filter(content::Column::Status.eq("publish"))
+        .group_by(relationship::Column::Mid)
+        .into_tuple::<(u32, i64)>()
+        .all(conn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("count relationships failed".to_string()))?
+        .into_iter()
+        .collect();
+
+    let metas = Meta::find()
+        .order_by_asc(meta::Column::Mid)
+        .all(conn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("fetch metas failed".to_string()))?;
+
+    let mut res = vec![];
+    for m in metas {
+        let count = counts.get(&m.mid).cloned().unwrap_or(0) as u32;
+        if count != m.count {
+            res.push(CountChange {
+                r#type: m.r#type,
+                id: m.mid,
+                name: m.name,
+                field: "count".to_string(),
+                old: m.count,
+                new: count,
+            });
+        }
+    }
+    Ok(res)
+}
+
+async fn get_comments_num_changes<C: ConnectionTrait>(
+    conn: &C,
+) -> Result<Vec<CountChange>, FieldError> {
+    let counts: HashMap<u32, i64> = Comment::find()
+        .select_only()
+        .column(comment::Column::Cid)
+        .column_as(comment::Column::Coid.count(), "count")
+        .filter(comment::Column::Status.eq("approved"))
+        .group_by(comment::Column::Cid)
+        .into_tuple::<(u32, i64)>()
+        .all(conn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("count comments failed".to_string()))?
+        .into_iter()
+        .collect();
+
+    let contents = Content::find()
+        .select_only()
+        .columns([
+            content::Column::Cid,
+            content::Column::Type,
+            content::Column::Title,
+            content::Column::CommentsNum,
+        ])
+        .order_by_asc(content::Column::Cid)
+        .into_tuple::<(u32, String, Option<String>, u32)>()
+        .all(conn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("fetch contents failed".to_string()))?;
+
+    let mut res = vec![];
+    for (cid, content_type, title, comments_num) in contents {
+        let count = counts.get(&cid).cloned().unwrap_or(0) as u32;
+        if count != comments_num {
+            res.push(CountChange {
+                r#type: content_type,
+                id: cid,
+                name: title,
+                field: "commentsNum".to_string(),
+                old: comments_num,
+                new: count,
+            });
+        }
+    }
+    Ok(res)
+}
+
+/// Recomputes `meta.count` and `content.commentsNum`, returns what was wrong.
+pub async fn repair_counts(
+    state: &AppState,
+    dry_run: bool,
+) -> Result<Vec<CountChange>, FieldError> {
+    let txn = state
+        .conn
+        .begin()
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("begin transaction failed".to_string()))?;
+
+    let meta_changes = get_meta_count_changes(&txn).await?;
+    let content_changes = get_comments_num_changes(&txn).await?;
+    if dry_run {
+        return Ok(meta_changes.into_iter().chain(content_changes).collect());
+    }
+
+    for change in meta_changes.iter() {
+        Meta::update_many()
+            .col_expr(meta::Column::Count, Expr::value(change.new))
+            .filter(meta::Column::Mid.eq(change.id))
+            .exec(&txn)
+            .await
+            .map_err(|_| FieldError::DatabaseFailed("update meta count failed".to_string()))?;
+    }
+    for change in content_changes.iter() {
+        Content::update_many()
+            .col_expr(content::Column::CommentsNum, Expr::value(change.new))
+            .filter(content::Column::Cid.eq(change.id))
+            .exec(&txn)
+            .await
+            .map_err(|_| FieldError::DatabaseFailed("update comments num failed".to_string()))?;
+    }
+
+    txn.commit()
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("commit transaction failed".to_string()))?;
+    Ok(meta_changes.into_iter().chain(content_changes).collect())
+}
@@ -0,0 +1,7 @@
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+#[derive(Serialize, Deserialize, Validate)]
+pub struct RepairQuery {
+    pub dry_run: Option<bool>,
+}
@@ -0,0 +1,7 @@
+pub mod db;
+mod forms;
+pub mod models;
+mod urls;
+mod views;
+
+pub use urls::repair_routers;
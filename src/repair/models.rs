@@ -0,0 +1,28 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize)]
+pub struct CountChange {
+    pub r#type: String,
+    pub id: u32,
+    pub name: Option<String>,
+    pub field: String,
+    pub old: u32,
+    pub new: u32,
+}
+
+impl fmt::Display for CountChange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} {} ({}) {}: {} -> {}",
+            self.r#type,
+            self.id,
+            self.name.as_deref().unwrap_or_default(),
+            self.field,
+            self.old,
+            self.new
+        )
+    }
+}
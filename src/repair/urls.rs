@@ -0,0 +1,14 @@
+use axum::{routing::post, Router};
+use std::sync::Arc;
+
+use super::views;
+use crate::AppState;
+
+pub fn repair_routers(ro: bool) -> Router<Arc<AppState>> {
+    let repair_route = Router::new();
+    if !ro {
+        repair_route.route("/api/repair", post(views::repair_counts))
+    } else {
+        repair_route
+    }
+}
@@ -0,0 +1,26 @@
+use std::sync::Arc;
+
+use axum::extract::State;
+use axum::response::Json;
+use serde_json::{json, Value};
+
+use super::db;
+use super::forms::RepairQuery;
+use crate::common::errors::FieldError;
+use crate::common::extractors::{PMAdministrator, ValidatedQuery};
+use crate::AppState;
+
+pub async fn repair_counts(
+    State(state): State<Arc<AppState>>,
+    PMAdministrator(_): PMAdministrator,
+    ValidatedQuery(q): ValidatedQuery<RepairQuery>,
+) -> Result<Json<Value>, FieldError> {
+    let dry_run = q.dry_run.unwrap_or(false);
+
+    let changes = db::repair_counts(&state, dry_run).await?;
+    Ok(Json(json!({
+        "dry_run": dry_run,
+        "count": changes.len(),
+        "results": changes
+    })))
+}
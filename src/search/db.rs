@@ -0,0 +1,187 @@
+use sea_orm::sea_query::LikeExpr;
+use sea_orm::*;
+
+use super::models::SearchMatch;
+use crate::common::errors::FieldError;
+use crate::common::utils::get_now;
+use crate::entity::{content, content::Entity as Content};
+use crate::{AppState, TABLE_PREFIX};
+
+const SEARCH_LIMIT: u64 = 1000;
+
+fn get_full_text_statement(
+    db_backend: DatabaseBackend,
+    keywords: &[String],
+    private: bool,
+) -> Option<Statement> {
+    let contents = format!("{}contents", *TABLE_PREFIX);
+    let status = if private {
+        " AND c.status != 'trash'".to_string()
+    } else {
+        format!(" AND c.status = 'publish' AND c.created <= {}", get_now())
+    };
+    match db_backend {
+        DatabaseBackend::Sqlite => {
+            // the trigram tokenizer can not match keywords shorter than 3 characters
+            if keywords.iter().any(|k| k.chars().count() < 3) {
+                return None;
+            }
+            let fts = format!("{}contents_fts", *TABLE_PREFIX);
+            let query = keywords
+                .iter()
+                .map(|k| format!("\"{}\"", k.replace('"', "\"\"")))
+                .collect::<Vec<_>>()
+                .join(" ");
+            let sql = format!(
+                "SELECT c.cid, c.title, c.password, -bm25({fts}) AS score FROM {fts} JOIN {contents} c ON c.cid = {fts}.rowid WHERE {fts} MATCH ? AND c.type IN ('post', 'page'){status} ORDER BY score DESC LIMIT {SEARCH_LIMIT}"
+            );
+            Some(Statement::from_sql_and_values(
+                db_backend,
+                sql,
+                [query.into()],
+            ))
+        }
+        DatabaseBackend::MySql => {
+            let query = keywords.join(" ");
+            let sql = format!(
+                "SELECT c.cid, c.title, c.password, MATCH(c.title, c.text) AGAINST (? IN NATURAL LANGUAGE MODE) AS score FROM `{contents}` c WHERE MATCH(c.title, c.text) AGAINST (? IN NATURAL LANGUAGE MODE) AND c.type IN ('post', 'page'){status} ORDER BY score DESC LIMIT {SEARCH_LIMIT}"
+            );
+            Some(Statement::from_sql_and_values(
+                db_backend,
+                sql,
+                [query.clone().into(), query.into()],
+            ))
+        }
+        DatabaseBackend::Postgres => {
+            let query = keywords.join(" ");
+            let document =
+                "to_tsvector('simple', coalesce(c.title, '') || ' ' || coalesce(c.text, ''))";
+            let sql = format!(
+                "SELECT c.cid, c.title, c.password, ts_rank({document}, plainto_tsquery('simple', $1))::float8 AS score FROM \"{contents}\" c WHERE {document} @@ plainto_tsquery('simple', $1) AND c.type IN ('post', 'page'){status} ORDER BY score DESC LIMIT {SEARCH_LIMIT}"
+            );
+            Some(Statement::from_sql_and_values(
+                db_backend,
+                sql,
+                [query.into()],
+            ))
+        }
+    }
+}
+
+async fn search_contents_by_like(
+    state: &AppState,
+    keywords: &[String],
+    private: bool,
+) -> Result<Vec<SearchMatch>, FieldError> {
+    let stmt = Content::find()
+        .select_only()
+        .columns([
+            content::Column::Cid,
+            content::Column::Title,
+            content::Column::Password,
+        ])
+        .filter(content::Column::Type.is_in(["post", "page"]));
+    let stmt = if private {
+        stmt.filter(content::Column::Status.ne("trash"))
+    } else {
+        stmt.filter(content::Column::Status.eq("publish"))
+            .filter(content::Column::Created.lte(get_now()))
+    };
+
+    let mut condition = Condition::all();
+    for keyword in keywords {
+        let pattern = format!(
+            "%{}%",
+            keyword
+                .replace('\\', "\\\\")
+                .replace('%', "\\%")
+                .replace('_', "\\_")
+        );
+        condition = condition.add(
+            Condition::any()
+                .add(content::Column::Title.like(LikeExpr::new(&pattern).escape('\\')))
+                .add(content::Column::Text.like(LikeExpr::new(&pattern).escape('\\'))),
+        );
+    }
+
+    let rows: Vec<(u32, Option<String>, Option<String>)> = stmt
+        .filter(condition)
+        .order_by_desc(content::Column::Cid)
+        .limit(SEARCH_LIMIT)
+        .into_tuple()
+        .all(&state.conn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("search contents failed".to_string()))?;
+
+    let mut res = vec![];
+    for (cid, title, password) in rows {
+        let lower_title = title.as_deref().unwrap_or_default().to_lowercase();
+        let score = keywords
+            .iter()
+            .map(|k| {
+                if lower_title.contains(&k.to_lowercase()) {
+                    2.0
+                } else {
+                    1.0
+                }
+            })
+            .sum();
+        res.push(SearchMatch {
+            cid,
+            title,
+            password,
+            score,
+        });
+    }
+    res.sort_by(|a, b| b.score.total_cmp(&a.score));
+    Ok(res)
+}
+
+pub async fn search_contents_by_keywords(
+    state: &AppState,
+    keywords: &[String],
+    private: bool,
+) -> Result<Vec<SearchMatch>, FieldError> {
+    let db_backend = state.conn.get_database_backend();
+    if let Some(stmt) = get_full_text_statement(db_backend, keywords, private) {
+        match SearchMatch::find_by_statement(stmt).all(&state.conn).await {
+            Ok(matches) => return Ok(matches),
+            Err(e) if is_query_error(&e) => return Err(FieldError::InvalidParams("q".to_string())),
+            // the full-text index is missing, LIKE still works
+            Err(_) => {}
+        }
+    }
+    search_contents_by_like(state, keywords, private).await
+}
+
+/// Errors caused by the keywords themselves, other errors mean the index is unavailable.
+fn is_query_error(e: &DbErr) -> bool {
+    let message = e.to_string();
+    [
+        "fts5: syntax error",
+        "malformed MATCH",
+        "unterminated string",
+        "syntax error in tsquery",
+    ]
+    .iter()
+    .any(|m| message.contains(m))
+}
+
+pub async fn get_contents_by_cids(
+    state: &AppState,
+    cids: Vec<u32>,
+) -> Result<Vec<content::Model>, FieldError> {
+    Content::find()
+        .filter(content::Column::Cid.is_in(cids))
+        .all(&state.conn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("fetch contents failed".to_string()))
+}
+
+#[test]
+fn test_is_query_error() {
+    let e = DbErr::Custom("fts5: syntax error near \"\"".to_string());
+    assert!(is_query_error(&e));
+    let e = DbErr::Custom("no such table: typecho_contents_fts".to_string());
+    assert!(!is_query_error(&e));
+}
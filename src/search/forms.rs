@@ -0,0 +1,13 @@
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+#[derive(Serialize, Deserialize, Validate)]
+pub struct SearchQuery {
+    #[validate(length(min = 1, max = 100, message = "q length must greater than 1"))]
+    pub q: String,
+    #[validate(range(min = 1, max = 1000, message = "page must between 1 and 1000"))]
+    pub page: Option<u64>,
+    #[validate(range(min = 1, max = 100, message = "page_size must between 1 and 100"))]
+    pub page_size: Option<u64>,
+    pub private: Option<bool>,
+}
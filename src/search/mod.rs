@@ -0,0 +1,8 @@
+mod db;
+mod forms;
+mod models;
+mod urls;
+mod utils;
+mod views;
+
+pub use urls::search_routers;
@@ -0,0 +1,9 @@
+use sea_orm::FromQueryResult;
+
+#[derive(FromQueryResult)]
+pub struct SearchMatch {
+    pub cid: u32,
+    pub title: Option<String>,
+    pub password: Option<String>,
+    pub score: f64,
+}
@@ -0,0 +1,9 @@
+use axum::{routing::get, Router};
+use std::sync::Arc;
+
+use super::views;
+use crate::AppState;
+
+pub fn search_routers(_ro: bool) -> Router<Arc<AppState>> {
+    Router::new().route("/api/search", get(views::search_contents))
+}
@@ -0,0 +1,69 @@
+use crate::common::utils::escape_xml;
+
+const SNIPPET_LENGTH: usize = 120;
+
+fn lower_char(c: char) -> char {
+    c.to_lowercase().next().unwrap_or(c)
+}
+
+fn match_keyword_at(text: &[char], index: usize, keywords: &[Vec<char>]) -> Option<usize> {
+    keywords
+        .iter()
+        .filter(|k| {
+            !k.is_empty()
+                && index + k.len() <= text.len()
+                && text[index..index + k.len()]
+                    .iter()
+                    .zip(k.iter())
+                    .all(|(a, b)| lower_char(*a) == *b)
+        })
+        .map(|k| k.len())
+        .max()
+}
+
+pub fn get_snippet(text: &str, keywords: &[String]) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let keywords: Vec<Vec<char>> = keywords
+        .iter()
+        .map(|k| k.chars().map(lower_char).collect())
+        .collect();
+
+    let first = (0..chars.len())
+        .find(|i| match_keyword_at(&chars, *i, &keywords).is_some())
+        .unwrap_or(0);
+    let start = first.saturating_sub(SNIPPET_LENGTH / 4);
+    let end = chars.len().min(start + SNIPPET_LENGTH);
+
+    let mut res = String::new();
+    if start > 0 {
+        res.push_str("...");
+    }
+    let mut index = start;
+    while index < end {
+        match match_keyword_at(&chars, index, &keywords) {
+            Some(len) => {
+                let keyword: String = chars[index..index + len].iter().collect();
+                res.push_str(&format!("<mark>{}</mark>", escape_xml(&keyword)));
+                index += len;
+            }
+            None => {
+                res.push_str(&escape_xml(&chars[index].to_string()));
+                index += 1;
+            }
+        }
+    }
+    if index < chars.len() {
+        res.push_str("...");
+    }
+    res
+}
+
+#[test]
+fn test_get_snippet() {
+    let keywords = vec!["rust".to_string()];
+    assert_eq!(
+        "Learn <mark>Rust</mark> &amp; <mark>rust</mark>",
+        get_snippet("Learn Rust & rust", &keywords)
+    );
+    assert_eq!("no match", get_snippet("no match", &keywords));
+}
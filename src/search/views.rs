@@ -0,0 +1,95 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use axum::extract::State;
+use axum::response::Json;
+use serde_json::{json, Value};
+
+use super::db;
+use super::forms::SearchQuery;
+use super::utils::get_snippet;
+use crate::common::errors::FieldError;
+use crate::common::extractors::{PMVisitor, ValidatedQuery};
+use crate::common::markdown::{render_text_to_html, strip_tags};
+use crate::AppState;
+
+pub async fn search_contents(
+    State(state): State<Arc<AppState>>,
+    PMVisitor(user): PMVisitor,
+    ValidatedQuery(q): ValidatedQuery<SearchQuery>,
+) -> Result<Json<Value>, FieldError> {
+    let admin = user.group == "editor" || user.group == "administrator";
+    let private = q.private.unwrap_or(false) && admin;
+
+    let keywords: Vec<String> = q.q.split_whitespace().take(8).map(String::from).collect();
+    if keywords.is_empty() {
+        return Err(FieldError::InvalidParams("q".to_string()));
+    }
+
+    let matches = db::search_contents_by_keywords(&state, &keywords, private).await?;
+
+    // password protected contents can only be found by title
+    let matches: Vec<_> = matches
+        .into_iter()
+        .filter(|m| {
+            if private || m.password.as_deref().unwrap_or_default().is_empty() {
+                return true;
+            }
+            let title = m.title.as_deref().unwrap_or_default().to_lowercase();
+            keywords.iter().any(|k| title.contains(&k.to_lowercase()))
+        })
+        .collect();
+
+    let page = q.page.unwrap_or(1);
+    let page_size = q.page_size.unwrap_or(10);
+    let page_matches: Vec<_> = matches
+        .iter()
+        .skip((page - 1).saturating_mul(page_size) as usize)
+        .take(page_size as usize)
+        .collect();
+
+    let cids = page_matches.iter().map(|m| m.cid).collect();
+    let contents: HashMap<u32, _> = db::get_contents_by_cids(&state, cids)
+        .await?
+        .into_iter()
+        .map(|c| (c.cid, c))
+        .collect();
+
+    let mut results = vec![];
+    for m in page_matches {
+        let Some(content) = contents.get(&m.cid) else {
+            continue;
+        };
+        let locked = !private && content.password.as_deref().is_some_and(|p| !p.is_empty());
+        let snippet = if locked {
+            String::new()
+        } else {
+            let html = render_text_to_html(
+                content.cid,
+                content.modified,
+                content.text.as_deref().unwrap_or_default(),
+            );
+            get_snippet(&strip_tags(&html), &keywords)
+        };
+        results.push(json!({
+            "cid": content.cid,
+            "title": content.title,
+            "slug": content.slug,
+            "type": content.r#type,
+            "status": content.status,
+            "created": content.created,
+            "modified": content.modified,
+            "author_id": content.author_id,
+            "score": m.score,
+            "snippet": snippet,
+        }));
+    }
+
+    Ok(Json(json!({
+        "page": page,
+        "page_size": page_size,
+        "all_count": matches.len(),
+        "count": results.len(),
+        "results": results
+    })))
+}
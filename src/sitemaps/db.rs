@@ -0,0 +1,53 @@
+use sea_orm::*;
+
+use crate::common::errors::FieldError;
+use crate::common::utils::get_now;
+use crate::entity::{
+    content, content::Entity as Content, meta, meta::Entity as Meta, relationship,
+    relationship::Entity as Relationship,
+};
+use crate::AppState;
+
+pub async fn get_published_contents_without_password(
+    state: &AppState,
+) -> Result<Vec<(u32, Option<String>, String, u32, u32)>, FieldError> {
+    Content::find()
+        .select_only()
+        .columns([
+            content::Column::Cid,
+            content::Column::Slug,
+            content::Column::Type,
+            content::Column::Created,
+            content::Column::Modified,
+        ])
+        .filter(content::Column::Type.is_in(["post", "page"]))
+        .filter(content::Column::Status.eq("publish"))
+        .filter(content::Column::Created.lte(get_now()))
+        .filter(
+            Condition::any()
+                .add(content::Column::Password.is_null())
+                .add(content::Column::Password.eq("")),
+        )
+        .order_by_asc(content::Column::Cid)
+        .into_tuple()
+        .all(&state.conn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("fetch contents failed".to_string()))
+}
+
+pub async fn get_metas(state: &AppState) -> Result<Vec<meta::Model>, FieldError> {
+    Meta::find()
+        .order_by_asc(meta::Column::Mid)
+        .all(&state.conn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("fetch metas failed".to_string()))
+}
+
+pub async fn get_relationships(state: &AppState) -> Result<Vec<relationship::Model>, FieldError> {
+    Relationship::find()
+        .order_by_asc(relationship::Column::Cid)
+        .order_by_asc(relationship::Column::Mid)
+        .all(&state.conn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("fetch relationships failed".to_string()))
+}
@@ -0,0 +1,7 @@
+mod db;
+mod models;
+mod urls;
+mod utils;
+mod views;
+
+pub use urls::sitemaps_routers;
@@ -0,0 +1,4 @@
+pub struct SitemapUrl {
+    pub loc: String,
+    pub lastmod: Option<u32>,
+}
@@ -0,0 +1,11 @@
+use axum::{routing::get, Router};
+use std::sync::Arc;
+
+use super::views;
+use crate::AppState;
+
+pub fn sitemaps_routers(_ro: bool) -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/sitemap.xml", get(views::get_sitemap))
+        .route("/sitemaps/:filename", get(views::get_sitemap_by_filename))
+}
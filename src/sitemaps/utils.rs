@@ -0,0 +1,42 @@
+use chrono::DateTime;
+
+use super::models::SitemapUrl;
+use crate::common::utils::escape_xml;
+
+pub const SITEMAP_MAX_URLS: usize = 50000;
+
+fn to_w3c_date(timestamp: u32) -> String {
+    DateTime::from_timestamp(timestamp as i64, 0)
+        .unwrap_or_default()
+        .to_rfc3339()
+}
+
+pub fn render_urlset(urls: &[SitemapUrl]) -> String {
+    let mut output = String::from(r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+    output.push_str(r#"<urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">"#);
+    for url in urls {
+        output.push_str("<url>");
+        output.push_str(&format!("<loc>{}</loc>", escape_xml(&url.loc)));
+        if let Some(lastmod) = url.lastmod {
+            output.push_str(&format!("<lastmod>{}</lastmod>", to_w3c_date(lastmod)));
+        }
+        output.push_str("</url>");
+    }
+    output.push_str("</urlset>");
+    output
+}
+
+pub fn render_sitemap_index(sitemaps: &[SitemapUrl]) -> String {
+    let mut output = String::from(r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+    output.push_str(r#"<sitemapindex xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">"#);
+    for sitemap in sitemaps {
+        output.push_str("<sitemap>");
+        output.push_str(&format!("<loc>{}</loc>", escape_xml(&sitemap.loc)));
+        if let Some(lastmod) = sitemap.lastmod {
+            output.push_str(&format!("<lastmod>{}</lastmod>", to_w3c_date(lastmod)));
+        }
+        output.push_str("</sitemap>");
+    }
+    output.push_str("</sitemapindex>");
+    output
+}
@@ -0,0 +1,128 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use axum::extract::{Path, State};
+use axum::http::header;
+use axum::response::{IntoResponse, Response};
+
+use super::db;
+use super::models::SitemapUrl;
+use super::utils::{render_sitemap_index, render_urlset, SITEMAP_MAX_URLS};
+use crate::common::db as common_db;
+use crate::common::errors::FieldError;
+use crate::common::utils::Permalinks;
+use crate::AppState;
+
+const SITEMAP_GROUPS: [&str; 4] = ["posts", "pages", "categories", "tags"];
+
+async fn get_sitemap_groups(
+    state: &AppState,
+) -> Result<(Permalinks, HashMap<&'static str, Vec<SitemapUrl>>), FieldError> {
+    let options = common_db::get_site_options(state).await?;
+    let permalinks = Permalinks::from_options(&options);
+
+    let contents = db::get_published_contents_without_password(state).await?;
+    let metas = db::get_metas(state).await?;
+    let relationships = db::get_relationships(state).await?;
+
+    let modified: HashMap<u32, u32> = contents.iter().map(|c| (c.0, c.4)).collect();
+    let categories: HashMap<u32, &str> = metas
+        .iter()
+        .filter(|m| m.r#type == "category")
+        .map(|m| (m.mid, m.slug.as_deref().unwrap_or_default()))
+        .collect();
+
+    let mut post_categories: HashMap<u32, &str> = HashMap::new();
+    let mut meta_modified: HashMap<u32, u32> = HashMap::new();
+    for r in relationships.iter() {
+        let Some(m) = modified.get(&r.cid) else {
+            continue;
+        };
+        if let Some(slug) = categories.get(&r.mid) {
+            post_categories.entry(r.cid).or_insert(slug);
+        }
+        let lastmod = meta_modified.entry(r.mid).or_insert(0);
+        *lastmod = (*lastmod).max(*m);
+    }
+
+    let mut groups: HashMap<&'static str, Vec<SitemapUrl>> = HashMap::new();
+    for (cid, slug, r#type, created, modified) in contents.iter() {
+        let loc = permalinks.content(
+            r#type,
+            *cid,
+            slug.as_deref().unwrap_or_default(),
+            *created,
+            post_categories.get(cid).copied(),
+        );
+        let group = if r#type == "page" { "pages" } else { "posts" };
+        groups.entry(group).or_default().push(SitemapUrl {
+            loc,
+            lastmod: Some(*modified),
+        });
+    }
+    for m in metas.iter() {
+        let Some(lastmod) = meta_modified.get(&m.mid) else {
+            continue;
+        };
+        let loc = permalinks.meta(&m.r#type, m.mid, m.slug.as_deref().unwrap_or_default());
+        let group = if m.r#type == "tag" { "tags" } else { "categories" };
+        groups.entry(group).or_default().push(SitemapUrl {
+            loc,
+            lastmod: Some(*lastmod),
+        });
+    }
+    Ok((permalinks, groups))
+}
+
+fn xml_response(body: String) -> Response {
+    ([(header::CONTENT_TYPE, "application/xml; charset=UTF-8")], body).into_response()
+}
+
+pub async fn get_sitemap(State(state): State<Arc<AppState>>) -> Result<Response, FieldError> {
+    let (permalinks, mut groups) = get_sitemap_groups(&state).await?;
+
+    let count: usize = groups.values().map(|g| g.len()).sum();
+    if count <= SITEMAP_MAX_URLS {
+        let mut urls = vec![];
+        for name in SITEMAP_GROUPS {
+            urls.extend(groups.remove(name).unwrap_or_default());
+        }
+        return Ok(xml_response(render_urlset(&urls)));
+    }
+
+    let mut sitemaps = vec![];
+    for name in SITEMAP_GROUPS {
+        let urls = groups.remove(name).unwrap_or_default();
+        for (index, chunk) in urls.chunks(SITEMAP_MAX_URLS).enumerate() {
+            sitemaps.push(SitemapUrl {
+                loc: format!(
+                    "{}/sitemaps/{}-{}.xml",
+                    permalinks.site_url,
+                    name,
+                    index + 1
+                ),
+                lastmod: chunk.iter().filter_map(|u| u.lastmod).max(),
+            });
+        }
+    }
+    Ok(xml_response(render_sitemap_index(&sitemaps)))
+}
+
+pub async fn get_sitemap_by_filename(
+    State(state): State<Arc<AppState>>,
+    Path(filename): Path<String>,
+) -> Result<Response, FieldError> {
+    let (name, page) = filename
+        .strip_suffix(".xml")
+        .and_then(|f| f.rsplit_once('-'))
+        .and_then(|(name, page)| Some((name, page.parse::<usize>().ok()?)))
+        .filter(|(name, page)| SITEMAP_GROUPS.contains(name) && *page >= 1)
+        .ok_or(FieldError::NotFound("filename".to_string()))?;
+
+    let (_, mut groups) = get_sitemap_groups(&state).await?;
+    let urls = groups.remove(name).unwrap_or_default();
+    match urls.chunks(SITEMAP_MAX_URLS).nth(page - 1) {
+        Some(chunk) => Ok(xml_response(render_urlset(chunk))),
+        None => Err(FieldError::NotFound("filename".to_string())),
+    }
+}
@@ -1,9 +1,13 @@
+use sea_orm::sea_query::Expr;
 use sea_orm::*;
 
 use super::forms::TagCreate;
 use crate::common::db as common_db;
 use crate::common::errors::FieldError;
-use crate::entity::meta;
+use crate::entity::{
+    meta, meta::Entity as Meta, redirect, redirect::Entity as Redirect, relationship,
+    relationship::Entity as Relationship,
+};
 use crate::AppState;
 
 pub async fn create_tag_by_tag_create(
@@ -46,12 +50,158 @@ pub async fn modify_tag_by_mid_and_tag_modify(
         _ => return Err(FieldError::InvalidParams("mid".to_string())),
     };
 
+    let old_slug = exist_tag.slug.clone().unwrap_or_default();
+
+    let txn = state
+        .conn
+        .begin()
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("begin transaction failed".to_string()))?;
+
     let mut t = meta::ActiveModel::from(exist_tag);
     t.name = Set(Some(tag_modify.name.clone()));
     t.slug = Set(Some(tag_modify.slug.clone()));
     t.description = Set(tag_modify.description.clone());
     t.parent = Set(tag_parent);
-    t.update(&state.conn)
+    let tag = t
+        .update(&txn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("modify tag failed".to_string()))?;
+
+    if !old_slug.is_empty() && old_slug != tag_modify.slug {
+        create_tag_redirect_by_slug_and_mid(&txn, &old_slug, mid).await?;
+    }
+
+    txn.commit()
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("commit transaction failed".to_string()))?;
+    Ok(tag)
+}
+
+/// Points `slug` at the tag `mid`, a live tag taking the slug back drops the record.
+async fn create_tag_redirect_by_slug_and_mid<C: ConnectionTrait>(
+    conn: &C,
+    slug: &str,
+    mid: u32,
+) -> Result<(), FieldError> {
+    Redirect::delete_many()
+        .filter(redirect::Column::Type.eq("tag"))
+        .filter(redirect::Column::Slug.eq(slug))
+        .exec(conn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("delete redirect failed".to_string()))?;
+    redirect::ActiveModel {
+        r#type: Set("tag".to_string()),
+        slug: Set(slug.to_string()),
+        mid: Set(mid),
+        ..Default::default()
+    }
+    .insert(conn)
+    .await
+    .map_err(|_| FieldError::DatabaseFailed("create redirect failed".to_string()))?;
+    Ok(())
+}
+
+/// Same as `get_meta_by_slug`, but falls back to the slugs a tag was renamed from.
+pub async fn get_tag_by_slug_with_redirect(
+    state: &AppState,
+    slug: &str,
+) -> Result<Option<meta::Model>, FieldError> {
+    if let Some(tag) = common_db::get_meta_by_slug(state, slug, true).await? {
+        return Ok(Some(tag));
+    }
+
+    let redirect = Redirect::find()
+        .filter(redirect::Column::Type.eq("tag"))
+        .filter(redirect::Column::Slug.eq(slug))
+        .one(&state.conn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("fetch redirect failed".to_string()))?;
+    match redirect {
+        Some(r) => common_db::get_meta_by_mid(state, r.mid).await,
+        None => Ok(None),
+    }
+}
+
+pub async fn get_tags_by_count(
+    state: &AppState,
+    limit: u64,
+) -> Result<Vec<meta::Model>, FieldError> {
+    Meta::find()
+        .filter(meta::Column::Type.eq("tag"))
+        .filter(meta::Column::Count.gt(0))
+        .order_by_desc(meta::Column::Count)
+        .order_by_asc(meta::Column::Mid)
+        .limit(limit)
+        .all(&state.conn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("fetch tags failed".to_string()))
+}
+
+pub async fn merge_tag_into_target(
+    state: &AppState,
+    source: &meta::Model,
+    target: &meta::Model,
+) -> Result<(), FieldError> {
+    let txn = state
+        .conn
+        .begin()
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("begin transaction failed".to_string()))?;
+
+    let target_cids: Vec<u32> = Relationship::find()
+        .filter(relationship::Column::Mid.eq(target.mid))
+        .all(&txn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("fetch relationships failed".to_string()))?
+        .into_iter()
+        .map(|r| r.cid)
+        .collect();
+    Relationship::delete_many()
+        .filter(relationship::Column::Mid.eq(source.mid))
+        .filter(relationship::Column::Cid.is_in(target_cids))
+        .exec(&txn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("delete relationships failed".to_string()))?;
+    Relationship::update_many()
+        .col_expr(relationship::Column::Mid, Expr::value(target.mid))
+        .filter(relationship::Column::Mid.eq(source.mid))
+        .exec(&txn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("update relationships failed".to_string()))?;
+
+    common_db::update_metas_count_by_mids(&txn, &[target.mid]).await?;
+    Meta::delete_many()
+        .filter(meta::Column::Mid.eq(source.mid))
+        .exec(&txn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("delete tag failed".to_string()))?;
+
+    // old links of the source keep working after the merge
+    Redirect::update_many()
+        .col_expr(redirect::Column::Mid, Expr::value(target.mid))
+        .filter(redirect::Column::Type.eq("tag"))
+        .filter(redirect::Column::Mid.eq(source.mid))
+        .exec(&txn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("update redirects failed".to_string()))?;
+    if let Some(slug) = &source.slug {
+        create_tag_redirect_by_slug_and_mid(&txn, slug, target.mid).await?;
+    }
+
+    txn.commit()
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("commit transaction failed".to_string()))
+}
+
+pub async fn delete_tag_redirects_by_mid(
+    state: &AppState,
+    mid: u32,
+) -> Result<DeleteResult, FieldError> {
+    Redirect::delete_many()
+        .filter(redirect::Column::Type.eq("tag"))
+        .filter(redirect::Column::Mid.eq(mid))
+        .exec(&state.conn)
         .await
-        .map_err(|_| FieldError::DatabaseFailed("modify tag failed".to_string()))
+        .map_err(|_| FieldError::DatabaseFailed("delete redirects failed".to_string()))
 }
@@ -19,3 +19,15 @@ pub struct TagPostAdd {
     #[validate(length(min = 1, max = 150, message = "slug can not be longer than 150"))]
     pub slug: String,
 }
+
+#[derive(Serialize, Deserialize, Validate)]
+pub struct TagMerge {
+    #[validate(length(min = 1, max = 150, message = "slug can not be longer than 150"))]
+    pub slug: String,
+}
+
+#[derive(Serialize, Deserialize, Validate)]
+pub struct TagCloudQuery {
+    #[validate(range(min = 1, max = 500, message = "limit must between 1 and 500"))]
+    pub limit: Option<u64>,
+}
@@ -1,6 +1,8 @@
+pub mod db;
 pub mod forms;
+mod models;
 mod urls;
+mod utils;
 mod views;
-mod db;
 
 pub use urls::tags_routers;
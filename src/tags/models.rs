@@ -0,0 +1,10 @@
+use serde::Serialize;
+
+use crate::entity::meta;
+
+#[derive(Serialize)]
+pub struct TagCloudItem {
+    #[serde(flatten)]
+    pub tag: meta::Model,
+    pub weight: u32,
+}
@@ -10,6 +10,7 @@ use crate::AppState;
 pub fn tags_routers(ro: bool) -> Router<Arc<AppState>> {
     let tags_route = Router::new()
         .route("/api/tags/", get(views::list_tags))
+        .route("/api/tags/cloud", get(views::list_tags_cloud))
         .route("/api/tags/:slug", get(views::get_tag_by_slug))
         .route("/api/tags/:slug/posts/", get(views::list_tag_posts_by_slug));
     if !ro {
@@ -17,6 +18,7 @@ pub fn tags_routers(ro: bool) -> Router<Arc<AppState>> {
             .route("/api/tags/", post(views::create_tag))
             .route("/api/tags/:slug", patch(views::modify_tag_by_slug))
             .route("/api/tags/:slug", delete(views::delete_tag_by_slug))
+            .route("/api/tags/:slug/merge", post(views::merge_tag_by_slug))
             .route("/api/tags/:slug/posts/", post(views::add_post_to_tag))
             .route(
                 "/api/tags/:slug/posts/:post_slug",
@@ -0,0 +1,32 @@
+use super::models::TagCloudItem;
+use crate::entity::meta;
+
+pub const TAG_CLOUD_LEVELS: u32 = 10;
+
+/// Scales `count` linearly between the least and the most used tags, from 1 to `TAG_CLOUD_LEVELS`.
+pub fn get_tag_weight(count: u32, min: u32, max: u32) -> u32 {
+    if max <= min {
+        return 1;
+    }
+    1 + (count.saturating_sub(min) as u64 * (TAG_CLOUD_LEVELS - 1) as u64 / (max - min) as u64)
+        as u32
+}
+
+pub fn get_tag_cloud(tags: Vec<meta::Model>) -> Vec<TagCloudItem> {
+    let min = tags.iter().map(|t| t.count).min().unwrap_or(0);
+    let max = tags.iter().map(|t| t.count).max().unwrap_or(0);
+    tags.into_iter()
+        .map(|tag| TagCloudItem {
+            weight: get_tag_weight(tag.count, min, max),
+            tag,
+        })
+        .collect()
+}
+
+#[test]
+fn test_get_tag_weight() {
+    assert_eq!(1, get_tag_weight(3, 3, 3));
+    assert_eq!(1, get_tag_weight(1, 1, 100));
+    assert_eq!(5, get_tag_weight(50, 1, 100));
+    assert_eq!(TAG_CLOUD_LEVELS, get_tag_weight(100, 1, 100));
+}
@@ -5,12 +5,15 @@ use serde_json::{json, Value};
 use std::sync::Arc;
 
 use super::db::{self};
-use super::forms::{TagCreate, TagPostAdd};
-use crate::common::db as common_db;
+use super::forms::{TagCloudQuery, TagCreate, TagMerge, TagPostAdd};
+use super::utils::get_tag_cloud;
+use crate::common::db::{self as common_db, ContentsPage};
 use crate::common::errors::FieldError;
 use crate::common::extractors::{PMEditor, PMVisitor, ValidatedJson, ValidatedQuery};
 use crate::common::forms::ListQuery;
+use crate::common::markdown::is_html_format;
 use crate::posts::forms::PostsQuery;
+use crate::posts::utils::{get_posts_results, is_summary_fields};
 use crate::AppState;
 
 pub async fn create_tag(
@@ -47,11 +50,25 @@ pub async fn list_tags(
     })))
 }
 
+pub async fn list_tags_cloud(
+    State(state): State<Arc<AppState>>,
+    ValidatedQuery(q): ValidatedQuery<TagCloudQuery>,
+) -> Result<Json<Value>, FieldError> {
+    let limit = q.limit.unwrap_or(50);
+
+    let tags = db::get_tags_by_count(&state, limit).await?;
+    let tags = get_tag_cloud(tags);
+    Ok(Json(json!({
+        "count": tags.len(),
+        "results": tags
+    })))
+}
+
 pub async fn get_tag_by_slug(
     State(state): State<Arc<AppState>>,
     Path(slug): Path<String>,
 ) -> Result<Json<Value>, FieldError> {
-    match common_db::get_meta_by_slug(&state, &slug, true).await {
+    match db::get_tag_by_slug_with_redirect(&state, &slug).await {
         Ok(Some(tag)) => Ok(Json(json!(tag))),
         _ => Err(FieldError::NotFound("slug".to_string())),
     }
@@ -91,6 +108,26 @@ pub async fn delete_tag_by_slug(
 
     let _ = common_db::delete_relationships_by_mid(&state, exist_tag.mid).await?;
     let _ = common_db::delete_meta_by_mid(&state, exist_tag.mid).await?;
+    let _ = db::delete_tag_redirects_by_mid(&state, exist_tag.mid).await?;
+    Ok(Json(json!({ "msg": "ok" })))
+}
+
+pub async fn merge_tag_by_slug(
+    State(state): State<Arc<AppState>>,
+    PMEditor(_): PMEditor,
+    Path(slug): Path<String>,
+    ValidatedJson(tag_merge): ValidatedJson<TagMerge>,
+) -> Result<Json<Value>, FieldError> {
+    let source = match common_db::get_meta_by_slug(&state, &slug, true).await {
+        Ok(Some(t)) => t,
+        _ => return Err(FieldError::InvalidParams("slug".to_owned())),
+    };
+    let target = match common_db::get_meta_by_slug(&state, &tag_merge.slug, true).await {
+        Ok(Some(t)) if t.mid != source.mid => t,
+        _ => return Err(FieldError::InvalidParams("target slug".to_owned())),
+    };
+
+    db::merge_tag_into_target(&state, &source, &target).await?;
     Ok(Json(json!({ "msg": "ok" })))
 }
 
@@ -114,7 +151,7 @@ pub async fn add_post_to_tag(
 
     if !exist {
         let _ = common_db::create_relationship_by_cid_and_mid(&state, cid, mid).await?;
-        let _ = common_db::update_meta_by_mid_for_increase_count(&state, mid).await?;
+        common_db::update_metas_count_by_mids(&state.conn, &[mid]).await?;
         Ok((StatusCode::CREATED, Json(json!({"msg": "ok"}))))
     } else {
         Err(FieldError::AlreadyExist("slug".to_string()))
@@ -140,7 +177,7 @@ pub async fn delete_post_from_tag(
 
     if exist {
         let _ = common_db::delete_relationship_by_cid_and_mid(&state, cid, mid).await?;
-        let _ = common_db::update_meta_by_mid_for_decrease_count(&state, mid).await?;
+        common_db::update_metas_count_by_mids(&state.conn, &[mid]).await?;
         Ok(Json(json!({"msg": "ok"})))
     } else {
         Err(FieldError::AlreadyExist("slug".to_string()))
@@ -153,24 +190,38 @@ pub async fn list_tag_posts_by_slug(
     Path(slug): Path<String>,
     ValidatedQuery(q): ValidatedQuery<PostsQuery>,
 ) -> Result<Json<Value>, FieldError> {
-    let mid = match common_db::get_meta_by_slug(&state, &slug, true).await {
-        Ok(Some(tag)) => tag.mid,
+    let mids = match db::get_tag_by_slug_with_redirect(&state, &slug).await {
+        Ok(Some(tag)) => [tag.mid],
         _ => return Err(FieldError::InvalidParams("slug".to_string())),
     };
 
     let private =
         q.private.unwrap_or(false) && (user.group == "editor" || user.group == "administrator");
+    let html = is_html_format(q.format.as_deref())?;
+    let summary = is_summary_fields(q.fields.as_deref())?;
 
-    let all_count = common_db::get_meta_posts_count_by_mid_with_private(&state, mid, private).await;
+    let all_count =
+        common_db::get_meta_posts_count_by_mids_with_private(&state, &mids, private).await;
 
     let page = q.page.unwrap_or(1);
     let page_size = q.page_size.unwrap_or(10);
     let order_by = q.order_by.unwrap_or("-cid".to_string());
 
-    let posts = common_db::get_contents_with_metas_user_and_fields_by_mid_list_query_and_private(
-        &state, mid, private, &user, page_size, page, &order_by, true,
+    let contents_page = ContentsPage {
+        page_size,
+        page,
+        order_by: &order_by,
+    };
+    let posts = common_db::get_contents_with_metas_user_and_fields_by_mids_list_query_and_private(
+        &state,
+        &mids,
+        private,
+        &contents_page,
+        true,
+        false,
     )
     .await?;
+    let posts = get_posts_results(posts, html, summary, state.excerpt_length);
     Ok(Json(json!({
         "page": page,
         "page_size": page_size,
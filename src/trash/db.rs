@@ -0,0 +1,263 @@
+use sea_orm::sea_query::Expr;
+use sea_orm::*;
+
+use crate::common::db::update_metas_count_by_cid;
+use crate::common::errors::FieldError;
+use crate::common::utils::get_now;
+use crate::entity::{
+    comment, comment::Entity as Comment, content, content::Entity as Content, trash,
+    trash::Entity as Trash,
+};
+use crate::AppState;
+
+pub async fn move_content_to_trash_with_uid(
+    state: &AppState,
+    content: &content::Model,
+    uid: u32,
+    delete_attachments: bool,
+) -> Result<trash::Model, FieldError> {
+    let txn = state
+        .conn
+        .begin()
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("begin transaction failed".to_string()))?;
+
+    let t = trash::ActiveModel {
+        r#type: Set(content.r#type.to_owned()),
+        item_id: Set(content.cid),
+        status: Set(content.status.to_owned()),
+        created: Set(get_now()),
+        author_id: Set(uid),
+        delete_attachments: Set(delete_attachments),
+        ..Default::default()
+    }
+    .insert(&txn)
+    .await
+    .map_err(|_| FieldError::DatabaseFailed("create trash failed".to_string()))?;
+
+    let mut c = content::ActiveModel::from(content.clone());
+    c.status = Set("trash".to_string());
+    c.update(&txn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("update content failed".to_string()))?;
+    update_metas_count_by_cid(&txn, content.cid).await?;
+
+    txn.commit()
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("commit transaction failed".to_string()))?;
+    Ok(t)
+}
+
+pub async fn move_comment_to_trash_with_uid(
+    state: &AppState,
+    comment: &comment::Model,
+    uid: u32,
+) -> Result<trash::Model, FieldError> {
+    let txn = state
+        .conn
+        .begin()
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("begin transaction failed".to_string()))?;
+
+    let t = trash::ActiveModel {
+        r#type: Set("comment".to_string()),
+        item_id: Set(comment.coid),
+        status: Set(comment.status.to_owned()),
+        created: Set(get_now()),
+        author_id: Set(uid),
+        ..Default::default()
+    }
+    .insert(&txn)
+    .await
+    .map_err(|_| FieldError::DatabaseFailed("create trash failed".to_string()))?;
+
+    let mut c = comment::ActiveModel::from(comment.clone());
+    c.status = Set("trash".to_string());
+    c.update(&txn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("update comment failed".to_string()))?;
+
+    // only approved comments are counted
+    if comment.status == "approved" {
+        Content::update_many()
+            .col_expr(
+                content::Column::CommentsNum,
+                Expr::col(content::Column::CommentsNum).sub(1),
+            )
+            .filter(content::Column::Cid.eq(comment.cid))
+            .filter(content::Column::CommentsNum.gt(0))
+            .exec(&txn)
+            .await
+            .map_err(|_| FieldError::DatabaseFailed("update content failed".to_string()))?;
+    }
+
+    txn.commit()
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("commit transaction failed".to_string()))?;
+    Ok(t)
+}
+
+pub async fn get_trash_by_tid(
+    state: &AppState,
+    tid: u32,
+) -> Result<Option<trash::Model>, FieldError> {
+    Trash::find_by_id(tid)
+        .one(&state.conn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("fetch trash failed".to_string()))
+}
+
+pub async fn get_trash_item_title(state: &AppState, trash: &trash::Model) -> Option<String> {
+    if trash.r#type == "comment" {
+        Comment::find_by_id(trash.item_id)
+            .one(&state.conn)
+            .await
+            .ok()
+            .flatten()
+            .and_then(|c| c.text)
+    } else {
+        Content::find_by_id(trash.item_id)
+            .one(&state.conn)
+            .await
+            .ok()
+            .flatten()
+            .and_then(|c| c.title)
+    }
+}
+
+pub async fn get_trash_count_by_type(state: &AppState, r#type: Option<&str>) -> u64 {
+    let stmt = Trash::find();
+    let stmt = match r#type {
+        Some(t) => stmt.filter(trash::Column::Type.eq(t)),
+        None => stmt,
+    };
+    stmt.count(&state.conn).await.unwrap_or(0)
+}
+
+pub async fn get_trash_by_type_and_list_query(
+    state: &AppState,
+    r#type: Option<&str>,
+    page_size: u64,
+    page: u64,
+    order_by: &str,
+) -> Result<Vec<trash::Model>, FieldError> {
+    let stmt = Trash::find();
+    let stmt = match r#type {
+        Some(t) => stmt.filter(trash::Column::Type.eq(t)),
+        None => stmt,
+    };
+
+    let stmt = match order_by {
+        "-tid" => stmt.order_by_desc(trash::Column::Tid),
+        "tid" => stmt.order_by_asc(trash::Column::Tid),
+        "-created" => stmt.order_by_desc(trash::Column::Created),
+        "created" => stmt.order_by_asc(trash::Column::Created),
+        _ => stmt.order_by_desc(trash::Column::Tid),
+    };
+    let paginator = stmt.paginate(&state.conn, page_size);
+
+    paginator
+        .fetch_page(page - 1)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("fetch trash failed".to_string()))
+}
+
+pub async fn get_trash_by_created_before(
+    state: &AppState,
+    created: u32,
+) -> Result<Vec<trash::Model>, FieldError> {
+    Trash::find()
+        .filter(trash::Column::Created.lt(created))
+        .order_by_asc(trash::Column::Tid)
+        .all(&state.conn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("fetch trash failed".to_string()))
+}
+
+pub async fn restore_trash(state: &AppState, trash: &trash::Model) -> Result<(), FieldError> {
+    let txn = state
+        .conn
+        .begin()
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("begin transaction failed".to_string()))?;
+
+    if trash.r#type == "comment" {
+        let comment = Comment::find_by_id(trash.item_id)
+            .one(&txn)
+            .await
+            .map_err(|_| FieldError::DatabaseFailed("fetch comment failed".to_string()))?
+            .ok_or(FieldError::NotFound("tid".to_string()))?;
+        let cid = comment.cid;
+
+        let mut c = comment::ActiveModel::from(comment);
+        c.status = Set(trash.status.to_owned());
+        c.update(&txn)
+            .await
+            .map_err(|_| FieldError::DatabaseFailed("update comment failed".to_string()))?;
+
+        if trash.status == "approved" {
+            Content::update_many()
+                .col_expr(
+                    content::Column::CommentsNum,
+                    Expr::col(content::Column::CommentsNum).add(1),
+                )
+                .filter(content::Column::Cid.eq(cid))
+                .exec(&txn)
+                .await
+                .map_err(|_| FieldError::DatabaseFailed("update content failed".to_string()))?;
+        }
+    } else {
+        let content = Content::find_by_id(trash.item_id)
+            .one(&txn)
+            .await
+            .map_err(|_| FieldError::DatabaseFailed("fetch content failed".to_string()))?
+            .ok_or(FieldError::NotFound("tid".to_string()))?;
+
+        let mut c = content::ActiveModel::from(content);
+        c.status = Set(trash.status.to_owned());
+        c.update(&txn)
+            .await
+            .map_err(|_| FieldError::DatabaseFailed("update content failed".to_string()))?;
+        update_metas_count_by_cid(&txn, trash.item_id).await?;
+    }
+
+    Trash::delete_by_id(trash.tid)
+        .exec(&txn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("delete trash failed".to_string()))?;
+
+    txn.commit()
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("commit transaction failed".to_string()))
+}
+
+pub async fn delete_comment_with_trash(
+    state: &AppState,
+    trash: &trash::Model,
+) -> Result<(), FieldError> {
+    let txn = state
+        .conn
+        .begin()
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("begin transaction failed".to_string()))?;
+
+    Comment::delete_by_id(trash.item_id)
+        .exec(&txn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("delete comment failed".to_string()))?;
+    Trash::delete_by_id(trash.tid)
+        .exec(&txn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("delete trash failed".to_string()))?;
+
+    txn.commit()
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("commit transaction failed".to_string()))
+}
+
+pub async fn delete_trash_by_tid(state: &AppState, tid: u32) -> Result<DeleteResult, FieldError> {
+    Trash::delete_by_id(tid)
+        .exec(&state.conn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("delete trash failed".to_string()))
+}
@@ -0,0 +1,14 @@
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+#[derive(Serialize, Deserialize, Validate)]
+pub struct TrashQuery {
+    #[validate(range(min = 1, message = "page must greater than 1"))]
+    pub page: Option<u64>,
+    #[validate(range(min = 1, message = "page_size must greater than 1"))]
+    pub page_size: Option<u64>,
+    #[validate(length(min = 1, max = 13, message = "order_by length must greater than 1"))]
+    pub order_by: Option<String>,
+    #[validate(length(min = 1, max = 16, message = "type length must greater than 1"))]
+    pub r#type: Option<String>,
+}
@@ -0,0 +1,9 @@
+pub mod db;
+mod forms;
+mod models;
+pub mod scheduler;
+mod urls;
+pub mod utils;
+mod views;
+
+pub use urls::trash_routers;
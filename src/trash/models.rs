@@ -0,0 +1,30 @@
+use serde::{Deserialize, Serialize};
+
+use crate::entity::trash;
+
+#[derive(Serialize, Deserialize)]
+pub struct TrashItem {
+    pub tid: u32,
+    pub r#type: String,
+    pub item_id: u32,
+    pub status: String,
+    pub created: u32,
+    pub author_id: u32,
+    pub delete_attachments: bool,
+    pub title: Option<String>,
+}
+
+impl From<trash::Model> for TrashItem {
+    fn from(trash: trash::Model) -> Self {
+        Self {
+            tid: trash.tid,
+            r#type: trash.r#type,
+            item_id: trash.item_id,
+            status: trash.status,
+            created: trash.created,
+            author_id: trash.author_id,
+            delete_attachments: trash.delete_attachments,
+            title: None,
+        }
+    }
+}
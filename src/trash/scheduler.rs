@@ -0,0 +1,41 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use tracing::{info, warn};
+
+use super::db;
+use super::utils::purge_trash;
+use crate::common::utils::get_now;
+use crate::AppState;
+
+const PURGE_INTERVAL: u64 = 3600;
+
+pub async fn purge_expired_trash(state: Arc<AppState>) {
+    if state.trash_retention_days == 0 {
+        return;
+    }
+
+    let mut interval = tokio::time::interval(Duration::from_secs(PURGE_INTERVAL));
+    loop {
+        interval.tick().await;
+        let expired = get_now().saturating_sub(state.trash_retention_days * 86400);
+        purge_trash_created_before(&state, expired).await;
+    }
+}
+
+/// Purges the trash created before `created` once, with the attachments choice recorded at delete time.
+pub async fn purge_trash_created_before(state: &AppState, created: u32) {
+    let items = match db::get_trash_by_created_before(state, created).await {
+        Ok(items) => items,
+        Err(e) => {
+            warn!("check expired trash failed: {:?}", e);
+            return;
+        }
+    };
+    for item in items {
+        match purge_trash(state, &item, item.delete_attachments).await {
+            Ok(_) => info!("trash purged: type={} id={}", item.r#type, item.item_id),
+            Err(e) => warn!("purge trash {} failed: {:?}", item.tid, e),
+        }
+    }
+}
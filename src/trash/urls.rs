@@ -0,0 +1,19 @@
+use axum::{
+    routing::{delete, get, post},
+    Router,
+};
+use std::sync::Arc;
+
+use super::views;
+use crate::AppState;
+
+pub fn trash_routers(ro: bool) -> Router<Arc<AppState>> {
+    let trash_route = Router::new().route("/api/trash/", get(views::list_trash));
+    if !ro {
+        trash_route
+            .route("/api/trash/:tid/restore", post(views::restore_trash_by_tid))
+            .route("/api/trash/:tid", delete(views::purge_trash_by_tid))
+    } else {
+        trash_route
+    }
+}
@@ -0,0 +1,35 @@
+use super::db;
+use crate::attachments::utils::delete_attachment_files;
+use crate::common::db as common_db;
+use crate::common::errors::FieldError;
+use crate::entity::trash;
+use crate::AppState;
+
+pub async fn purge_trash(
+    state: &AppState,
+    trash: &trash::Model,
+    delete_attachments: bool,
+) -> Result<(), FieldError> {
+    if trash.r#type == "comment" {
+        return db::delete_comment_with_trash(state, trash).await;
+    }
+
+    let content = match common_db::get_content_by_cid(state, trash.item_id).await? {
+        Some(c) => c,
+        None => {
+            let _ = db::delete_trash_by_tid(state, trash.tid).await?;
+            return Ok(());
+        }
+    };
+
+    let mut attachments =
+        common_db::delete_content_with_relations_by_cid(state, content.cid, delete_attachments)
+            .await?;
+    if content.r#type == "attachment" {
+        attachments.push(content);
+    }
+
+    let base_dir = std::path::Path::new(&state.upload_root);
+    delete_attachment_files(base_dir.to_path_buf(), attachments).await;
+    Ok(())
+}
@@ -0,0 +1,76 @@
+use std::sync::Arc;
+
+use axum::extract::{Path, State};
+use axum::response::Json;
+use serde_json::{json, Value};
+
+use super::db;
+use super::forms::TrashQuery;
+use super::models::TrashItem;
+use super::utils::purge_trash;
+use crate::common::errors::FieldError;
+use crate::common::extractors::{PMEditor, ValidatedQuery};
+use crate::common::forms::DeleteQuery;
+use crate::AppState;
+
+pub async fn list_trash(
+    State(state): State<Arc<AppState>>,
+    PMEditor(_): PMEditor,
+    ValidatedQuery(q): ValidatedQuery<TrashQuery>,
+) -> Result<Json<Value>, FieldError> {
+    let r#type = q.r#type.as_deref();
+    let all_count = db::get_trash_count_by_type(&state, r#type).await;
+
+    let page = q.page.unwrap_or(1);
+    let page_size = q.page_size.unwrap_or(10);
+    let order_by = q.order_by.unwrap_or("-tid".to_string());
+
+    let trash =
+        db::get_trash_by_type_and_list_query(&state, r#type, page_size, page, &order_by).await?;
+
+    let mut results = vec![];
+    for t in trash {
+        let title = db::get_trash_item_title(&state, &t).await;
+        let mut item = TrashItem::from(t);
+        item.title = title;
+        results.push(item);
+    }
+
+    Ok(Json(json!({
+        "page": page,
+        "page_size": page_size,
+        "all_count": all_count,
+        "count": results.len(),
+        "results": results
+    })))
+}
+
+pub async fn restore_trash_by_tid(
+    State(state): State<Arc<AppState>>,
+    PMEditor(_): PMEditor,
+    Path(tid): Path<u32>,
+) -> Result<Json<Value>, FieldError> {
+    let trash = match db::get_trash_by_tid(&state, tid).await? {
+        Some(t) => t,
+        None => return Err(FieldError::NotFound("tid".to_string())),
+    };
+
+    db::restore_trash(&state, &trash).await?;
+    Ok(Json(json!({ "msg": "ok" })))
+}
+
+pub async fn purge_trash_by_tid(
+    State(state): State<Arc<AppState>>,
+    PMEditor(_): PMEditor,
+    Path(tid): Path<u32>,
+    ValidatedQuery(q): ValidatedQuery<DeleteQuery>,
+) -> Result<Json<Value>, FieldError> {
+    let trash = match db::get_trash_by_tid(&state, tid).await? {
+        Some(t) => t,
+        None => return Err(FieldError::NotFound("tid".to_string())),
+    };
+
+    let delete_attachments = q.delete_attachments.unwrap_or(trash.delete_attachments);
+    purge_trash(&state, &trash, delete_attachments).await?;
+    Ok(Json(json!({ "msg": "ok" })))
+}
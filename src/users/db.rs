@@ -19,6 +19,17 @@ pub async fn get_user_by_mail(
         .map_err(|_| FieldError::InvalidParams("mail".to_string()))
 }
 
+pub async fn get_user_by_name(
+    state: &AppState,
+    name: &str,
+) -> Result<Option<user::Model>, FieldError> {
+    User::find()
+        .filter(user::Column::Name.eq(name))
+        .one(&state.conn)
+        .await
+        .map_err(|_| FieldError::InvalidParams("name".to_string()))
+}
+
 pub async fn get_user_by_uid(
     state: &AppState,
     uid: u32,
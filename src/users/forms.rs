@@ -1,11 +1,25 @@
 #![allow(non_snake_case)]
 use serde::{Deserialize, Serialize};
-use validator::Validate;
+use validator::{Validate, ValidationError};
+
+/// Scopes a token can be limited to, same as the ones of micropub.
+pub const SCOPES: [&str; 4] = ["create", "update", "delete", "media"];
 
 #[derive(Serialize, Deserialize)]
 pub struct TokenData {
     pub sub: String,
     pub exp: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+}
+
+fn validate_scope(scope: &str) -> Result<(), ValidationError> {
+    let scopes: Vec<&str> = scope.split_whitespace().collect();
+    if !scopes.is_empty() && scopes.iter().all(|s| SCOPES.contains(s)) {
+        Ok(())
+    } else {
+        Err(ValidationError::new("scope"))
+    }
 }
 
 #[derive(Serialize, Deserialize, Validate)]
@@ -14,6 +28,11 @@ pub struct UserLogin {
     pub mail: String,
     #[validate(length(max = 150, message = "password can not be longer than 150"))]
     pub password: String,
+    #[validate(
+        length(min = 1, max = 64, message = "scope length must greater than 1"),
+        custom(function = "validate_scope")
+    )]
+    pub scope: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Validate)]
@@ -9,10 +9,11 @@ use md5::{Digest, Md5};
 use rand::Rng;
 use sha2::Sha256;
 
-use super::db::{get_user_by_mail, get_user_by_uid};
+use super::db::{get_user_by_mail, get_user_by_name, get_user_by_uid};
 use super::forms::{TokenData, UserLogin};
 use crate::entity::user::Model as User;
 use crate::common::errors::AuthError;
+use crate::common::utils::get_now;
 use crate::AppState;
 
 const ITOA64: [&str; 64] = [
@@ -104,27 +105,68 @@ pub async fn authenticate_user(state: &AppState, user_login: &UserLogin) -> Opti
     None
 }
 
-pub async fn get_user(parts: &mut Parts, state: AppState) -> Result<User, AuthError> {
+/// Same as typecho, the login name can be either the user name or the mail.
+pub async fn authenticate_user_by_name(
+    state: &AppState,
+    name: &str,
+    password: &str,
+) -> Option<User> {
+    let user = if name.contains('@') {
+        get_user_by_mail(state, name).await
+    } else {
+        get_user_by_name(state, name).await
+    };
+    if let Ok(Some(user)) = user {
+        let user_password = user.password.clone().unwrap_or_default();
+        if verify(password, &user_password) {
+            return Some(user);
+        }
+    }
+    None
+}
+
+/// Returns the user of the token, and the scope it is limited to.
+pub async fn get_user_with_scope(
+    parts: &mut Parts,
+    state: AppState,
+) -> Result<(User, Option<String>), AuthError> {
     let TypedHeader(Authorization(bearer)) = parts
         .extract::<TypedHeader<Authorization<Bearer>>>()
         .await
         .map_err(|_| AuthError::InvalidToken)?;
+    get_user_with_scope_by_token(&state, bearer.token()).await
+}
 
+/// Same as `get_user_with_scope`, for tokens sent outside the `Authorization` header.
+pub async fn get_user_with_scope_by_token(
+    state: &AppState,
+    token: &str,
+) -> Result<(User, Option<String>), AuthError> {
     let secret_key = &state.secret_key;
 
     let key: Hmac<Sha256> =
         Hmac::new_from_slice(secret_key.as_bytes()).map_err(|_| AuthError::InvalidToken)?;
-    let token_data: TokenData = bearer
-        .token()
+    let token_data: TokenData = token
         .verify_with_key(&key)
         .map_err(|_| AuthError::InvalidToken)?;
+    if token_data.exp < get_now() as u64 {
+        return Err(AuthError::InvalidToken);
+    }
 
     let user_id = token_data
         .sub
         .parse::<u32>()
         .map_err(|_| AuthError::InvalidToken)?;
-    if let Ok(Some(user)) = get_user_by_uid(&state, user_id).await {
-        return Ok(user);
+    if let Ok(Some(user)) = get_user_by_uid(state, user_id).await {
+        return Ok((user, token_data.scope));
     }
     Err(AuthError::InvalidToken)
 }
+
+pub async fn get_user(parts: &mut Parts, state: AppState) -> Result<User, AuthError> {
+    // tokens limited to a scope only work on micropub
+    match get_user_with_scope(parts, state).await? {
+        (user, None) => Ok(user),
+        (_, Some(_)) => Err(AuthError::PermissionDeny),
+    }
+}
@@ -31,6 +31,7 @@ pub async fn login_for_access_token(
         let token_data = TokenData {
             sub: format!("{}", user.uid),
             exp: now + state.access_token_expire_secondes,
+            scope: user_login.scope.clone(),
         };
         let access_token = token_data.sign_with_key(&key).unwrap();
 
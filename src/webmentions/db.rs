@@ -0,0 +1,183 @@
+use sea_orm::*;
+
+use crate::common::errors::FieldError;
+use crate::common::utils::get_now;
+use crate::entity::{webmention, webmention::Entity as Webmention};
+use crate::AppState;
+
+pub async fn create_webmention_with_params(
+    state: &AppState,
+    cid: u32,
+    source: &str,
+    target: &str,
+    direction: &str,
+    ip: Option<String>,
+) -> Result<webmention::Model, FieldError> {
+    let now = get_now();
+    webmention::ActiveModel {
+        cid: Set(cid),
+        source: Set(source.to_string()),
+        target: Set(target.to_string()),
+        direction: Set(direction.to_string()),
+        ip: Set(ip),
+        status: Set("pending".to_string()),
+        attempts: Set(0),
+        next_attempt: Set(now),
+        created: Set(now),
+        ..Default::default()
+    }
+    .insert(&state.conn)
+    .await
+    .map_err(|_| FieldError::DatabaseFailed("create webmention failed".to_string()))
+}
+
+/// Whether the same mention is still queued or was received since `since`.
+pub async fn check_recent_webmention(
+    state: &AppState,
+    direction: &str,
+    source: &str,
+    target: &str,
+    since: u32,
+) -> Result<bool, FieldError> {
+    let count = Webmention::find()
+        .filter(webmention::Column::Direction.eq(direction))
+        .filter(webmention::Column::Source.eq(source))
+        .filter(webmention::Column::Target.eq(target))
+        .filter(
+            Condition::any()
+                .add(webmention::Column::Status.eq("pending"))
+                .add(webmention::Column::Created.gte(since)),
+        )
+        .count(&state.conn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("fetch webmentions failed".to_string()))?;
+    Ok(count > 0)
+}
+
+/// Created times of the mentions received from the ip or for the source since `since`, oldest first.
+pub async fn get_received_created_by_ip_or_source_since(
+    state: &AppState,
+    ip: &str,
+    source: &str,
+    since: u32,
+) -> Result<Vec<u32>, FieldError> {
+    let webmentions = Webmention::find()
+        .filter(webmention::Column::Direction.eq("in"))
+        .filter(
+            Condition::any()
+                .add(webmention::Column::Ip.eq(ip))
+                .add(webmention::Column::Source.eq(source)),
+        )
+        .filter(webmention::Column::Created.gte(since))
+        .order_by_asc(webmention::Column::Created)
+        .all(&state.conn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("fetch webmentions failed".to_string()))?;
+    Ok(webmentions.into_iter().map(|w| w.created).collect())
+}
+
+/// Received mentions are only kept for the rate limit, older ones are removed.
+pub async fn delete_received_webmentions_before(
+    state: &AppState,
+    before: u32,
+) -> Result<DeleteResult, FieldError> {
+    Webmention::delete_many()
+        .filter(webmention::Column::Direction.eq("in"))
+        .filter(webmention::Column::Status.eq("received"))
+        .filter(webmention::Column::Created.lt(before))
+        .exec(&state.conn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("delete webmentions failed".to_string()))
+}
+
+pub async fn get_webmentions_by_cid_and_direction(
+    state: &AppState,
+    cid: u32,
+    direction: &str,
+) -> Result<Vec<webmention::Model>, FieldError> {
+    Webmention::find()
+        .filter(webmention::Column::Cid.eq(cid))
+        .filter(webmention::Column::Direction.eq(direction))
+        .order_by_asc(webmention::Column::Wid)
+        .all(&state.conn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("fetch webmentions failed".to_string()))
+}
+
+pub async fn get_pending_webmentions_before(
+    state: &AppState,
+    now: u32,
+    limit: u64,
+) -> Result<Vec<webmention::Model>, FieldError> {
+    Webmention::find()
+        .filter(webmention::Column::Status.eq("pending"))
+        .filter(webmention::Column::NextAttempt.lte(now))
+        .order_by_asc(webmention::Column::Wid)
+        .limit(limit)
+        .all(&state.conn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("fetch webmentions failed".to_string()))
+}
+
+/// Moves `next_attempt` to `lease_until` only if no other worker did it first.
+pub async fn claim_webmention_until(
+    state: &AppState,
+    exist_webmention: &webmention::Model,
+    lease_until: u32,
+) -> Result<bool, FieldError> {
+    let res = Webmention::update_many()
+        .col_expr(
+            webmention::Column::NextAttempt,
+            sea_query::Expr::value(lease_until),
+        )
+        .filter(webmention::Column::Wid.eq(exist_webmention.wid))
+        .filter(webmention::Column::Status.eq("pending"))
+        .filter(webmention::Column::NextAttempt.eq(exist_webmention.next_attempt))
+        .exec(&state.conn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("claim webmention failed".to_string()))?;
+    Ok(res.rows_affected == 1)
+}
+
+pub async fn delete_webmention_by_wid(
+    state: &AppState,
+    wid: u32,
+) -> Result<DeleteResult, FieldError> {
+    Webmention::delete_many()
+        .filter(webmention::Column::Wid.eq(wid))
+        .exec(&state.conn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("delete webmention failed".to_string()))
+}
+
+pub async fn modify_webmention_status_by_wid(
+    state: &AppState,
+    wid: u32,
+    status: &str,
+) -> Result<UpdateResult, FieldError> {
+    Webmention::update_many()
+        .col_expr(webmention::Column::Status, sea_query::Expr::value(status))
+        .filter(webmention::Column::Wid.eq(wid))
+        .exec(&state.conn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("update webmention failed".to_string()))
+}
+
+pub async fn modify_webmention_for_retry(
+    state: &AppState,
+    exist_webmention: webmention::Model,
+    next_attempt: Option<u32>,
+    error: &str,
+) -> Result<webmention::Model, FieldError> {
+    let attempts = exist_webmention.attempts + 1;
+    let mut w = webmention::ActiveModel::from(exist_webmention);
+    w.attempts = Set(attempts);
+    w.last_error = Set(Some(error.chars().take(255).collect()));
+    match next_attempt {
+        Some(t) => w.next_attempt = Set(t),
+        None => w.status = Set("failed".to_string()),
+    }
+    w.update(&state.conn)
+        .await
+        .map_err(|_| FieldError::DatabaseFailed("update webmention failed".to_string()))
+}
@@ -0,0 +1,10 @@
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+#[derive(Serialize, Deserialize, Validate)]
+pub struct WebmentionCreate {
+    #[validate(url, length(max = 255, message = "source can not be longer than 255"))]
+    pub source: String,
+    #[validate(url, length(max = 255, message = "target can not be longer than 255"))]
+    pub target: String,
+}
@@ -0,0 +1,9 @@
+pub mod db;
+mod forms;
+pub mod scheduler;
+pub mod sender;
+mod urls;
+mod utils;
+mod views;
+
+pub use urls::webmentions_routers;
@@ -0,0 +1,162 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use tracing::{info, warn};
+
+use super::db;
+use super::utils::discover_endpoint;
+use crate::comments::pings::{create_ping, Ping, PingError};
+use crate::common::db as common_db;
+use crate::common::utils::get_now;
+use crate::entity::webmention;
+use crate::AppState;
+
+const PROCESS_INTERVAL: u64 = 60;
+const PROCESS_BATCH: u64 = 50;
+const PROCESS_LEASE: u32 = 300;
+const MAX_ATTEMPTS: u32 = 5;
+const RECEIVED_RETENTION: u32 = 86400;
+
+/// Whether a webmention is done, or should be tried again later.
+enum Outcome {
+    Done,
+    Retry(String),
+}
+
+pub async fn process_queued_webmentions(state: Arc<AppState>) {
+    let mut interval = tokio::time::interval(Duration::from_secs(PROCESS_INTERVAL));
+    loop {
+        interval.tick().await;
+        let now = get_now();
+        if let Err(e) =
+            db::delete_received_webmentions_before(&state, now.saturating_sub(RECEIVED_RETENTION))
+                .await
+        {
+            warn!("delete received webmentions failed: {:?}", e);
+        }
+        match db::get_pending_webmentions_before(&state, now, PROCESS_BATCH).await {
+            Ok(webmentions) => {
+                for w in webmentions {
+                    process_webmention(&state, w).await;
+                }
+            }
+            Err(e) => warn!("check pending webmentions failed: {:?}", e),
+        }
+    }
+}
+
+/// Verifies a received webmention or sends an outgoing one, failures are retried with
+/// exponential backoff until `MAX_ATTEMPTS`.
+pub async fn process_webmention(state: &AppState, w: webmention::Model) {
+    let now = get_now();
+    match db::claim_webmention_until(state, &w, now + PROCESS_LEASE).await {
+        Ok(true) => (),
+        Ok(false) => return,
+        Err(e) => {
+            warn!("claim webmention {} failed: {:?}", w.wid, e);
+            return;
+        }
+    }
+
+    let outcome = if w.direction == "in" {
+        verify_webmention(state, &w).await
+    } else {
+        send_webmention(state, &w).await
+    };
+    let wid = w.wid;
+    match outcome {
+        // unlink notices are only sent once
+        Outcome::Done if w.direction == "unlink" => {
+            if let Err(e) = db::delete_webmention_by_wid(state, wid).await {
+                warn!("delete webmention {} failed: {:?}", wid, e);
+            }
+        }
+        // sent links are kept, so later saves of the post only mention changed links,
+        // received ones are kept for the rate limit of the endpoint
+        Outcome::Done => {
+            let status = if w.direction == "out" {
+                "sent"
+            } else {
+                "received"
+            };
+            if let Err(e) = db::modify_webmention_status_by_wid(state, wid, status).await {
+                warn!("update webmention {} failed: {:?}", wid, e);
+            }
+        }
+        Outcome::Retry(error) => {
+            warn!("webmention {} failed: {}", wid, error);
+            let next_attempt = if w.attempts + 1 < MAX_ATTEMPTS {
+                Some(now + PROCESS_INTERVAL as u32 * 2u32.pow(w.attempts))
+            } else {
+                None
+            };
+            if let Err(e) = db::modify_webmention_for_retry(state, w, next_attempt, &error).await {
+                warn!("update webmention {} failed: {:?}", wid, e);
+            }
+        }
+    }
+}
+
+async fn verify_webmention(state: &AppState, w: &webmention::Model) -> Outcome {
+    let content = match common_db::get_content_by_cid(state, w.cid).await {
+        Ok(Some(c)) => c,
+        Ok(None) => return Outcome::Done,
+        Err(e) => return Outcome::Retry(format!("{:?}", e)),
+    };
+    let options = match common_db::get_site_options(state).await {
+        Ok(o) => o,
+        Err(e) => return Outcome::Retry(format!("{:?}", e)),
+    };
+
+    let ping = Ping {
+        ping_type: "webmention",
+        source: &w.source,
+        title: None,
+        excerpt: None,
+        blog_name: None,
+        ip: w.ip.as_deref().unwrap_or_default(),
+        ua: "",
+    };
+    match create_ping(state, &options, &content, ping).await {
+        Ok(status) => {
+            info!("webmention received: source={} status={}", w.source, status);
+            Outcome::Done
+        }
+        Err(e @ (PingError::SourceNotFound | PingError::Failed)) => {
+            Outcome::Retry(e.message().to_string())
+        }
+        Err(e) => {
+            info!("webmention rejected: source={} {}", w.source, e.message());
+            Outcome::Done
+        }
+    }
+}
+
+async fn send_webmention(state: &AppState, w: &webmention::Model) -> Outcome {
+    let response = match state.http_client.get(&w.target).await {
+        Ok(r) => r,
+        Err(e) => return Outcome::Retry(e),
+    };
+    let endpoint = match discover_endpoint(&response) {
+        Some(e) => e,
+        None => return Outcome::Done,
+    };
+
+    let form = vec![
+        ("source".to_string(), w.source.clone()),
+        ("target".to_string(), w.target.clone()),
+    ];
+    match state.http_client.post_form(&endpoint, form).await {
+        Ok(status) if (200..300).contains(&status) => {
+            info!("webmention sent: source={} target={}", w.source, w.target);
+            Outcome::Done
+        }
+        // the receiver refused it, sending again will not help
+        Ok(status) if (400..500).contains(&status) => {
+            warn!("webmention refused: target={} status={}", w.target, status);
+            Outcome::Done
+        }
+        Ok(status) => Outcome::Retry(format!("http status {}", status)),
+        Err(e) => Outcome::Retry(e),
+    }
+}
@@ -0,0 +1,86 @@
+use std::sync::Arc;
+use tracing::warn;
+
+use super::db;
+use super::scheduler::process_webmention;
+use super::utils::extract_links;
+use crate::common::db as common_db;
+use crate::common::errors::FieldError;
+use crate::common::markdown::render_text_to_html;
+use crate::common::utils::{get_now, Permalinks};
+use crate::entity::{content, webmention};
+use crate::posts::db as posts_db;
+use crate::AppState;
+
+/// Queues a mention for every page the published post newly links to or stopped linking to, then sends them.
+pub async fn send_post_webmentions(state: Arc<AppState>, post: content::Model) {
+    match queue_post_webmentions(&state, &post).await {
+        Ok(webmentions) => {
+            for w in webmentions {
+                process_webmention(&state, w).await;
+            }
+        }
+        Err(e) => warn!("queue webmentions of post {} failed: {:?}", post.cid, e),
+    }
+}
+
+async fn queue_post_webmentions(
+    state: &AppState,
+    post: &content::Model,
+) -> Result<Vec<webmention::Model>, FieldError> {
+    // scheduled posts are sent by the scheduler once they are published
+    if post.r#type != "post"
+        || post.status != "publish"
+        || post.created > get_now()
+        || post.password.as_deref().is_some_and(|p| !p.is_empty())
+    {
+        return Ok(vec![]);
+    }
+    let slug = post.slug.as_deref().unwrap_or_default();
+    let post =
+        posts_db::get_content_with_metas_user_fields_by_slug_and_private(state, slug, true).await?;
+    let options = common_db::get_site_options(state).await?;
+    let permalinks = Permalinks::from_options(&options);
+    let source = permalinks.content(
+        &post.r#type,
+        post.cid,
+        slug,
+        post.created,
+        post.categories.first().and_then(|m| m.slug.as_deref()),
+    );
+
+    let html = render_text_to_html(
+        post.cid,
+        post.modified,
+        post.text.as_deref().unwrap_or_default(),
+    );
+    let links = extract_links(&html, &permalinks.site_url);
+    let tracked = db::get_webmentions_by_cid_and_direction(state, post.cid, "out").await?;
+    let mut webmentions = vec![];
+    // a removed link, or one under an old permalink, is mentioned once more so the target can drop it
+    for w in tracked
+        .iter()
+        .filter(|w| w.source != source || !links.contains(&w.target))
+    {
+        db::delete_webmention_by_wid(state, w.wid).await?;
+        if w.status == "sent" {
+            let w = db::create_webmention_with_params(
+                state, post.cid, &w.source, &w.target, "unlink", None,
+            )
+            .await?;
+            webmentions.push(w);
+        }
+    }
+    for target in links {
+        if tracked
+            .iter()
+            .any(|w| w.source == source && w.target == target)
+        {
+            continue;
+        }
+        let w = db::create_webmention_with_params(state, post.cid, &source, &target, "out", None)
+            .await?;
+        webmentions.push(w);
+    }
+    Ok(webmentions)
+}
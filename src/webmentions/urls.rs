@@ -0,0 +1,14 @@
+use axum::{routing::post, Router};
+use std::sync::Arc;
+
+use super::views;
+use crate::AppState;
+
+pub fn webmentions_routers(ro: bool) -> Router<Arc<AppState>> {
+    let webmentions_route = Router::new();
+    if !ro {
+        webmentions_route.route("/api/webmention", post(views::receive_webmention))
+    } else {
+        webmentions_route
+    }
+}
@@ -0,0 +1,123 @@
+use regex::Regex;
+use url::Url;
+
+use crate::common::http::HttpResponse;
+
+pub const MAX_LINKS: usize = 20;
+
+lazy_static! {
+    static ref HREF_RE: Regex = Regex::new(r#"(?i)\bhref\s*=\s*["']([^"']*)["']"#).unwrap();
+    static ref REL_RE: Regex = Regex::new(r#"(?i)\brel\s*=\s*["']([^"']*)["']"#).unwrap();
+    static ref LINK_TAG_RE: Regex = Regex::new(r"(?is)<(?:link|a)\b[^>]*>").unwrap();
+    static ref LINK_HEADER_RE: Regex =
+        Regex::new(r#"<([^>]*)>\s*;[^,]*?\brel\s*=\s*"?([^";,]*)"?"#).unwrap();
+}
+
+fn has_webmention_rel(rel: &str) -> bool {
+    rel.split_whitespace()
+        .any(|r| r.eq_ignore_ascii_case("webmention"))
+}
+
+/// Whether the link points to the site, the host and port decide and http equals https.
+fn is_site_link(url: &Url, site: Option<&Url>) -> bool {
+    site.is_some_and(|site| url.host_str() == site.host_str() && url.port() == site.port())
+}
+
+/// Absolute http(s) links of the rendered text, links to the site itself are left out.
+pub fn extract_links(html: &str, site_url: &str) -> Vec<String> {
+    let site = Url::parse(site_url).ok();
+    let mut links: Vec<String> = vec![];
+    for caps in HREF_RE.captures_iter(html) {
+        let href = caps[1].replace("&amp;", "&");
+        let url = match Url::parse(&href) {
+            Ok(u) if u.scheme() == "http" || u.scheme() == "https" => u,
+            _ => continue,
+        };
+        let link = url.to_string();
+        if is_site_link(&url, site.as_ref()) || links.contains(&link) {
+            continue;
+        }
+        links.push(link);
+        if links.len() >= MAX_LINKS {
+            break;
+        }
+    }
+    links
+}
+
+/// Finds the endpoint in the `Link` headers first, then in the first `<link>` or `<a>` of the page.
+pub fn discover_endpoint(response: &HttpResponse) -> Option<String> {
+    let base = Url::parse(&response.url).ok()?;
+    let header_endpoint = response.header_values("link").find_map(|value| {
+        LINK_HEADER_RE
+            .captures_iter(value)
+            .find(|caps| has_webmention_rel(&caps[2]))
+            .map(|caps| caps[1].to_string())
+    });
+    let endpoint = header_endpoint.or_else(|| {
+        LINK_TAG_RE.find_iter(&response.body).find_map(|tag| {
+            let rel = REL_RE.captures(tag.as_str())?;
+            if !has_webmention_rel(&rel[1]) {
+                return None;
+            }
+            HREF_RE
+                .captures(tag.as_str())
+                .map(|href| href[1].replace("&amp;", "&"))
+        })
+    })?;
+    // an empty href is the page itself
+    base.join(&endpoint).ok().map(|u| u.to_string())
+}
+
+#[test]
+fn test_extract_links() {
+    let html = r#"<p><a href="https://a.local.host/1?x=1&amp;y=2">a</a>
+<a href="/relative">b</a> <a href="https://rumo.cf/archives/1/">self</a>
+<a href="mailto:a@local.host">c</a> <a href='http://b.local.host/'>d</a>
+<a href="https://a.local.host/1?x=1&amp;y=2">again</a></p>
+<a href="http://RUMO.cf/archives/2/">self over http</a> <a href="https://rumo.cf:8443/">other port</a>
+<a href="https://rumo.cf.local.host/">lookalike</a>"#;
+    assert_eq!(
+        vec![
+            "https://a.local.host/1?x=1&y=2".to_string(),
+            "http://b.local.host/".to_string(),
+            "https://rumo.cf:8443/".to_string(),
+            "https://rumo.cf.local.host/".to_string(),
+        ],
+        extract_links(html, "https://rumo.cf")
+    );
+}
+
+#[test]
+fn test_discover_endpoint() {
+    let mut response = HttpResponse {
+        url: "https://a.local.host/post/1".to_string(),
+        headers: vec![(
+            "Link".to_string(),
+            r#"<https://a.local.host/>; rel="home", </mention>; rel="other webmention""#
+                .to_string(),
+        )],
+        body: r#"<link rel="webmention" href="/ignored">"#.to_string(),
+    };
+    assert_eq!(
+        Some("https://a.local.host/mention".to_string()),
+        discover_endpoint(&response)
+    );
+
+    response.headers = vec![];
+    response.body = r#"<abbr rel="webmention"></abbr><a href="/x">x</a>
+<a rel="webmention" href="endpoint?v=1&amp;w=2">mention</a><link rel="webmention" href="/later">"#
+        .to_string();
+    assert_eq!(
+        Some("https://a.local.host/post/endpoint?v=1&w=2".to_string()),
+        discover_endpoint(&response)
+    );
+
+    response.body = r#"<link href="" rel="webmention">"#.to_string();
+    assert_eq!(
+        Some("https://a.local.host/post/1".to_string()),
+        discover_endpoint(&response)
+    );
+    response.body = String::new();
+    assert_eq!(None, discover_endpoint(&response));
+}
@@ -0,0 +1,57 @@
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::Json;
+use axum_client_ip::SecureClientIp;
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+use super::db;
+use super::forms::WebmentionCreate;
+use super::scheduler::process_webmention;
+use crate::comments::pings::get_content_by_target_url;
+use crate::common::db as common_db;
+use crate::common::errors::FieldError;
+use crate::common::extractors::ValidatedForm;
+use crate::common::utils::get_now;
+use crate::AppState;
+
+const RECEIVE_WINDOW: u32 = 60;
+const RECEIVE_LIMIT: usize = 10;
+
+pub async fn receive_webmention(
+    State(state): State<Arc<AppState>>,
+    SecureClientIp(client_ip): SecureClientIp,
+    ValidatedForm(webmention_create): ValidatedForm<WebmentionCreate>,
+) -> Result<(StatusCode, Json<Value>), FieldError> {
+    let source = webmention_create.source;
+    let target = webmention_create.target;
+    if !source.starts_with("http://") && !source.starts_with("https://") || source == target {
+        return Err(FieldError::InvalidParams("source".to_string()));
+    }
+
+    let options = common_db::get_site_options(&state).await?;
+    let content = match get_content_by_target_url(&state, &options, &target).await {
+        Ok(c) if c.status == "publish" && c.allow_ping == "1" => c,
+        _ => return Err(FieldError::InvalidParams("target".to_string())),
+    };
+
+    // the endpoint is open to anyone, so every ip and source gets a few mentions a minute
+    let now = get_now();
+    let since = now.saturating_sub(RECEIVE_WINDOW);
+    let ip = client_ip.to_string();
+    if db::check_recent_webmention(&state, "in", &source, &target, since).await? {
+        return Ok((StatusCode::ACCEPTED, Json(json!({ "msg": "ok" }))));
+    }
+    let received =
+        db::get_received_created_by_ip_or_source_since(&state, &ip, &source, since).await?;
+    if received.len() >= RECEIVE_LIMIT {
+        let retry_after = received[received.len() - RECEIVE_LIMIT] + RECEIVE_WINDOW - now;
+        return Err(FieldError::TooManyRequests(retry_after.max(1) as u64));
+    }
+
+    let webmention =
+        db::create_webmention_with_params(&state, content.cid, &source, &target, "in", Some(ip))
+            .await?;
+    tokio::spawn(async move { process_webmention(&state, webmention).await });
+    Ok((StatusCode::ACCEPTED, Json(json!({ "msg": "ok" }))))
+}
@@ -0,0 +1,827 @@
+use axum::body::Bytes;
+use chrono::FixedOffset;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use validator::Validate;
+
+use super::models::{Fault, XmlValue};
+use super::utils::{format_datetime, parse_datetime};
+use crate::attachments::utils::save_attachment;
+use crate::categories::db as categories_db;
+use crate::common::db as common_db;
+use crate::common::errors::FieldError;
+use crate::common::models::ContentWithMetasUsersFields;
+use crate::common::utils::{get_now, get_timezone, Permalinks};
+use crate::entity::{content, user};
+use crate::posts::db as posts_db;
+use crate::posts::forms::PostCreate;
+use crate::posts::utils::{
+    create_post_revision_with_exist_post, create_post_with_default_slug, get_default_category,
+    get_post_create_by_content, is_admin, modify_post_with_exist_post, set_contributor_status,
+};
+use crate::trash::db as trash_db;
+use crate::users::utils::authenticate_user_by_name;
+use crate::webmentions::sender::send_post_webmentions;
+use crate::AppState;
+
+const MAX_RECENT_POSTS: i64 = 100;
+const LOGIN_FREE_FAILURES: u32 = 3;
+const LOGIN_MAX_DELAY: u32 = 3600;
+
+lazy_static! {
+    /// Failed logins of every name from every client ip, as (failures, last failure),
+    /// forgotten after an hour.
+    static ref LOGIN_FAILURES: Mutex<HashMap<(String, IpAddr), (u32, u32)>> =
+        Mutex::new(HashMap::new());
+}
+
+fn invalid_params() -> Fault {
+    Fault::new(-32602, "invalid method parameters")
+}
+
+/// Same as wordpress, an edit of a post that was changed since the client read it.
+pub fn modified_fault() -> Fault {
+    Fault::new(409, "there is a revision of this post that is more recent")
+}
+
+fn param(params: &[XmlValue], index: usize) -> Result<&XmlValue, Fault> {
+    params.get(index).ok_or_else(invalid_params)
+}
+
+fn param_str(params: &[XmlValue], index: usize) -> Result<&str, Fault> {
+    param(params, index)?.as_str().ok_or_else(invalid_params)
+}
+
+fn param_cid(params: &[XmlValue], index: usize) -> Result<u32, Fault> {
+    param(params, index)?
+        .as_i64()
+        .and_then(|i| u32::try_from(i).ok())
+        .ok_or_else(invalid_params)
+}
+
+fn member_str<'a>(value: &'a XmlValue, name: &str) -> Option<&'a str> {
+    value.get(name).and_then(|v| v.as_str())
+}
+
+fn member_strings(value: Option<&XmlValue>) -> Option<Vec<String>> {
+    value.and_then(|v| v.as_array()).map(|values| {
+        values
+            .iter()
+            .filter_map(|v| v.as_str())
+            .map(|s| s.to_string())
+            .collect()
+    })
+}
+
+/// Seconds to wait after the last failed login, doubling with every failure past the free ones.
+fn get_login_delay(failures: u32) -> u32 {
+    if failures < LOGIN_FREE_FAILURES {
+        return 0;
+    }
+    let exp = (failures - LOGIN_FREE_FAILURES).min(6);
+    (60 << exp).min(LOGIN_MAX_DELAY)
+}
+
+/// Checks the user name and password at `index`, only contributors and above can write.
+/// Every call carries the password, so failures of a name from a client ip slow down later
+/// logins from there, the owner still logs in from elsewhere.
+async fn login(
+    state: &AppState,
+    params: &[XmlValue],
+    index: usize,
+    client_ip: IpAddr,
+) -> Result<user::Model, Fault> {
+    let name = param_str(params, index)?;
+    let password = param_str(params, index + 1)?;
+    let key = (name.to_lowercase(), client_ip);
+    let now = get_now();
+    if let Some((failures, last)) = LOGIN_FAILURES.lock().unwrap().get(&key) {
+        let until = last + get_login_delay(*failures);
+        if now < until {
+            let message = format!(
+                "too many failed logins, retry after {} seconds",
+                until - now
+            );
+            return Err(Fault::new(429, &message));
+        }
+    }
+
+    let user = match authenticate_user_by_name(state, name, password).await {
+        Some(user) => {
+            LOGIN_FAILURES.lock().unwrap().remove(&key);
+            user
+        }
+        None => {
+            let mut login_failures = LOGIN_FAILURES.lock().unwrap();
+            login_failures.retain(|_, (_, last)| now < *last + LOGIN_MAX_DELAY);
+            let (failures, last) = login_failures.entry(key).or_insert((0, now));
+            *failures += 1;
+            *last = now;
+            return Err(Fault::new(403, "incorrect username or password"));
+        }
+    };
+    match user.group.as_str() {
+        "contributor" | "editor" | "administrator" => Ok(user),
+        _ => Err(Fault::new(401, "permission denied")),
+    }
+}
+
+async fn get_exist_post_with_user(
+    state: &AppState,
+    cid: u32,
+    user: &user::Model,
+) -> Result<content::Model, Fault> {
+    let exist_post = match common_db::get_content_by_cid(state, cid).await? {
+        Some(p) if p.r#type == "post" && p.status != "trash" => p,
+        _ => return Err(Fault::new(404, "post not found")),
+    };
+    if exist_post.author_id != user.uid && !is_admin(user) {
+        return Err(Fault::new(401, "permission denied"));
+    }
+    Ok(exist_post)
+}
+
+/// Maps the status of wordpress clients to the one of typecho.
+fn to_status(status: &str) -> Result<String, Fault> {
+    match status {
+        "publish" | "draft" | "private" => Ok(status.to_string()),
+        "pending" => Ok("waiting".to_string()),
+        _ => Err(Fault::new(-32602, "invalid post status")),
+    }
+}
+
+fn from_status(status: &str) -> &str {
+    match status {
+        "waiting" => "pending",
+        s => s,
+    }
+}
+
+fn split_tags(keywords: &str) -> Vec<String> {
+    keywords
+        .split(',')
+        .map(|t| t.trim())
+        .filter(|t| !t.is_empty())
+        .map(|t| t.to_string())
+        .collect()
+}
+
+fn new_post_create() -> PostCreate {
+    PostCreate {
+        title: String::new(),
+        slug: String::new(),
+        created: get_now(),
+        text: String::new(),
+        status: "publish".to_string(),
+        password: None,
+        allowComment: None,
+        allowPing: None,
+        allowFeed: None,
+        categories: None,
+        tags: None,
+    }
+}
+
+fn apply_created(
+    post: &mut PostCreate,
+    gmt: Option<&XmlValue>,
+    local: Option<&XmlValue>,
+    timezone: &FixedOffset,
+) {
+    let utc = FixedOffset::east_opt(0).unwrap();
+    let created = match (gmt, local) {
+        (Some(XmlValue::DateTime(d)), _) => parse_datetime(d, &utc),
+        (_, Some(XmlValue::DateTime(d))) => parse_datetime(d, timezone),
+        _ => None,
+    };
+    if let Some(created) = created {
+        post.created = created;
+    }
+}
+
+/// Fills the post with a metaWeblog struct, `publish` decides the status unless
+/// `post_status` is given.
+fn apply_metaweblog_struct(
+    post: &mut PostCreate,
+    value: &XmlValue,
+    publish: Option<bool>,
+    timezone: &FixedOffset,
+) -> Result<(), Fault> {
+    if !matches!(value, XmlValue::Struct(_)) {
+        return Err(invalid_params());
+    }
+    if let Some(title) = member_str(value, "title") {
+        post.title = title.to_string();
+    }
+    if let Some(description) = member_str(value, "description") {
+        post.text = match member_str(value, "mt_text_more") {
+            Some(more) if !more.is_empty() => format!("{}\n<!--more-->\n{}", description, more),
+            _ => description.to_string(),
+        };
+    }
+    if let Some(slug) = member_str(value, "wp_slug").filter(|s| !s.is_empty()) {
+        post.slug = slug.to_string();
+    }
+    if let Some(password) = member_str(value, "wp_password") {
+        post.password = Some(password.to_string()).filter(|p| !p.is_empty());
+    }
+    if let Some(allow) = value.get("mt_allow_comments").and_then(|v| v.as_bool()) {
+        post.allowComment = Some(allow);
+    }
+    if let Some(allow) = value.get("mt_allow_pings").and_then(|v| v.as_bool()) {
+        post.allowPing = Some(allow);
+    }
+    if let Some(categories) = member_strings(value.get("categories")) {
+        post.categories = Some(categories);
+    }
+    if let Some(keywords) = member_str(value, "mt_keywords") {
+        post.tags = Some(split_tags(keywords));
+    }
+    apply_created(
+        post,
+        value.get("date_created_gmt"),
+        value.get("dateCreated"),
+        timezone,
+    );
+
+    if let Some(status) = member_str(value, "post_status") {
+        post.status = to_status(status)?;
+    } else if let Some(publish) = publish {
+        post.status = if publish { "publish" } else { "draft" }.to_string();
+    }
+    Ok(())
+}
+
+/// Fills the post with the content struct of `wp.newPost` and `wp.editPost`.
+fn apply_wp_struct(
+    post: &mut PostCreate,
+    value: &XmlValue,
+    timezone: &FixedOffset,
+) -> Result<(), Fault> {
+    if !matches!(value, XmlValue::Struct(_)) {
+        return Err(invalid_params());
+    }
+    if let Some(title) = member_str(value, "post_title") {
+        post.title = title.to_string();
+    }
+    if let Some(text) = member_str(value, "post_content") {
+        post.text = text.to_string();
+    }
+    if let Some(slug) = member_str(value, "post_name").filter(|s| !s.is_empty()) {
+        post.slug = slug.to_string();
+    }
+    if let Some(status) = member_str(value, "post_status") {
+        post.status = to_status(status)?;
+    }
+    if let Some(password) = member_str(value, "post_password") {
+        post.password = Some(password.to_string()).filter(|p| !p.is_empty());
+    }
+    if let Some(allow) = value.get("comment_status").and_then(|v| v.as_bool()) {
+        post.allowComment = Some(allow);
+    }
+    if let Some(allow) = value.get("ping_status").and_then(|v| v.as_bool()) {
+        post.allowPing = Some(allow);
+    }
+    if let Some(terms) = value.get("terms_names") {
+        if let Some(categories) = member_strings(terms.get("category")) {
+            post.categories = Some(categories);
+        }
+        if let Some(tags) = member_strings(terms.get("post_tag")) {
+            post.tags = Some(tags);
+        }
+    }
+    apply_created(
+        post,
+        value.get("post_date_gmt"),
+        value.get("post_date"),
+        timezone,
+    );
+    Ok(())
+}
+
+fn check_post_create(user: &user::Model, post_create: &mut PostCreate) -> Result<(), Fault> {
+    set_contributor_status(user, post_create);
+    post_create.validate().map_err(|e| {
+        let message = format!("invalid post: {}", e).replace('\n', ", ");
+        Fault::new(-32602, &message)
+    })
+}
+
+async fn create_post(
+    state: &Arc<AppState>,
+    options: &HashMap<String, String>,
+    user: &user::Model,
+    mut post_create: PostCreate,
+) -> Result<u32, Fault> {
+    set_contributor_status(user, &mut post_create);
+    let default_category = get_default_category(options);
+    let post =
+        create_post_with_default_slug(state, post_create, user.uid, default_category).await?;
+    let cid = post.cid;
+    tokio::spawn(send_post_webmentions(state.clone(), post));
+    Ok(cid)
+}
+
+/// Edits go through the same checks as the api, a published post saved as a draft is kept
+/// as a revision instead, same as typecho.
+async fn modify_post(
+    state: &Arc<AppState>,
+    options: &HashMap<String, String>,
+    user: &user::Model,
+    exist_post: &content::Model,
+    mut post_modify: PostCreate,
+    if_match: &str,
+) -> Result<(), Fault> {
+    check_post_create(user, &mut post_modify)?;
+
+    if exist_post.status == "publish" && post_modify.status == "draft" {
+        let _ = create_post_revision_with_exist_post(state, post_modify, exist_post).await?;
+        return Ok(());
+    }
+    let default_category = get_default_category(options);
+    let post =
+        modify_post_with_exist_post(state, &post_modify, exist_post, if_match, default_category)
+            .await
+            .map_err(|e| match e {
+                FieldError::AlreadyExist(_) => Fault::new(-32602, "slug already exist"),
+                e => e.into(),
+            })?;
+    tokio::spawn(send_post_webmentions(state.clone(), post));
+    Ok(())
+}
+
+async fn get_post_with_metas(
+    state: &AppState,
+    post: &content::Model,
+) -> Result<ContentWithMetasUsersFields, Fault> {
+    let slug = post.slug.as_deref().unwrap_or_default();
+    Ok(posts_db::get_content_with_metas_user_fields_by_slug_and_private(state, slug, true).await?)
+}
+
+fn post_link(permalinks: &Permalinks, post: &ContentWithMetasUsersFields) -> String {
+    permalinks.content(
+        &post.r#type,
+        post.cid,
+        post.slug.as_deref().unwrap_or_default(),
+        post.created,
+        post.categories.first().and_then(|m| m.slug.as_deref()),
+    )
+}
+
+fn metaweblog_post_struct(
+    post: &ContentWithMetasUsersFields,
+    permalinks: &Permalinks,
+    timezone: &FixedOffset,
+) -> XmlValue {
+    let utc = FixedOffset::east_opt(0).unwrap();
+    let text = post.text.as_deref().unwrap_or_default();
+    let (description, more) = match text.split_once("<!--more-->") {
+        Some((d, m)) => (d.trim_end(), m.trim_start()),
+        None => (text, ""),
+    };
+    let link = post_link(permalinks, post);
+    let categories = post
+        .categories
+        .iter()
+        .map(|m| XmlValue::String(m.name.clone().unwrap_or_default()))
+        .collect();
+    let tags: Vec<&str> = post.tags.iter().filter_map(|m| m.name.as_deref()).collect();
+    XmlValue::Struct(vec![
+        ("postid".to_string(), XmlValue::String(post.cid.to_string())),
+        (
+            "userid".to_string(),
+            XmlValue::String(post.author_id.to_string()),
+        ),
+        (
+            "dateCreated".to_string(),
+            format_datetime(post.created, timezone),
+        ),
+        (
+            "date_created_gmt".to_string(),
+            format_datetime(post.created, &utc),
+        ),
+        (
+            "title".to_string(),
+            XmlValue::String(post.title.clone().unwrap_or_default()),
+        ),
+        (
+            "description".to_string(),
+            XmlValue::String(description.to_string()),
+        ),
+        (
+            "mt_text_more".to_string(),
+            XmlValue::String(more.to_string()),
+        ),
+        ("mt_excerpt".to_string(), XmlValue::String(String::new())),
+        ("mt_keywords".to_string(), XmlValue::String(tags.join(","))),
+        (
+            "mt_allow_comments".to_string(),
+            XmlValue::Int((post.allow_comment == "1") as i64),
+        ),
+        (
+            "mt_allow_pings".to_string(),
+            XmlValue::Int((post.allow_ping == "1") as i64),
+        ),
+        ("categories".to_string(), XmlValue::Array(categories)),
+        ("link".to_string(), XmlValue::String(link.clone())),
+        ("permaLink".to_string(), XmlValue::String(link)),
+        (
+            "wp_slug".to_string(),
+            XmlValue::String(post.slug.clone().unwrap_or_default()),
+        ),
+        (
+            "wp_password".to_string(),
+            XmlValue::String(post.password.clone().unwrap_or_default()),
+        ),
+        (
+            "post_status".to_string(),
+            XmlValue::String(from_status(&post.status).to_string()),
+        ),
+    ])
+}
+
+fn wp_post_struct(
+    post: &ContentWithMetasUsersFields,
+    permalinks: &Permalinks,
+    timezone: &FixedOffset,
+) -> XmlValue {
+    let utc = FixedOffset::east_opt(0).unwrap();
+    let open = |allow: &str| if allow == "1" { "open" } else { "closed" }.to_string();
+    let terms = post
+        .categories
+        .iter()
+        .map(|m| (m, "category"))
+        .chain(post.tags.iter().map(|m| (m, "post_tag")))
+        .map(|(m, taxonomy)| {
+            XmlValue::Struct(vec![
+                ("term_id".to_string(), XmlValue::String(m.mid.to_string())),
+                (
+                    "name".to_string(),
+                    XmlValue::String(m.name.clone().unwrap_or_default()),
+                ),
+                (
+                    "slug".to_string(),
+                    XmlValue::String(m.slug.clone().unwrap_or_default()),
+                ),
+                (
+                    "taxonomy".to_string(),
+                    XmlValue::String(taxonomy.to_string()),
+                ),
+            ])
+        })
+        .collect();
+    XmlValue::Struct(vec![
+        (
+            "post_id".to_string(),
+            XmlValue::String(post.cid.to_string()),
+        ),
+        (
+            "post_title".to_string(),
+            XmlValue::String(post.title.clone().unwrap_or_default()),
+        ),
+        (
+            "post_date".to_string(),
+            format_datetime(post.created, timezone),
+        ),
+        (
+            "post_date_gmt".to_string(),
+            format_datetime(post.created, &utc),
+        ),
+        (
+            "post_modified".to_string(),
+            format_datetime(post.modified, timezone),
+        ),
+        (
+            "post_modified_gmt".to_string(),
+            format_datetime(post.modified, &utc),
+        ),
+        (
+            "post_status".to_string(),
+            XmlValue::String(from_status(&post.status).to_string()),
+        ),
+        (
+            "post_type".to_string(),
+            XmlValue::String(post.r#type.clone()),
+        ),
+        (
+            "post_name".to_string(),
+            XmlValue::String(post.slug.clone().unwrap_or_default()),
+        ),
+        (
+            "post_author".to_string(),
+            XmlValue::String(post.author_id.to_string()),
+        ),
+        (
+            "post_password".to_string(),
+            XmlValue::String(post.password.clone().unwrap_or_default()),
+        ),
+        (
+            "post_content".to_string(),
+            XmlValue::String(post.text.clone().unwrap_or_default()),
+        ),
+        (
+            "link".to_string(),
+            XmlValue::String(post_link(permalinks, post)),
+        ),
+        (
+            "comment_status".to_string(),
+            XmlValue::String(open(&post.allow_comment)),
+        ),
+        (
+            "ping_status".to_string(),
+            XmlValue::String(open(&post.allow_ping)),
+        ),
+        ("terms".to_string(), XmlValue::Array(terms)),
+    ])
+}
+
+/// `blogger.getUsersBlogs` and `wp.getUsersBlogs`, the site is the only blog.
+pub async fn get_users_blogs(
+    state: &AppState,
+    options: &HashMap<String, String>,
+    params: &[XmlValue],
+    client_ip: IpAddr,
+    index: usize,
+) -> Result<XmlValue, Fault> {
+    let user = login(state, params, index, client_ip).await?;
+    let permalinks = Permalinks::from_options(options);
+    let name = options.get("title").cloned().unwrap_or_default();
+    Ok(XmlValue::Array(vec![XmlValue::Struct(vec![
+        (
+            "isAdmin".to_string(),
+            XmlValue::Boolean(user.group == "administrator"),
+        ),
+        (
+            "url".to_string(),
+            XmlValue::String(format!("{}/", permalinks.site_url)),
+        ),
+        ("blogid".to_string(), XmlValue::String("1".to_string())),
+        ("blogName".to_string(), XmlValue::String(name)),
+    ])]))
+}
+
+/// `metaWeblog.getCategories` and `wp.getCategories`.
+pub async fn get_categories(
+    state: &AppState,
+    options: &HashMap<String, String>,
+    params: &[XmlValue],
+    client_ip: IpAddr,
+) -> Result<XmlValue, Fault> {
+    let _ = login(state, params, 1, client_ip).await?;
+    let permalinks = Permalinks::from_options(options);
+    let categories = categories_db::get_categories(state).await?;
+    let results = categories
+        .into_iter()
+        .map(|m| {
+            let name = m.name.unwrap_or_default();
+            let slug = m.slug.unwrap_or_default();
+            XmlValue::Struct(vec![
+                (
+                    "categoryId".to_string(),
+                    XmlValue::String(m.mid.to_string()),
+                ),
+                (
+                    "parentId".to_string(),
+                    XmlValue::String(m.parent.to_string()),
+                ),
+                ("categoryName".to_string(), XmlValue::String(name.clone())),
+                (
+                    "categoryDescription".to_string(),
+                    XmlValue::String(m.description.unwrap_or_default()),
+                ),
+                ("description".to_string(), XmlValue::String(name)),
+                (
+                    "htmlUrl".to_string(),
+                    XmlValue::String(permalinks.meta("category", m.mid, &slug)),
+                ),
+            ])
+        })
+        .collect();
+    Ok(XmlValue::Array(results))
+}
+
+/// `metaWeblog.newPost`, returns the cid of the new post.
+pub async fn metaweblog_new_post(
+    state: &Arc<AppState>,
+    options: &HashMap<String, String>,
+    params: &[XmlValue],
+    client_ip: IpAddr,
+) -> Result<XmlValue, Fault> {
+    let user = login(state, params, 1, client_ip).await?;
+    let publish = params.get(4).and_then(|p| p.as_bool());
+    let mut post_create = new_post_create();
+    apply_metaweblog_struct(
+        &mut post_create,
+        param(params, 3)?,
+        publish,
+        &get_timezone(options),
+    )?;
+
+    let cid = create_post(state, options, &user, post_create).await?;
+    Ok(XmlValue::String(cid.to_string()))
+}
+
+/// `metaWeblog.editPost`.
+pub async fn metaweblog_edit_post(
+    state: &Arc<AppState>,
+    options: &HashMap<String, String>,
+    params: &[XmlValue],
+    client_ip: IpAddr,
+    if_match: &str,
+) -> Result<XmlValue, Fault> {
+    let user = login(state, params, 1, client_ip).await?;
+    let exist_post = get_exist_post_with_user(state, param_cid(params, 0)?, &user).await?;
+    let publish = params.get(4).and_then(|p| p.as_bool());
+    let mut post_modify = get_post_create_by_content(&exist_post);
+    apply_metaweblog_struct(
+        &mut post_modify,
+        param(params, 3)?,
+        publish,
+        &get_timezone(options),
+    )?;
+
+    modify_post(state, options, &user, &exist_post, post_modify, if_match).await?;
+    Ok(XmlValue::Boolean(true))
+}
+
+/// `metaWeblog.getPost`.
+pub async fn metaweblog_get_post(
+    state: &AppState,
+    options: &HashMap<String, String>,
+    params: &[XmlValue],
+    client_ip: IpAddr,
+) -> Result<XmlValue, Fault> {
+    let user = login(state, params, 1, client_ip).await?;
+    let exist_post = get_exist_post_with_user(state, param_cid(params, 0)?, &user).await?;
+    let post = get_post_with_metas(state, &exist_post).await?;
+    let permalinks = Permalinks::from_options(options);
+    Ok(metaweblog_post_struct(
+        &post,
+        &permalinks,
+        &get_timezone(options),
+    ))
+}
+
+async fn get_recent_posts(
+    state: &AppState,
+    user: &user::Model,
+    number: Option<i64>,
+) -> Result<Vec<ContentWithMetasUsersFields>, Fault> {
+    let page_size = number.unwrap_or(10).clamp(1, MAX_RECENT_POSTS) as u64;
+    // contributors only see their own posts
+    let own = !is_admin(user);
+    Ok(
+        posts_db::get_contents_with_metas_user_and_fields_by_filter_and_list_query(
+            state, true, own, user, page_size, 1, "-cid", true, false,
+        )
+        .await?,
+    )
+}
+
+/// `metaWeblog.getRecentPosts`.
+pub async fn metaweblog_get_recent_posts(
+    state: &AppState,
+    options: &HashMap<String, String>,
+    params: &[XmlValue],
+    client_ip: IpAddr,
+) -> Result<XmlValue, Fault> {
+    let user = login(state, params, 1, client_ip).await?;
+    let number = params.get(3).and_then(|p| p.as_i64());
+    let posts = get_recent_posts(state, &user, number).await?;
+    let permalinks = Permalinks::from_options(options);
+    let timezone = get_timezone(options);
+    Ok(XmlValue::Array(
+        posts
+            .iter()
+            .map(|p| metaweblog_post_struct(p, &permalinks, &timezone))
+            .collect(),
+    ))
+}
+
+/// `metaWeblog.newMediaObject` and `wp.uploadFile`, the file is saved as an attachment.
+pub async fn new_media_object(
+    state: &AppState,
+    options: &HashMap<String, String>,
+    params: &[XmlValue],
+    client_ip: IpAddr,
+) -> Result<XmlValue, Fault> {
+    let user = login(state, params, 1, client_ip).await?;
+    let data = param(params, 3)?;
+    // some clients send the name with a directory
+    let name = member_str(data, "name")
+        .and_then(|n| n.rsplit('/').next())
+        .filter(|n| !n.is_empty())
+        .ok_or_else(invalid_params)?;
+    let bits = match data.get("bits") {
+        Some(XmlValue::Base64(b)) => b.clone(),
+        _ => return Err(invalid_params()),
+    };
+    let mime = member_str(data, "type").unwrap_or("application/octet-stream");
+
+    let stream = futures::stream::once(async move { Ok::<_, std::io::Error>(Bytes::from(bits)) });
+    let (cid, text) =
+        save_attachment(state, user.uid, name.to_string(), mime.to_string(), stream).await?;
+    let permalinks = Permalinks::from_options(options);
+    Ok(XmlValue::Struct(vec![
+        ("id".to_string(), XmlValue::String(cid.to_string())),
+        ("file".to_string(), XmlValue::String(text.name)),
+        (
+            "url".to_string(),
+            XmlValue::String(format!("{}{}", permalinks.site_url, text.path)),
+        ),
+        ("type".to_string(), XmlValue::String(text.mime)),
+    ]))
+}
+
+/// `blogger.deletePost` and `wp.deletePost`, the post is moved to the trash.
+pub async fn delete_post(
+    state: &AppState,
+    params: &[XmlValue],
+    client_ip: IpAddr,
+    cid_index: usize,
+    index: usize,
+) -> Result<XmlValue, Fault> {
+    let user = login(state, params, index, client_ip).await?;
+    let exist_post = get_exist_post_with_user(state, param_cid(params, cid_index)?, &user).await?;
+    let _ = trash_db::move_content_to_trash_with_uid(state, &exist_post, user.uid, false).await?;
+    Ok(XmlValue::Boolean(true))
+}
+
+/// `wp.newPost`, returns the cid of the new post.
+pub async fn wp_new_post(
+    state: &Arc<AppState>,
+    options: &HashMap<String, String>,
+    params: &[XmlValue],
+    client_ip: IpAddr,
+) -> Result<XmlValue, Fault> {
+    let user = login(state, params, 1, client_ip).await?;
+    let mut post_create = new_post_create();
+    // wordpress creates drafts unless told otherwise
+    post_create.status = "draft".to_string();
+    apply_wp_struct(&mut post_create, param(params, 3)?, &get_timezone(options))?;
+
+    let cid = create_post(state, options, &user, post_create).await?;
+    Ok(XmlValue::String(cid.to_string()))
+}
+
+/// `wp.editPost`, `if_not_modified_since` refuses the edit once the post changed later.
+pub async fn wp_edit_post(
+    state: &Arc<AppState>,
+    options: &HashMap<String, String>,
+    params: &[XmlValue],
+    client_ip: IpAddr,
+    if_match: &str,
+) -> Result<XmlValue, Fault> {
+    let user = login(state, params, 1, client_ip).await?;
+    let exist_post = get_exist_post_with_user(state, param_cid(params, 3)?, &user).await?;
+    let content = param(params, 4)?;
+    if let Some(XmlValue::DateTime(d)) = content.get("if_not_modified_since") {
+        let utc = FixedOffset::east_opt(0).unwrap();
+        if parse_datetime(d, &utc).is_some_and(|t| exist_post.modified > t) {
+            return Err(modified_fault());
+        }
+    }
+    let mut post_modify = get_post_create_by_content(&exist_post);
+    apply_wp_struct(&mut post_modify, content, &get_timezone(options))?;
+
+    modify_post(state, options, &user, &exist_post, post_modify, if_match).await?;
+    Ok(XmlValue::Boolean(true))
+}
+
+/// `wp.getPost`.
+pub async fn wp_get_post(
+    state: &AppState,
+    options: &HashMap<String, String>,
+    params: &[XmlValue],
+    client_ip: IpAddr,
+) -> Result<XmlValue, Fault> {
+    let user = login(state, params, 1, client_ip).await?;
+    let exist_post = get_exist_post_with_user(state, param_cid(params, 3)?, &user).await?;
+    let post = get_post_with_metas(state, &exist_post).await?;
+    let permalinks = Permalinks::from_options(options);
+    Ok(wp_post_struct(&post, &permalinks, &get_timezone(options)))
+}
+
+/// `wp.getPosts`, only the `number` filter is supported.
+pub async fn wp_get_posts(
+    state: &AppState,
+    options: &HashMap<String, String>,
+    params: &[XmlValue],
+    client_ip: IpAddr,
+) -> Result<XmlValue, Fault> {
+    let user = login(state, params, 1, client_ip).await?;
+    let number = params
+        .get(3)
+        .and_then(|p| p.get("number"))
+        .and_then(|n| n.as_i64());
+    let posts = get_recent_posts(state, &user, number).await?;
+    let permalinks = Permalinks::from_options(options);
+    let timezone = get_timezone(options);
+    Ok(XmlValue::Array(
+        posts
+            .iter()
+            .map(|p| wp_post_struct(p, &permalinks, &timezone))
+            .collect(),
+    ))
+}
@@ -0,0 +1,7 @@
+mod blog;
+pub mod models;
+mod urls;
+pub mod utils;
+mod views;
+
+pub use urls::xmlrpc_routers;
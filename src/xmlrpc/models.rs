@@ -0,0 +1,91 @@
+use crate::common::errors::FieldError;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum XmlValue {
+    Int(i64),
+    Boolean(bool),
+    String(String),
+    Double(f64),
+    DateTime(String),
+    Base64(Vec<u8>),
+    Struct(Vec<(String, XmlValue)>),
+    Array(Vec<XmlValue>),
+    Nil,
+}
+
+impl XmlValue {
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            XmlValue::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Clients send ids either as int or as string.
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            XmlValue::Int(i) => Some(*i),
+            XmlValue::String(s) => s.trim().parse().ok(),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            XmlValue::Boolean(b) => Some(*b),
+            XmlValue::Int(i) => Some(*i != 0),
+            XmlValue::String(s) => match s.as_str() {
+                "1" | "true" | "open" => Some(true),
+                "0" | "false" | "closed" => Some(false),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[XmlValue]> {
+        match self {
+            XmlValue::Array(values) => Some(values),
+            _ => None,
+        }
+    }
+
+    /// Returns the member of a struct.
+    pub fn get(&self, name: &str) -> Option<&XmlValue> {
+        match self {
+            XmlValue::Struct(members) => members.iter().find(|(n, _)| n == name).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fault {
+    pub code: i32,
+    pub message: String,
+}
+
+impl Fault {
+    pub fn new(code: i32, message: &str) -> Self {
+        Fault {
+            code,
+            message: message.to_string(),
+        }
+    }
+}
+
+impl From<FieldError> for Fault {
+    fn from(e: FieldError) -> Self {
+        match e {
+            FieldError::NotFound(field) => Fault::new(404, &format!("{} not found", field)),
+            FieldError::AlreadyExist(field) => {
+                Fault::new(-32602, &format!("{} already exist", field))
+            }
+            FieldError::PermissionDeny => Fault::new(401, "permission denied"),
+            FieldError::InvalidParams(field) => Fault::new(-32602, &format!("invalid {}", field)),
+            FieldError::PreconditionFailed(_, _) => super::blog::modified_fault(),
+            FieldError::DatabaseFailed(s) => Fault::new(500, &s),
+            _ => Fault::new(500, "request failed"),
+        }
+    }
+}
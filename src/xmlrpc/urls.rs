@@ -0,0 +1,14 @@
+use axum::{routing::post, Router};
+use std::sync::Arc;
+
+use super::views;
+use crate::AppState;
+
+pub fn xmlrpc_routers(ro: bool) -> Router<Arc<AppState>> {
+    let xmlrpc_route = Router::new();
+    if !ro {
+        xmlrpc_route.route("/api/xmlrpc", post(views::xmlrpc))
+    } else {
+        xmlrpc_route
+    }
+}
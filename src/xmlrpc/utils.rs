@@ -0,0 +1,361 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::{DateTime, FixedOffset, NaiveDateTime, TimeZone};
+
+use super::models::{Fault, XmlValue};
+use crate::common::utils::escape_xml;
+
+const MAX_DEPTH: usize = 64;
+
+#[derive(Debug, Default)]
+struct XmlElement {
+    name: String,
+    children: Vec<XmlElement>,
+    text: String,
+}
+
+impl XmlElement {
+    fn child(&self, name: &str) -> Option<&XmlElement> {
+        self.children.iter().find(|c| c.name == name)
+    }
+}
+
+/// Reads the few XML constructs an XML-RPC request uses, DTDs are refused.
+struct XmlParser<'a> {
+    input: &'a str,
+    pos: usize,
+}
+
+impl<'a> XmlParser<'a> {
+    fn rest(&self) -> &'a str {
+        &self.input[self.pos..]
+    }
+
+    fn skip_until(&mut self, end: &str) -> Result<&'a str, String> {
+        let rest = self.rest();
+        let index = rest.find(end).ok_or(format!("missing {}", end))?;
+        self.pos += index + end.len();
+        Ok(&rest[..index])
+    }
+
+    fn skip_misc(&mut self) -> Result<(), String> {
+        loop {
+            let trimmed = self.rest().trim_start();
+            self.pos = self.input.len() - trimmed.len();
+            if trimmed.starts_with("<?") {
+                self.skip_until("?>")?;
+            } else if trimmed.starts_with("<!--") {
+                self.skip_until("-->")?;
+            } else if trimmed.starts_with("<!") {
+                return Err("doctype is not allowed".to_string());
+            } else {
+                return Ok(());
+            }
+        }
+    }
+
+    fn parse_document(&mut self) -> Result<XmlElement, String> {
+        self.skip_misc()?;
+        let root = self.parse_element(0)?;
+        self.skip_misc()?;
+        if !self.rest().is_empty() {
+            return Err("content after root element".to_string());
+        }
+        Ok(root)
+    }
+
+    fn parse_element(&mut self, depth: usize) -> Result<XmlElement, String> {
+        if depth > MAX_DEPTH {
+            return Err("too deep".to_string());
+        }
+        if !self.rest().starts_with('<') {
+            return Err("element expected".to_string());
+        }
+        let tag = self.skip_until(">")?;
+        let self_closing = tag.ends_with('/');
+        let name = tag[1..]
+            .trim_end_matches('/')
+            .split_whitespace()
+            .next()
+            .unwrap_or_default();
+        let mut element = XmlElement {
+            name: name.to_string(),
+            ..Default::default()
+        };
+        if self_closing {
+            return Ok(element);
+        }
+
+        loop {
+            let rest = self.rest();
+            if rest.starts_with("</") {
+                let end = self.skip_until(">")?;
+                if end[2..].trim() != element.name {
+                    return Err(format!("mismatched end of {}", element.name));
+                }
+                return Ok(element);
+            } else if rest.starts_with("<![CDATA[") {
+                self.pos += 9;
+                let text = self.skip_until("]]>")?;
+                element.text.push_str(text);
+            } else if rest.starts_with("<!--") {
+                self.skip_until("-->")?;
+            } else if rest.starts_with("<?") {
+                self.skip_until("?>")?;
+            } else if rest.starts_with('<') {
+                element.children.push(self.parse_element(depth + 1)?);
+            } else if rest.is_empty() {
+                return Err(format!("unclosed {}", element.name));
+            } else {
+                let index = rest.find('<').unwrap_or(rest.len());
+                element.text.push_str(&unescape_xml(&rest[..index])?);
+                self.pos += index;
+            }
+        }
+    }
+}
+
+fn unescape_xml(s: &str) -> Result<String, String> {
+    let mut res = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(start) = rest.find('&') {
+        res.push_str(&rest[..start]);
+        let end = rest[start..].find(';').ok_or("invalid entity")? + start;
+        let entity = &rest[start + 1..end];
+        let c = match entity {
+            "lt" => '<',
+            "gt" => '>',
+            "amp" => '&',
+            "quot" => '"',
+            "apos" => '\'',
+            _ => {
+                let code = if let Some(hex) = entity.strip_prefix("#x") {
+                    u32::from_str_radix(hex, 16).ok()
+                } else if let Some(dec) = entity.strip_prefix('#') {
+                    dec.parse::<u32>().ok()
+                } else {
+                    None
+                };
+                code.and_then(char::from_u32).ok_or("invalid entity")?
+            }
+        };
+        res.push(c);
+        rest = &rest[end + 1..];
+    }
+    res.push_str(rest);
+    Ok(res)
+}
+
+fn decode_value(element: &XmlElement) -> Result<XmlValue, String> {
+    let typed = match element.children.first() {
+        Some(t) => t,
+        // a value without a type is a string
+        None => return Ok(XmlValue::String(element.text.clone())),
+    };
+    let text = typed.text.trim();
+    let value = match typed.name.as_str() {
+        "int" | "i4" | "i8" => XmlValue::Int(text.parse().map_err(|_| "invalid int")?),
+        "boolean" => XmlValue::Boolean(match text {
+            "1" | "true" => true,
+            "0" | "false" => false,
+            _ => return Err("invalid boolean".to_string()),
+        }),
+        "string" => XmlValue::String(typed.text.clone()),
+        "double" => XmlValue::Double(text.parse().map_err(|_| "invalid double")?),
+        "dateTime.iso8601" => XmlValue::DateTime(text.to_string()),
+        "base64" => {
+            let data: String = text.split_whitespace().collect();
+            XmlValue::Base64(STANDARD.decode(data).map_err(|_| "invalid base64")?)
+        }
+        "struct" => {
+            let mut members = vec![];
+            for member in typed.children.iter().filter(|c| c.name == "member") {
+                let name = member.child("name").ok_or("member without name")?;
+                let value = member.child("value").ok_or("member without value")?;
+                members.push((name.text.trim().to_string(), decode_value(value)?));
+            }
+            XmlValue::Struct(members)
+        }
+        "array" => {
+            let data = typed.child("data").ok_or("array without data")?;
+            XmlValue::Array(
+                data.children
+                    .iter()
+                    .filter(|c| c.name == "value")
+                    .map(decode_value)
+                    .collect::<Result<Vec<_>, _>>()?,
+            )
+        }
+        "nil" => XmlValue::Nil,
+        _ => return Err(format!("unknown type {}", typed.name)),
+    };
+    Ok(value)
+}
+
+/// Parses a `methodCall` into the method name and its params.
+pub fn parse_method_call(body: &str) -> Result<(String, Vec<XmlValue>), Fault> {
+    let parse = || -> Result<(String, Vec<XmlValue>), String> {
+        let root = XmlParser {
+            input: body,
+            pos: 0,
+        }
+        .parse_document()?;
+        if root.name != "methodCall" {
+            return Err("methodCall expected".to_string());
+        }
+        let method = root.child("methodName").ok_or("methodName expected")?;
+        let mut params = vec![];
+        if let Some(p) = root.child("params") {
+            for param in p.children.iter().filter(|c| c.name == "param") {
+                let value = param.child("value").ok_or("param without value")?;
+                params.push(decode_value(value)?);
+            }
+        }
+        Ok((method.text.trim().to_string(), params))
+    };
+    parse().map_err(|e| Fault::new(-32700, &format!("parse error: {}", e)))
+}
+
+fn encode_value(value: &XmlValue, res: &mut String) {
+    res.push_str("<value>");
+    match value {
+        XmlValue::Int(i) => res.push_str(&format!("<int>{}</int>", i)),
+        XmlValue::Boolean(b) => res.push_str(&format!("<boolean>{}</boolean>", *b as u8)),
+        XmlValue::String(s) => res.push_str(&format!("<string>{}</string>", escape_xml(s))),
+        XmlValue::Double(d) => res.push_str(&format!("<double>{}</double>", d)),
+        XmlValue::DateTime(d) => {
+            res.push_str(&format!("<dateTime.iso8601>{}</dateTime.iso8601>", d))
+        }
+        XmlValue::Base64(b) => res.push_str(&format!("<base64>{}</base64>", STANDARD.encode(b))),
+        XmlValue::Struct(members) => {
+            res.push_str("<struct>");
+            for (name, value) in members.iter() {
+                res.push_str(&format!("<member><name>{}</name>", escape_xml(name)));
+                encode_value(value, res);
+                res.push_str("</member>");
+            }
+            res.push_str("</struct>");
+        }
+        XmlValue::Array(values) => {
+            res.push_str("<array><data>");
+            for value in values.iter() {
+                encode_value(value, res);
+            }
+            res.push_str("</data></array>");
+        }
+        XmlValue::Nil => res.push_str("<nil/>"),
+    }
+    res.push_str("</value>");
+}
+
+pub fn build_method_response(value: &XmlValue) -> String {
+    let mut res =
+        String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<methodResponse><params><param>");
+    encode_value(value, &mut res);
+    res.push_str("</param></params></methodResponse>");
+    res
+}
+
+pub fn build_fault_response(fault: &Fault) -> String {
+    let mut res =
+        String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<methodResponse><fault>");
+    let value = XmlValue::Struct(vec![
+        ("faultCode".to_string(), XmlValue::Int(fault.code as i64)),
+        (
+            "faultString".to_string(),
+            XmlValue::String(fault.message.clone()),
+        ),
+    ]);
+    encode_value(&value, &mut res);
+    res.push_str("</fault></methodResponse>");
+    res
+}
+
+/// Parses a `dateTime.iso8601`, times without offset are in `timezone`.
+pub fn parse_datetime(s: &str, timezone: &FixedOffset) -> Option<u32> {
+    let s = s.trim();
+    if let Ok(dt) = DateTime::parse_from_rfc3339(s) {
+        return u32::try_from(dt.timestamp()).ok();
+    }
+    let (s, timezone) = match s.strip_suffix('Z') {
+        Some(s) => (s, FixedOffset::east_opt(0).unwrap()),
+        None => (s, *timezone),
+    };
+    ["%Y%m%dT%H:%M:%S", "%Y-%m-%dT%H:%M:%S", "%Y%m%dT%H%M%S"]
+        .iter()
+        .find_map(|f| NaiveDateTime::parse_from_str(s, f).ok())
+        .and_then(|dt| timezone.from_local_datetime(&dt).single())
+        .and_then(|dt| u32::try_from(dt.timestamp()).ok())
+}
+
+pub fn format_datetime(timestamp: u32, timezone: &FixedOffset) -> XmlValue {
+    let dt = DateTime::from_timestamp(timestamp as i64, 0)
+        .unwrap_or_default()
+        .with_timezone(timezone);
+    XmlValue::DateTime(dt.format("%Y%m%dT%H:%M:%S").to_string())
+}
+
+#[test]
+fn test_parse_method_call() {
+    let body = r#"<?xml version="1.0"?>
+<!-- comment -->
+<methodCall>
+  <methodName>test.echo</methodName>
+  <params>
+    <param><value><string>a &amp; b</string></value></param>
+    <param><value>plain &#x3C;</value></param>
+    <param><value><i4>42</i4></value></param>
+    <param><value><struct>
+      <member><name>ok</name><value><boolean>1</boolean></value></member>
+      <member><name>bits</name><value><base64>aGk=</base64></value></member>
+    </struct></value></param>
+    <param><value><array><data><value><double>1.5</double></value></data></array></value></param>
+    <param><value><string><![CDATA[<p>x</p>]]></string></value></param>
+  </params>
+</methodCall>"#;
+    let (method, params) = parse_method_call(body).unwrap();
+    assert_eq!("test.echo", method);
+    assert_eq!(XmlValue::String("a & b".to_string()), params[0]);
+    assert_eq!(XmlValue::String("plain <".to_string()), params[1]);
+    assert_eq!(XmlValue::Int(42), params[2]);
+    assert_eq!(
+        XmlValue::Struct(vec![
+            ("ok".to_string(), XmlValue::Boolean(true)),
+            ("bits".to_string(), XmlValue::Base64(b"hi".to_vec())),
+        ]),
+        params[3]
+    );
+    assert_eq!(XmlValue::Array(vec![XmlValue::Double(1.5)]), params[4]);
+    assert_eq!(Some("<p>x</p>"), params[5].as_str());
+
+    let mut encoded = String::new();
+    encode_value(&params[3], &mut encoded);
+    let body = format!(
+        "<methodCall><methodName>m</methodName><params><param>{}</param></params></methodCall>",
+        encoded
+    );
+    assert_eq!(params[3], parse_method_call(&body).unwrap().1[0]);
+
+    assert!(parse_method_call("<!DOCTYPE x><methodCall/>").is_err());
+    assert!(parse_method_call("<methodCall><methodName>a</methodCall>").is_err());
+}
+
+#[test]
+fn test_datetime() {
+    let utc = FixedOffset::east_opt(0).unwrap();
+    let cst = FixedOffset::east_opt(8 * 3600).unwrap();
+    assert_eq!(Some(1666666666), parse_datetime("20221025T02:57:46", &utc));
+    assert_eq!(Some(1666666666), parse_datetime("20221025T10:57:46", &cst));
+    assert_eq!(
+        Some(1666666666),
+        parse_datetime("2022-10-25T02:57:46Z", &cst)
+    );
+    assert_eq!(
+        Some(1666666666),
+        parse_datetime("2022-10-25T10:57:46+08:00", &utc)
+    );
+    assert_eq!(None, parse_datetime("yesterday", &utc));
+    assert_eq!(
+        XmlValue::DateTime("20221025T10:57:46".to_string()),
+        format_datetime(1666666666, &cst)
+    );
+}
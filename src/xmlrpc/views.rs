@@ -0,0 +1,131 @@
+use axum::extract::State;
+use axum::http::{header, HeaderMap};
+use axum::response::IntoResponse;
+use axum_client_ip::SecureClientIp;
+use axum_extra::{headers::UserAgent, TypedHeader};
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Arc;
+
+use super::blog;
+use super::models::{Fault, XmlValue};
+use super::utils::{build_fault_response, build_method_response, parse_method_call};
+use crate::comments::pings::{create_ping, get_content_by_target_url, Ping};
+use crate::common::db as common_db;
+use crate::common::errors::FieldError;
+use crate::AppState;
+
+pub async fn xmlrpc(
+    State(state): State<Arc<AppState>>,
+    user_agent: Option<TypedHeader<UserAgent>>,
+    SecureClientIp(client_ip): SecureClientIp,
+    headers: HeaderMap,
+    body: String,
+) -> Result<impl IntoResponse, FieldError> {
+    let options = common_db::get_site_options(&state).await?;
+    // same as typecho, 0 turns xml-rpc off and 1 only turns pingback off
+    let allow_xmlrpc = options
+        .get("allowXmlRpc")
+        .map(|s| s.as_str())
+        .unwrap_or("2");
+    if allow_xmlrpc == "0" {
+        return Err(FieldError::NotFound("xmlrpc".to_string()));
+    }
+
+    let ua = user_agent.map(|u| u.to_string()).unwrap_or_default();
+    // clients rarely know the etag, edits still fail when the post changes while saving
+    let if_match = headers
+        .get(header::IF_MATCH)
+        .and_then(|h| h.to_str().ok())
+        .unwrap_or("*");
+    let result = match parse_method_call(&body) {
+        Ok((method, params)) if method == "pingback.ping" && allow_xmlrpc == "2" => {
+            pingback_ping(&state, &options, &params, client_ip, &ua).await
+        }
+        Ok((method, params)) => {
+            call_method(&state, &options, &method, &params, client_ip, if_match).await
+        }
+        Err(fault) => Err(fault),
+    };
+    let body = match result {
+        Ok(value) => build_method_response(&value),
+        Err(fault) => build_fault_response(&fault),
+    };
+    Ok(([(header::CONTENT_TYPE, "text/xml; charset=utf-8")], body))
+}
+
+async fn call_method(
+    state: &Arc<AppState>,
+    options: &HashMap<String, String>,
+    method: &str,
+    params: &[XmlValue],
+    client_ip: IpAddr,
+    if_match: &str,
+) -> Result<XmlValue, Fault> {
+    match method {
+        "blogger.getUsersBlogs" => {
+            blog::get_users_blogs(state, options, params, client_ip, 1).await
+        }
+        "blogger.deletePost" | "metaWeblog.deletePost" => {
+            blog::delete_post(state, params, client_ip, 1, 2).await
+        }
+        "metaWeblog.newPost" => blog::metaweblog_new_post(state, options, params, client_ip).await,
+        "metaWeblog.editPost" => {
+            blog::metaweblog_edit_post(state, options, params, client_ip, if_match).await
+        }
+        "metaWeblog.getPost" => blog::metaweblog_get_post(state, options, params, client_ip).await,
+        "metaWeblog.getRecentPosts" => {
+            blog::metaweblog_get_recent_posts(state, options, params, client_ip).await
+        }
+        "metaWeblog.getCategories" | "wp.getCategories" => {
+            blog::get_categories(state, options, params, client_ip).await
+        }
+        "metaWeblog.newMediaObject" | "wp.uploadFile" => {
+            blog::new_media_object(state, options, params, client_ip).await
+        }
+        "wp.getUsersBlogs" => blog::get_users_blogs(state, options, params, client_ip, 0).await,
+        "wp.newPost" => blog::wp_new_post(state, options, params, client_ip).await,
+        "wp.editPost" => blog::wp_edit_post(state, options, params, client_ip, if_match).await,
+        "wp.getPost" => blog::wp_get_post(state, options, params, client_ip).await,
+        "wp.getPosts" => blog::wp_get_posts(state, options, params, client_ip).await,
+        "wp.deletePost" => blog::delete_post(state, params, client_ip, 3, 1).await,
+        _ => Err(Fault::new(-32601, "requested method not found")),
+    }
+}
+
+async fn pingback_ping(
+    state: &AppState,
+    options: &HashMap<String, String>,
+    params: &[XmlValue],
+    client_ip: IpAddr,
+    ua: &str,
+) -> Result<XmlValue, Fault> {
+    let (source, target) = match (
+        params.first().and_then(|p| p.as_str()),
+        params.get(1).and_then(|p| p.as_str()),
+    ) {
+        (Some(s), Some(t)) => (s, t),
+        _ => return Err(Fault::new(-32602, "invalid method parameters")),
+    };
+    let ip = client_ip.to_string();
+    let ping = Ping {
+        ping_type: "pingback",
+        source,
+        title: None,
+        excerpt: None,
+        blog_name: None,
+        ip: &ip,
+        ua,
+    };
+    let result = match get_content_by_target_url(state, options, target).await {
+        Ok(content) => create_ping(state, options, &content, ping).await,
+        Err(e) => Err(e),
+    };
+    match result {
+        Ok(_) => Ok(XmlValue::String(format!(
+            "Pingback from {} to {} registered",
+            source, target
+        ))),
+        Err(e) => Err(Fault::new(e.code(), e.message())),
+    }
+}
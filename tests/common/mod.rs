@@ -1,28 +1,43 @@
 use std::env;
+use std::sync::Arc;
 
 use axum::Router;
 use axum::{
     body::Body,
-    http::{self, Request, StatusCode},
+    http::{self, HeaderMap, Request, StatusCode},
 };
+use base64::{engine::general_purpose::STANDARD, Engine};
 use http_body_util::BodyExt;
 use minijinja::Environment;
 use sea_orm::Database;
 use serde_json::{json, Value};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
 use tower::ServiceExt;
 
-use rumo::{app, AppState, INDEX_TPL};
+use rumo::{
+    app, AppState, DefaultHttpClient, SecureClientIpSource, COMMENT_MAIL_TPL, INDEX_TPL,
+    REPLY_MAIL_TPL,
+};
 
-async fn setup_state() -> AppState {
+#[allow(dead_code)]
+pub async fn setup_state() -> AppState {
     let conn = Database::connect(&env::var("DATABASE_URL").unwrap())
         .await
         .unwrap();
 
     let secret_key = env::var("SECRET_KEY").unwrap();
     let access_token_expire_secondes = 3600 * 24 * 30;
+    let excerpt_length = 200;
+    let trash_retention_days = 30;
     let preload_index = false;
     let mut jinja_env = Environment::new();
     jinja_env.add_template("index.html", &INDEX_TPL).unwrap();
+    jinja_env
+        .add_template("mail_comment.txt", COMMENT_MAIL_TPL)
+        .unwrap();
+    jinja_env.add_template("mail_reply.txt", REPLY_MAIL_TPL).unwrap();
     let upload_root = ".".to_string();
     let read_only = false;
 
@@ -30,10 +45,15 @@ async fn setup_state() -> AppState {
         conn,
         secret_key,
         access_token_expire_secondes,
+        excerpt_length,
+        trash_retention_days,
         upload_root,
         read_only,
         preload_index,
         jinja_env,
+        smtp: None,
+        http_client: Arc::new(DefaultHttpClient),
+        client_ip_source: SecureClientIpSource::RightmostXForwardedFor,
     }
 }
 
@@ -59,8 +79,96 @@ pub async fn get(url: &str) -> (StatusCode, Option<Value>) {
     (status_code, body)
 }
 
+#[allow(dead_code)]
+pub async fn get_etag(url: &str) -> (StatusCode, String) {
+    let state = setup_state().await;
+    let app = setup_app(state.clone()).await;
+
+    let request = Request::builder()
+        .method(http::Method::GET)
+        .uri(url)
+        .header(http::header::CONTENT_TYPE, "application/json")
+        .body(Body::empty())
+        .unwrap();
+    let response = app.oneshot(request).await.unwrap();
+    let status_code = response.status();
+    let etag = response
+        .headers()
+        .get(http::header::ETAG)
+        .map(|h| h.to_str().unwrap().to_string())
+        .unwrap_or_default();
+    (status_code, etag)
+}
+
+#[allow(dead_code)]
+pub async fn admin_get_etag(url: &str) -> (StatusCode, String) {
+    let state = setup_state().await;
+    let app = setup_app(state.clone()).await;
+    let login_data = json!({"mail": "admin@local.host", "password": "admin"}).to_string();
+    let request = Request::builder()
+        .method(http::Method::POST)
+        .uri("/api/users/token")
+        .header(http::header::CONTENT_TYPE, "application/json")
+        .body(Body::from(login_data))
+        .unwrap();
+    let response = app.oneshot(request).await.unwrap();
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    let token = body.get("access_token").unwrap().as_str().unwrap();
+
+    let app = setup_app(state.clone()).await;
+
+    let request = Request::builder()
+        .method(http::Method::GET)
+        .uri(url)
+        .header(http::header::CONTENT_TYPE, "application/json")
+        .header(http::header::AUTHORIZATION, format!("Bearer {}", token))
+        .body(Body::empty())
+        .unwrap();
+    let response = app.oneshot(request).await.unwrap();
+    let status_code = response.status();
+    let etag = response
+        .headers()
+        .get(http::header::ETAG)
+        .map(|h| h.to_str().unwrap().to_string())
+        .unwrap_or_default();
+    (status_code, etag)
+}
+
+#[allow(dead_code)]
+pub async fn get_text(url: &str) -> (StatusCode, String) {
+    let state = setup_state().await;
+    let app = setup_app(state.clone()).await;
+
+    let request = Request::builder()
+        .method(http::Method::GET)
+        .uri(url)
+        .body(Body::empty())
+        .unwrap();
+    let response = app.oneshot(request).await.unwrap();
+    let status_code = response.status();
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body = String::from_utf8(body.to_vec()).unwrap_or_default();
+    (status_code, body)
+}
+
 #[allow(dead_code)]
 pub async fn post(url: &str, data: String) -> (StatusCode, Option<Value>) {
+    post_from_ip(url, data, "1.1.1.1, 2.2.2.2").await
+}
+
+#[allow(dead_code)]
+pub async fn post_from_ip(url: &str, data: String, ip: &str) -> (StatusCode, Option<Value>) {
+    let (status_code, _, body) = post_from_ip_with_headers(url, data, ip).await;
+    (status_code, body)
+}
+
+#[allow(dead_code)]
+pub async fn post_from_ip_with_headers(
+    url: &str,
+    data: String,
+    ip: &str,
+) -> (StatusCode, HeaderMap, Option<Value>) {
     let state = setup_state().await;
     let app = setup_app(state.clone()).await;
 
@@ -69,14 +177,15 @@ pub async fn post(url: &str, data: String) -> (StatusCode, Option<Value>) {
         .uri(url)
         .header(http::header::CONTENT_TYPE, "application/json")
         .header("User-Agent", "test")
-        .header("X-Forwarded-For", "1.1.1.1, 2.2.2.2")
+        .header("X-Forwarded-For", ip)
         .body(Body::from(data))
         .unwrap();
     let response = app.oneshot(request).await.unwrap();
     let status_code = response.status();
+    let headers = response.headers().clone();
     let body = response.into_body().collect().await.unwrap().to_bytes();
     let body = serde_json::from_slice(&body).unwrap_or(None);
-    (status_code, body)
+    (status_code, headers, body)
 }
 
 #[allow(dead_code)]
@@ -207,6 +316,43 @@ pub async fn admin_patch(url: &str, data: String) -> (StatusCode, Option<Value>)
     (status_code, body)
 }
 
+#[allow(dead_code)]
+pub async fn admin_patch_if_match(
+    url: &str,
+    data: String,
+    if_match: &str,
+) -> (StatusCode, Option<Value>) {
+    let state = setup_state().await;
+    let app = setup_app(state.clone()).await;
+    let login_data = json!({"mail": "admin@local.host", "password": "admin"}).to_string();
+    let request = Request::builder()
+        .method(http::Method::POST)
+        .uri("/api/users/token")
+        .header(http::header::CONTENT_TYPE, "application/json")
+        .body(Body::from(login_data))
+        .unwrap();
+    let response = app.oneshot(request).await.unwrap();
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    let token = body.get("access_token").unwrap().as_str().unwrap();
+
+    let app = setup_app(state.clone()).await;
+
+    let request = Request::builder()
+        .method(http::Method::PATCH)
+        .uri(url)
+        .header(http::header::CONTENT_TYPE, "application/json")
+        .header(http::header::AUTHORIZATION, format!("Bearer {}", token))
+        .header(http::header::IF_MATCH, if_match)
+        .body(Body::from(data))
+        .unwrap();
+    let response = app.oneshot(request).await.unwrap();
+    let status_code = response.status();
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body = serde_json::from_slice(&body).unwrap_or(None);
+    (status_code, body)
+}
+
 #[allow(dead_code)]
 pub async fn admin_post_file(url: &str, data: Vec<u8>) -> (StatusCode, Option<Value>) {
     let state = setup_state().await;
@@ -287,3 +433,56 @@ pub fn get_multipart(filename: &str, content_type: &str) -> Vec<u8> {
 
     data
 }
+
+/// A local SMTP sink, every accepted message is sent as (recipient, decoded body).
+#[allow(dead_code)]
+pub async fn start_smtp_sink() -> (u16, mpsc::UnboundedReceiver<(String, String)>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let (tx, rx) = mpsc::unbounded_channel();
+
+    tokio::spawn(async move {
+        loop {
+            let (stream, _) = listener.accept().await.unwrap();
+            let tx = tx.clone();
+            tokio::spawn(async move {
+                let mut stream = BufReader::new(stream);
+                stream.get_mut().write_all(b"220 sink\r\n").await.unwrap();
+                let mut recipient = String::new();
+                let mut data: Option<Vec<String>> = None;
+                loop {
+                    let mut line = String::new();
+                    if stream.read_line(&mut line).await.unwrap_or(0) == 0 {
+                        break;
+                    }
+                    let line = line.trim_end().to_string();
+                    if let Some(lines) = data.as_mut() {
+                        if line != "." {
+                            lines.push(line);
+                            continue;
+                        }
+                        let lines = data.take().unwrap();
+                        let start = lines.iter().position(|l| l.is_empty()).unwrap();
+                        let headers = lines[..start].join("\n");
+                        let body = STANDARD.decode(lines[start + 1..].concat()).unwrap();
+                        let body = format!("{}\n\n{}", headers, String::from_utf8(body).unwrap());
+                        tx.send((recipient.clone(), body)).unwrap();
+                        stream.get_mut().write_all(b"250 ok\r\n").await.unwrap();
+                    } else if line.starts_with("RCPT TO:") {
+                        recipient = line[9..line.len() - 1].to_string();
+                        stream.get_mut().write_all(b"250 ok\r\n").await.unwrap();
+                    } else if line == "DATA" {
+                        data = Some(vec![]);
+                        stream.get_mut().write_all(b"354 go\r\n").await.unwrap();
+                    } else if line == "QUIT" {
+                        stream.get_mut().write_all(b"221 bye\r\n").await.unwrap();
+                        break;
+                    } else {
+                        stream.get_mut().write_all(b"250 ok\r\n").await.unwrap();
+                    }
+                }
+            });
+        }
+    });
+    (port, rx)
+}
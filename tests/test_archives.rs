@@ -0,0 +1,56 @@
+use axum::http::StatusCode;
+use chrono::{Datelike, FixedOffset, Utc};
+use serde_json::json;
+
+mod common;
+use common::{admin_post, get};
+
+#[tokio::test]
+async fn create_then_list_archives_success() {
+    let data = json!({
+        "title": "testArchivePost",
+        "slug": "test-archive-post",
+        "created": 1666666666,
+        "text": "testText",
+        "status": "publish",
+    })
+    .to_string();
+    let (status_code, _) = admin_post("/api/posts/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let now = Utc::now().with_timezone(&FixedOffset::east_opt(28800).unwrap());
+
+    let (status_code, body) = get("/api/archives/").await;
+    assert_eq!(status_code, StatusCode::OK);
+
+    let body = body.unwrap();
+    let results = body.get("results").unwrap().as_array().unwrap();
+    let archive = results
+        .iter()
+        .find(|a| a["year"] == now.year() && a["month"] == now.month())
+        .unwrap();
+    assert!(archive["count"].as_u64().unwrap() >= 1);
+
+    let url = format!("/api/archives/{}/{}/", now.year(), now.month());
+    let (status_code, body) = get(&url).await;
+    assert_eq!(status_code, StatusCode::OK);
+
+    let body = body.unwrap();
+    let results = body.get("results").unwrap().as_array().unwrap();
+    let post = results
+        .iter()
+        .find(|p| p["slug"] == "test-archive-post")
+        .unwrap();
+    // author fields come from the author, not from the visitor listing
+    assert_eq!("administrator", post["group"]);
+
+    let url = format!("/api/archives/{}/", now.year() - 100);
+    let (status_code, body) = get(&url).await;
+    assert_eq!(status_code, StatusCode::OK);
+
+    let body = body.unwrap();
+    assert_eq!(body.get("all_count").unwrap().as_u64().unwrap(), 0);
+
+    let (status_code, _) = get("/api/archives/2023/2/29/").await;
+    assert_eq!(status_code, StatusCode::BAD_REQUEST);
+}
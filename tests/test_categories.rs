@@ -158,3 +158,69 @@ async fn create_then_delete_category_post_success() {
     let new_count = body.get("all_count").unwrap().as_u64().unwrap();
     assert!(new_count < count);
 }
+
+#[tokio::test]
+async fn create_then_list_categories_tree_success() {
+    let data = json!({"name": "testTreeParent", "slug": "test-tree-parent"}).to_string();
+    let (status_code, _) = admin_post("/api/categories/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+    let (_, body) = get("/api/categories/test-tree-parent").await;
+    let parent = body.unwrap()["mid"].as_u64().unwrap();
+
+    let data =
+        json!({"name": "testTreeChild", "slug": "test-tree-child", "parent": parent}).to_string();
+    let (status_code, _) = admin_post("/api/categories/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+    let (_, body) = get("/api/categories/test-tree-child").await;
+    let child = body.unwrap()["mid"].as_u64().unwrap();
+
+    let data = json!({"name": "testTreeGrandchild", "slug": "test-tree-grandchild", "parent": child})
+        .to_string();
+    let (status_code, _) = admin_post("/api/categories/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+    let (_, body) = get("/api/categories/test-tree-grandchild").await;
+    let grandchild = body.unwrap()["mid"].as_u64().unwrap();
+
+    for mid in [parent, grandchild] {
+        let data = json!({"name": "testTreeParent", "slug": "test-tree-parent", "parent": mid})
+            .to_string();
+        let (status_code, _) = admin_patch("/api/categories/test-tree-parent", data).await;
+        assert_eq!(status_code, StatusCode::BAD_REQUEST);
+    }
+
+    let data = json!({
+        "title": "testPostTree",
+        "slug": "test-post-tree",
+        "created": 1666666666,
+        "text": "testText",
+        "status": "publish",
+        "categories": ["test-tree-child", "test-tree-grandchild"],
+    })
+    .to_string();
+    let (status_code, _) = admin_post("/api/posts/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let (_, body) = get("/api/categories/test-tree-parent/posts/").await;
+    assert_eq!(body.unwrap()["all_count"], 0);
+    let (_, body) = get("/api/categories/test-tree-parent/posts/?descendants=true").await;
+    assert_eq!(body.unwrap()["all_count"], 1);
+
+    let (status_code, body) = get("/api/categories/tree").await;
+    assert_eq!(status_code, StatusCode::OK);
+    let body = body.unwrap();
+    let node = body["results"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|n| n["slug"] == "test-tree-parent")
+        .unwrap();
+    assert_eq!(node["posts_count"], 1);
+    assert_eq!(node["children"][0]["slug"], "test-tree-child");
+    assert_eq!(node["children"][0]["posts_count"], 1);
+    assert_eq!(node["children"][0]["children"][0]["slug"], "test-tree-grandchild");
+
+    let (status_code, _) = admin_delete("/api/categories/test-tree-child").await;
+    assert_eq!(status_code, StatusCode::OK);
+    let (_, body) = get("/api/categories/test-tree-grandchild").await;
+    assert_eq!(body.unwrap()["parent"].as_u64().unwrap(), parent);
+}
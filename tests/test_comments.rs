@@ -1,8 +1,10 @@
-use axum::http::StatusCode;
+use axum::http::{self, StatusCode};
 use serde_json::json;
 
 mod common;
-use common::{admin_post, admin_get, admin_patch, admin_delete, get, post};
+use common::{
+    admin_delete, admin_get, admin_patch, admin_post, get, post_from_ip, post_from_ip_with_headers,
+};
 
 #[tokio::test]
 async fn create_then_list_comments_success() {
@@ -19,14 +21,15 @@ async fn create_then_list_comments_success() {
 
     let data = json!({
         "author": "testAuthor",
-        "mail": "test@local.host",
+        "mail": "test1@local.host",
         "url": "https://127.0.0.1",
         "text": "test comment",
     })
     .to_string();
-    let (status_code, body) = post("/api/posts/test-comment-post/comments/", data).await;
+    let (status_code, body) = post_from_ip("/api/posts/test-comment-post/comments/", data, "10.0.0.1").await;
     println!("{:?}", body);
     assert_eq!(status_code, StatusCode::CREATED);
+    assert_eq!(body.unwrap()["status"], "approved");
 
     let (status_code, body) = get("/api/posts/test-comment-post/comments/").await;
     println!("{:?}", body);
@@ -59,12 +62,12 @@ async fn create_then_modify_comments_success() {
 
     let data = json!({
         "author": "testAuthor",
-        "mail": "test@local.host",
+        "mail": "test2@local.host",
         "url": "https://127.0.0.1",
         "text": "test comment modify",
     })
     .to_string();
-    let (status_code, _) = post("/api/posts/test-comment-post-modify/comments/", data).await;
+    let (status_code, _) = post_from_ip("/api/posts/test-comment-post-modify/comments/", data, "10.0.0.2").await;
     assert_eq!(status_code, StatusCode::CREATED);
 
     let (status_code, body) = get("/api/posts/test-comment-post-modify/comments/").await;
@@ -109,12 +112,12 @@ async fn create_then_delete_comments_success() {
 
     let data = json!({
         "author": "testAuthor",
-        "mail": "test@local.host",
+        "mail": "test3@local.host",
         "url": "https://127.0.0.1",
         "text": "test comment delete",
     })
     .to_string();
-    let (status_code, _) = post("/api/posts/test-comment-post-delete/comments/", data).await;
+    let (status_code, _) = post_from_ip("/api/posts/test-comment-post-delete/comments/", data, "10.0.0.3").await;
     assert_eq!(status_code, StatusCode::CREATED);
 
     tokio::time::sleep(std::time::Duration::from_secs(1)).await;
@@ -151,3 +154,254 @@ async fn create_then_delete_comments_success() {
     let new_count = body.get("all_count").unwrap().as_u64().unwrap();
     assert!(count > new_count);
 }
+
+#[tokio::test]
+async fn create_comments_too_frequently_failed() {
+    let data = json!({
+        "title": "testCommentPostFlood",
+        "slug": "test-comment-post-flood",
+        "created": 1666666666,
+        "text": "testText",
+        "status": "publish",
+    })
+    .to_string();
+    let (status_code, _) = admin_post("/api/posts/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let data = json!({
+        "author": "testAuthor",
+        "mail": "test4@local.host",
+        "text": "test comment flood",
+    })
+    .to_string();
+    let (status_code, _) =
+        post_from_ip("/api/posts/test-comment-post-flood/comments/", data.clone(), "10.0.0.4").await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let (status_code, _) =
+        post_from_ip("/api/posts/test-comment-post-flood/comments/", data.clone(), "10.0.0.5").await;
+    assert_eq!(status_code, StatusCode::TOO_MANY_REQUESTS);
+
+    let data = json!({
+        "author": "testAuthor",
+        "mail": "test5@local.host",
+        "text": "test comment flood",
+    })
+    .to_string();
+    let (status_code, headers, body) =
+        post_from_ip_with_headers("/api/posts/test-comment-post-flood/comments/", data, "10.0.0.4")
+            .await;
+    assert_eq!(status_code, StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(body.unwrap()["msg"], "Too many requests");
+    let retry_after: u64 = headers[http::header::RETRY_AFTER]
+        .to_str()
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert!(retry_after > 0 && retry_after <= 60);
+
+    // only the rightmost address set by the proxy counts, a spoofed one is ignored
+    let data = json!({
+        "author": "testAuthor",
+        "mail": "test6@local.host",
+        "text": "test comment flood",
+    })
+    .to_string();
+    let (status_code, _) = post_from_ip(
+        "/api/posts/test-comment-post-flood/comments/",
+        data,
+        "10.0.0.9, 10.0.0.4",
+    )
+    .await;
+    assert_eq!(status_code, StatusCode::TOO_MANY_REQUESTS);
+}
+
+#[tokio::test]
+async fn create_comments_in_parallel_too_frequently_failed() {
+    let data = json!({
+        "title": "testCommentPostParallel",
+        "slug": "test-comment-post-parallel",
+        "created": 1666666666,
+        "text": "testText",
+        "status": "publish",
+    })
+    .to_string();
+    let (status_code, _) = admin_post("/api/posts/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let url = "/api/posts/test-comment-post-parallel/comments/";
+    let data = |i: u32| {
+        json!({
+            "author": "testAuthor",
+            "mail": format!("parallel{}@local.host", i),
+            "text": "test comment parallel",
+        })
+        .to_string()
+    };
+    let (first, second, third) = tokio::join!(
+        post_from_ip(url, data(1), "10.0.0.10"),
+        post_from_ip(url, data(2), "10.0.0.10"),
+        post_from_ip(url, data(3), "10.0.0.10"),
+    );
+    let status_codes = [first.0, second.0, third.0];
+    assert_eq!(
+        status_codes
+            .iter()
+            .filter(|s| **s == StatusCode::CREATED)
+            .count(),
+        1
+    );
+    assert_eq!(
+        status_codes
+            .iter()
+            .filter(|s| **s == StatusCode::TOO_MANY_REQUESTS)
+            .count(),
+        2
+    );
+}
+
+#[tokio::test]
+async fn create_then_list_comments_tree_success() {
+    let data = json!({
+        "title": "testCommentPostTree",
+        "slug": "test-comment-post-tree",
+        "created": 1666666666,
+        "text": "testText",
+        "status": "publish",
+    })
+    .to_string();
+    let (status_code, _) = admin_post("/api/posts/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let url = "/api/posts/test-comment-post-tree/comments/";
+    let mut parent = None;
+    for level in 1..=5 {
+        let data = json!({
+            "author": "testAuthor",
+            "mail": format!("tree{}@local.host", level),
+            "text": format!("test comment level {}", level),
+            "parent": parent,
+        })
+        .to_string();
+        let (status_code, _) = post_from_ip(url, data, &format!("10.0.3.{}", level)).await;
+        assert_eq!(status_code, StatusCode::CREATED);
+
+        let (_, body) = get(&format!("{}?order_by=-coid", url)).await;
+        parent = body.unwrap()["results"][0]["coid"].as_u64();
+    }
+
+    let data = json!({
+        "author": "testAuthor",
+        "mail": "tree6@local.host",
+        "text": "test comment level 6",
+        "parent": parent,
+    })
+    .to_string();
+    let (status_code, _) = post_from_ip(url, data, "10.0.3.6").await;
+    assert_eq!(status_code, StatusCode::BAD_REQUEST);
+
+    let (status_code, body) = get(&format!("{}?tree=true", url)).await;
+    assert_eq!(status_code, StatusCode::OK);
+
+    let body = body.unwrap();
+    assert_eq!(body["all_count"], 1);
+    let level_5 = &body["results"][0]["children"][0]["children"][0]["children"][0]["children"][0];
+    assert_eq!(level_5["coid"].as_u64(), parent);
+    assert_eq!(level_5["children"].as_array().unwrap().len(), 0);
+}
+
+#[tokio::test]
+async fn create_spam_comment_then_mark_as_ham_success() {
+    let data = json!({
+        "title": "testCommentPostSpam",
+        "slug": "test-comment-post-spam",
+        "created": 1666666666,
+        "text": "testText",
+        "status": "publish",
+    })
+    .to_string();
+    let (status_code, _) = admin_post("/api/posts/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let data = json!({
+        "author": "testAuthor",
+        "mail": "spam@local.host",
+        "text": "test comment spam",
+        "honeypot": "filled by bot",
+    })
+    .to_string();
+    let (status_code, body) =
+        post_from_ip("/api/posts/test-comment-post-spam/comments/", data, "10.0.4.1").await;
+    assert_eq!(status_code, StatusCode::CREATED);
+    assert_eq!(body.unwrap()["status"], "spam");
+
+    let (status_code, body) = get("/api/posts/test-comment-post-spam/comments/").await;
+    assert_eq!(status_code, StatusCode::OK);
+    assert_eq!(body.unwrap()["all_count"], 0);
+
+    let (status_code, body) = admin_get("/api/comments/?status=spam&page_size=100").await;
+    assert_eq!(status_code, StatusCode::OK);
+
+    let body = body.unwrap();
+    let comments = body["results"].as_array().unwrap();
+    let spam = comments.iter().find(|c| c["text"] == "test comment spam").unwrap();
+    let coid = spam["coid"].as_u64().unwrap();
+
+    let url = format!("/api/comments/{}/ham", coid);
+    let (status_code, _) = admin_post(&url, "".to_string()).await;
+    assert_eq!(status_code, StatusCode::OK);
+
+    let (status_code, _) = admin_post(&url, "".to_string()).await;
+    assert_eq!(status_code, StatusCode::NOT_FOUND);
+
+    let (status_code, body) = get("/api/posts/test-comment-post-spam/comments/").await;
+    assert_eq!(status_code, StatusCode::OK);
+    assert_eq!(body.unwrap()["all_count"], 1);
+}
+
+#[tokio::test]
+async fn list_comments_by_page_success() {
+    let data = json!({
+        "title": "testCommentPostPages",
+        "slug": "test-comment-post-pages",
+        "created": 1666666666,
+        "text": "testText",
+        "status": "publish",
+    })
+    .to_string();
+    let (status_code, _) = admin_post("/api/posts/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    // waiting comments are only made here, so the pages don't move while listing
+    for i in 0..2 {
+        let data = json!({
+            "author": "testPagesAuthor",
+            "mail": format!("pages{}@local.host", i),
+            "text": format!("test comment page {}", i),
+        })
+        .to_string();
+        let ip = format!("10.0.4.{}", i + 2);
+        let (status_code, _) =
+            post_from_ip("/api/posts/test-comment-post-pages/comments/", data, &ip).await;
+        assert_eq!(status_code, StatusCode::CREATED);
+    }
+    let (_, body) = get("/api/posts/test-comment-post-pages/comments/").await;
+    for comment in body.unwrap()["results"].as_array().unwrap() {
+        let data = json!({"text": comment["text"], "status": "waiting"}).to_string();
+        let (status_code, _) =
+            admin_patch(&format!("/api/comments/{}", comment["coid"]), data).await;
+        assert_eq!(status_code, StatusCode::OK);
+    }
+
+    let url = "/api/comments/?status=waiting&order_by=coid";
+    let (_, body) = admin_get(&format!("{}&page_size=100", url)).await;
+    let body = body.unwrap();
+    let all = body["results"].as_array().unwrap();
+    assert_eq!(body["all_count"], all.len());
+    assert!(all.len() >= 2);
+
+    let (_, body) = admin_get(&format!("{}&page=1&page_size=1", url)).await;
+    assert_eq!(body.unwrap()["results"][0]["coid"], all[0]["coid"]);
+    let (_, body) = admin_get(&format!("{}&page=2&page_size=1", url)).await;
+    assert_eq!(body.unwrap()["results"][0]["coid"], all[1]["coid"]);
+}
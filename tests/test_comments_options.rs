@@ -0,0 +1,156 @@
+use axum::http::StatusCode;
+use sea_orm::{ConnectionTrait, Statement};
+use serde_json::json;
+
+mod common;
+use common::{admin_get, admin_patch, admin_post, get, post_from_ip, setup_state};
+
+use rumo::TABLE_PREFIX;
+
+async fn execute(sql: String) {
+    let state = setup_state().await;
+    let db_backend = state.conn.get_database_backend();
+    let res = state
+        .conn
+        .execute(Statement::from_string(db_backend, sql))
+        .await;
+    assert!(res.is_ok());
+}
+
+// site options are shared, so every case runs in this single test
+async fn set_option(name: &str, value: &str) {
+    execute(format!(
+        "UPDATE {}options SET value = '{}' WHERE name = '{}' AND user IN (0, 1);",
+        *TABLE_PREFIX, value, name
+    ))
+    .await;
+}
+
+async fn create_comment(
+    author: &str,
+    mail: Option<&str>,
+    url: Option<&str>,
+    ip: &str,
+) -> (StatusCode, Option<String>) {
+    let data = json!({
+        "author": author,
+        "mail": mail,
+        "url": url,
+        "text": format!("test comment of {}", author),
+    })
+    .to_string();
+    let (status_code, body) =
+        post_from_ip("/api/posts/test-comment-post-options/comments/", data, ip).await;
+    let status = body.and_then(|b| b["status"].as_str().map(|s| s.to_string()));
+    (status_code, status)
+}
+
+async fn get_comments_num() -> u64 {
+    let (_, body) = get("/api/posts/test-comment-post-options").await;
+    body.unwrap()["comments_num"].as_u64().unwrap()
+}
+
+#[tokio::test]
+async fn create_comments_with_options_success() {
+    let data = json!({
+        "title": "testCommentPostOptions",
+        "slug": "test-comment-post-options",
+        "created": 1666666666,
+        "text": "testText",
+        "status": "publish",
+    })
+    .to_string();
+    let (status_code, _) = admin_post("/api/posts/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+    set_option("commentsPostIntervalEnable", "0").await;
+
+    // moderation holds every comment, only approved ones are counted
+    set_option("commentsRequireModeration", "1").await;
+    let (status_code, status) = create_comment(
+        "testModerated",
+        Some("moderated@local.host"),
+        None,
+        "10.0.9.1",
+    )
+    .await;
+    assert_eq!(status_code, StatusCode::CREATED);
+    assert_eq!(status.as_deref(), Some("waiting"));
+    assert_eq!(get_comments_num().await, 0);
+
+    let (_, body) = get("/api/posts/test-comment-post-options/comments/").await;
+    assert_eq!(body.unwrap()["all_count"], 0);
+    let (_, body) = admin_get("/api/comments/?status=waiting&page_size=100").await;
+    let body = body.unwrap();
+    let coid = body["results"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|c| c["author"] == "testModerated")
+        .unwrap()["coid"]
+        .clone();
+    let data = json!({"text": "test comment of testModerated", "status": "approved"}).to_string();
+    let (status_code, _) = admin_patch(&format!("/api/comments/{}", coid), data).await;
+    assert_eq!(status_code, StatusCode::OK);
+    assert_eq!(get_comments_num().await, 1);
+    set_option("commentsRequireModeration", "0").await;
+
+    // the whitelist works without moderation
+    set_option("commentsWhitelist", "1").await;
+    let (_, status) = create_comment(
+        "testModerated",
+        Some("moderated@local.host"),
+        None,
+        "10.0.9.2",
+    )
+    .await;
+    assert_eq!(status.as_deref(), Some("approved"));
+    let (_, status) = create_comment(
+        "testStranger",
+        Some("stranger@local.host"),
+        None,
+        "10.0.9.3",
+    )
+    .await;
+    assert_eq!(status.as_deref(), Some("waiting"));
+    assert_eq!(get_comments_num().await, 2);
+    set_option("commentsWhitelist", "0").await;
+
+    // mail and url are only required by the options
+    let (status_code, status) = create_comment("testNoMail", None, None, "10.0.9.4").await;
+    assert_eq!(status_code, StatusCode::BAD_REQUEST);
+    assert_eq!(status, None);
+    set_option("commentsRequireMail", "0").await;
+    let (status_code, _) = create_comment("testNoMail", None, None, "10.0.9.4").await;
+    assert_eq!(status_code, StatusCode::CREATED);
+    set_option("commentsRequireMail", "1").await;
+
+    set_option("commentsRequireURL", "1").await;
+    let (status_code, _) =
+        create_comment("testNoUrl", Some("nourl@local.host"), None, "10.0.9.5").await;
+    assert_eq!(status_code, StatusCode::BAD_REQUEST);
+    let (status_code, _) = create_comment(
+        "testNoUrl",
+        Some("nourl@local.host"),
+        Some("https://127.0.0.1"),
+        "10.0.9.5",
+    )
+    .await;
+    assert_eq!(status_code, StatusCode::CREATED);
+    set_option("commentsRequireURL", "0").await;
+    assert_eq!(get_comments_num().await, 4);
+
+    // old contents are closed
+    execute(format!(
+        "UPDATE {}contents SET created = 1666666666 WHERE slug = 'test-comment-post-options';",
+        *TABLE_PREFIX
+    ))
+    .await;
+    set_option("commentsAutoClose", "1").await;
+    let (status_code, _) =
+        create_comment("testClosed", Some("closed@local.host"), None, "10.0.9.6").await;
+    set_option("commentsAutoClose", "0").await;
+    assert_eq!(status_code, StatusCode::FORBIDDEN);
+
+    set_option("commentsPostIntervalEnable", "1").await;
+    assert_eq!(get_comments_num().await, 4);
+}
@@ -0,0 +1,112 @@
+use axum::http::StatusCode;
+use serde_json::json;
+
+mod common;
+use common::{admin_post, get, get_text};
+
+#[tokio::test]
+async fn create_then_get_feed_success() {
+    let data = json!({
+        "title": "testFeedPost",
+        "slug": "test-feed-post",
+        "created": 1666666666,
+        "text": "<!--markdown-->## testFeedHeading\n\n[testFeedLink](https://rumo.cf/)",
+        "status": "publish",
+    })
+    .to_string();
+    let (status_code, _) = admin_post("/api/posts/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let data = json!({
+        "title": "testNoFeedPost",
+        "slug": "test-no-feed-post",
+        "created": 1666666666,
+        "text": "testText",
+        "status": "publish",
+        "allowFeed": false,
+    })
+    .to_string();
+    let (status_code, _) = admin_post("/api/posts/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let (status_code, body) = get_text("/feed").await;
+    assert_eq!(status_code, StatusCode::OK);
+    assert!(body.contains("<rss version=\"2.0\""));
+    assert!(body.contains("<title>testFeedPost</title>"));
+    assert!(!body.contains("testNoFeedPost"));
+    assert!(body.contains("&lt;h2 id=&quot;testfeedheading&quot;&gt;testFeedHeading&lt;/h2&gt;"));
+
+    let (status_code, body) = get_text("/feed/atom").await;
+    assert_eq!(status_code, StatusCode::OK);
+    assert!(body.contains("<feed xmlns=\"http://www.w3.org/2005/Atom\">"));
+    assert!(body.contains("<title>testFeedPost</title>"));
+
+    let (status_code, body) = get("/feed/json").await;
+    assert_eq!(status_code, StatusCode::OK);
+    let body = body.unwrap();
+    let items = body.get("items").unwrap().as_array().unwrap();
+    let item = items.iter().find(|i| i["title"] == "testFeedPost").unwrap();
+    let content = item["content_html"].as_str().unwrap();
+    assert!(content.contains("<h2 id=\"testfeedheading\">testFeedHeading</h2>"));
+    assert!(content.contains("<a href=\"https://rumo.cf/\""));
+    assert!(!items.iter().any(|i| i["title"] == "testNoFeedPost"));
+
+    let (status_code, _) = get_text("/feed/unknown").await;
+    assert_eq!(status_code, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn create_then_get_category_feed_success() {
+    let data = json!({"name": "testFeedCategory", "slug": "test-feed-category"}).to_string();
+    let (status_code, _) = admin_post("/api/categories/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let data = json!({
+        "title": "testCategoryFeedPost",
+        "slug": "test-category-feed-post",
+        "created": 1666666666,
+        "text": "testText",
+        "status": "publish",
+    })
+    .to_string();
+    let (status_code, _) = admin_post("/api/posts/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let data = json!({"slug": "test-category-feed-post"}).to_string();
+    let (status_code, _) = admin_post("/api/categories/test-feed-category/posts/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let (status_code, body) = get_text("/api/categories/test-feed-category/feed").await;
+    assert_eq!(status_code, StatusCode::OK);
+    assert!(body.contains("<title>testCategoryFeedPost</title>"));
+    assert!(body.contains("<category>testFeedCategory</category>"));
+
+    let (status_code, _) = get_text("/api/categories/test-feed-category-none/feed").await;
+    assert_eq!(status_code, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn create_then_get_tag_feed_success() {
+    let data = json!({"name": "testFeedTag", "slug": "test-feed-tag"}).to_string();
+    let (status_code, _) = admin_post("/api/tags/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let data = json!({
+        "title": "testTagFeedPost",
+        "slug": "test-tag-feed-post",
+        "created": 1666666666,
+        "text": "testText",
+        "status": "publish",
+    })
+    .to_string();
+    let (status_code, _) = admin_post("/api/posts/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let data = json!({"slug": "test-tag-feed-post"}).to_string();
+    let (status_code, _) = admin_post("/api/tags/test-feed-tag/posts/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let (status_code, body) = get_text("/api/tags/test-feed-tag/feed/atom").await;
+    assert_eq!(status_code, StatusCode::OK);
+    assert!(body.contains("<title>testTagFeedPost</title>"));
+}
@@ -0,0 +1,128 @@
+use axum::{
+    body::Body,
+    http::{self, Request, StatusCode},
+};
+use serde_json::json;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tower::ServiceExt;
+
+mod common;
+use common::{admin_post, get, post, setup_state, start_smtp_sink};
+use rumo::{app, SmtpConfig, SmtpSecurity};
+
+async fn post_comment_with_smtp(port: u16, url: &str, data: String, ip: &str) -> StatusCode {
+    let mut state = setup_state().await;
+    state.smtp = Some(SmtpConfig {
+        host: "127.0.0.1".to_string(),
+        port,
+        security: SmtpSecurity::None,
+        user: None,
+        password: None,
+        from: "rumo@local.host".to_string(),
+    });
+    let app = app(Some(state)).await;
+
+    let request = Request::builder()
+        .method(http::Method::POST)
+        .uri(url)
+        .header(http::header::CONTENT_TYPE, "application/json")
+        .header("User-Agent", "test")
+        .header("X-Forwarded-For", ip)
+        .body(Body::from(data))
+        .unwrap();
+    app.oneshot(request).await.unwrap().status()
+}
+
+async fn receive_mail(rx: &mut mpsc::UnboundedReceiver<(String, String)>) -> (String, String) {
+    tokio::time::timeout(Duration::from_secs(10), rx.recv())
+        .await
+        .unwrap()
+        .unwrap()
+}
+
+#[tokio::test]
+async fn create_comment_then_notify_by_mail_success() {
+    let (port, mut rx) = start_smtp_sink().await;
+
+    let data = json!({
+        "title": "testMailPost",
+        "slug": "test-mail-post",
+        "created": 1666666666,
+        "text": "testText",
+        "status": "publish",
+    })
+    .to_string();
+    let (status_code, _) = admin_post("/api/posts/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let url = "/api/posts/test-mail-post/comments/";
+    let data = json!({
+        "author": "testMailAuthor",
+        "mail": "mail1@local.host",
+        "text": "test mail comment",
+    })
+    .to_string();
+    let status_code = post_comment_with_smtp(port, url, data, "10.0.5.1").await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let (to, body) = receive_mail(&mut rx).await;
+    assert_eq!(to, "admin@local.host");
+    assert!(body.contains("testMailPost"));
+    assert!(body.contains("test mail comment"));
+
+    let (_, body) = get(&format!("{}?order_by=-coid", url)).await;
+    let coid = body.unwrap()["results"][0]["coid"].as_u64().unwrap();
+    let data = json!({
+        "author": "testMailReplier",
+        "mail": "mail2@local.host",
+        "text": "test mail reply",
+        "parent": coid,
+    })
+    .to_string();
+    let status_code = post_comment_with_smtp(port, url, data, "10.0.5.2").await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let mut mails = vec![receive_mail(&mut rx).await, receive_mail(&mut rx).await];
+    mails.sort();
+    assert_eq!(mails[0].0, "admin@local.host");
+    assert_eq!(mails[1].0, "mail1@local.host");
+    assert!(mails[1].1.contains("test mail reply"));
+
+    let header = mails[1].1.lines().find(|l| l.starts_with("List-Unsubscribe: ")).unwrap();
+    let token = header.split("token=").nth(1).unwrap().trim_end_matches('>');
+    let unsubscribe_url = format!("/api/mails/unsubscribe?token={}", token);
+    let (status_code, body) = get(&unsubscribe_url).await;
+    assert_eq!(status_code, StatusCode::OK);
+    let body = body.unwrap();
+    assert_eq!(body["mail"], "mail1@local.host");
+    assert_eq!(body["unsubscribed"], false);
+
+    // opening the link changes nothing, the unsubscribe is confirmed by a POST
+    let (status_code, _) = post(&unsubscribe_url, "".to_string()).await;
+    assert_eq!(status_code, StatusCode::OK);
+    let (_, body) = get(&unsubscribe_url).await;
+    assert_eq!(body.unwrap()["unsubscribed"], true);
+
+    let (status_code, _) = get("/api/mails/unsubscribe?token=invalid").await;
+    assert_eq!(status_code, StatusCode::BAD_REQUEST);
+    let (status_code, _) = post("/api/mails/unsubscribe?token=invalid", "".to_string()).await;
+    assert_eq!(status_code, StatusCode::BAD_REQUEST);
+
+    // later replies only reach the post author
+    let data = json!({
+        "author": "testMailReplierAgain",
+        "mail": "mail3@local.host",
+        "text": "test mail reply again",
+        "parent": coid,
+    })
+    .to_string();
+    let status_code = post_comment_with_smtp(port, url, data, "10.0.5.7").await;
+    assert_eq!(status_code, StatusCode::CREATED);
+    let (to, body) = receive_mail(&mut rx).await;
+    assert_eq!(to, "admin@local.host");
+    assert!(body.contains("test mail reply again"));
+    assert!(tokio::time::timeout(Duration::from_secs(2), rx.recv())
+        .await
+        .is_err());
+}
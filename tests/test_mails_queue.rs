@@ -0,0 +1,123 @@
+use sea_orm::{ConnectionTrait, DatabaseConnection, Statement};
+use std::net::TcpListener;
+use std::time::Duration;
+
+mod common;
+use common::{setup_state, start_smtp_sink};
+use rumo::{send_pending_mails, AppState, SmtpConfig, SmtpSecurity, TABLE_PREFIX};
+
+const MESSAGE: &str = "Subject: queue\r\n\r\ndGVzdCBxdWV1ZQ==";
+
+async fn setup_smtp_state(port: u16, security: SmtpSecurity, auth: bool) -> AppState {
+    let mut state = setup_state().await;
+    state.smtp = Some(SmtpConfig {
+        host: "127.0.0.1".to_string(),
+        port,
+        security,
+        user: auth.then(|| "rumo".to_string()),
+        password: auth.then(|| "password".to_string()),
+        from: "rumo@local.host".to_string(),
+    });
+    state
+}
+
+/// A port nothing listens on, so every connection is refused.
+fn get_closed_port() -> u16 {
+    TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port()
+}
+
+// mails are queued with next attempts long before now, so only this test sends them
+async fn queue_mail(conn: &DatabaseConnection, recipient: &str, next_attempt: u32) {
+    let sql = format!(
+        "INSERT INTO {}mails (recipient, subject, body, status, attempts, nextAttempt, created) VALUES ('{}', 'queue', '{}', 'pending', 0, {}, {});",
+        *TABLE_PREFIX, recipient, MESSAGE, next_attempt, next_attempt
+    );
+    let res = conn
+        .execute(Statement::from_string(conn.get_database_backend(), sql))
+        .await;
+    assert!(res.is_ok());
+}
+
+/// Returns (status, attempts, next attempt, last error) of the mail to `recipient`.
+async fn get_mail(
+    conn: &DatabaseConnection,
+    recipient: &str,
+) -> Option<(String, u32, u32, Option<String>)> {
+    let sql = format!(
+        "SELECT status, attempts, nextAttempt, lastError FROM {}mails WHERE recipient = '{}';",
+        *TABLE_PREFIX, recipient
+    );
+    let row = conn
+        .query_one(Statement::from_string(conn.get_database_backend(), sql))
+        .await
+        .unwrap()?;
+    Some((
+        row.try_get("", "status").unwrap(),
+        row.try_get("", "attempts").unwrap(),
+        row.try_get("", "nextAttempt").unwrap(),
+        row.try_get("", "lastError").unwrap(),
+    ))
+}
+
+#[tokio::test]
+async fn send_queued_mails_with_retry_success() {
+    let state = setup_state().await;
+    queue_mail(&state.conn, "queue1@local.host", 1000).await;
+
+    // credentials are refused before connecting when the connection is not encrypted
+    let auth_state = setup_smtp_state(get_closed_port(), SmtpSecurity::None, true).await;
+    send_pending_mails(&auth_state, 1000).await;
+    let (status, attempts, next_attempt, error) =
+        get_mail(&state.conn, "queue1@local.host").await.unwrap();
+    assert_eq!(status, "pending");
+    assert_eq!(attempts, 1);
+    assert_eq!(next_attempt, 1060);
+    assert_eq!(error.as_deref(), Some("smtp auth requires tls"));
+
+    // every failure doubles the wait, the fifth one gives up
+    let failed_state = setup_smtp_state(get_closed_port(), SmtpSecurity::None, false).await;
+    send_pending_mails(&failed_state, 1059).await;
+    assert_eq!(
+        get_mail(&state.conn, "queue1@local.host").await.unwrap().1,
+        1
+    );
+    for (now, attempts, next_attempt) in [(1060, 2, 1180), (1180, 3, 1420), (1420, 4, 1900)] {
+        send_pending_mails(&failed_state, now).await;
+        let (status, a, n, _) = get_mail(&state.conn, "queue1@local.host").await.unwrap();
+        assert_eq!(status, "pending");
+        assert_eq!((a, n), (attempts, next_attempt));
+    }
+    send_pending_mails(&failed_state, 1900).await;
+    let (status, attempts, _, _) = get_mail(&state.conn, "queue1@local.host").await.unwrap();
+    assert_eq!(status, "failed");
+    assert_eq!(attempts, 5);
+
+    // STARTTLS is required once configured, the sink does not offer it
+    let (port, mut rx) = start_smtp_sink().await;
+    let starttls_state = setup_smtp_state(port, SmtpSecurity::StartTls, true).await;
+    queue_mail(&state.conn, "queue3@local.host", 1950).await;
+    send_pending_mails(&starttls_state, 1950).await;
+    let (_, attempts, _, error) = get_mail(&state.conn, "queue3@local.host").await.unwrap();
+    assert_eq!(attempts, 1);
+    assert_eq!(
+        error.as_deref(),
+        Some("smtp server does not support STARTTLS")
+    );
+
+    // a sent mail leaves the queue, a failed one is not sent again
+    let sink_state = setup_smtp_state(port, SmtpSecurity::None, false).await;
+    queue_mail(&state.conn, "queue2@local.host", 2000).await;
+    send_pending_mails(&sink_state, 2000).await;
+    let (to, body) = tokio::time::timeout(Duration::from_secs(10), rx.recv())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(to, "queue2@local.host");
+    assert!(body.contains("test queue"));
+    assert_eq!(get_mail(&state.conn, "queue2@local.host").await, None);
+    assert!(rx.try_recv().is_err());
+}
@@ -0,0 +1,273 @@
+use axum::{
+    body::Body,
+    http::{self, HeaderMap, Request, StatusCode},
+};
+use hmac::{Hmac, Mac};
+use http_body_util::BodyExt;
+use jwt::SignWithKey;
+use serde_json::{json, Value};
+use sha2::Sha256;
+use tower::ServiceExt;
+
+mod common;
+use common::{get, get_multipart, setup_state};
+use rumo::app;
+
+async fn get_token(scope: &str) -> String {
+    let (_, body) = login_with_scope(scope).await;
+    body["access_token"].as_str().unwrap().to_string()
+}
+
+async fn login_with_scope(scope: &str) -> (StatusCode, Value) {
+    let state = setup_state().await;
+    let app = app(Some(state)).await;
+    let login_data =
+        json!({"mail": "admin@local.host", "password": "admin", "scope": scope}).to_string();
+    let request = Request::builder()
+        .method(http::Method::POST)
+        .uri("/api/users/token")
+        .header(http::header::CONTENT_TYPE, "application/json")
+        .body(Body::from(login_data))
+        .unwrap();
+    let response = app.oneshot(request).await.unwrap();
+    let status_code = response.status();
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    (status_code, serde_json::from_slice(&body).unwrap())
+}
+
+async fn micropub(
+    method: http::Method,
+    url: &str,
+    token: &str,
+    content_type: &str,
+    data: Vec<u8>,
+) -> (StatusCode, HeaderMap, Option<Value>) {
+    let state = setup_state().await;
+    let app = app(Some(state)).await;
+    let request = Request::builder()
+        .method(method)
+        .uri(url)
+        .header(http::header::CONTENT_TYPE, content_type)
+        .header(http::header::AUTHORIZATION, format!("Bearer {}", token))
+        .body(Body::from(data))
+        .unwrap();
+    let response = app.oneshot(request).await.unwrap();
+    let status_code = response.status();
+    let headers = response.headers().clone();
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body = serde_json::from_slice(&body).unwrap_or(None);
+    (status_code, headers, body)
+}
+
+async fn post_form(token: &str, data: &str) -> (StatusCode, HeaderMap, Option<Value>) {
+    micropub(
+        http::Method::POST,
+        "/api/micropub",
+        token,
+        "application/x-www-form-urlencoded",
+        data.as_bytes().to_vec(),
+    )
+    .await
+}
+
+/// Sends the token as `access_token` in the form, with or without the header.
+async fn post_form_with_body_token(
+    token: &str,
+    data: &str,
+    header: bool,
+) -> (StatusCode, HeaderMap, Option<Value>) {
+    let state = setup_state().await;
+    let app = app(Some(state)).await;
+    let mut request = Request::builder()
+        .method(http::Method::POST)
+        .uri("/api/micropub")
+        .header(
+            http::header::CONTENT_TYPE,
+            "application/x-www-form-urlencoded",
+        );
+    if header {
+        request = request.header(http::header::AUTHORIZATION, format!("Bearer {}", token));
+    }
+    let request = request
+        .body(Body::from(format!("{}&access_token={}", data, token)))
+        .unwrap();
+    let response = app.oneshot(request).await.unwrap();
+    let status_code = response.status();
+    let headers = response.headers().clone();
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body = serde_json::from_slice(&body).unwrap_or(None);
+    (status_code, headers, body)
+}
+
+async fn post_json(token: &str, data: Value) -> (StatusCode, HeaderMap, Option<Value>) {
+    micropub(
+        http::Method::POST,
+        "/api/micropub",
+        token,
+        "application/json",
+        data.to_string().into_bytes(),
+    )
+    .await
+}
+
+const ALL_SCOPES: &str = "create update delete media";
+
+#[tokio::test]
+async fn micropub_form_create_and_delete_success() {
+    let token = get_token(ALL_SCOPES).await;
+    let (status_code, headers, _) = post_form(
+        &token,
+        "h=entry&content=testMicropubNote&category[]=micropubTag&mp-slug=test-micropub-form",
+    )
+    .await;
+    assert_eq!(status_code, StatusCode::CREATED);
+    let location = headers[http::header::LOCATION]
+        .to_str()
+        .unwrap()
+        .to_string();
+
+    let (status_code, body) = get("/api/posts/test-micropub-form").await;
+    assert_eq!(status_code, StatusCode::OK);
+    let body = body.unwrap();
+    assert_eq!("testMicropubNote", body["title"]);
+    assert_eq!("<!--markdown-->testMicropubNote", body["text"]);
+    assert_eq!("micropubTag", body["tags"][0]["name"]);
+
+    let (status_code, _, _) = post_form(&token, &format!("action=delete&url={}", location)).await;
+    assert_eq!(status_code, StatusCode::OK);
+
+    let (status_code, _) = get("/api/posts/test-micropub-form").await;
+    assert_eq!(status_code, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn micropub_form_with_body_token_success() {
+    let token = get_token(ALL_SCOPES).await;
+    let data = "h=entry&content=testMicropubBodyToken&mp-slug=test-micropub-body-token";
+    let (status_code, _, _) = post_form_with_body_token(&token, data, true).await;
+    assert_eq!(status_code, StatusCode::BAD_REQUEST);
+
+    let (status_code, _, _) = post_form_with_body_token(&token, data, false).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+    let (_, body) = get("/api/posts/test-micropub-body-token").await;
+    assert_eq!("testMicropubBodyToken", body.unwrap()["title"]);
+
+    let (status_code, _, _) = post_form_with_body_token("invalid", data, false).await;
+    assert_eq!(status_code, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn micropub_json_create_and_update_success() {
+    let token = get_token(ALL_SCOPES).await;
+    let (status_code, headers, _) = post_json(
+        &token,
+        json!({
+            "type": ["h-entry"],
+            "properties": {
+                "name": ["testMicropubJson"],
+                "content": [{"html": "<p>testText</p>"}],
+                "category": ["micropubJsonTag"],
+                "mp-slug": ["test-micropub-json"],
+            }
+        }),
+    )
+    .await;
+    assert_eq!(status_code, StatusCode::CREATED);
+    let location = headers[http::header::LOCATION]
+        .to_str()
+        .unwrap()
+        .to_string();
+
+    let (status_code, _, _) = post_json(
+        &token,
+        json!({
+            "action": "update",
+            "url": location,
+            "replace": {"content": ["updated"]},
+            "add": {"category": ["micropubAddedTag"]},
+        }),
+    )
+    .await;
+    assert_eq!(status_code, StatusCode::OK);
+
+    let (_, body) = get("/api/posts/test-micropub-json").await;
+    let body = body.unwrap();
+    assert_eq!("testMicropubJson", body["title"]);
+    assert_eq!("<!--markdown-->updated", body["text"]);
+    assert_eq!(2, body["tags"].as_array().unwrap().len());
+
+    let (status_code, _, body) = micropub(
+        http::Method::GET,
+        &format!("/api/micropub?q=source&url={}", location),
+        &token,
+        "application/json",
+        vec![],
+    )
+    .await;
+    assert_eq!(status_code, StatusCode::OK);
+    let body = body.unwrap();
+    assert_eq!("updated", body["properties"]["content"][0]);
+    assert_eq!("test-micropub-json", body["properties"]["mp-slug"][0]);
+}
+
+#[tokio::test]
+async fn micropub_scope_failed() {
+    let token = get_token("media").await;
+    let (status_code, _, _) = post_form(&token, "h=entry&content=testMicropubScope").await;
+    assert_eq!(status_code, StatusCode::FORBIDDEN);
+
+    // scoped tokens only work on micropub
+    let (status_code, _, _) = micropub(
+        http::Method::GET,
+        "/api/users/me",
+        &token,
+        "application/json",
+        vec![],
+    )
+    .await;
+    assert_eq!(status_code, StatusCode::FORBIDDEN);
+
+    // a token has at least one scope
+    let (status_code, _) = login_with_scope("  ").await;
+    assert_eq!(status_code, StatusCode::BAD_REQUEST);
+
+    let key: Hmac<Sha256> =
+        Hmac::new_from_slice(std::env::var("SECRET_KEY").unwrap().as_bytes()).unwrap();
+    let expired = json!({"sub": "1", "exp": 1666666666, "scope": "create"})
+        .sign_with_key(&key)
+        .unwrap();
+    let (status_code, _, _) = post_form(&expired, "h=entry&content=testMicropubExpired").await;
+    assert_eq!(status_code, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn micropub_config_and_media_success() {
+    let token = get_token(ALL_SCOPES).await;
+    let (status_code, _, body) = micropub(
+        http::Method::GET,
+        "/api/micropub?q=config",
+        &token,
+        "application/json",
+        vec![],
+    )
+    .await;
+    assert_eq!(status_code, StatusCode::OK);
+    let body = body.unwrap();
+    assert!(body["media-endpoint"]
+        .as_str()
+        .unwrap()
+        .ends_with("/api/micropub/media"));
+
+    let (status_code, headers, body) = micropub(
+        http::Method::POST,
+        "/api/micropub/media",
+        &token,
+        "multipart/form-data; boundary=testfileboundary",
+        get_multipart("testMicropubMedia.png", "image/png"),
+    )
+    .await;
+    assert_eq!(status_code, StatusCode::CREATED);
+    let location = headers[http::header::LOCATION].to_str().unwrap();
+    assert!(location.contains("/usr/uploads/"));
+    assert_eq!(location, body.unwrap()["url"]);
+}
@@ -2,7 +2,7 @@ use axum::http::StatusCode;
 use serde_json::json;
 
 mod common;
-use common::{admin_post, admin_patch, get, admin_delete};
+use common::{admin_post, admin_patch, admin_patch_if_match, get, get_etag, admin_delete};
 
 #[tokio::test]
 async fn create_then_list_pages_success() {
@@ -79,8 +79,9 @@ async fn create_then_modify_page_by_slug_success() {
     let (status_code, _) = admin_post("/api/pages/", data).await;
     assert_eq!(status_code, StatusCode::CREATED);
 
-    let (status_code, _) = get("/api/pages/test-page-modify").await;
+    let (status_code, etag) = get_etag("/api/pages/test-page-modify").await;
     assert_eq!(status_code, StatusCode::OK);
+    assert!(!etag.is_empty());
 
     let data = json!({
         "title": "testPageModified",
@@ -89,13 +90,42 @@ async fn create_then_modify_page_by_slug_success() {
         "text": "testText",
     })
     .to_string();
-    let (status_code, _) = admin_patch("/api/pages/test-page-modify", data).await;
+    let (status_code, _) =
+        admin_patch_if_match("/api/pages/test-page-modify", data, &etag).await;
     assert_eq!(status_code, StatusCode::OK);
 
     let (status_code, _) = get("/api/pages/test-page-modified").await;
     assert_eq!(status_code, StatusCode::OK);
 }
 
+#[tokio::test]
+async fn modify_page_by_slug_with_stale_etag_failed() {
+    let data = json!({
+        "title": "testPageConflict",
+        "slug": "test-page-conflict",
+        "created": 1666666666,
+        "text": "testText",
+    })
+    .to_string();
+    let (status_code, _) = admin_post("/api/pages/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let data = json!({
+        "title": "testPageConflicted",
+        "slug": "test-page-conflict",
+        "created": 1666666666,
+        "text": "testText",
+    })
+    .to_string();
+    let (status_code, _) = admin_patch("/api/pages/test-page-conflict", data.clone()).await;
+    assert_eq!(status_code, StatusCode::PRECONDITION_REQUIRED);
+
+    let (status_code, body) =
+        admin_patch_if_match("/api/pages/test-page-conflict", data, "\"0-0\"").await;
+    assert_eq!(status_code, StatusCode::PRECONDITION_FAILED);
+    assert_eq!(body.unwrap()["current"]["title"], "testPageConflict");
+}
+
 #[tokio::test]
 async fn create_then_get_page_field_success() {
     let data = json!({
@@ -0,0 +1,214 @@
+use axum::{
+    body::Body,
+    http::{self, Request, StatusCode},
+};
+use futures::future::BoxFuture;
+use http_body_util::BodyExt;
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tower::ServiceExt;
+
+mod common;
+use common::{admin_post, get, setup_state};
+use rumo::{app, HttpClient, HttpResponse};
+
+/// Serves fixed pages instead of fetching them.
+struct StubHttpClient {
+    pages: HashMap<String, String>,
+}
+
+impl HttpClient for StubHttpClient {
+    fn get(&self, url: &str) -> BoxFuture<'static, Result<HttpResponse, String>> {
+        let response = self
+            .pages
+            .get(url)
+            .map(|body| HttpResponse {
+                url: url.to_string(),
+                headers: vec![],
+                body: body.clone(),
+            })
+            .ok_or("not found".to_string());
+        Box::pin(async move { response })
+    }
+
+    fn post_form(
+        &self,
+        _url: &str,
+        _form: Vec<(String, String)>,
+    ) -> BoxFuture<'static, Result<u16, String>> {
+        Box::pin(async move { Err("not supported".to_string()) })
+    }
+}
+
+async fn post_with_stub(
+    pages: &[(&str, String)],
+    url: &str,
+    content_type: &str,
+    data: String,
+) -> (StatusCode, String) {
+    let mut state = setup_state().await;
+    state.http_client = Arc::new(StubHttpClient {
+        pages: pages
+            .iter()
+            .map(|(u, p)| (u.to_string(), p.clone()))
+            .collect(),
+    });
+    let app = app(Some(state)).await;
+
+    let request = Request::builder()
+        .method(http::Method::POST)
+        .uri(url)
+        .header(http::header::CONTENT_TYPE, content_type)
+        .header("User-Agent", "test")
+        .header("X-Forwarded-For", "10.0.6.1")
+        .body(Body::from(data))
+        .unwrap();
+    let response = app.oneshot(request).await.unwrap();
+    let status_code = response.status();
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    (status_code, String::from_utf8(body.to_vec()).unwrap())
+}
+
+fn pingback_call(source: &str, target: &str) -> String {
+    format!(
+        r#"<?xml version="1.0"?>
+<methodCall>
+  <methodName>pingback.ping</methodName>
+  <params>
+    <param><value><string>{}</string></value></param>
+    <param><value><string>{}</string></value></param>
+  </params>
+</methodCall>"#,
+        source, target
+    )
+}
+
+#[tokio::test]
+async fn pingback_success() {
+    let data = json!({
+        "title": "testPingbackPost",
+        "slug": "test-pingback-post",
+        "created": 1666666666,
+        "text": "testText",
+        "status": "publish",
+    })
+    .to_string();
+    let (status_code, _) = admin_post("/api/posts/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+    let (_, body) = get("/api/posts/test-pingback-post").await;
+    let cid = body.unwrap()["cid"].as_u64().unwrap();
+
+    let source = "https://blog.local.host/pingback-source";
+    let target = format!("https://rumo.cf/archives/{}/", cid);
+    let page = format!(
+        r#"<html><head><title>Source Blog</title></head><body><a href="{}">a post</a></body></html>"#,
+        target
+    );
+    let pages = [(source, page)];
+
+    let (status_code, body) = post_with_stub(
+        &pages,
+        "/api/xmlrpc",
+        "text/xml",
+        pingback_call(source, &target),
+    )
+    .await;
+    assert_eq!(status_code, StatusCode::OK);
+    assert!(body.contains("registered"));
+
+    let (_, body) = post_with_stub(
+        &pages,
+        "/api/xmlrpc",
+        "text/xml",
+        pingback_call(source, &target),
+    )
+    .await;
+    assert!(body.contains("<name>faultCode</name><value><int>48</int>"));
+
+    let other = "https://blog.local.host/pingback-other";
+    let pages = [(other, "<a href=\"https://rumo.cf/\">home</a>".to_string())];
+    let (_, body) = post_with_stub(
+        &pages,
+        "/api/xmlrpc",
+        "text/xml",
+        pingback_call(other, &target),
+    )
+    .await;
+    assert!(body.contains("<name>faultCode</name><value><int>17</int>"));
+
+    let (_, body) = post_with_stub(
+        &pages,
+        "/api/xmlrpc",
+        "text/xml",
+        pingback_call(other, "https://rumo.cf/archives/999999/"),
+    )
+    .await;
+    assert!(body.contains("<name>faultCode</name><value><int>32</int>"));
+}
+
+#[tokio::test]
+async fn trackback_success() {
+    let data = json!({
+        "title": "testTrackbackPost",
+        "slug": "test-trackback-post",
+        "created": 1666666666,
+        "text": "testText",
+        "status": "publish",
+    })
+    .to_string();
+    let (status_code, _) = admin_post("/api/posts/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+    let (_, body) = get("/api/posts/test-trackback-post").await;
+    let cid = body.unwrap()["cid"].as_u64().unwrap();
+
+    let source = "https://blog.local.host/trackback-source";
+    let page = format!(
+        r#"<p><a href="https://rumo.cf/archives/{}/">read</a></p>"#,
+        cid
+    );
+    let pages = [(source, page)];
+    let url = "/api/posts/test-trackback-post/trackback";
+    let data = format!(
+        "url={}&title=Tracked&excerpt=some%20text&blog_name=Source%20Blog",
+        source
+    );
+    let form = "application/x-www-form-urlencoded";
+
+    let (status_code, body) = post_with_stub(&pages, url, form, data.clone()).await;
+    assert_eq!(status_code, StatusCode::OK);
+    assert!(body.contains("<error>0</error>"));
+
+    let (_, body) = post_with_stub(&pages, url, form, data).await;
+    assert!(body.contains("<error>1</error>"));
+
+    let (_, body) = post_with_stub(&pages, url, form, "title=nourl".to_string()).await;
+    assert!(body.contains("<error>1</error>"));
+}
+
+#[tokio::test]
+async fn trackback_with_ping_disallowed_failed() {
+    let data = json!({
+        "title": "testNoPingPost",
+        "slug": "test-no-ping-post",
+        "created": 1666666666,
+        "text": "testText",
+        "status": "publish",
+        "allowPing": false,
+    })
+    .to_string();
+    let (status_code, _) = admin_post("/api/posts/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let source = "https://blog.local.host/no-ping-source";
+    let pages = [(source, String::new())];
+    let data = format!("url={}", source);
+    let (_, body) = post_with_stub(
+        &pages,
+        "/api/posts/test-no-ping-post/trackback",
+        "application/x-www-form-urlencoded",
+        data,
+    )
+    .await;
+    assert!(body.contains("cannot be used as a target"));
+}
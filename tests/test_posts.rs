@@ -1,8 +1,13 @@
+use std::time::SystemTime;
+
 use axum::http::StatusCode;
 use serde_json::json;
 
 mod common;
-use common::{admin_post, admin_patch, admin_delete, get};
+use common::{
+    admin_delete, admin_get, admin_get_etag, admin_patch, admin_patch_if_match, admin_post,
+    admin_post_file, get, get_etag, get_multipart, post_from_ip,
+};
 
 #[tokio::test]
 async fn create_then_list_posts_success() {
@@ -86,8 +91,9 @@ async fn create_then_modify_post_by_slug_success() {
     let (status_code, _) = admin_post("/api/posts/", data).await;
     assert_eq!(status_code, StatusCode::CREATED);
 
-    let (status_code, _) = get("/api/posts/test-post-modify").await;
+    let (status_code, etag) = get_etag("/api/posts/test-post-modify").await;
     assert_eq!(status_code, StatusCode::OK);
+    assert!(!etag.is_empty());
 
     let data = json!({
         "title": "testPostModied",
@@ -97,13 +103,167 @@ async fn create_then_modify_post_by_slug_success() {
         "status": "publish",
     })
     .to_string();
-    let (status_code, _) = admin_patch("/api/posts/test-post-modify", data).await;
+    let (status_code, _) = admin_patch_if_match("/api/posts/test-post-modify", data, &etag).await;
     assert_eq!(status_code, StatusCode::OK);
 
     let (status_code, _) = get("/api/posts/test-post-modified").await;
     assert_eq!(status_code, StatusCode::OK);
 }
 
+#[tokio::test]
+async fn modify_post_by_slug_with_stale_etag_failed() {
+    let data = json!({
+        "title": "testPostConflict",
+        "slug": "test-post-conflict",
+        "created": 1666666666,
+        "text": "testText",
+        "status": "publish",
+    })
+    .to_string();
+    let (status_code, _) = admin_post("/api/posts/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let (status_code, etag) = get_etag("/api/posts/test-post-conflict").await;
+    assert_eq!(status_code, StatusCode::OK);
+
+    let data = json!({
+        "title": "testPostConflicted",
+        "slug": "test-post-conflict",
+        "created": 1666666666,
+        "text": "testTextModified",
+        "status": "publish",
+    })
+    .to_string();
+    let (status_code, _) = admin_patch("/api/posts/test-post-conflict", data.clone()).await;
+    assert_eq!(status_code, StatusCode::PRECONDITION_REQUIRED);
+
+    let (status_code, body) =
+        admin_patch_if_match("/api/posts/test-post-conflict", data, "\"0-0\"").await;
+    assert_eq!(status_code, StatusCode::PRECONDITION_FAILED);
+    let body = body.unwrap();
+    assert_eq!(body["current"]["slug"], "test-post-conflict");
+    assert_eq!(body["current"]["title"], "testPostConflict");
+
+    let (_, current) = get_etag("/api/posts/test-post-conflict").await;
+    assert_eq!(current, etag);
+}
+
+#[tokio::test]
+async fn modify_scheduled_post_twice_with_same_etag_failed() {
+    let data = json!({
+        "title": "testPostScheduledConflict",
+        "slug": "test-post-scheduled-conflict",
+        "created": 4102444800u32,
+        "text": "testText",
+        "status": "publish",
+    })
+    .to_string();
+    let (status_code, _) = admin_post("/api/posts/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let (status_code, etag) =
+        admin_get_etag("/api/posts/test-post-scheduled-conflict?private=true").await;
+    assert_eq!(status_code, StatusCode::OK);
+
+    let data = json!({
+        "title": "testPostScheduledConflict",
+        "slug": "test-post-scheduled-conflict",
+        "created": 4102444800u32,
+        "text": "testTextFirst",
+        "status": "publish",
+    })
+    .to_string();
+    let (status_code, _) =
+        admin_patch_if_match("/api/posts/test-post-scheduled-conflict", data, &etag).await;
+    assert_eq!(status_code, StatusCode::OK);
+
+    // the second edit within the same second still sees the first one
+    let data = json!({
+        "title": "testPostScheduledConflict",
+        "slug": "test-post-scheduled-conflict",
+        "created": 4102444800u32,
+        "text": "testTextSecond",
+        "status": "publish",
+    })
+    .to_string();
+    let (status_code, body) =
+        admin_patch_if_match("/api/posts/test-post-scheduled-conflict", data, &etag).await;
+    assert_eq!(status_code, StatusCode::PRECONDITION_FAILED);
+    assert_eq!(body.unwrap()["current"]["text"], "testTextFirst");
+}
+
+#[tokio::test]
+async fn create_then_modify_post_with_categories_and_tags_success() {
+    let data = json!({"name": "testInlineCategory", "slug": "test-inline-category"}).to_string();
+    let (status_code, _) = admin_post("/api/categories/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let data = json!({
+        "title": "testPostInline",
+        "slug": "test-post-inline",
+        "created": 1666666666,
+        "text": "testText",
+        "status": "publish",
+        "categories": ["testInlineCategory"],
+        "tags": ["test-inline-tag", "test-inline-tag"],
+    })
+    .to_string();
+    let (status_code, _) = admin_post("/api/posts/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let (status_code, body) = get("/api/posts/test-post-inline").await;
+    assert_eq!(status_code, StatusCode::OK);
+    let body = body.unwrap();
+    assert_eq!(body["categories"][0]["slug"], "test-inline-category");
+    assert_eq!(body["tags"].as_array().unwrap().len(), 1);
+    assert_eq!(body["tags"][0]["slug"], "test-inline-tag");
+
+    let (_, body) = get("/api/tags/test-inline-tag").await;
+    assert_eq!(body.unwrap()["count"], 1);
+
+    let (_, etag) = get_etag("/api/posts/test-post-inline").await;
+    let data = json!({
+        "title": "testPostInline",
+        "slug": "test-post-inline",
+        "created": 1666666666,
+        "text": "testText",
+        "status": "publish",
+        "categories": [],
+        "tags": [],
+    })
+    .to_string();
+    let (status_code, _) = admin_patch_if_match("/api/posts/test-post-inline", data, &etag).await;
+    assert_eq!(status_code, StatusCode::OK);
+
+    let (_, body) = get("/api/posts/test-post-inline").await;
+    let body = body.unwrap();
+    assert_eq!(body["categories"][0]["slug"], "default");
+    assert_eq!(body["tags"].as_array().unwrap().len(), 0);
+
+    let (_, body) = get("/api/tags/test-inline-tag").await;
+    assert_eq!(body.unwrap()["count"], 0);
+    let (_, body) = get("/api/categories/test-inline-category").await;
+    assert_eq!(body.unwrap()["count"], 0);
+}
+
+#[tokio::test]
+async fn create_post_with_unknown_category_failed() {
+    let data = json!({
+        "title": "testPostUnknownCategory",
+        "slug": "test-post-unknown-category",
+        "created": 1666666666,
+        "text": "testText",
+        "status": "publish",
+        "categories": ["test-unknown-category"],
+    })
+    .to_string();
+    let (status_code, _) = admin_post("/api/posts/", data).await;
+    assert_eq!(status_code, StatusCode::BAD_REQUEST);
+
+    let (status_code, _) = get("/api/posts/test-post-unknown-category").await;
+    assert_eq!(status_code, StatusCode::NOT_FOUND);
+}
+
 #[tokio::test]
 async fn create_then_delete_post_by_slug_success() {
     let data = json!({
@@ -217,7 +377,8 @@ async fn create_then_modify_post_field_success() {
         "str_value": "test-str-feild-modified",
     })
     .to_string();
-    let (status_code, _) = admin_patch("/api/posts/test-post-field-modify/fields/test_str", data).await;
+    let (status_code, _) =
+        admin_patch("/api/posts/test-post-field-modify/fields/test_str", data).await;
     assert_eq!(status_code, StatusCode::OK);
 
     let (status_code, body) = get("/api/posts/test-post-field-modify/fields/test_str").await;
@@ -279,3 +440,231 @@ async fn create_then_delete_post_field_success() {
     let count = body.get("fields").unwrap().as_array().unwrap().len();
     assert!(count == 1);
 }
+
+#[tokio::test]
+async fn create_then_get_post_html_by_slug_success() {
+    let data = json!({
+        "title": "testPostHtml",
+        "slug": "test-post-html",
+        "created": 1666666666,
+        "text": "<!--markdown-->## Post Title\n\n**bold**<script>alert(1)</script>",
+        "status": "publish",
+    })
+    .to_string();
+    let (status_code, _) = admin_post("/api/posts/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let (status_code, body) = get("/api/posts/test-post-html?format=html").await;
+    assert_eq!(status_code, StatusCode::OK);
+
+    let body = body.unwrap();
+    let text = body.get("text").unwrap().as_str().unwrap();
+    assert!(text.contains("<h2 id=\"post-title\">Post Title</h2>"));
+    assert!(text.contains("<strong>bold</strong>"));
+    assert!(!text.contains("<script>"));
+
+    let (status_code, body) = get("/api/posts/test-post-html").await;
+    assert_eq!(status_code, StatusCode::OK);
+
+    let body = body.unwrap();
+    let text = body.get("text").unwrap().as_str().unwrap();
+    assert!(text.starts_with("<!--markdown-->"));
+
+    let (status_code, _) = get("/api/posts/test-post-html?format=pdf").await;
+    assert_eq!(status_code, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn create_then_list_posts_summary_success() {
+    let data = json!({
+        "title": "testPostExcerpt",
+        "slug": "test-post-excerpt",
+        "created": 1666666666,
+        "text": "<!--markdown-->excerpt part\n\n<!--more-->\n\nrest part",
+        "status": "publish",
+    })
+    .to_string();
+    let (status_code, _) = admin_post("/api/posts/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let (status_code, body) = get("/api/posts/?page=1&page_size=10&order_by=-cid").await;
+    assert_eq!(status_code, StatusCode::OK);
+
+    let body = body.unwrap();
+    let results = body.get("results").unwrap().as_array().unwrap();
+    let post = results
+        .iter()
+        .find(|p| p["slug"] == "test-post-excerpt")
+        .unwrap();
+    assert_eq!(post["excerpt"], "excerpt part");
+    assert!(post.get("text").is_some());
+
+    let (status_code, body) =
+        get("/api/posts/?page=1&page_size=10&order_by=-cid&fields=summary").await;
+    assert_eq!(status_code, StatusCode::OK);
+
+    let body = body.unwrap();
+    let results = body.get("results").unwrap().as_array().unwrap();
+    let post = results
+        .iter()
+        .find(|p| p["slug"] == "test-post-excerpt")
+        .unwrap();
+    assert_eq!(post["excerpt"], "excerpt part");
+    assert!(post.get("text").is_none());
+
+    let (status_code, _) = get("/api/posts/?fields=unknown").await;
+    assert_eq!(status_code, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn create_then_get_scheduled_post_success() {
+    let created = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+        + 3600;
+    let data = json!({
+        "title": "testPostScheduled",
+        "slug": "test-post-scheduled",
+        "created": created,
+        "text": "testText",
+        "status": "publish",
+    })
+    .to_string();
+    let (status_code, _) = admin_post("/api/posts/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let (status_code, _) = get("/api/posts/test-post-scheduled").await;
+    assert_eq!(status_code, StatusCode::NOT_FOUND);
+
+    let (status_code, body) = get("/api/posts/?page=1&page_size=100&order_by=-cid").await;
+    assert_eq!(status_code, StatusCode::OK);
+
+    let body = body.unwrap();
+    let results = body.get("results").unwrap().as_array().unwrap();
+    assert!(results
+        .iter()
+        .all(|p| p.get("slug").unwrap() != "test-post-scheduled"));
+
+    let (status_code, body) = admin_get("/api/posts/test-post-scheduled?private=true").await;
+    assert_eq!(status_code, StatusCode::OK);
+
+    let body = body.unwrap();
+    assert_eq!(body.get("created").unwrap().as_u64().unwrap(), created);
+    assert_eq!(body.get("scheduled").unwrap(), true);
+}
+
+async fn upload_attachment(name: &str) -> u64 {
+    let data = get_multipart(name, "image/png");
+    let (status_code, _) = admin_post_file("/api/attachments/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let (status_code, body) = admin_get("/api/attachments/?page_size=100").await;
+    assert_eq!(status_code, StatusCode::OK);
+
+    let body = body.unwrap();
+    let attachments = body.get("results").unwrap().as_array().unwrap();
+    attachments
+        .iter()
+        .find(|at| at.get("name").unwrap() == name)
+        .map(|at| at.get("cid").unwrap().as_u64().unwrap())
+        .unwrap()
+}
+
+async fn get_trash_tid(title: &str) -> u64 {
+    let (status_code, body) = admin_get("/api/trash/?type=post&page_size=100").await;
+    assert_eq!(status_code, StatusCode::OK);
+
+    let body = body.unwrap();
+    let items = body.get("results").unwrap().as_array().unwrap();
+    items
+        .iter()
+        .find(|t| t.get("title").unwrap() == title)
+        .map(|t| t.get("tid").unwrap().as_u64().unwrap())
+        .unwrap()
+}
+
+#[tokio::test]
+async fn create_then_delete_post_with_relations_success() {
+    let data = json!({"name": "testCategoryCascade", "slug": "test-category-cascade"}).to_string();
+    let (status_code, _) = admin_post("/api/categories/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    for (i, slug) in ["test-post-cascade", "test-post-cascade-2"]
+        .into_iter()
+        .enumerate()
+    {
+        let data = json!({
+            "title": slug,
+            "slug": slug,
+            "created": 1666666666,
+            "text": "testText",
+            "status": "publish",
+        })
+        .to_string();
+        let (status_code, _) = admin_post("/api/posts/", data).await;
+        assert_eq!(status_code, StatusCode::CREATED);
+
+        let data = json!({ "slug": slug }).to_string();
+        let (status_code, _) =
+            admin_post("/api/categories/test-category-cascade/posts/", data).await;
+        assert_eq!(status_code, StatusCode::CREATED);
+
+        let data = json!({
+            "author": "testAuthor",
+            "mail": format!("cascade{}@local.host", i),
+            "url": "https://127.0.0.1",
+            "text": "test cascade comment",
+        })
+        .to_string();
+        let url = format!("/api/posts/{}/comments/", slug);
+        let (status_code, _) = post_from_ip(&url, data, &format!("10.0.2.{}", i)).await;
+        assert_eq!(status_code, StatusCode::CREATED);
+    }
+
+    let detached = upload_attachment("testCascadeDetach.png").await;
+    let data = json!({ "cid": detached }).to_string();
+    let (status_code, _) = admin_post("/api/posts/test-post-cascade/attachments/", data).await;
+    assert_eq!(status_code, StatusCode::OK);
+
+    let deleted = upload_attachment("testCascadeDelete.png").await;
+    let data = json!({ "cid": deleted }).to_string();
+    let (status_code, _) = admin_post("/api/posts/test-post-cascade-2/attachments/", data).await;
+    assert_eq!(status_code, StatusCode::OK);
+
+    let (status_code, body) = get("/api/categories/test-category-cascade").await;
+    assert_eq!(status_code, StatusCode::OK);
+    assert_eq!(body.unwrap().get("count").unwrap(), 2);
+
+    let (status_code, _) = admin_delete("/api/posts/test-post-cascade").await;
+    assert_eq!(status_code, StatusCode::OK);
+    let tid = get_trash_tid("test-post-cascade").await;
+    let (status_code, _) = admin_delete(&format!("/api/trash/{}", tid)).await;
+    assert_eq!(status_code, StatusCode::OK);
+
+    let (status_code, _) = admin_get(&format!("/api/attachments/{}", detached)).await;
+    assert_eq!(status_code, StatusCode::OK);
+
+    let (status_code, _) = admin_delete("/api/posts/test-post-cascade-2").await;
+    assert_eq!(status_code, StatusCode::OK);
+    let tid = get_trash_tid("test-post-cascade-2").await;
+    let url = format!("/api/trash/{}?delete_attachments=true", tid);
+    let (status_code, _) = admin_delete(&url).await;
+    assert_eq!(status_code, StatusCode::OK);
+
+    let (status_code, _) = admin_get(&format!("/api/attachments/{}", deleted)).await;
+    assert_eq!(status_code, StatusCode::NOT_FOUND);
+
+    let (status_code, body) = get("/api/categories/test-category-cascade").await;
+    assert_eq!(status_code, StatusCode::OK);
+    assert_eq!(body.unwrap().get("count").unwrap(), 0);
+
+    let (status_code, body) = admin_get("/api/comments/?page_size=100").await;
+    assert_eq!(status_code, StatusCode::OK);
+
+    let body = body.unwrap();
+    let comments = body.get("results").unwrap().as_array().unwrap();
+    assert!(comments
+        .iter()
+        .all(|c| c.get("text").unwrap() != "test cascade comment"));
+}
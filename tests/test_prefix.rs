@@ -0,0 +1,33 @@
+use axum::http::StatusCode;
+use sea_orm::{ConnectionTrait, Statement};
+
+mod common;
+use common::{get, setup_state};
+
+use rumo::TABLE_PREFIX;
+
+#[tokio::test]
+async fn tables_use_configured_prefix_success() {
+    let state = setup_state().await;
+    let db_backend = state.conn.get_database_backend();
+
+    for table in [
+        "comments",
+        "contents",
+        "fields",
+        "metas",
+        "options",
+        "relationships",
+        "users",
+    ] {
+        let sql = format!("SELECT COUNT(*) FROM {}{};", *TABLE_PREFIX, table);
+        let res = state
+            .conn
+            .query_one(Statement::from_string(db_backend, sql))
+            .await;
+        assert!(res.is_ok(), "{}{} not found", *TABLE_PREFIX, table);
+    }
+
+    let (status_code, _) = get("/api/posts/").await;
+    assert_eq!(status_code, StatusCode::OK);
+}
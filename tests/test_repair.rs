@@ -0,0 +1,90 @@
+use axum::http::StatusCode;
+use sea_orm::{ConnectionTrait, Statement};
+use serde_json::json;
+
+mod common;
+use common::{admin_post, get, setup_state};
+
+use rumo::TABLE_PREFIX;
+
+#[tokio::test]
+async fn repair_counts_success() {
+    for (slug, status) in [
+        ("test-post-repair", "publish"),
+        ("test-post-repair-waiting", "waiting"),
+    ] {
+        let data = json!({
+            "title": slug,
+            "slug": slug,
+            "created": 1666666666,
+            "text": "testText",
+            "status": status,
+            "tags": ["test-tag-repair"],
+        })
+        .to_string();
+        let (status_code, _) = admin_post("/api/posts/", data).await;
+        assert_eq!(status_code, StatusCode::CREATED);
+    }
+
+    // only the published post is counted
+    let (_, body) = get("/api/tags/test-tag-repair").await;
+    assert_eq!(body.unwrap()["count"], 1);
+
+    let state = setup_state().await;
+    let db_backend = state.conn.get_database_backend();
+    for sql in [
+        format!(
+            "UPDATE {}contents SET commentsNum = 5 WHERE slug = 'test-post-repair';",
+            *TABLE_PREFIX
+        ),
+        format!(
+            "UPDATE {}metas SET count = 2 WHERE slug = 'test-tag-repair';",
+            *TABLE_PREFIX
+        ),
+    ] {
+        let res = state
+            .conn
+            .execute(Statement::from_string(db_backend, sql))
+            .await;
+        assert!(res.is_ok());
+    }
+
+    let (_, body) = get("/api/tags/test-tag-repair").await;
+    assert_eq!(body.unwrap()["count"], 2);
+
+    let (status_code, body) = admin_post("/api/repair?dry_run=true", "".to_string()).await;
+    assert_eq!(status_code, StatusCode::OK);
+    let body = body.unwrap();
+    let results = body["results"].as_array().unwrap();
+    let change = results
+        .iter()
+        .find(|c| c["name"] == "test-tag-repair")
+        .unwrap();
+    assert_eq!(change["old"], 2);
+    assert_eq!(change["new"], 1);
+    let change = results
+        .iter()
+        .find(|c| c["name"] == "test-post-repair")
+        .unwrap();
+    assert_eq!(change["field"], "commentsNum");
+    assert_eq!(change["new"], 0);
+
+    let (_, body) = get("/api/tags/test-tag-repair").await;
+    assert_eq!(body.unwrap()["count"], 2);
+
+    let (status_code, _) = admin_post("/api/repair", "".to_string()).await;
+    assert_eq!(status_code, StatusCode::OK);
+
+    let (_, body) = get("/api/tags/test-tag-repair").await;
+    assert_eq!(body.unwrap()["count"], 1);
+    let (_, body) = get("/api/posts/test-post-repair").await;
+    assert_eq!(body.unwrap()["comments_num"], 0);
+
+    let (_, body) = admin_post("/api/repair?dry_run=true", "".to_string()).await;
+    let body = body.unwrap();
+    assert!(!body["results"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .any(|c| c["name"] == "test-tag-repair"));
+}
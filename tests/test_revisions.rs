@@ -0,0 +1,111 @@
+use axum::http::StatusCode;
+use serde_json::json;
+
+mod common;
+use common::{admin_get, admin_post, get};
+
+#[tokio::test]
+async fn create_then_publish_post_revision_success() {
+    let data = json!({
+        "title": "testPostRevision",
+        "slug": "test-post-revision",
+        "created": 1666666666,
+        "text": "publishedText",
+        "status": "publish",
+    })
+    .to_string();
+    let (status_code, _) = admin_post("/api/posts/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let data = json!({
+        "title": "testPostRevisionDraft",
+        "slug": "test-post-revision",
+        "created": 1666666666,
+        "text": "draftText",
+        "status": "publish",
+    })
+    .to_string();
+    let (status_code, body) = admin_post("/api/posts/test-post-revision/revisions/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+    let cid = body.unwrap().get("cid").unwrap().as_u64().unwrap();
+
+    let (status_code, body) = get("/api/posts/test-post-revision").await;
+    assert_eq!(status_code, StatusCode::OK);
+    let body = body.unwrap();
+    assert_eq!(body.get("text").unwrap(), "publishedText");
+
+    let (status_code, body) = admin_get("/api/posts/test-post-revision/revisions/").await;
+    assert_eq!(status_code, StatusCode::OK);
+    let body = body.unwrap();
+    assert_eq!(body.get("all_count").unwrap(), 1);
+
+    let url = format!("/api/posts/test-post-revision/revisions/{}", cid);
+    let (status_code, body) = admin_get(&url).await;
+    assert_eq!(status_code, StatusCode::OK);
+    let body = body.unwrap();
+    assert_eq!(body.get("type").unwrap(), "post_draft");
+    assert_eq!(body.get("text").unwrap(), "draftText");
+
+    let (status_code, _) = get(&format!("/api/posts/@{}", cid)).await;
+    assert_eq!(status_code, StatusCode::NOT_FOUND);
+
+    let url = format!("/api/posts/test-post-revision/revisions/{}/publish", cid);
+    let (status_code, _) = admin_post(&url, "".to_string()).await;
+    assert_eq!(status_code, StatusCode::OK);
+
+    let (status_code, body) = get("/api/posts/test-post-revision").await;
+    assert_eq!(status_code, StatusCode::OK);
+    let body = body.unwrap();
+    assert_eq!(body.get("title").unwrap(), "testPostRevisionDraft");
+    assert_eq!(body.get("text").unwrap(), "draftText");
+
+    let (status_code, body) = admin_get("/api/posts/test-post-revision/revisions/").await;
+    assert_eq!(status_code, StatusCode::OK);
+    let body = body.unwrap();
+    assert_eq!(body.get("all_count").unwrap(), 0);
+}
+
+#[tokio::test]
+async fn create_then_restore_post_revision_success() {
+    let data = json!({
+        "title": "testPostRestore",
+        "slug": "test-post-restore",
+        "created": 1666666666,
+        "text": "publishedText",
+        "status": "publish",
+    })
+    .to_string();
+    let (status_code, _) = admin_post("/api/posts/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let data = json!({
+        "title": "testPostRestore",
+        "slug": "test-post-restore",
+        "created": 1666666666,
+        "text": "oldText",
+        "status": "publish",
+    })
+    .to_string();
+    let (status_code, body) = admin_post("/api/posts/test-post-restore/revisions/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+    let cid = body.unwrap().get("cid").unwrap().as_u64().unwrap();
+
+    let url = format!("/api/posts/test-post-restore/revisions/{}/restore", cid);
+    let (status_code, _) = admin_post(&url, "".to_string()).await;
+    assert_eq!(status_code, StatusCode::OK);
+
+    let (status_code, body) = get("/api/posts/test-post-restore").await;
+    assert_eq!(status_code, StatusCode::OK);
+    let body = body.unwrap();
+    assert_eq!(body.get("text").unwrap(), "oldText");
+
+    let (status_code, body) = admin_get("/api/posts/test-post-restore/revisions/").await;
+    assert_eq!(status_code, StatusCode::OK);
+    let body = body.unwrap();
+    assert_eq!(body.get("all_count").unwrap(), 2);
+    let results = body.get("results").unwrap().as_array().unwrap();
+    assert_eq!(results[0].get("text").unwrap(), "publishedText");
+
+    let (status_code, _) = admin_get("/api/posts/test-post-restore/revisions/999999").await;
+    assert_eq!(status_code, StatusCode::NOT_FOUND);
+}
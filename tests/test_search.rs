@@ -0,0 +1,97 @@
+use axum::http::StatusCode;
+use serde_json::json;
+
+mod common;
+use common::{admin_get, admin_post, get};
+
+#[tokio::test]
+async fn create_then_search_contents_success() {
+    let data = json!({
+        "title": "testSearchPost",
+        "slug": "test-search-post",
+        "created": 1666666666,
+        "text": "<!--markdown-->some **searchablekeyword** text",
+        "status": "publish",
+    })
+    .to_string();
+    let (status_code, _) = admin_post("/api/posts/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let data = json!({
+        "title": "testSearchDraft",
+        "slug": "test-search-draft",
+        "created": 1666666666,
+        "text": "draft searchablekeyword text",
+        "status": "draft",
+    })
+    .to_string();
+    let (status_code, _) = admin_post("/api/posts/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let data = json!({
+        "title": "testSearchLocked",
+        "slug": "test-search-locked",
+        "created": 1666666666,
+        "text": "locked searchablekeyword text",
+        "status": "publish",
+        "password": "secret",
+    })
+    .to_string();
+    let (status_code, _) = admin_post("/api/posts/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let (status_code, body) = get("/api/search?q=searchablekeyword").await;
+    assert_eq!(status_code, StatusCode::OK);
+
+    let body = body.unwrap();
+    let results = body.get("results").unwrap().as_array().unwrap();
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0]["slug"], "test-search-post");
+    assert!(results[0]["snippet"]
+        .as_str()
+        .unwrap()
+        .contains("<mark>searchablekeyword</mark>"));
+
+    let (status_code, body) = admin_get("/api/search?q=searchablekeyword&private=true").await;
+    assert_eq!(status_code, StatusCode::OK);
+
+    let body = body.unwrap();
+    let count = body.get("all_count").unwrap().as_u64().unwrap();
+    assert_eq!(count, 3);
+
+    let (status_code, body) = get("/api/search?q=testSearchLocked").await;
+    assert_eq!(status_code, StatusCode::OK);
+
+    let body = body.unwrap();
+    let results = body.get("results").unwrap().as_array().unwrap();
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0]["snippet"], "");
+
+    let (status_code, _) = get("/api/search?q=").await;
+    assert_eq!(status_code, StatusCode::BAD_REQUEST);
+
+    let (status_code, _) =
+        get("/api/search?q=searchablekeyword&page=18446744073709551615&page_size=100").await;
+    assert_eq!(status_code, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn search_contents_with_short_keyword_success() {
+    let data = json!({
+        "title": "testSearchShort",
+        "slug": "test-search-short",
+        "created": 1666666666,
+        "text": "short zq keyword",
+        "status": "publish",
+    })
+    .to_string();
+    let (status_code, _) = admin_post("/api/posts/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let (status_code, body) = get("/api/search?q=zq%20keyword").await;
+    assert_eq!(status_code, StatusCode::OK);
+
+    let body = body.unwrap();
+    let results = body.get("results").unwrap().as_array().unwrap();
+    assert!(results.iter().any(|r| r["slug"] == "test-search-short"));
+}
@@ -0,0 +1,78 @@
+use axum::http::StatusCode;
+use serde_json::json;
+
+mod common;
+use common::{admin_post, get_text};
+
+#[tokio::test]
+async fn create_then_get_sitemap_success() {
+    let data = json!({
+        "title": "testSitemapPage",
+        "slug": "test-sitemap-page",
+        "created": 1666666666,
+        "text": "testText",
+        "publish": true,
+    })
+    .to_string();
+    let (status_code, _) = admin_post("/api/pages/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let data = json!({
+        "title": "testSitemapHiddenPage",
+        "slug": "test-sitemap-hidden-page",
+        "created": 1666666666,
+        "text": "testText",
+        "publish": false,
+    })
+    .to_string();
+    let (status_code, _) = admin_post("/api/pages/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let data = json!({
+        "title": "testSitemapPasswordPost",
+        "slug": "test-sitemap-password-post",
+        "created": 1666666666,
+        "text": "testText",
+        "status": "publish",
+        "password": "secret",
+    })
+    .to_string();
+    let (status_code, _) = admin_post("/api/posts/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let data = json!({"name": "testSitemapCategory", "slug": "test-sitemap-category"}).to_string();
+    let (status_code, _) = admin_post("/api/categories/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let data = json!({
+        "title": "testSitemapPost",
+        "slug": "test-sitemap-post",
+        "created": 1666666666,
+        "text": "testText",
+        "status": "publish",
+    })
+    .to_string();
+    let (status_code, _) = admin_post("/api/posts/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let data = json!({"slug": "test-sitemap-post"}).to_string();
+    let (status_code, _) = admin_post("/api/categories/test-sitemap-category/posts/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let (status_code, body) = get_text("/sitemap.xml").await;
+    assert_eq!(status_code, StatusCode::OK);
+    assert!(body.contains("<urlset"));
+    assert!(body.contains("<loc>https://rumo.cf/test-sitemap-page.html</loc>"));
+    assert!(!body.contains("test-sitemap-hidden-page"));
+    assert!(body.contains("<loc>https://rumo.cf/category/test-sitemap-category/</loc>"));
+    assert!(body.contains("<loc>https://rumo.cf/archives/"));
+    assert!(body.contains("<lastmod>"));
+
+    let (status_code, body) = get_text("/sitemaps/pages-1.xml").await;
+    assert_eq!(status_code, StatusCode::OK);
+    assert!(body.contains("<loc>https://rumo.cf/test-sitemap-page.html</loc>"));
+    assert!(!body.contains("/category/"));
+
+    let (status_code, _) = get_text("/sitemaps/unknown-1.xml").await;
+    assert_eq!(status_code, StatusCode::NOT_FOUND);
+}
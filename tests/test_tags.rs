@@ -2,7 +2,9 @@ use axum::http::StatusCode;
 use serde_json::json;
 
 mod common;
-use common::{admin_post, admin_delete, admin_patch, get};
+use common::{
+    admin_delete, admin_get, admin_get_etag, admin_patch, admin_patch_if_match, admin_post, get,
+};
 
 #[tokio::test]
 async fn create_then_list_tags_success() {
@@ -150,3 +152,135 @@ async fn create_then_delete_tag_posts_success() {
 
     assert!(new_count < count);
 }
+
+#[tokio::test]
+async fn create_then_merge_tag_success() {
+    for slug in ["test-tag-merge-source", "test-tag-merge-target"] {
+        let data = json!({"name": slug, "slug": slug}).to_string();
+        let (status_code, _) = admin_post("/api/tags/", data).await;
+        assert_eq!(status_code, StatusCode::CREATED);
+    }
+
+    for (slug, tags, status) in [
+        (
+            "test-post-tag-merge-both",
+            vec!["test-tag-merge-source", "test-tag-merge-target"],
+            "publish",
+        ),
+        (
+            "test-post-tag-merge-source",
+            vec!["test-tag-merge-source"],
+            "publish",
+        ),
+        (
+            "test-post-tag-merge-draft",
+            vec!["test-tag-merge-source"],
+            "draft",
+        ),
+    ] {
+        let data = json!({
+            "title": slug,
+            "slug": slug,
+            "created": 1666666666,
+            "text": "testText",
+            "status": status,
+            "tags": tags,
+        })
+        .to_string();
+        let (status_code, _) = admin_post("/api/posts/", data).await;
+        assert_eq!(status_code, StatusCode::CREATED);
+    }
+
+    let data = json!({"slug": "test-tag-merge-source"}).to_string();
+    let (status_code, _) = admin_post("/api/tags/test-tag-merge-source/merge", data).await;
+    assert_eq!(status_code, StatusCode::BAD_REQUEST);
+
+    let data = json!({"slug": "test-tag-merge-target"}).to_string();
+    let (status_code, _) = admin_post("/api/tags/test-tag-merge-source/merge", data).await;
+    assert_eq!(status_code, StatusCode::OK);
+
+    // the draft is moved to the target but not counted
+    let (_, body) = get("/api/tags/test-tag-merge-target").await;
+    assert_eq!(body.unwrap()["count"], 2);
+    let (_, body) = get("/api/tags/test-tag-merge-target/posts/").await;
+    assert_eq!(body.unwrap()["all_count"], 2);
+    let (_, body) = admin_get("/api/tags/test-tag-merge-target/posts/?private=true").await;
+    assert_eq!(body.unwrap()["all_count"], 3);
+
+    let (status_code, body) = get("/api/tags/test-tag-merge-source").await;
+    assert_eq!(status_code, StatusCode::OK);
+    assert_eq!(body.unwrap()["slug"], "test-tag-merge-target");
+
+    let (status_code, body) = get("/api/tags/cloud").await;
+    assert_eq!(status_code, StatusCode::OK);
+    let body = body.unwrap();
+    let tag = body["results"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|t| t["slug"] == "test-tag-merge-target")
+        .unwrap();
+    assert!(tag["weight"].as_u64().unwrap() >= 1);
+}
+
+#[tokio::test]
+async fn modify_tag_slug_then_get_by_old_slug_success() {
+    let data = json!({"name": "testTagRename", "slug": "test-tag-rename"}).to_string();
+    let (status_code, _) = admin_post("/api/tags/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let data = json!({"name": "testTagRenamed", "slug": "test-tag-renamed"}).to_string();
+    let (status_code, _) = admin_patch("/api/tags/test-tag-rename", data).await;
+    assert_eq!(status_code, StatusCode::OK);
+
+    let (status_code, body) = get("/api/tags/test-tag-rename").await;
+    assert_eq!(status_code, StatusCode::OK);
+    assert_eq!(body.unwrap()["slug"], "test-tag-renamed");
+
+    let (status_code, _) = get("/api/tags/test-tag-rename/posts/").await;
+    assert_eq!(status_code, StatusCode::OK);
+
+    let (status_code, _) = admin_delete("/api/tags/test-tag-renamed").await;
+    assert_eq!(status_code, StatusCode::OK);
+
+    let (status_code, _) = get("/api/tags/test-tag-rename").await;
+    assert_eq!(status_code, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn tag_count_follows_post_status_success() {
+    let post = |status: &str| {
+        json!({
+            "title": "testPostTagStatus",
+            "slug": "test-post-tag-status",
+            "created": 1666666666,
+            "text": "testText",
+            "status": status,
+            "tags": ["test tag status"],
+        })
+        .to_string()
+    };
+    let (status_code, _) = admin_post("/api/posts/", post("draft")).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    // tags created from a name get a normalized slug
+    let (status_code, body) = get("/api/tags/test-tag-status").await;
+    assert_eq!(status_code, StatusCode::OK);
+    let body = body.unwrap();
+    assert_eq!(body["name"], "test tag status");
+    assert_eq!(body["count"], 0);
+
+    for (status, count) in [("publish", 1), ("draft", 0), ("publish", 1)] {
+        let (_, etag) = admin_get_etag("/api/posts/test-post-tag-status?private=true").await;
+        let (status_code, _) =
+            admin_patch_if_match("/api/posts/test-post-tag-status", post(status), &etag).await;
+        assert_eq!(status_code, StatusCode::OK);
+        let (_, body) = get("/api/tags/test-tag-status").await;
+        assert_eq!(body.unwrap()["count"], count);
+    }
+
+    let (status_code, _) = admin_delete("/api/posts/test-post-tag-status").await;
+    assert_eq!(status_code, StatusCode::OK);
+    let (_, body) = get("/api/tags/test-tag-status").await;
+    assert_eq!(body.unwrap()["count"], 0);
+}
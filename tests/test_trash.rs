@@ -0,0 +1,339 @@
+use axum::http::StatusCode;
+use sea_orm::{ConnectionTrait, Statement};
+use serde_json::{json, Value};
+
+mod common;
+use common::{
+    admin_delete, admin_get, admin_patch, admin_post, admin_post_file, get, get_multipart,
+    post_from_ip, setup_state,
+};
+
+use rumo::{purge_trash_created_before, TABLE_PREFIX};
+
+async fn find_trash(r#type: &str, title: &str) -> Option<Value> {
+    let url = format!("/api/trash/?type={}&page_size=100", r#type);
+    let (status_code, body) = admin_get(&url).await;
+    assert_eq!(status_code, StatusCode::OK);
+
+    let body = body.unwrap();
+    let items = body.get("results").unwrap().as_array().unwrap();
+    items
+        .iter()
+        .find(|t| t.get("title").unwrap() == title)
+        .cloned()
+}
+
+#[tokio::test]
+async fn delete_then_restore_post_success() {
+    let data = json!({
+        "title": "testPostTrash",
+        "slug": "test-post-trash",
+        "created": 1666666666,
+        "text": "testText",
+        "status": "hidden",
+    })
+    .to_string();
+    let (status_code, _) = admin_post("/api/posts/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let (status_code, _) = admin_delete("/api/posts/test-post-trash").await;
+    assert_eq!(status_code, StatusCode::OK);
+
+    let (status_code, _) = admin_get("/api/posts/test-post-trash?private=true").await;
+    assert_eq!(status_code, StatusCode::NOT_FOUND);
+
+    let (status_code, _) = admin_delete("/api/posts/test-post-trash").await;
+    assert_eq!(status_code, StatusCode::BAD_REQUEST);
+
+    let trash = find_trash("post", "testPostTrash").await.unwrap();
+    assert_eq!(trash.get("status").unwrap(), "hidden");
+
+    let url = format!("/api/trash/{}/restore", trash.get("tid").unwrap());
+    let (status_code, _) = admin_post(&url, "".to_string()).await;
+    assert_eq!(status_code, StatusCode::OK);
+
+    let (status_code, body) = admin_get("/api/posts/test-post-trash?private=true").await;
+    assert_eq!(status_code, StatusCode::OK);
+    assert_eq!(body.unwrap().get("status").unwrap(), "hidden");
+
+    assert!(find_trash("post", "testPostTrash").await.is_none());
+}
+
+#[tokio::test]
+async fn delete_then_restore_comment_success() {
+    let data = json!({
+        "title": "testPostTrashComment",
+        "slug": "test-post-trash-comment",
+        "created": 1666666666,
+        "text": "testText",
+        "status": "publish",
+    })
+    .to_string();
+    let (status_code, _) = admin_post("/api/posts/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let data = json!({
+        "author": "testAuthor",
+        "mail": "trash@local.host",
+        "url": "https://127.0.0.1",
+        "text": "test trash comment",
+    })
+    .to_string();
+    let (status_code, _) = post_from_ip(
+        "/api/posts/test-post-trash-comment/comments/",
+        data,
+        "10.0.1.1",
+    )
+    .await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let (status_code, body) = get("/api/posts/test-post-trash-comment/comments/").await;
+    assert_eq!(status_code, StatusCode::OK);
+    let body = body.unwrap();
+    let coid = body.get("results").unwrap()[0].get("coid").unwrap().clone();
+
+    let (status_code, _) = admin_delete(&format!("/api/comments/{}", coid)).await;
+    assert_eq!(status_code, StatusCode::OK);
+
+    let (status_code, body) = get("/api/posts/test-post-trash-comment").await;
+    assert_eq!(status_code, StatusCode::OK);
+    assert_eq!(body.unwrap().get("comments_num").unwrap(), 0);
+
+    let trash = find_trash("comment", "test trash comment").await.unwrap();
+    let url = format!("/api/trash/{}/restore", trash.get("tid").unwrap());
+    let (status_code, _) = admin_post(&url, "".to_string()).await;
+    assert_eq!(status_code, StatusCode::OK);
+
+    let (status_code, body) = get("/api/posts/test-post-trash-comment").await;
+    assert_eq!(status_code, StatusCode::OK);
+    assert_eq!(body.unwrap().get("comments_num").unwrap(), 1);
+
+    let (status_code, _) = admin_get(&format!("/api/comments/{}", coid)).await;
+    assert_eq!(status_code, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn purge_draft_keeps_tag_count_success() {
+    for (slug, status) in [
+        ("test-post-purge-publish", "publish"),
+        ("test-post-purge-draft", "draft"),
+    ] {
+        let data = json!({
+            "title": slug,
+            "slug": slug,
+            "created": 1666666666,
+            "text": "testText",
+            "status": status,
+            "tags": ["testPurgeTag"],
+        })
+        .to_string();
+        let (status_code, _) = admin_post("/api/posts/", data).await;
+        assert_eq!(status_code, StatusCode::CREATED);
+    }
+
+    // only the published post is counted
+    let (_, body) = get("/api/tags/testPurgeTag").await;
+    assert_eq!(body.unwrap().get("count").unwrap(), 1);
+
+    let (status_code, _) = admin_delete("/api/posts/test-post-purge-draft").await;
+    assert_eq!(status_code, StatusCode::OK);
+    let trash = find_trash("post", "test-post-purge-draft").await.unwrap();
+    let url = format!("/api/trash/{}", trash.get("tid").unwrap());
+    let (status_code, _) = admin_delete(&url).await;
+    assert_eq!(status_code, StatusCode::OK);
+
+    let (status_code, body) = get("/api/tags/testPurgeTag").await;
+    assert_eq!(status_code, StatusCode::OK);
+    assert_eq!(body.unwrap().get("count").unwrap(), 1);
+}
+
+#[tokio::test]
+async fn trashed_post_is_not_reachable_success() {
+    let data = json!({
+        "title": "testPostTrashReach",
+        "slug": "test-post-trash-reach",
+        "created": 1666666666,
+        "text": "testText",
+        "status": "publish",
+    })
+    .to_string();
+    let (status_code, _) = admin_post("/api/posts/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    // a post can not be trashed by a modify
+    let data = json!({
+        "title": "testPostTrashReach",
+        "slug": "test-post-trash-reach",
+        "created": 1666666666,
+        "text": "testText",
+        "status": "trash",
+    })
+    .to_string();
+    let (status_code, _) = admin_patch("/api/posts/test-post-trash-reach", data).await;
+    assert_eq!(status_code, StatusCode::BAD_REQUEST);
+
+    let (status_code, _) = admin_delete("/api/posts/test-post-trash-reach").await;
+    assert_eq!(status_code, StatusCode::OK);
+
+    let data = json!({
+        "author": "testAuthor",
+        "mail": "trash@local.host",
+        "url": "https://127.0.0.1",
+        "text": "test trashed post comment",
+    })
+    .to_string();
+    let (status_code, _) = post_from_ip(
+        "/api/posts/test-post-trash-reach/comments/",
+        data,
+        "10.0.1.2",
+    )
+    .await;
+    assert_eq!(status_code, StatusCode::BAD_REQUEST);
+
+    let (status_code, _) = get("/api/posts/test-post-trash-reach/comments/").await;
+    assert_eq!(status_code, StatusCode::BAD_REQUEST);
+
+    // only a restore brings it back
+    let data = json!({
+        "title": "testPostTrashReach",
+        "slug": "test-post-trash-reach",
+        "created": 1666666666,
+        "text": "testText",
+        "status": "publish",
+    })
+    .to_string();
+    let (status_code, _) = admin_patch("/api/posts/test-post-trash-reach", data).await;
+    assert_eq!(status_code, StatusCode::NOT_FOUND);
+    assert!(find_trash("post", "testPostTrashReach").await.is_some());
+
+    // the slug is still taken
+    let data = json!({
+        "title": "testPostTrashReach",
+        "slug": "test-post-trash-reach",
+        "created": 1666666666,
+        "text": "testText",
+        "status": "publish",
+    })
+    .to_string();
+    let (status_code, _) = admin_post("/api/posts/", data).await;
+    assert_eq!(status_code, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn delete_then_restore_waiting_comment_success() {
+    let data = json!({
+        "title": "testPostTrashWaiting",
+        "slug": "test-post-trash-waiting",
+        "created": 1666666666,
+        "text": "testText",
+        "status": "publish",
+    })
+    .to_string();
+    let (status_code, _) = admin_post("/api/posts/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    for (text, ip) in [
+        ("test trash approved", "10.0.1.3"),
+        ("test trash waiting", "10.0.1.4"),
+    ] {
+        let data = json!({
+            "author": "testAuthor",
+            "mail": format!("{}@local.host", ip),
+            "url": "https://127.0.0.1",
+            "text": text,
+        })
+        .to_string();
+        let (status_code, _) =
+            post_from_ip("/api/posts/test-post-trash-waiting/comments/", data, ip).await;
+        assert_eq!(status_code, StatusCode::CREATED);
+    }
+    let (_, body) = get("/api/posts/test-post-trash-waiting").await;
+    assert_eq!(body.unwrap().get("comments_num").unwrap(), 2);
+
+    let (_, body) = get("/api/posts/test-post-trash-waiting/comments/").await;
+    let body = body.unwrap();
+    let coid = body["results"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|c| c["text"] == "test trash waiting")
+        .unwrap()["coid"]
+        .clone();
+    let data = json!({"text": "test trash waiting", "status": "waiting"}).to_string();
+    let (status_code, _) = admin_patch(&format!("/api/comments/{}", coid), data).await;
+    assert_eq!(status_code, StatusCode::OK);
+    let (_, body) = get("/api/posts/test-post-trash-waiting").await;
+    assert_eq!(body.unwrap().get("comments_num").unwrap(), 1);
+
+    let (status_code, _) = admin_delete(&format!("/api/comments/{}", coid)).await;
+    assert_eq!(status_code, StatusCode::OK);
+    let (_, body) = get("/api/posts/test-post-trash-waiting").await;
+    assert_eq!(body.unwrap().get("comments_num").unwrap(), 1);
+
+    // a trashed comment can not be moved out by a modify
+    let data = json!({"text": "test trash waiting", "status": "approved"}).to_string();
+    let (status_code, _) = admin_patch(&format!("/api/comments/{}", coid), data).await;
+    assert_eq!(status_code, StatusCode::NOT_FOUND);
+
+    let trash = find_trash("comment", "test trash waiting").await.unwrap();
+    let url = format!("/api/trash/{}/restore", trash.get("tid").unwrap());
+    let (status_code, _) = admin_post(&url, "".to_string()).await;
+    assert_eq!(status_code, StatusCode::OK);
+    let (_, body) = get("/api/posts/test-post-trash-waiting").await;
+    assert_eq!(body.unwrap().get("comments_num").unwrap(), 1);
+}
+
+#[tokio::test]
+async fn auto_purge_post_with_attachments_success() {
+    let data = json!({
+        "title": "testPostTrashPurge",
+        "slug": "test-post-trash-purge",
+        "created": 1666666666,
+        "text": "testText",
+        "status": "publish",
+    })
+    .to_string();
+    let (status_code, _) = admin_post("/api/posts/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    let data = get_multipart("testTrashPurge.png", "image/png");
+    let (status_code, _) = admin_post_file("/api/attachments/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+    let (_, body) = admin_get("/api/attachments/?page_size=100").await;
+    let body = body.unwrap();
+    let cid = body["results"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|at| at["name"] == "testTrashPurge.png")
+        .unwrap()["cid"]
+        .clone();
+    let data = json!({ "cid": cid }).to_string();
+    let (status_code, _) = admin_post("/api/posts/test-post-trash-purge/attachments/", data).await;
+    assert_eq!(status_code, StatusCode::OK);
+
+    let (status_code, _) =
+        admin_delete("/api/posts/test-post-trash-purge?delete_attachments=true").await;
+    assert_eq!(status_code, StatusCode::OK);
+    let trash = find_trash("post", "testPostTrashPurge").await.unwrap();
+    assert_eq!(trash["delete_attachments"], true);
+
+    // expire only this trash, other tests trash at the current time
+    let state = setup_state().await;
+    let db_backend = state.conn.get_database_backend();
+    let sql = format!(
+        "UPDATE {}trash SET created = 1 WHERE tid = {};",
+        *TABLE_PREFIX, trash["tid"]
+    );
+    let res = state
+        .conn
+        .execute(Statement::from_string(db_backend, sql))
+        .await;
+    assert!(res.is_ok());
+
+    purge_trash_created_before(&state, 2).await;
+
+    assert!(find_trash("post", "testPostTrashPurge").await.is_none());
+    let (status_code, _) = admin_get(&format!("/api/attachments/{}", cid)).await;
+    assert_eq!(status_code, StatusCode::NOT_FOUND);
+}
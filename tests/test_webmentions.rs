@@ -0,0 +1,347 @@
+use axum::{
+    body::Body,
+    http::{self, Request, StatusCode},
+};
+use futures::future::BoxFuture;
+use http_body_util::BodyExt;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tower::ServiceExt;
+
+mod common;
+use common::{admin_get, admin_post, get, setup_state};
+use rumo::{app, AppState, HttpClient, HttpResponse};
+
+type Posted = Arc<Mutex<Vec<(String, Vec<(String, String)>)>>>;
+
+/// Serves fixed pages and records the forms posted to it.
+struct StubHttpClient {
+    pages: HashMap<String, String>,
+    posted: Posted,
+}
+
+impl HttpClient for StubHttpClient {
+    fn get(&self, url: &str) -> BoxFuture<'static, Result<HttpResponse, String>> {
+        let response = self
+            .pages
+            .get(url)
+            .map(|body| HttpResponse {
+                url: url.to_string(),
+                headers: vec![],
+                body: body.clone(),
+            })
+            .ok_or("not found".to_string());
+        Box::pin(async move { response })
+    }
+
+    fn post_form(
+        &self,
+        url: &str,
+        form: Vec<(String, String)>,
+    ) -> BoxFuture<'static, Result<u16, String>> {
+        self.posted.lock().unwrap().push((url.to_string(), form));
+        Box::pin(async move { Ok(202) })
+    }
+}
+
+async fn setup_stub_state(pages: &[(&str, String)], posted: Posted) -> AppState {
+    let mut state = setup_state().await;
+    state.http_client = Arc::new(StubHttpClient {
+        pages: pages
+            .iter()
+            .map(|(u, p)| (u.to_string(), p.clone()))
+            .collect(),
+        posted,
+    });
+    state
+}
+
+async fn send(state: AppState, request: Request<Body>) -> (StatusCode, Option<Value>) {
+    let app = app(Some(state)).await;
+    let response = app.oneshot(request).await.unwrap();
+    let status_code = response.status();
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    (status_code, serde_json::from_slice(&body).unwrap_or(None))
+}
+
+fn form_request(url: &str, data: String, ip: &str) -> Request<Body> {
+    Request::builder()
+        .method(http::Method::POST)
+        .uri(url)
+        .header(
+            http::header::CONTENT_TYPE,
+            "application/x-www-form-urlencoded",
+        )
+        .header("X-Forwarded-For", ip)
+        .body(Body::from(data))
+        .unwrap()
+}
+
+async fn post_form_with_stub(
+    pages: &[(&str, String)],
+    url: &str,
+    data: String,
+) -> (StatusCode, Option<Value>) {
+    let state = setup_stub_state(pages, Posted::default()).await;
+    send(state, form_request(url, data, "10.0.7.1")).await
+}
+
+/// Posts a webmention from `ip`, returns the status and the Retry-After header.
+async fn post_webmention_from_ip(data: String, ip: &str) -> (StatusCode, Option<u64>) {
+    let state = setup_stub_state(&[], Posted::default()).await;
+    let app = app(Some(state)).await;
+    let response = app
+        .oneshot(form_request("/api/webmention", data, ip))
+        .await
+        .unwrap();
+    let retry_after = response
+        .headers()
+        .get(http::header::RETRY_AFTER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse().ok());
+    (response.status(), retry_after)
+}
+
+async fn admin_post_with_stub(
+    pages: &[(&str, String)],
+    posted: Posted,
+    url: &str,
+    data: String,
+) -> StatusCode {
+    admin_send_with_stub(pages, posted, http::Method::POST, url, data).await
+}
+
+async fn admin_send_with_stub(
+    pages: &[(&str, String)],
+    posted: Posted,
+    method: http::Method,
+    url: &str,
+    data: String,
+) -> StatusCode {
+    let state = setup_stub_state(pages, posted).await;
+    let login_data = json!({"mail": "admin@local.host", "password": "admin"}).to_string();
+    let request = Request::builder()
+        .method(http::Method::POST)
+        .uri("/api/users/token")
+        .header(http::header::CONTENT_TYPE, "application/json")
+        .body(Body::from(login_data))
+        .unwrap();
+    let (_, body) = send(state.clone(), request).await;
+    let token = body.unwrap()["access_token"].as_str().unwrap().to_string();
+
+    let request = Request::builder()
+        .method(method)
+        .uri(url)
+        .header(http::header::CONTENT_TYPE, "application/json")
+        .header(http::header::AUTHORIZATION, format!("Bearer {}", token))
+        .header(http::header::IF_MATCH, "*")
+        .body(Body::from(data))
+        .unwrap();
+    send(state, request).await.0
+}
+
+#[tokio::test]
+async fn receive_webmention_success() {
+    let data = json!({
+        "title": "testWebmentionPost",
+        "slug": "test-webmention-post",
+        "created": 1666666666,
+        "text": "testText",
+        "status": "publish",
+    })
+    .to_string();
+    let (status_code, _) = admin_post("/api/posts/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+    let (_, body) = get("/api/posts/test-webmention-post").await;
+    let cid = body.unwrap()["cid"].as_u64().unwrap();
+
+    let source = "https://blog.local.host/webmention-source";
+    let target = format!("https://rumo.cf/archives/{}/", cid);
+    let page = format!(r#"<p><a href="{}">mentioned</a></p>"#, target);
+    let pages = [(source, page)];
+    let data = format!("source={}&target={}", source, target);
+
+    let (status_code, _) = post_form_with_stub(&pages, "/api/webmention", data).await;
+    assert_eq!(status_code, StatusCode::ACCEPTED);
+
+    let mut found = false;
+    for _ in 0..50 {
+        let (_, body) = admin_get("/api/comments/?page_size=100&order_by=-coid").await;
+        found = body.unwrap()["results"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|c| c["type"] == "webmention" && c["url"] == source);
+        if found {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    assert!(found);
+
+    let data = format!("source={}&target=https://rumo.cf/archives/999999/", source);
+    let (status_code, _) = post_form_with_stub(&pages, "/api/webmention", data).await;
+    assert_eq!(status_code, StatusCode::BAD_REQUEST);
+
+    let data = format!("source={}&target={}", target, target);
+    let (status_code, _) = post_form_with_stub(&pages, "/api/webmention", data).await;
+    assert_eq!(status_code, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn receive_webmention_with_ping_disallowed_failed() {
+    let data = json!({
+        "title": "testNoWebmentionPost",
+        "slug": "test-no-webmention-post",
+        "created": 1666666666,
+        "text": "testText",
+        "status": "publish",
+        "allowPing": false,
+    })
+    .to_string();
+    let (status_code, _) = admin_post("/api/posts/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+    let (_, body) = get("/api/posts/test-no-webmention-post").await;
+    let cid = body.unwrap()["cid"].as_u64().unwrap();
+
+    let data = format!(
+        "source=https://blog.local.host/a&target=https://rumo.cf/archives/{}/",
+        cid
+    );
+    let (status_code, _) = post_form_with_stub(&[], "/api/webmention", data).await;
+    assert_eq!(status_code, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn receive_webmention_with_rate_limit_failed() {
+    let data = json!({
+        "title": "testWebmentionLimitPost",
+        "slug": "test-webmention-limit-post",
+        "created": 1666666666,
+        "text": "testText",
+        "status": "publish",
+    })
+    .to_string();
+    let (status_code, _) = admin_post("/api/posts/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+    let (_, body) = get("/api/posts/test-webmention-limit-post").await;
+    let cid = body.unwrap()["cid"].as_u64().unwrap();
+    let target = format!("https://rumo.cf/archives/{}/", cid);
+
+    // a repeated mention is accepted but only queued once
+    let data = format!("source=https://limit.local.host/0&target={}", target);
+    for _ in 0..12 {
+        let (status_code, _) = post_webmention_from_ip(data.clone(), "10.0.7.2").await;
+        assert_eq!(status_code, StatusCode::ACCEPTED);
+    }
+    for i in 1..10 {
+        let data = format!("source=https://limit.local.host/{}&target={}", i, target);
+        let (status_code, _) = post_webmention_from_ip(data, "10.0.7.2").await;
+        assert_eq!(status_code, StatusCode::ACCEPTED);
+    }
+
+    let data = format!("source=https://limit.local.host/10&target={}", target);
+    let (status_code, retry_after) = post_webmention_from_ip(data.clone(), "10.0.7.2").await;
+    assert_eq!(status_code, StatusCode::TOO_MANY_REQUESTS);
+    assert!((1..=60).contains(&retry_after.unwrap()));
+    let (status_code, _) = post_webmention_from_ip(data, "10.0.7.3").await;
+    assert_eq!(status_code, StatusCode::ACCEPTED);
+}
+
+#[tokio::test]
+async fn publish_post_then_send_webmention_success() {
+    let target = "https://remote.local.host/article";
+    let page = r#"<html><head><link rel="webmention" href="/endpoint"></head></html>"#;
+    let pages = [(target, page.to_string())];
+    let posted = Posted::default();
+
+    let data = json!({
+        "title": "testSendWebmentionPost",
+        "slug": "test-send-webmention-post",
+        "created": 1666666666,
+        "text": format!("<!--markdown-->see [this]({}) and [home](https://rumo.cf/)", target),
+        "status": "publish",
+    })
+    .to_string();
+    let status_code = admin_post_with_stub(&pages, posted.clone(), "/api/posts/", data).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    for _ in 0..50 {
+        if !posted.lock().unwrap().is_empty() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    let posted = posted.lock().unwrap();
+    assert_eq!(1, posted.len());
+    let (endpoint, form) = &posted[0];
+    assert_eq!("https://remote.local.host/endpoint", endpoint);
+    assert_eq!(("target".to_string(), target.to_string()), form[1]);
+    assert!(form[0].1.starts_with("https://rumo.cf/archives/"));
+}
+
+async fn wait_posted(posted: &Posted, len: usize) -> Vec<(String, Vec<(String, String)>)> {
+    for _ in 0..50 {
+        if posted.lock().unwrap().len() >= len {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    posted.lock().unwrap().clone()
+}
+
+/// Gives a mention that should not be sent the time to show up.
+async fn settle_posted(posted: &Posted) -> usize {
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    posted.lock().unwrap().len()
+}
+
+#[tokio::test]
+async fn modify_post_then_send_changed_webmentions_success() {
+    let target = "https://remote.local.host/changed";
+    let page = r#"<html><head><link rel="webmention" href="/endpoint"></head></html>"#;
+    let pages = [(target, page.to_string())];
+    let posted = Posted::default();
+
+    let post = |text: String| {
+        json!({
+            "title": "testChangedWebmentionPost",
+            "slug": "test-changed-webmention-post",
+            "created": 1666666666,
+            "text": text,
+            "status": "publish",
+        })
+        .to_string()
+    };
+    let linked = format!("<!--markdown-->see [this]({})", target);
+    let status_code =
+        admin_post_with_stub(&pages, posted.clone(), "/api/posts/", post(linked.clone())).await;
+    assert_eq!(status_code, StatusCode::CREATED);
+    assert_eq!(1, wait_posted(&posted, 1).await.len());
+    assert_eq!(1, settle_posted(&posted).await);
+
+    // the same link is not mentioned again on every save
+    let url = "/api/posts/test-changed-webmention-post";
+    let data = post(format!("{} again", linked));
+    let status_code =
+        admin_send_with_stub(&pages, posted.clone(), http::Method::PATCH, url, data).await;
+    assert_eq!(status_code, StatusCode::OK);
+    assert_eq!(1, settle_posted(&posted).await);
+
+    // a removed link is mentioned once, so the target can drop it
+    let data = post("<!--markdown-->no links".to_string());
+    let status_code =
+        admin_send_with_stub(&pages, posted.clone(), http::Method::PATCH, url, data).await;
+    assert_eq!(status_code, StatusCode::OK);
+    let sent = wait_posted(&posted, 2).await;
+    assert_eq!(2, sent.len());
+    assert_eq!(("target".to_string(), target.to_string()), sent[1].1[1]);
+
+    let data = post("<!--markdown-->still no links".to_string());
+    let status_code =
+        admin_send_with_stub(&pages, posted.clone(), http::Method::PATCH, url, data).await;
+    assert_eq!(status_code, StatusCode::OK);
+    assert_eq!(2, settle_posted(&posted).await);
+}
@@ -0,0 +1,318 @@
+use axum::{
+    body::Body,
+    http::{self, Request, StatusCode},
+};
+use http_body_util::BodyExt;
+use tower::ServiceExt;
+
+mod common;
+use common::{admin_get, get, setup_state};
+use rumo::app;
+
+async fn post_xml(data: String) -> (StatusCode, String) {
+    post_xml_with_if_match(data, "*").await
+}
+
+async fn post_xml_with_if_match(data: String, if_match: &str) -> (StatusCode, String) {
+    post_xml_from_ip(data, if_match, "10.0.8.1").await
+}
+
+async fn post_xml_from_ip(data: String, if_match: &str, ip: &str) -> (StatusCode, String) {
+    let state = setup_state().await;
+    let app = app(Some(state)).await;
+
+    let request = Request::builder()
+        .method(http::Method::POST)
+        .uri("/api/xmlrpc")
+        .header(http::header::CONTENT_TYPE, "text/xml")
+        .header(http::header::IF_MATCH, if_match)
+        .header("X-Forwarded-For", ip)
+        .body(Body::from(data))
+        .unwrap();
+    let response = app.oneshot(request).await.unwrap();
+    let status_code = response.status();
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    (status_code, String::from_utf8(body.to_vec()).unwrap())
+}
+
+fn method_call(method: &str, params: &[&str]) -> String {
+    let params: String = params
+        .iter()
+        .map(|p| format!("<param><value>{}</value></param>", p))
+        .collect();
+    format!(
+        r#"<?xml version="1.0"?><methodCall><methodName>{}</methodName><params>{}</params></methodCall>"#,
+        method, params
+    )
+}
+
+fn string(s: &str) -> String {
+    format!("<string>{}</string>", s)
+}
+
+/// Returns the text of the first string in a response.
+fn first_string(body: &str) -> &str {
+    let start = body.find("<string>").unwrap() + 8;
+    let end = body[start..].find("</string>").unwrap() + start;
+    &body[start..end]
+}
+
+const LOGIN: [&str; 2] = ["<string>admin</string>", "<string>admin</string>"];
+
+#[tokio::test]
+async fn metaweblog_post_success() {
+    let post = r#"<struct>
+      <member><name>title</name><value><string>testXmlRpcPost</string></value></member>
+      <member><name>description</name><value><string>&lt;p&gt;testText&lt;/p&gt;</string></value></member>
+      <member><name>mt_text_more</name><value><string>more text</string></value></member>
+      <member><name>wp_slug</name><value><string>test-xmlrpc-post</string></value></member>
+      <member><name>mt_keywords</name><value><string>xmlrpcTag1, xmlrpcTag2</string></value></member>
+      <member><name>categories</name><value><array><data>
+        <value><string>默认分类</string></value>
+      </data></array></value></member>
+      <member><name>dateCreated</name><value><dateTime.iso8601>20221025T02:57:46</dateTime.iso8601></value></member>
+    </struct>"#;
+    let (status_code, body) = post_xml(method_call(
+        "metaWeblog.newPost",
+        &[
+            &string("1"),
+            LOGIN[0],
+            LOGIN[1],
+            post,
+            "<boolean>1</boolean>",
+        ],
+    ))
+    .await;
+    assert_eq!(status_code, StatusCode::OK);
+    let cid = first_string(&body).to_string();
+    assert!(cid.parse::<u32>().is_ok());
+
+    let (_, body) = get("/api/posts/test-xmlrpc-post").await;
+    let body = body.unwrap();
+    assert_eq!("<p>testText</p>\n<!--more-->\nmore text", body["text"]);
+    assert_eq!(2, body["tags"].as_array().unwrap().len());
+
+    let (_, body) = post_xml(method_call(
+        "metaWeblog.getPost",
+        &[&string(&cid), LOGIN[0], LOGIN[1]],
+    ))
+    .await;
+    assert!(body.contains("<name>title</name><value><string>testXmlRpcPost</string>"));
+    assert!(body.contains("<name>mt_text_more</name><value><string>more text</string>"));
+    assert!(body.contains("<name>mt_keywords</name><value><string>xmlrpcTag1,xmlrpcTag2</string>"));
+    assert!(body.contains("<value><string>默认分类</string></value>"));
+
+    let modify = r#"<struct>
+      <member><name>title</name><value><string>testXmlRpcPostModified</string></value></member>
+    </struct>"#;
+    let (_, body) = post_xml(method_call(
+        "metaWeblog.editPost",
+        &[
+            &string(&cid),
+            LOGIN[0],
+            LOGIN[1],
+            modify,
+            "<boolean>0</boolean>",
+        ],
+    ))
+    .await;
+    assert!(body.contains("<boolean>1</boolean>"));
+
+    // a draft of a published post is saved as a revision
+    let (_, body) = get("/api/posts/test-xmlrpc-post").await;
+    assert_eq!("testXmlRpcPost", body.unwrap()["title"]);
+    let (_, body) = admin_get("/api/posts/test-xmlrpc-post/revisions/").await;
+    let body = body.unwrap();
+    assert_eq!(1, body["all_count"]);
+    assert_eq!("testXmlRpcPostModified", body["results"][0]["title"]);
+    assert_eq!("draft", body["results"][0]["status"]);
+
+    // a stale etag is refused like the api does
+    let (_, body) = post_xml_with_if_match(
+        method_call(
+            "metaWeblog.editPost",
+            &[
+                &string(&cid),
+                LOGIN[0],
+                LOGIN[1],
+                modify,
+                "<boolean>1</boolean>",
+            ],
+        ),
+        "\"stale\"",
+    )
+    .await;
+    assert!(body.contains("<name>faultCode</name><value><int>409</int>"));
+
+    let (_, body) = post_xml(method_call(
+        "metaWeblog.editPost",
+        &[
+            &string(&cid),
+            LOGIN[0],
+            LOGIN[1],
+            modify,
+            "<boolean>1</boolean>",
+        ],
+    ))
+    .await;
+    assert!(body.contains("<boolean>1</boolean>"));
+
+    let (_, body) = post_xml(method_call(
+        "metaWeblog.getRecentPosts",
+        &[&string("1"), LOGIN[0], LOGIN[1], "<int>100</int>"],
+    ))
+    .await;
+    assert!(body.contains("<string>testXmlRpcPostModified</string>"));
+    assert!(body.contains("<name>post_status</name><value><string>publish</string>"));
+
+    let (_, body) = post_xml(method_call(
+        "blogger.deletePost",
+        &[&string(""), &string(&cid), LOGIN[0], LOGIN[1]],
+    ))
+    .await;
+    assert!(body.contains("<boolean>1</boolean>"));
+
+    let (_, body) = post_xml(method_call(
+        "metaWeblog.getPost",
+        &[&string(&cid), LOGIN[0], LOGIN[1]],
+    ))
+    .await;
+    assert!(body.contains("<name>faultCode</name><value><int>404</int>"));
+}
+
+#[tokio::test]
+async fn wp_post_success() {
+    let post = r#"<struct>
+      <member><name>post_title</name><value><string>testWpPost</string></value></member>
+      <member><name>post_content</name><value><string>testText</string></value></member>
+      <member><name>post_status</name><value><string>publish</string></value></member>
+      <member><name>terms_names</name><value><struct>
+        <member><name>post_tag</name><value><array><data>
+          <value><string>wpTag</string></value>
+        </data></array></value></member>
+      </struct></value></member>
+    </struct>"#;
+    let (_, body) = post_xml(method_call(
+        "wp.newPost",
+        &[&string("1"), LOGIN[0], LOGIN[1], post],
+    ))
+    .await;
+    let cid = first_string(&body).to_string();
+
+    let untitled = r#"<struct>
+      <member><name>post_content</name><value><string>testText</string></value></member>
+    </struct>"#;
+    let (_, body) = post_xml(method_call(
+        "wp.newPost",
+        &[&string("1"), LOGIN[0], LOGIN[1], untitled],
+    ))
+    .await;
+    assert!(body.contains("<name>faultCode</name><value><int>-32602</int>"));
+    assert!(body.contains("invalid title"));
+
+    // posts without slug use the cid, same as typecho
+    let (status_code, body) = get(&format!("/api/posts/{}", cid)).await;
+    assert_eq!(status_code, StatusCode::OK);
+    assert_eq!("testWpPost", body.unwrap()["title"]);
+
+    // clients can refuse to overwrite a post changed after they read it
+    let modify = r#"<struct>
+      <member><name>post_status</name><value><string>pending</string></value></member>
+      <member><name>if_not_modified_since</name><value><dateTime.iso8601>20221025T02:57:46</dateTime.iso8601></value></member>
+    </struct>"#;
+    let (_, body) = post_xml(method_call(
+        "wp.editPost",
+        &[&string("1"), LOGIN[0], LOGIN[1], &string(&cid), modify],
+    ))
+    .await;
+    assert!(body.contains("<name>faultCode</name><value><int>409</int>"));
+
+    let modify = r#"<struct>
+      <member><name>post_status</name><value><string>pending</string></value></member>
+    </struct>"#;
+    let (_, body) = post_xml(method_call(
+        "wp.editPost",
+        &[&string("1"), LOGIN[0], LOGIN[1], &string(&cid), modify],
+    ))
+    .await;
+    assert!(body.contains("<boolean>1</boolean>"));
+
+    let (_, body) = post_xml(method_call(
+        "wp.getPost",
+        &[
+            &string("1"),
+            LOGIN[0],
+            LOGIN[1],
+            &format!("<int>{}</int>", cid),
+        ],
+    ))
+    .await;
+    assert!(body.contains("<name>post_title</name><value><string>testWpPost</string>"));
+    assert!(body.contains("<name>post_status</name><value><string>pending</string>"));
+    assert!(body.contains("<name>taxonomy</name><value><string>post_tag</string>"));
+
+    let (_, body) = post_xml(method_call(
+        "wp.deletePost",
+        &[&string("1"), LOGIN[0], LOGIN[1], &string(&cid)],
+    ))
+    .await;
+    assert!(body.contains("<boolean>1</boolean>"));
+}
+
+#[tokio::test]
+async fn xmlrpc_blogs_and_categories_success() {
+    let (_, body) = post_xml(method_call("wp.getUsersBlogs", &LOGIN)).await;
+    assert!(body.contains("<name>blogid</name><value><string>1</string>"));
+    assert!(body.contains("<name>isAdmin</name><value><boolean>1</boolean>"));
+
+    let (_, body) = post_xml(method_call(
+        "metaWeblog.getCategories",
+        &[&string("1"), LOGIN[0], LOGIN[1]],
+    ))
+    .await;
+    assert!(body.contains("<name>categoryName</name><value><string>默认分类</string>"));
+
+    let (_, body) = post_xml(method_call(
+        "wp.getUsersBlogs",
+        &[LOGIN[0], &string("wrong-password")],
+    ))
+    .await;
+    assert!(body.contains("<name>faultCode</name><value><int>403</int>"));
+}
+
+#[tokio::test]
+async fn xmlrpc_login_with_backoff_failed() {
+    let login = ["<string>admin</string>", "<string>wrong-password</string>"];
+    for _ in 0..3 {
+        let (_, body) =
+            post_xml_from_ip(method_call("wp.getUsersBlogs", &login), "*", "10.0.8.2").await;
+        assert!(body.contains("<name>faultCode</name><value><int>403</int>"));
+    }
+    // later logins from there have to wait before the password is checked again
+    let (_, body) =
+        post_xml_from_ip(method_call("wp.getUsersBlogs", &LOGIN), "*", "10.0.8.2").await;
+    assert!(body.contains("<name>faultCode</name><value><int>429</int>"));
+    assert!(body.contains("retry after"));
+
+    // the owner still logs in from elsewhere
+    let (_, body) =
+        post_xml_from_ip(method_call("wp.getUsersBlogs", &LOGIN), "*", "10.0.8.3").await;
+    assert!(body.contains("<name>isAdmin</name>"));
+}
+
+#[tokio::test]
+async fn metaweblog_new_media_object_success() {
+    let media = r#"<struct>
+      <member><name>name</name><value><string>images/xmlrpc.txt</string></value></member>
+      <member><name>type</name><value><string>text/plain</string></value></member>
+      <member><name>bits</name><value><base64>aGVsbG8=</base64></value></member>
+    </struct>"#;
+    let (_, body) = post_xml(method_call(
+        "metaWeblog.newMediaObject",
+        &[&string("1"), LOGIN[0], LOGIN[1], media],
+    ))
+    .await;
+    assert!(body.contains("<name>file</name><value><string>xmlrpc.txt</string>"));
+    assert!(body.contains("/usr/uploads/"));
+    assert!(body.contains("<name>type</name><value><string>text/plain</string>"));
+}